@@ -5,6 +5,10 @@ use crate::node::PNode;
 #[derive(Clone, Debug, Default)]
 pub struct Metadata {
     pub variable_names: Vec<String>,
+    /// For an expression expanded from a [`crate::GraphExpr`], the graph node behind each of its
+    /// nodes: nodes with equal ids are uses of one shared node (see [`crate::graph`]). Empty for a
+    /// plain tree.
+    pub graph_ids: Vec<u32>,
 }
 
 #[derive(Debug)]
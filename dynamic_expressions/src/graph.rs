@@ -0,0 +1,636 @@
+//! Expressions as DAGs, where a subexpression may be shared by several parents.
+//!
+//! A [`GraphExpr`] stores nodes in topological order (children before parents, root last), so
+//! evaluation computes each shared node once. Sharing is by identity: [`GraphExpr::to_postfix`]
+//! expands the graph into a tree that records the graph node behind each tree node in
+//! `meta.graph_ids`, and [`GraphExpr::from_postfix`] merges the tree nodes with equal ids back into
+//! one. Identical subtrees with different ids stay separate, and [`inherit_sharing`] carries the ids
+//! through edits of the tree.
+
+use core::fmt;
+use core::marker::PhantomData;
+
+use ndarray::ArrayView2;
+use num_traits::Float;
+use rustc_hash::FxHashMap;
+
+use crate::dispatch::{EvalKernelCtx, GradKernelCtx, GradRef, SrcRef};
+use crate::evaluate::EvalOptions;
+use crate::evaluate_derivative::GradMatrix;
+use crate::expression::{Metadata, PostfixExpr};
+use crate::node::PNode;
+use crate::strings::{
+    StringTreeOptions, combine_op_string, default_string_constant, default_string_variable, strip_outer_parens,
+};
+use crate::traits::{OpId, OperatorSet};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum GraphNode<const D: usize> {
+    Var {
+        feature: u16,
+    },
+    Const {
+        idx: u16,
+    },
+    /// `children[..arity]` are indices of earlier nodes.
+    Op {
+        arity: u8,
+        op: u16,
+        children: [u32; D],
+    },
+}
+
+impl<const D: usize> GraphNode<D> {
+    pub fn children(&self) -> &[u32] {
+        match self {
+            GraphNode::Op { arity, children, .. } => &children[..usize::from(*arity)],
+            _ => &[],
+        }
+    }
+}
+
+/// The graph nodes of the postfix `nodes`, in topological order: nodes tagged with the same id in
+/// `ids` (see `Metadata::graph_ids`) become one shared node, provided their subtrees match. Without
+/// one id per node, every node is its own.
+pub fn graph_nodes<const D: usize>(nodes: &[PNode], ids: &[u32]) -> Vec<GraphNode<D>> {
+    let ids = (ids.len() == nodes.len()).then_some(ids);
+    let mut graph: Vec<GraphNode<D>> = Vec::with_capacity(nodes.len());
+    let mut by_id: FxHashMap<u32, u32> = FxHashMap::default();
+    let mut stack: Vec<u32> = Vec::with_capacity(nodes.len());
+    for (i, n) in nodes.iter().enumerate() {
+        let node = match *n {
+            PNode::Var { feature } => GraphNode::Var { feature },
+            PNode::Const { idx } => GraphNode::Const { idx },
+            PNode::Op { arity, op } => {
+                let a = usize::from(arity);
+                assert!(a <= D && stack.len() >= a, "malformed postfix expression");
+                let mut children = [0u32; D];
+                children[..a].copy_from_slice(&stack[stack.len() - a..]);
+                stack.truncate(stack.len() - a);
+                GraphNode::Op { arity, op, children }
+            }
+        };
+        let shared = ids
+            .and_then(|ids| by_id.get(&ids[i]))
+            .copied()
+            .filter(|&j| graph[j as usize] == node);
+        let id = shared.unwrap_or_else(|| {
+            graph.push(node);
+            let j = (graph.len() - 1) as u32;
+            if let Some(ids) = ids {
+                by_id.entry(ids[i]).or_insert(j);
+            }
+            j
+        });
+        stack.push(id);
+    }
+    graph
+}
+
+/// Gives `child`, an edit of `parent` (e.g. a mutation of it), the sharing of the nodes it kept.
+///
+/// Kept nodes are those of the unchanged prefix and suffix of `parent.nodes`, and of an unchanged
+/// block between them (a subtree that got a new parent, or that replaced its parent), whose whole
+/// subtree is unchanged too; they keep their ids in `meta.graph_ids`. Edited nodes and their
+/// ancestors become unshared, so editing one use of a shared subexpression gives that use its own
+/// copy.
+pub fn inherit_sharing<T, Ops, const D: usize>(parent: &PostfixExpr<T, Ops, D>, child: &mut PostfixExpr<T, Ops, D>) {
+    child.meta.graph_ids.clear();
+    let old_ids = &parent.meta.graph_ids;
+    let (old, new) = (&parent.nodes, &child.nodes);
+    if old_ids.len() != old.len() {
+        return;
+    }
+    // Constants may have been renumbered (e.g. by `compress_constants`), consistently across uses.
+    let same = |(a, b): &(&PNode, &PNode)| matches!((a, b), (PNode::Const { .. }, PNode::Const { .. })) || a == b;
+    let prefix = old.iter().zip(new).take_while(same).count();
+    let max_suffix = old.len().min(new.len()) - prefix;
+    let suffix = old
+        .iter()
+        .rev()
+        .zip(new.iter().rev())
+        .take(max_suffix)
+        .take_while(same)
+        .count();
+    let suffix_start = new.len() - suffix;
+    // The shorter of the two edited middles, if found whole in the longer one: `(start, end)` in
+    // `new` and the matching start in `old`.
+    let (old_mid, new_mid) = (prefix..old.len() - suffix, prefix..suffix_start);
+    let find = |short: &[PNode], long: &[PNode]| {
+        (!short.is_empty())
+            .then(|| {
+                long.windows(short.len())
+                    .position(|w| w.iter().zip(short).all(|p| same(&p)))
+            })
+            .flatten()
+    };
+    let block = if old_mid.len() <= new_mid.len() {
+        find(&old[old_mid.clone()], &new[new_mid.clone()])
+            .map(|k| (new_mid.start + k, new_mid.start + k + old_mid.len(), old_mid.start))
+    } else {
+        find(&new[new_mid.clone()], &old[old_mid.clone()]).map(|k| (new_mid.start, new_mid.end, old_mid.start + k))
+    };
+    let mut fresh = old_ids.iter().max().map_or(0, |&m| m + 1);
+    let mut ids = Vec::with_capacity(new.len());
+    for (j, &start) in subtree_starts(new).iter().enumerate() {
+        let id = if j < prefix {
+            old_ids[j]
+        } else if start >= suffix_start {
+            old_ids[j + old.len() - new.len()]
+        } else if let Some((begin, _, old_begin)) = block.filter(|&(begin, end, _)| start >= begin && j < end) {
+            old_ids[old_begin + j - begin]
+        } else {
+            fresh += 1;
+            fresh - 1
+        };
+        ids.push(id);
+    }
+    let mut seen = FxHashMap::default();
+    if ids.iter().any(|&id| seen.insert(id, ()).is_some()) {
+        child.meta.graph_ids = ids;
+    }
+}
+
+/// Index of the first node of every node's subtree.
+fn subtree_starts(nodes: &[PNode]) -> Vec<usize> {
+    let mut starts = Vec::with_capacity(nodes.len());
+    let mut stack: Vec<usize> = Vec::with_capacity(nodes.len());
+    for (i, n) in nodes.iter().enumerate() {
+        let start = match *n {
+            PNode::Op { arity, .. } if arity > 0 => {
+                let a = usize::from(arity);
+                assert!(stack.len() >= a, "malformed postfix expression");
+                let start = stack[stack.len() - a];
+                stack.truncate(stack.len() - a);
+                start
+            }
+            _ => i,
+        };
+        stack.push(start);
+        starts.push(start);
+    }
+    starts
+}
+
+#[derive(Debug)]
+pub struct GraphExpr<T, Ops, const D: usize = 2> {
+    pub nodes: Vec<GraphNode<D>>,
+    pub consts: Vec<T>,
+    pub meta: Metadata,
+    _ops: PhantomData<Ops>,
+}
+
+impl<T: Clone, Ops, const D: usize> Clone for GraphExpr<T, Ops, D> {
+    fn clone(&self) -> Self {
+        Self {
+            nodes: self.nodes.clone(),
+            consts: self.consts.clone(),
+            meta: self.meta.clone(),
+            _ops: PhantomData,
+        }
+    }
+}
+
+impl<T, Ops, const D: usize> GraphExpr<T, Ops, D> {
+    /// `nodes` must be in topological order with the root last.
+    pub fn new(nodes: Vec<GraphNode<D>>, consts: Vec<T>, meta: Metadata) -> Self {
+        debug_assert!(
+            nodes
+                .iter()
+                .enumerate()
+                .all(|(i, n)| n.children().iter().all(|&c| (c as usize) < i)),
+            "graph nodes must be topologically ordered"
+        );
+        Self {
+            nodes,
+            consts,
+            meta,
+            _ops: PhantomData,
+        }
+    }
+
+    /// Builds the graph of `expr`, merging the nodes that share an id in `expr.meta.graph_ids`.
+    pub fn from_postfix(expr: &PostfixExpr<T, Ops, D>) -> Self
+    where
+        T: Clone,
+    {
+        let nodes = graph_nodes::<D>(&expr.nodes, &expr.meta.graph_ids);
+        let meta = Metadata {
+            variable_names: expr.meta.variable_names.clone(),
+            graph_ids: Vec::new(),
+        };
+        let mut out = Self::new(nodes, expr.consts.clone(), meta);
+        out.reorder();
+        out
+    }
+
+    /// Expands shared nodes into a tree, recording in its `meta.graph_ids` which graph node each
+    /// tree node came from (left empty without sharing). Shared constants keep a single index.
+    pub fn to_postfix(&self) -> PostfixExpr<T, Ops, D>
+    where
+        T: Clone,
+    {
+        let (nodes, ids): (Vec<PNode>, Vec<u32>) = self.expand(self.root()).into_iter().unzip();
+        let mut meta = self.meta.clone();
+        meta.graph_ids = if self.parent_counts().iter().any(|&p| p > 1) {
+            ids
+        } else {
+            Vec::new()
+        };
+        PostfixExpr::new(nodes, self.consts.clone(), meta)
+    }
+
+    /// The postfix nodes of the subgraph under node `i`, each with its graph node.
+    fn expand(&self, i: usize) -> Vec<(PNode, u32)> {
+        let mut out = Vec::new();
+        let mut stack = vec![(i, false)];
+        while let Some((i, expanded)) = stack.pop() {
+            let node = self.nodes[i];
+            if expanded || node.children().is_empty() {
+                let pnode = match node {
+                    GraphNode::Var { feature } => PNode::Var { feature },
+                    GraphNode::Const { idx } => PNode::Const { idx },
+                    GraphNode::Op { arity, op, .. } => PNode::Op { arity, op },
+                };
+                out.push((pnode, i as u32));
+                continue;
+            }
+            stack.push((i, true));
+            stack.extend(node.children().iter().rev().map(|&c| (c as usize, false)));
+        }
+        out
+    }
+
+    pub fn root(&self) -> usize {
+        self.nodes.len() - 1
+    }
+
+    /// Number of incoming edges of each node (a parent using a child twice counts twice).
+    pub fn parent_counts(&self) -> Vec<usize> {
+        let mut counts = vec![0usize; self.nodes.len()];
+        for n in &self.nodes {
+            for &c in n.children() {
+                counts[c as usize] += 1;
+            }
+        }
+        counts
+    }
+
+    /// Points child `slot` of `parent` at the existing node `target`, creating sharing.
+    ///
+    /// `target` must come before `parent` (so no cycle can form). Nodes no longer reachable are
+    /// dropped. Returns `false` if the edge is invalid or already points at `target`.
+    pub fn connect(&mut self, parent: usize, slot: usize, target: usize) -> bool {
+        if target >= parent || parent >= self.nodes.len() {
+            return false;
+        }
+        let GraphNode::Op { arity, children, .. } = &mut self.nodes[parent] else {
+            return false;
+        };
+        if slot >= usize::from(*arity) || children[slot] as usize == target {
+            return false;
+        }
+        children[slot] = target as u32;
+        self.reorder();
+        true
+    }
+
+    /// Gives child `slot` of `parent` its own copy of the child's subgraph, with fresh copies of its
+    /// constants, removing that edge's sharing. Returns `false` if the edge is invalid.
+    pub fn disconnect(&mut self, parent: usize, slot: usize) -> bool
+    where
+        T: Clone,
+    {
+        let Some(&child) = self.nodes.get(parent).and_then(|n| n.children().get(slot)) else {
+            return false;
+        };
+        let child = child as usize;
+        let root = self.root();
+
+        // Copy nodes reachable from `child` (in topological order), remapping constants.
+        let mut reachable = vec![false; child + 1];
+        reachable[child] = true;
+        for i in (0..=child).rev() {
+            if reachable[i] {
+                for &c in self.nodes[i].children() {
+                    reachable[c as usize] = true;
+                }
+            }
+        }
+        let mut new_index: FxHashMap<usize, u32> = FxHashMap::default();
+        let mut new_const: FxHashMap<u16, u16> = FxHashMap::default();
+        for i in (0..=child).filter(|&i| reachable[i]) {
+            let node = match self.nodes[i] {
+                leaf @ GraphNode::Var { .. } => leaf,
+                GraphNode::Const { idx } => {
+                    let idx = *new_const.entry(idx).or_insert_with(|| {
+                        let value = self.consts[usize::from(idx)].clone();
+                        self.consts.push(value);
+                        u16::try_from(self.consts.len() - 1).expect("too many constants")
+                    });
+                    GraphNode::Const { idx }
+                }
+                GraphNode::Op {
+                    arity,
+                    op,
+                    mut children,
+                } => {
+                    for c in &mut children[..usize::from(arity)] {
+                        *c = new_index.get(&(*c as usize)).copied().unwrap_or(*c);
+                    }
+                    GraphNode::Op { arity, op, children }
+                }
+            };
+            self.nodes.push(node);
+            new_index.insert(i, (self.nodes.len() - 1) as u32);
+        }
+
+        // The copies were appended after the root; renumbering from it restores topological order.
+        let copy = new_index.get(&child).copied().unwrap_or(child as u32);
+        if let GraphNode::Op { children, .. } = &mut self.nodes[parent] {
+            children[slot] = copy;
+        }
+        self.reorder_from(root);
+        true
+    }
+
+    /// Renumbers nodes in post-order from the root, dropping unreachable nodes.
+    fn reorder(&mut self) {
+        self.reorder_from(self.root());
+    }
+
+    fn reorder_from(&mut self, root: usize) {
+        let mut new_index: Vec<Option<u32>> = vec![None; self.nodes.len()];
+        let mut order: Vec<usize> = Vec::with_capacity(self.nodes.len());
+        let mut stack = vec![(root, false)];
+        while let Some((i, expanded)) = stack.pop() {
+            if new_index[i].is_some() {
+                continue;
+            }
+            if expanded {
+                new_index[i] = Some(order.len() as u32);
+                order.push(i);
+                continue;
+            }
+            stack.push((i, true));
+            stack.extend(
+                self.nodes[i]
+                    .children()
+                    .iter()
+                    .rev()
+                    .filter(|&&c| new_index[c as usize].is_none())
+                    .map(|&c| (c as usize, false)),
+            );
+        }
+        self.nodes = order
+            .into_iter()
+            .map(|i| match self.nodes[i] {
+                GraphNode::Op {
+                    arity,
+                    op,
+                    mut children,
+                } => {
+                    for c in &mut children[..usize::from(arity)] {
+                        *c = new_index[*c as usize].expect("child visited before parent");
+                    }
+                    GraphNode::Op { arity, op, children }
+                }
+                leaf => leaf,
+            })
+            .collect();
+    }
+}
+
+fn graph_src<'a, T: Float, const D: usize>(
+    node: GraphNode<D>,
+    i: usize,
+    x: &'a [T],
+    n_rows: usize,
+    consts: &[T],
+    bufs: &'a [Vec<T>],
+) -> SrcRef<'a, T> {
+    match node {
+        GraphNode::Var { feature } => {
+            let f = usize::from(feature);
+            SrcRef::Slice(&x[f * n_rows..(f + 1) * n_rows])
+        }
+        GraphNode::Const { idx } => SrcRef::Const(consts[usize::from(idx)]),
+        GraphNode::Op { .. } => SrcRef::Slice(&bufs[i]),
+    }
+}
+
+/// Evaluates `expr` on `x` with shape `(n_features, n_rows)`, computing each shared node once.
+pub fn eval_graph_array<T, Ops, const D: usize>(
+    expr: &GraphExpr<T, Ops, D>,
+    x: ArrayView2<'_, T>,
+    opts: &EvalOptions,
+) -> (Vec<T>, bool)
+where
+    T: Float,
+    Ops: OperatorSet<T = T>,
+{
+    assert!(x.is_standard_layout(), "X must be contiguous");
+    let n_rows = x.ncols();
+    let x_data = x.as_slice().expect("X must be contiguous");
+    let mut bufs: Vec<Vec<T>> = vec![Vec::new(); expr.nodes.len()];
+    let mut complete = true;
+
+    for (i, node) in expr.nodes.iter().enumerate() {
+        let GraphNode::Op { arity, op, children } = *node else {
+            continue;
+        };
+        let (before, rest) = bufs.split_at_mut(i);
+        let out = &mut rest[0];
+        out.resize(n_rows, T::zero());
+        let mut args: [SrcRef<'_, T>; D] = [SrcRef::Const(T::zero()); D];
+        for (a, &c) in args.iter_mut().zip(&children[..usize::from(arity)]) {
+            let c = c as usize;
+            *a = graph_src(expr.nodes[c], c, x_data, n_rows, &expr.consts, before);
+        }
+        let ok = Ops::eval(
+            OpId { arity, id: op },
+            EvalKernelCtx {
+                out,
+                args: &args[..usize::from(arity)],
+                opts,
+            },
+        );
+        complete &= ok;
+        if opts.early_exit && !ok {
+            return (vec![T::nan(); n_rows], false);
+        }
+    }
+
+    let root = expr.root();
+    let out = match graph_src(expr.nodes[root], root, x_data, n_rows, &expr.consts, &bufs) {
+        SrcRef::Slice(s) => s.to_vec(),
+        SrcRef::Const(v) => {
+            if opts.check_finite && !v.is_finite() {
+                complete = false;
+            }
+            vec![v; n_rows]
+        }
+    };
+    (out, complete)
+}
+
+/// Evaluates `expr` and its gradient with respect to the constants (direction-major,
+/// `n_consts * n_rows`). Contributions of a shared constant are summed over its uses.
+pub fn eval_grad_graph_array<T, Ops, const D: usize>(
+    expr: &GraphExpr<T, Ops, D>,
+    x: ArrayView2<'_, T>,
+    opts: &EvalOptions,
+) -> (Vec<T>, GradMatrix<T>, bool)
+where
+    T: Float,
+    Ops: OperatorSet<T = T>,
+{
+    assert!(x.is_standard_layout(), "X must be contiguous");
+    let n_rows = x.ncols();
+    let n_dir = expr.consts.len();
+    let x_data = x.as_slice().expect("X must be contiguous");
+    let mut vals: Vec<Vec<T>> = vec![Vec::new(); expr.nodes.len()];
+    let mut grads: Vec<Vec<T>> = vec![Vec::new(); expr.nodes.len()];
+    let mut complete = true;
+    let nan_return = || {
+        (
+            vec![T::nan(); n_rows],
+            GradMatrix {
+                data: vec![T::nan(); n_dir * n_rows],
+                n_dir,
+                n_rows,
+            },
+            false,
+        )
+    };
+
+    for (i, node) in expr.nodes.iter().enumerate() {
+        let GraphNode::Op { arity, op, children } = *node else {
+            continue;
+        };
+        let (vals_before, vals_rest) = vals.split_at_mut(i);
+        let (grads_before, grads_rest) = grads.split_at_mut(i);
+        let out_val = &mut vals_rest[0];
+        let out_grad = &mut grads_rest[0];
+        out_val.resize(n_rows, T::zero());
+        out_grad.resize(n_dir * n_rows, T::zero());
+
+        let mut args: [SrcRef<'_, T>; D] = [SrcRef::Const(T::zero()); D];
+        let mut arg_grads: [GradRef<'_, T>; D] = [GradRef::Zero; D];
+        for ((a, g), &c) in args
+            .iter_mut()
+            .zip(arg_grads.iter_mut())
+            .zip(&children[..usize::from(arity)])
+        {
+            let c = c as usize;
+            *a = graph_src(expr.nodes[c], c, x_data, n_rows, &expr.consts, vals_before);
+            *g = match expr.nodes[c] {
+                GraphNode::Var { .. } => GradRef::Zero,
+                GraphNode::Const { idx } => GradRef::Basis(usize::from(idx)),
+                GraphNode::Op { .. } => GradRef::Slice(&grads_before[c]),
+            };
+        }
+        let ok = Ops::grad(
+            OpId { arity, id: op },
+            GradKernelCtx {
+                out_val,
+                out_grad,
+                args: &args[..usize::from(arity)],
+                arg_grads: &arg_grads[..usize::from(arity)],
+                n_dir,
+                n_rows,
+                opts,
+            },
+        );
+        complete &= ok;
+        if opts.early_exit && !ok {
+            return nan_return();
+        }
+    }
+
+    let root = expr.root();
+    let (out_val, out_grad) = match expr.nodes[root] {
+        GraphNode::Op { .. } => (vals.swap_remove(root), grads.swap_remove(root)),
+        GraphNode::Var { feature } => {
+            let f = usize::from(feature);
+            (
+                x_data[f * n_rows..(f + 1) * n_rows].to_vec(),
+                vec![T::zero(); n_dir * n_rows],
+            )
+        }
+        GraphNode::Const { idx } => {
+            let v = expr.consts[usize::from(idx)];
+            if opts.check_finite && !v.is_finite() {
+                complete = false;
+                if opts.early_exit {
+                    return nan_return();
+                }
+            }
+            let mut g = vec![T::zero(); n_dir * n_rows];
+            let dir = usize::from(idx);
+            g[dir * n_rows..(dir + 1) * n_rows].fill(T::one());
+            (vec![v; n_rows], g)
+        }
+    };
+    (
+        out_val,
+        GradMatrix {
+            data: out_grad,
+            n_dir,
+            n_rows,
+        },
+        complete,
+    )
+}
+
+/// Formats `expr` with a let-binding for every shared operator node, e.g.
+/// `let z0 = sin(x0) in z0 * (z0 + 1.5)`. Without sharing this matches [`crate::string_tree`].
+pub fn string_graph<T, Ops, const D: usize>(expr: &GraphExpr<T, Ops, D>, opts: StringTreeOptions<'_>) -> String
+where
+    T: fmt::Display,
+    Ops: OperatorSet,
+{
+    let names = opts.variable_names.or({
+        if expr.meta.variable_names.is_empty() {
+            None
+        } else {
+            Some(expr.meta.variable_names.as_slice())
+        }
+    });
+    let parents = expr.parent_counts();
+    let mut rendered: Vec<String> = Vec::with_capacity(expr.nodes.len());
+    let mut bindings: Vec<String> = Vec::new();
+    for (i, node) in expr.nodes.iter().enumerate() {
+        let s = match *node {
+            GraphNode::Var { feature } => default_string_variable(feature, names),
+            GraphNode::Const { idx } => default_string_constant(&expr.consts[usize::from(idx)]),
+            GraphNode::Op { arity, op, .. } => {
+                let children: Vec<String> = node.children().iter().map(|&c| rendered[c as usize].clone()).collect();
+                combine_op_string::<Ops>(OpId { arity, id: op }, &children, opts.pretty)
+            }
+        };
+        if matches!(node, GraphNode::Op { .. }) && parents[i] > 1 {
+            let name = format!("z{}", bindings.len());
+            bindings.push(format!("{name} = {}", strip_outer_parens(&s)));
+            rendered.push(name);
+        } else {
+            rendered.push(s);
+        }
+    }
+    let body = strip_outer_parens(rendered.last().map_or("", String::as_str)).to_string();
+    if bindings.is_empty() {
+        body
+    } else {
+        format!("let {} in {body}", bindings.join(", "))
+    }
+}
+
+impl<T, Ops, const D: usize> fmt::Display for GraphExpr<T, Ops, D>
+where
+    T: fmt::Display,
+    Ops: OperatorSet,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&string_graph(self, StringTreeOptions::default()))
+    }
+}
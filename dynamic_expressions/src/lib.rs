@@ -6,10 +6,12 @@ pub mod evaluate;
 pub mod evaluate_derivative;
 pub mod expression;
 pub mod expression_algebra;
+pub mod graph;
 pub mod node;
 pub mod node_utils;
 pub mod operator_enum;
 pub mod operators;
+pub mod parse;
 #[cfg(feature = "proptest-utils")]
 pub mod proptest_utils;
 pub mod simplify;
@@ -26,11 +28,15 @@ pub use crate::evaluate_derivative::{
 };
 pub use crate::expression::{Metadata, PostfixExpr, PostfixExpression, PostfixExpressionMut};
 pub use crate::expression_algebra::{Lit, lit};
+pub use crate::graph::{
+    GraphExpr, GraphNode, eval_grad_graph_array, eval_graph_array, graph_nodes, inherit_sharing, string_graph,
+};
 pub use crate::node::{PNode, Src};
 pub use crate::node_utils::{
     count_constant_nodes, count_depth, count_nodes, has_constants, has_operators, subtree_range, subtree_sizes,
     tree_mapreduce,
 };
+pub use crate::parse::{ParseError, parse_expr, parse_postfix_string, postfix_string};
 pub use crate::simplify::{combine_operators_in_place, simplify_in_place, simplify_tree_in_place};
 pub use crate::strings::{StringTreeOptions, print_tree, string_tree};
 pub use crate::traits::{HasOp, LookupError, OpId, OpMeta, OpTag, Operator, OperatorSet};
@@ -0,0 +1,441 @@
+//! Parsing expression strings back into [`PostfixExpr`].
+//!
+//! [`parse_expr`] reads the infix form produced by [`crate::string_tree`] (and by
+//! DynamicExpressions.jl, e.g. PySR's `hall_of_fame.csv`). [`postfix_string`] and
+//! [`parse_postfix_string`] are an exact, operator-name based serialization.
+
+use core::fmt;
+
+use num_traits::Float;
+
+use crate::expression::{Metadata, PostfixExpr};
+use crate::node::PNode;
+use crate::traits::{OpId, OperatorSet};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// Byte offset into the input where the error was detected.
+    pub pos: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    fn new(pos: usize, message: impl Into<String>) -> Self {
+        Self {
+            pos,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (at byte {})", self.message, self.pos)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Tok<'s> {
+    Num(&'s str),
+    Ident(&'s str),
+    Sym(&'s str),
+    LParen,
+    RParen,
+    Comma,
+    End,
+}
+
+struct Lexer<'s> {
+    src: &'s str,
+    pos: usize,
+    /// Infix symbols known to the operator set, longest first.
+    symbols: Vec<&'static str>,
+}
+
+impl<'s> Lexer<'s> {
+    fn skip_ws(&mut self) {
+        let rest = &self.src[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Returns the next token and its start offset without consuming it.
+    fn peek(&mut self) -> Result<(Tok<'s>, usize), ParseError> {
+        self.skip_ws();
+        let start = self.pos;
+        let rest = &self.src[start..];
+        let Some(c) = rest.chars().next() else {
+            return Ok((Tok::End, start));
+        };
+        let tok = match c {
+            '(' => Tok::LParen,
+            ')' => Tok::RParen,
+            ',' => Tok::Comma,
+            c if c.is_ascii_digit() || (c == '.' && rest[1..].starts_with(|d: char| d.is_ascii_digit())) => {
+                Tok::Num(&rest[..number_len(rest)])
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let len = rest
+                    .char_indices()
+                    .find(|&(_, c)| !(c.is_alphanumeric() || c == '_'))
+                    .map_or(rest.len(), |(i, _)| i);
+                Tok::Ident(&rest[..len])
+            }
+            _ => match self.symbols.iter().find(|s| rest.starts_with(**s)) {
+                Some(s) => Tok::Sym(&rest[..s.len()]),
+                None => return Err(ParseError::new(start, format!("unexpected character {c:?}"))),
+            },
+        };
+        Ok((tok, start))
+    }
+
+    fn next(&mut self) -> Result<(Tok<'s>, usize), ParseError> {
+        let (tok, start) = self.peek()?;
+        self.pos = start
+            + match tok {
+                Tok::Num(s) | Tok::Ident(s) | Tok::Sym(s) => s.len(),
+                Tok::LParen | Tok::RParen | Tok::Comma => 1,
+                Tok::End => 0,
+            };
+        Ok((tok, start))
+    }
+
+    fn expect(&mut self, want: Tok<'s>, what: &str) -> Result<(), ParseError> {
+        let (tok, pos) = self.next()?;
+        if tok == want {
+            Ok(())
+        } else {
+            Err(ParseError::new(pos, format!("expected {what}, found {tok:?}")))
+        }
+    }
+}
+
+/// Length of the decimal literal (digits, fraction, exponent) at the start of `s`.
+fn number_len(s: &str) -> usize {
+    let b = s.as_bytes();
+    let digits = |mut i: usize| {
+        while i < b.len() && b[i].is_ascii_digit() {
+            i += 1;
+        }
+        i
+    };
+    let mut i = digits(0);
+    if i < b.len() && b[i] == b'.' {
+        i = digits(i + 1);
+    }
+    if i < b.len() && (b[i] == b'e' || b[i] == b'E') {
+        let mut j = i + 1;
+        if j < b.len() && (b[j] == b'+' || b[j] == b'-') {
+            j += 1;
+        }
+        if j < b.len() && b[j].is_ascii_digit() {
+            i = digits(j);
+        }
+    }
+    i
+}
+
+fn parse_number<T: Float>(s: &str, pos: usize) -> Result<T, ParseError> {
+    T::from_str_radix(s, 10).map_err(|_| ParseError::new(pos, format!("invalid number {s:?}")))
+}
+
+struct Parser<'s, 'n, T, Ops, const D: usize> {
+    lex: Lexer<'s>,
+    variable_names: &'n [String],
+    nodes: Vec<PNode>,
+    consts: Vec<T>,
+    _ops: core::marker::PhantomData<Ops>,
+}
+
+impl<T: Float, Ops: OperatorSet, const D: usize> Parser<'_, '_, T, Ops, D> {
+    /// Looks up an operator by token, also accepting `^` for an operator named `pow`.
+    fn lookup(token: &str, arity: usize) -> Option<OpId> {
+        if arity == 0 || arity > D || arity > usize::from(Ops::MAX_ARITY) {
+            return None;
+        }
+        let arity = arity as u8;
+        Ops::lookup_with_arity(token, arity).ok().or_else(|| {
+            (token == "^")
+                .then(|| Ops::lookup_with_arity("pow", arity).ok())
+                .flatten()
+        })
+    }
+
+    /// Binding power and right-associativity of an infix symbol.
+    fn precedence(sym: &str) -> (u8, bool) {
+        match sym {
+            "+" | "-" => (1, false),
+            "*" | "/" => (2, false),
+            "^" => (4, true),
+            _ => (1, false),
+        }
+    }
+
+    /// Binary operator for `sym`, or an n-ary one written as `a sym b sym c`.
+    fn infix_op(sym: &str) -> Option<OpId> {
+        Self::lookup(sym, 2).or_else(|| (3..=D).find_map(|arity| Self::lookup(sym, arity)))
+    }
+
+    fn push_const(&mut self, v: T, pos: usize) -> Result<(), ParseError> {
+        let idx = u16::try_from(self.consts.len()).map_err(|_| ParseError::new(pos, "too many constants"))?;
+        self.consts.push(v);
+        self.nodes.push(PNode::Const { idx });
+        Ok(())
+    }
+
+    fn push_op(&mut self, op: OpId) {
+        self.nodes.push(PNode::Op {
+            arity: op.arity,
+            op: op.id,
+        });
+    }
+
+    fn expr(&mut self, min_prec: u8) -> Result<(), ParseError> {
+        self.unary()?;
+        loop {
+            let (tok, pos) = self.lex.peek()?;
+            let Tok::Sym(sym) = tok else {
+                return Ok(());
+            };
+            let (prec, right_assoc) = Self::precedence(sym);
+            if prec < min_prec {
+                return Ok(());
+            }
+            let op = Self::infix_op(sym).ok_or_else(|| ParseError::new(pos, format!("no infix operator {sym:?}")))?;
+            self.lex.next()?;
+            let next_prec = if right_assoc { prec } else { prec + 1 };
+            self.expr(next_prec)?;
+            for _ in 2..op.arity {
+                self.lex
+                    .expect(Tok::Sym(sym), &format!("{sym:?} (operator has arity {})", op.arity))?;
+                self.expr(next_prec)?;
+            }
+            self.push_op(op);
+        }
+    }
+
+    fn unary(&mut self) -> Result<(), ParseError> {
+        let (tok, pos) = self.lex.peek()?;
+        let Tok::Sym(sym) = tok else {
+            return self.power();
+        };
+        self.lex.next()?;
+        let op = Self::lookup(sym, 1);
+        let start = self.nodes.len();
+        if sym == "-" && matches!(self.lex.peek()?.0, Tok::Num(_)) {
+            // A negated literal becomes a negative constant, but `-2 ^ x` is `-(2 ^ x)`.
+            self.power()?;
+            if let [PNode::Const { idx }] = self.nodes[start..] {
+                let c = &mut self.consts[usize::from(idx)];
+                *c = -*c;
+                return Ok(());
+            }
+        } else {
+            self.unary()?;
+        }
+        let op = op.ok_or_else(|| ParseError::new(pos, format!("no unary operator {sym:?}")))?;
+        self.push_op(op);
+        Ok(())
+    }
+
+    fn power(&mut self) -> Result<(), ParseError> {
+        self.primary()?;
+        if let (Tok::Sym("^"), pos) = self.lex.peek()? {
+            let op = Self::lookup("^", 2).ok_or_else(|| ParseError::new(pos, "no infix operator \"^\""))?;
+            self.lex.next()?;
+            self.unary()?;
+            self.push_op(op);
+        }
+        Ok(())
+    }
+
+    fn primary(&mut self) -> Result<(), ParseError> {
+        let (tok, pos) = self.lex.next()?;
+        match tok {
+            Tok::Num(s) => {
+                let v = parse_number(s, pos)?;
+                self.push_const(v, pos)
+            }
+            Tok::LParen => {
+                self.expr(0)?;
+                self.lex.expect(Tok::RParen, "')'")
+            }
+            Tok::Ident(name) => {
+                if self.lex.peek()?.0 == Tok::LParen {
+                    return self.call(name, pos);
+                }
+                if let Some(feature) = self.variable_names.iter().position(|v| v == name) {
+                    return self.push_var(feature, pos);
+                }
+                if let Some(feature) = name.strip_prefix('x').and_then(|d| d.parse::<usize>().ok()) {
+                    return self.push_var(feature, pos);
+                }
+                match name.to_ascii_lowercase().as_str() {
+                    "inf" | "nan" => self.push_const(parse_number(name, pos)?, pos),
+                    _ => Err(ParseError::new(pos, format!("unknown variable {name:?}"))),
+                }
+            }
+            other => Err(ParseError::new(pos, format!("unexpected token {other:?}"))),
+        }
+    }
+
+    fn push_var(&mut self, feature: usize, pos: usize) -> Result<(), ParseError> {
+        let feature = u16::try_from(feature).map_err(|_| ParseError::new(pos, "feature index out of range"))?;
+        self.nodes.push(PNode::Var { feature });
+        Ok(())
+    }
+
+    fn call(&mut self, name: &str, pos: usize) -> Result<(), ParseError> {
+        self.lex.expect(Tok::LParen, "'('")?;
+        let mut n_args = 0usize;
+        if self.lex.peek()?.0 == Tok::RParen {
+            self.lex.next()?;
+        } else {
+            loop {
+                self.expr(0)?;
+                n_args += 1;
+                let (tok, tok_pos) = self.lex.next()?;
+                match tok {
+                    Tok::Comma => continue,
+                    Tok::RParen => break,
+                    other => {
+                        return Err(ParseError::new(
+                            tok_pos,
+                            format!("expected ',' or ')', found {other:?}"),
+                        ));
+                    }
+                }
+            }
+        }
+        let op = Self::lookup(name, n_args)
+            .ok_or_else(|| ParseError::new(pos, format!("no operator {name:?} with arity {n_args}")))?;
+        self.push_op(op);
+        Ok(())
+    }
+}
+
+/// Parses an infix expression string, e.g. `"sin(x0) + (2.5 * y)"`.
+///
+/// Variables are matched against `variable_names` first, then as `x{N}`. Functions are looked up
+/// by name, display string or infix symbol with the call's arity; `^` falls back to `pow`.
+/// Every literal becomes its own constant, in order of appearance.
+pub fn parse_expr<T, Ops, const D: usize>(
+    s: &str,
+    variable_names: &[String],
+) -> Result<PostfixExpr<T, Ops, D>, ParseError>
+where
+    T: Float,
+    Ops: OperatorSet,
+{
+    let mut symbols: Vec<&'static str> = vec!["^"];
+    Ops::for_each_op(|op| {
+        if let Some(s) = Ops::infix(op) {
+            symbols.push(s);
+        }
+    });
+    symbols.sort_by_key(|s| core::cmp::Reverse(s.len()));
+    symbols.dedup();
+
+    let mut p = Parser::<T, Ops, D> {
+        lex: Lexer {
+            src: s,
+            pos: 0,
+            symbols,
+        },
+        variable_names,
+        nodes: Vec::new(),
+        consts: Vec::new(),
+        _ops: core::marker::PhantomData,
+    };
+    p.expr(0)?;
+    p.lex.expect(Tok::End, "end of input")?;
+    let meta = Metadata {
+        variable_names: variable_names.to_vec(),
+        ..Default::default()
+    };
+    Ok(PostfixExpr::new(p.nodes, p.consts, meta))
+}
+
+/// Exact serialization: space-separated postfix tokens (`x{feature}`, `c{idx}`, `{name}/{arity}`),
+/// then `|` and the constants.
+pub fn postfix_string<T, Ops, const D: usize>(expr: &PostfixExpr<T, Ops, D>) -> String
+where
+    T: fmt::Display,
+    Ops: OperatorSet,
+{
+    let mut out: Vec<String> = expr
+        .nodes
+        .iter()
+        .map(|n| match *n {
+            PNode::Var { feature } => format!("x{feature}"),
+            PNode::Const { idx } => format!("c{idx}"),
+            PNode::Op { arity, op } => format!("{}/{arity}", Ops::name(OpId { arity, id: op })),
+        })
+        .collect();
+    out.push("|".to_string());
+    out.extend(expr.consts.iter().map(|c| c.to_string()));
+    out.join(" ")
+}
+
+/// Inverse of [`postfix_string`]. Operators are matched by exact name and arity.
+pub fn parse_postfix_string<T, Ops, const D: usize>(s: &str) -> Result<PostfixExpr<T, Ops, D>, ParseError>
+where
+    T: Float,
+    Ops: OperatorSet,
+{
+    let (nodes_part, consts_part) = s
+        .split_once('|')
+        .ok_or_else(|| ParseError::new(s.len(), "missing '|' before constants"))?;
+    let offset = |tok: &str| tok.as_ptr() as usize - s.as_ptr() as usize;
+
+    let consts = consts_part
+        .split_whitespace()
+        .map(|tok| parse_number::<T>(tok, offset(tok)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut nodes = Vec::new();
+    let mut depth = 0usize;
+    for tok in nodes_part.split_whitespace() {
+        let pos = offset(tok);
+        let bad = || ParseError::new(pos, format!("invalid token {tok:?}"));
+        let node = if let Some((name, arity)) = tok.split_once('/') {
+            let arity: u8 = arity.parse().map_err(|_| bad())?;
+            let mut found = None;
+            Ops::for_each_op(|op| {
+                if op.arity == arity && Ops::name(op) == name {
+                    found = Some(op);
+                }
+            });
+            let op = found.filter(|_| usize::from(arity) <= D).ok_or_else(bad)?;
+            if depth < usize::from(arity) {
+                return Err(ParseError::new(pos, format!("not enough operands for {tok:?}")));
+            }
+            depth -= usize::from(arity) - 1;
+            PNode::Op { arity, op: op.id }
+        } else {
+            depth += 1;
+            if let Some(feature) = tok.strip_prefix('x') {
+                PNode::Var {
+                    feature: feature.parse().map_err(|_| bad())?,
+                }
+            } else if let Some(idx) = tok.strip_prefix('c') {
+                let idx: u16 = idx.parse().map_err(|_| bad())?;
+                if usize::from(idx) >= consts.len() {
+                    return Err(ParseError::new(pos, format!("constant index {idx} out of range")));
+                }
+                PNode::Const { idx }
+            } else {
+                return Err(bad());
+            }
+        };
+        nodes.push(node);
+    }
+    if depth != 1 {
+        return Err(ParseError::new(
+            nodes_part.len(),
+            format!("expected one expression, found {depth}"),
+        ));
+    }
+    Ok(PostfixExpr::new(nodes, consts, Metadata::default()))
+}
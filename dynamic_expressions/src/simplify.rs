@@ -500,13 +500,20 @@ where
     combine_operators_in_place_with_cfg(expr, cfg)
 }
 
+/// Folds constants and combines operators. Nodes of a graph expression (see
+/// [`crate::graph`]) that the simplification left untouched stay shared.
 pub fn simplify_in_place<T, Ops, const D: usize>(expr: &mut PostfixExpr<T, Ops, D>, eval_opts: &EvalOptions) -> bool
 where
     T: Float,
     Ops: OperatorSet<T = T>,
 {
+    let before = (!expr.meta.graph_ids.is_empty()).then(|| expr.clone());
     let c1 = simplify_tree_in_place(expr, eval_opts);
     let c2 = combine_operators_in_place(expr);
     let c3 = crate::utils::compress_constants(expr);
-    c1 || c2 || c3
+    let changed = c1 || c2 || c3;
+    if let Some(before) = before.filter(|_| changed) {
+        crate::graph::inherit_sharing(&before, expr);
+    }
+    changed
 }
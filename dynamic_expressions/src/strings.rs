@@ -23,7 +23,7 @@ pub fn default_string_constant<T: fmt::Display>(v: &T) -> String {
     v.to_string()
 }
 
-fn strip_outer_parens(mut s: &str) -> &str {
+pub(crate) fn strip_outer_parens(mut s: &str) -> &str {
     loop {
         let bytes = s.as_bytes();
         if bytes.len() < 2 || bytes[0] != b'(' || bytes[bytes.len() - 1] != b')' {
@@ -55,6 +55,37 @@ fn strip_outer_parens(mut s: &str) -> &str {
     }
 }
 
+/// Formats one operator application from its already-formatted arguments.
+pub(crate) fn combine_op_string<Ops: OperatorSet>(op: OpId, children: &[String], pretty: bool) -> String {
+    let has_infix = Ops::infix(op).is_some();
+    let op_display = if pretty {
+        Ops::display(op)
+    } else {
+        Ops::infix(op).unwrap_or(Ops::name(op))
+    };
+    if has_infix && op.arity > 1 {
+        // A negative base must keep its parentheses: `-2 ^ x` parses as `-(2 ^ x)`.
+        let base_parens = Ops::infix(op) == Some("^") && children.first().is_some_and(|c| c.starts_with('-'));
+        let args = children.iter().enumerate().map(|(i, s)| {
+            if i == 0 && base_parens {
+                format!("({s})")
+            } else {
+                s.clone()
+            }
+        });
+        // Infix form, like {c1} {op} {c2} {op} {c3} ...
+        let joined = args.collect::<Vec<_>>().join(format!(" {op_display} ").as_str());
+        format!("({joined})")
+    } else {
+        let joined = children
+            .iter()
+            .map(|s| strip_outer_parens(s))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("{op_display}({joined})")
+    }
+}
+
 pub fn string_tree<T, Ops, const D: usize>(expr: &PostfixExpr<T, Ops, D>, opts: StringTreeOptions<'_>) -> String
 where
     T: fmt::Display,
@@ -81,21 +112,7 @@ where
         },
         |op, children| {
             debug_assert_eq!(children.len(), op.arity as usize);
-            let has_infix = Ops::infix(op).is_some();
-            let op_display = if opts.pretty {
-                Ops::display(op)
-            } else {
-                Ops::infix(op).unwrap_or(Ops::name(op))
-            };
-            let args = children.iter().map(|s| s.as_str());
-            if has_infix && op.arity > 1 {
-                // Infix form, like {c1} {op} {c2} {op} {c3} ...
-                let joined = args.collect::<Vec<_>>().join(format!(" {op_display} ").as_str());
-                format!("({joined})")
-            } else {
-                let joined = args.map(strip_outer_parens).collect::<Vec<_>>().join(", ");
-                format!("{op_display}({joined})")
-            }
+            combine_op_string::<Ops>(op, children, opts.pretty)
         },
     );
     strip_outer_parens(&out).to_string()
@@ -0,0 +1,198 @@
+mod common;
+
+use common::*;
+use dynamic_expressions::{
+    EvalOptions, GradContext, GraphExpr, GraphNode, PostfixExpr, eval_grad_graph_array, eval_grad_tree_array,
+    eval_graph_array, eval_tree_array, graph_nodes, inherit_sharing, parse_expr, string_graph, string_tree,
+};
+
+const OPTS: EvalOptions = EvalOptions {
+    check_finite: true,
+    early_exit: true,
+};
+
+fn parse(s: &str) -> PostfixExpr<f64, TestOps, 3> {
+    parse_expr(s, &[]).unwrap()
+}
+
+/// `(x0 * c0) + sin(x0 * c0)` with the constant tied and both products tagged as one graph node.
+fn tied_expr() -> PostfixExpr<f64, TestOps, 3> {
+    let mut ex = parse("(x0 * 0.5) + sin(x0 * 0.5)");
+    ex.consts.truncate(1);
+    for n in &mut ex.nodes {
+        if let dynamic_expressions::PNode::Const { idx } = n {
+            *idx = 0;
+        }
+    }
+    ex.meta.graph_ids = vec![0, 1, 2, 0, 1, 2, 3, 4];
+    ex
+}
+
+#[test]
+fn from_postfix_merges_nodes_with_equal_ids() {
+    let ex = tied_expr();
+    let graph = GraphExpr::from_postfix(&ex);
+    // x0, 0.5, mul, sin, add
+    assert_eq!(graph.nodes.len(), 5);
+    assert_eq!(graph.parent_counts()[2], 2);
+    let back = graph.to_postfix();
+    assert_eq!(back.nodes, ex.nodes);
+    assert_eq!(back.consts, ex.consts);
+    assert_eq!(GraphExpr::from_postfix(&back).nodes, graph.nodes);
+
+    // Tagged nodes whose subtrees differ stay separate: with untied constants only `x0` merges.
+    let untied = parse("(x0 * 0.5) + sin(x0 * 0.5)");
+    assert_eq!(graph_nodes::<3>(&untied.nodes, &ex.meta.graph_ids).len(), 7);
+}
+
+#[test]
+fn untagged_repeats_are_not_shared() {
+    let mut ex = tied_expr();
+    ex.meta.graph_ids.clear();
+    assert_eq!(GraphExpr::from_postfix(&ex).nodes.len(), ex.nodes.len());
+    assert_eq!(graph_nodes::<3>(&parse("x0 * x0").nodes, &[]).len(), 3);
+    // A stale id list (e.g. after an edit that changed the node count) is ignored.
+    assert_eq!(graph_nodes::<3>(&ex.nodes, &[0, 1, 2, 0, 1, 2]).len(), ex.nodes.len());
+}
+
+#[test]
+fn edits_keep_the_sharing_of_untouched_nodes() {
+    let tied = tied_expr();
+
+    // Replacing `sin` touches neither product, so they stay one node.
+    let mut child = tied.clone();
+    child.nodes[6] = parse("cos(x0)").nodes[1];
+    inherit_sharing(&tied, &mut child);
+    let graph = GraphExpr::from_postfix(&child);
+    assert_eq!(graph.nodes.len(), 5);
+    assert_eq!(graph.to_string(), "let z0 = x0 * 0.5 in z0 + cos(z0)");
+
+    // A new root over the whole expression keeps it shared.
+    let mut child = tied.clone();
+    child.nodes.insert(0, dynamic_expressions::PNode::Var { feature: 1 });
+    child.nodes.push(parse("x0 + x1").nodes[2]);
+    inherit_sharing(&tied, &mut child);
+    let graph = GraphExpr::from_postfix(&child);
+    assert_eq!(graph.nodes.len(), 7);
+    assert_eq!(graph.to_string(), "let z0 = x0 * 0.5 in x1 + (z0 + sin(z0))");
+
+    // Editing inside one use gives that use its own copy; the other use is no longer shared.
+    let mut child = tied.clone();
+    child.nodes[3] = dynamic_expressions::PNode::Var { feature: 1 };
+    inherit_sharing(&tied, &mut child);
+    let graph = GraphExpr::from_postfix(&child);
+    assert_eq!(graph.to_string(), "(x0 * 0.5) + sin(x1 * 0.5)");
+    // Only the tied constant is still one node.
+    assert_eq!(graph.nodes.len(), 7);
+    let parents = graph.parent_counts();
+    assert!(
+        graph
+            .nodes
+            .iter()
+            .zip(&parents)
+            .all(|(n, &p)| !matches!(n, GraphNode::Op { .. }) || p <= 1)
+    );
+
+    // An unrelated identical subtree created by the edit is not shared with anything.
+    let before = parse("(x0 * x1) + sin(x1)");
+    let mut before_ids = before.clone();
+    before_ids.meta.graph_ids = (0..before.nodes.len() as u32).collect();
+    let mut child = parse("(x0 * x1) + sin(x0 * x1)");
+    inherit_sharing(&before_ids, &mut child);
+    assert!(child.meta.graph_ids.is_empty());
+    assert_eq!(GraphExpr::from_postfix(&child).nodes.len(), child.nodes.len());
+}
+
+#[test]
+fn eval_and_grad_match_tree_evaluation() {
+    let ex = tied_expr();
+    let graph = GraphExpr::from_postfix(&ex);
+    let (_, x) = make_x(1, 17);
+
+    let (tree_out, tree_ok) = eval_tree_array::<f64, TestOps, 3>(&ex, x.view(), &OPTS);
+    let (graph_out, graph_ok) = eval_graph_array(&graph, x.view(), &OPTS);
+    assert!(tree_ok && graph_ok);
+    assert_eq!(tree_out, graph_out);
+
+    let mut ctx = GradContext::<f64, 3>::new(x.ncols());
+    let (_, tree_grad, _) = eval_grad_tree_array::<f64, TestOps, 3>(&ex, x.view(), false, &mut ctx, &OPTS);
+    let (_, graph_grad, ok) = eval_grad_graph_array(&graph, x.view(), &OPTS);
+    assert!(ok);
+    assert_eq!(graph_grad.n_dir, 1);
+    for (a, b) in tree_grad.data.iter().zip(&graph_grad.data) {
+        assert!((a - b).abs() < 1e-12);
+    }
+    // d/dc [x c + sin(x c)] = x (1 + cos(x c))
+    let x0 = x[(0, 3)];
+    assert!((graph_grad.data[3] - x0 * (1.0 + (0.5 * x0).cos())).abs() < 1e-12);
+}
+
+#[test]
+fn leaf_roots_evaluate() {
+    let graph = GraphExpr::from_postfix(&parse("1.5"));
+    let (_, x) = make_x(1, 4);
+    let (out, grad, ok) = eval_grad_graph_array(&graph, x.view(), &OPTS);
+    assert!(ok);
+    assert_eq!(out, vec![1.5; 4]);
+    assert_eq!(grad.data, vec![1.0; 4]);
+}
+
+#[test]
+fn string_graph_uses_let_bindings_for_shared_nodes() {
+    let graph = GraphExpr::from_postfix(&tied_expr());
+    assert_eq!(
+        string_graph(&graph, Default::default()),
+        "let z0 = x0 * 0.5 in z0 + sin(z0)"
+    );
+
+    let plain = parse("x0 * cos(x1 - 3.2)");
+    assert_eq!(
+        GraphExpr::from_postfix(&plain).to_string(),
+        string_tree(&plain, Default::default())
+    );
+}
+
+#[test]
+fn connect_creates_sharing_and_drops_orphans() {
+    // sin(x0) * cos(x1): point the cos argument at sin's node.
+    let mut graph = GraphExpr::from_postfix(&parse("sin(x0) * cos(x1)"));
+    let sin = graph
+        .nodes
+        .iter()
+        .position(|n| matches!(n, GraphNode::Op { arity: 1, .. }))
+        .unwrap();
+    let cos = graph.root() - 1;
+    assert!(graph.connect(cos, 0, sin));
+    assert_eq!(graph.nodes.len(), 4);
+    assert_eq!(graph.to_string(), "let z0 = sin(x0) in z0 * cos(z0)");
+
+    // The expanded tree records the sharing, so it survives a round trip.
+    let tree = graph.to_postfix();
+    assert!(!tree.meta.graph_ids.is_empty());
+    assert_eq!(GraphExpr::from_postfix(&tree).nodes, graph.nodes);
+
+    // Invalid edges are rejected.
+    assert!(!graph.connect(0, 0, 0));
+    assert!(!graph.connect(1, 0, 2));
+}
+
+#[test]
+fn disconnect_copies_constants() {
+    let mut graph = GraphExpr::from_postfix(&tied_expr());
+    let add = graph.root();
+    assert!(graph.disconnect(add, 0));
+    assert_eq!(graph.consts.len(), 2);
+    assert_eq!(graph.to_string(), "(x0 * 0.5) + sin(x0 * 0.5)");
+    let parents = graph.parent_counts();
+    assert!(
+        graph
+            .nodes
+            .iter()
+            .zip(&parents)
+            .all(|(n, &p)| !matches!(n, GraphNode::Op { .. }) || p <= 1)
+    );
+
+    let tree = graph.to_postfix();
+    assert!(tree.meta.graph_ids.is_empty());
+    assert_eq!(GraphExpr::from_postfix(&tree).nodes.len(), tree.nodes.len());
+}
@@ -0,0 +1,131 @@
+mod common;
+
+use common::{TestOps, expr_readme_like, expr_ternary};
+use dynamic_expressions::strings::{StringTreeOptions, string_tree};
+use dynamic_expressions::{PNode, PostfixExpr, parse_expr, parse_postfix_string, postfix_string};
+
+dynamic_expressions::opset! {
+    struct PowOps<f64>;
+    ops {
+        (1, PowOp1) { Neg, }
+        (2, PowOp2) { Add, Mul, Pow, }
+    }
+}
+
+dynamic_expressions::custom_opset! {
+    struct CaretOps<f64> {
+        1 {
+            neg {
+                display: "-",
+                eval(args) { -args[0] },
+                partial(_args, _idx) { -1.0 },
+            }
+        }
+        2 {
+            sub {
+                display: "-",
+                infix: "-",
+                eval(args) { args[0] - args[1] },
+                partial(_args, idx) { if idx == 0 { 1.0 } else { -1.0 } },
+            }
+            pow {
+                display: "^",
+                infix: "^",
+                eval(args) { args[0].powf(args[1]) },
+                partial(args, idx) {
+                    if idx == 0 {
+                        args[1] * args[0].powf(args[1] - 1.0)
+                    } else {
+                        args[0].powf(args[1]) * args[0].ln()
+                    }
+                },
+            }
+        }
+    }
+}
+
+fn parse(s: &str) -> PostfixExpr<f64, TestOps, 3> {
+    parse_expr(s, &[]).unwrap()
+}
+
+#[test]
+fn parse_round_trips_string_tree() {
+    for ex in [expr_readme_like(), expr_ternary()] {
+        let s = string_tree(&ex, StringTreeOptions::default());
+        let parsed = parse(&s);
+        assert_eq!(parsed.nodes, ex.nodes);
+        assert_eq!(parsed.consts, ex.consts);
+        assert_eq!(string_tree(&parsed, StringTreeOptions::default()), s);
+    }
+}
+
+#[test]
+fn parse_respects_precedence_and_unary_minus() {
+    let ex = parse("x0 + x1 * -2.5 - -(x2)");
+    assert_eq!(
+        string_tree(&ex, StringTreeOptions::default()),
+        "(x0 + (x1 * -2.5)) - -(x2)"
+    );
+    assert_eq!(ex.consts, vec![-2.5]);
+}
+
+#[test]
+fn parse_uses_variable_names_and_numbers() {
+    let names = vec!["alpha".to_string(), "beta".to_string()];
+    let ex: PostfixExpr<f64, TestOps, 3> = parse_expr("sin(alpha) / (1e-3 + beta)", &names).unwrap();
+    assert_eq!(ex.nodes[0], PNode::Var { feature: 0 });
+    assert_eq!(ex.consts, vec![1e-3]);
+    assert_eq!(
+        string_tree(&ex, StringTreeOptions::default()),
+        "sin(alpha) / (0.001 + beta)"
+    );
+}
+
+#[test]
+fn parse_caret_maps_to_pow_and_is_right_associative() {
+    let ex: PostfixExpr<f64, PowOps, 2> = parse_expr("x0 ^ 2.0 ^ x1", &[]).unwrap();
+    assert_eq!(string_tree(&ex, StringTreeOptions::default()), "pow(x0, pow(2, x1))");
+}
+
+#[test]
+fn parse_binds_power_tighter_than_unary_minus() {
+    let ex: PostfixExpr<f64, PowOps, 2> = parse_expr("-2.0 ^ 2.0", &[]).unwrap();
+    assert_eq!(string_tree(&ex, StringTreeOptions::default()), "-(pow(2, 2))");
+    let ex: PostfixExpr<f64, PowOps, 2> = parse_expr("(-2.0) ^ 2.0 + -3.0", &[]).unwrap();
+    assert_eq!(string_tree(&ex, StringTreeOptions::default()), "pow(-2, 2) + -3");
+    assert_eq!(ex.consts, vec![-2.0, 2.0, -3.0]);
+
+    // With an infix `^`, a negative base is printed in parentheses so the round trip is exact.
+    let ex: PostfixExpr<f64, CaretOps, 2> = parse_expr("-2 ^ x0", &[]).unwrap();
+    assert_eq!(string_tree(&ex, StringTreeOptions::default()), "neg(2 ^ x0)");
+    for s in ["(-2) ^ x0", "neg(2 ^ x0)", "x0 ^ -2"] {
+        let ex: PostfixExpr<f64, CaretOps, 2> = parse_expr(s, &[]).unwrap();
+        assert_eq!(string_tree(&ex, StringTreeOptions::default()), s);
+    }
+}
+
+#[test]
+fn parse_reports_errors() {
+    for bad in ["x0 +", "sin(x0, x1)", "foo(x0)", "x0 $ x1", "(x0", "y"] {
+        let err = parse_expr::<f64, TestOps, 3>(bad, &[]).unwrap_err();
+        assert!(!err.message.is_empty(), "{bad}");
+    }
+}
+
+#[test]
+fn postfix_string_round_trips_exactly() {
+    let mut ex = expr_ternary();
+    ex.consts[0] = 0.1 + 0.2;
+    let s = postfix_string(&ex);
+    assert_eq!(s, "x0 x1 c0 fma/3 | 0.30000000000000004");
+    let back: PostfixExpr<f64, TestOps, 3> = parse_postfix_string(&s).unwrap();
+    assert_eq!(back.nodes, ex.nodes);
+    assert_eq!(back.consts, ex.consts);
+}
+
+#[test]
+fn parse_postfix_string_rejects_malformed_input() {
+    for bad in ["x0 x1", "x0 add/2 |", "x0 c1 add/2 | 1.0", "x0 nope/1 |", "x0"] {
+        assert!(parse_postfix_string::<f64, TestOps, 3>(bad).is_err(), "{bad}");
+    }
+}
@@ -13,8 +13,8 @@ use rand::rngs::StdRng;
 use rand::{Rng, SeedableRng};
 use rand_distr::StandardNormal;
 use symbolic_regression::{
-    Dataset, Evaluator, MemberId, NextGenerationCtx, Operators, OptimizeConstantsCtx, Options, PopMember, Population,
-    RunningSearchStatistics, TaggedDataset, best_of_sample, check_constraints, equation_search,
+    Dataset, Evaluator, MemberId, MutationStats, NextGenerationCtx, Operators, OptimizeConstantsCtx, Options,
+    PopMember, Population, RunningSearchStatistics, TaggedDataset, best_of_sample, check_constraints, equation_search,
     insert_random_op_in_place, next_generation, optimize_constants, rotate_tree_in_place,
 };
 
@@ -264,6 +264,8 @@ fn bench_utils(c: &mut Criterion) {
                             evaluator: &mut evaluator,
                             next_id: &mut next_id,
                             next_birth: &mut next_birth,
+                            mutation_stats: &mut MutationStats::default(),
+                            genealogy: None,
                             _ops: PhantomData::<Ops>,
                         };
                         let _ = next_generation(member, ctx);
@@ -319,7 +321,7 @@ fn bench_utils(c: &mut Criterion) {
         group.bench_function(BenchmarkId::new("compute_complexity_x10", "u16"), |b| {
             b.iter(|| {
                 for tree in &trees {
-                    let _ = symbolic_regression::compute_complexity(&tree.nodes, &options);
+                    let _ = symbolic_regression::compute_complexity(tree, &options);
                 }
             })
         });
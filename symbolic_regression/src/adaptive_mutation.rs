@@ -0,0 +1,177 @@
+//! Rust-only module (no direct Julia file): online credit assignment for mutation operators.
+//!
+//! Every population records, per [`MutationChoice`], how many children each operator was sampled
+//! for, how many of them `next_generation` accepted, and how many of those were cheaper (lower
+//! cost) than their parent. With `Options::mutation_adaptation` enabled, the sampling weights of
+//! the next mutations are the configured (and structurally conditioned) weights scaled by a bandit
+//! score of each operator's improvement rate, so operators that help on the data at hand are
+//! picked more often. Weights that are zero stay zero.
+
+use crate::mutate::MutationChoice;
+use crate::options::MutationWeights;
+
+/// How `next_generation` reweights mutation operators from their record (see [`MutationStats`]).
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum MutationAdaptation {
+    /// Sample from the configured weights only.
+    #[default]
+    Off,
+    /// Scale each weight by the UCB1 score `rate + exploration * sqrt(ln(N + 1) / (n + 1))`, where
+    /// `rate` is the operator's smoothed improvement rate, `n` its attempts and `N` all attempts.
+    Ucb { exploration: f64 },
+    /// Scale each weight by `exp((rate - best_rate) / temperature)`.
+    Softmax { temperature: f64 },
+}
+
+impl MutationAdaptation {
+    /// Whether the parameters keep every adapted weight finite and non-negative: a positive
+    /// `temperature` and a finite, non-negative `exploration`.
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            MutationAdaptation::Off => true,
+            MutationAdaptation::Ucb { exploration } => exploration.is_finite() && exploration >= 0.0,
+            MutationAdaptation::Softmax { temperature } => temperature > 0.0,
+        }
+    }
+}
+
+/// Outcomes of the children one mutation operator produced.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct MutationCounts {
+    /// Children the operator was sampled for, including failed mutations.
+    pub attempts: u64,
+    /// Children `next_generation` returned as accepted.
+    pub accepted: u64,
+    /// Accepted children with a lower cost than their parent.
+    pub improved: u64,
+}
+
+impl MutationCounts {
+    /// The improvement rate with one pseudo-success and one pseudo-failure, so untried operators
+    /// start at 1/2.
+    pub fn smoothed_improvement_rate(&self) -> f64 {
+        (self.improved as f64 + 1.0) / (self.attempts as f64 + 2.0)
+    }
+}
+
+/// Per-operator [`MutationCounts`] of one population.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MutationStats {
+    counts: [MutationCounts; MutationChoice::ALL.len()],
+    /// Counts of `MutationChoice::Custom(i)`, grown as custom mutations are first recorded.
+    custom: Vec<MutationCounts>,
+}
+
+impl MutationStats {
+    pub fn get(&self, choice: MutationChoice) -> MutationCounts {
+        match choice {
+            MutationChoice::Custom(i) => self.custom.get(i as usize).copied().unwrap_or_default(),
+            builtin => {
+                self.counts[MutationChoice::ALL
+                    .iter()
+                    .position(|&c| c == builtin)
+                    .expect("built-in choice")]
+            }
+        }
+    }
+
+    /// Built-in choices in declaration order, then the custom mutations recorded so far.
+    pub fn iter(&self) -> impl Iterator<Item = (MutationChoice, MutationCounts)> + '_ {
+        let custom = (0..self.custom.len()).map(|i| MutationChoice::Custom(i as u16));
+        MutationChoice::ALL
+            .into_iter()
+            .chain(custom)
+            .zip(self.counts.iter().chain(&self.custom).copied())
+    }
+
+    pub fn total_attempts(&self) -> u64 {
+        self.counts.iter().chain(&self.custom).map(|c| c.attempts).sum()
+    }
+
+    /// Adds `other`'s counts, e.g. to aggregate the populations of a search.
+    pub fn merge(&mut self, other: &MutationStats) {
+        for (choice, o) in other.iter() {
+            let c = self.counts_mut(choice);
+            c.attempts += o.attempts;
+            c.accepted += o.accepted;
+            c.improved += o.improved;
+        }
+    }
+
+    pub(crate) fn record(&mut self, choice: MutationChoice, accepted: bool, improved: bool) {
+        let c = self.counts_mut(choice);
+        c.attempts += 1;
+        c.accepted += u64::from(accepted);
+        c.improved += u64::from(accepted && improved);
+    }
+
+    /// Stats with `counts` in [`MutationStats::iter`] order.
+    pub(crate) fn from_counts(counts: impl IntoIterator<Item = MutationCounts>) -> Self {
+        let mut stats = MutationStats::default();
+        let mut counts = counts.into_iter();
+        for (c, v) in stats.counts.iter_mut().zip(counts.by_ref()) {
+            *c = v;
+        }
+        stats.custom = counts.collect();
+        stats
+    }
+
+    fn counts_mut(&mut self, choice: MutationChoice) -> &mut MutationCounts {
+        match choice {
+            MutationChoice::Custom(i) => {
+                let i = i as usize;
+                if self.custom.len() <= i {
+                    self.custom.resize(i + 1, MutationCounts::default());
+                }
+                &mut self.custom[i]
+            }
+            builtin => {
+                let i = MutationChoice::ALL
+                    .iter()
+                    .position(|&c| c == builtin)
+                    .expect("built-in choice");
+                &mut self.counts[i]
+            }
+        }
+    }
+}
+
+/// Scales `weights`, and the custom-mutation weights `custom`, by the bandit score of each operator
+/// under `adaptation`.
+pub(crate) fn adapt_mutation_weights(
+    weights: &mut MutationWeights,
+    custom: &mut [f64],
+    stats: &MutationStats,
+    adaptation: MutationAdaptation,
+) {
+    // Custom mutations not tried yet still get scored, as untried operators.
+    let choices: Vec<(MutationChoice, MutationCounts)> = MutationChoice::ALL
+        .into_iter()
+        .chain((0..custom.len()).map(|i| MutationChoice::Custom(i as u16)))
+        .map(|choice| (choice, stats.get(choice)))
+        .collect();
+    match adaptation {
+        MutationAdaptation::Off => {}
+        MutationAdaptation::Ucb { exploration } => {
+            let log_total = (stats.total_attempts() as f64 + 1.0).ln();
+            for &(choice, counts) in &choices {
+                let bonus = exploration * (log_total / (counts.attempts as f64 + 1.0)).sqrt();
+                if let Some(w) = choice.weight_mut(weights, custom) {
+                    *w *= counts.smoothed_improvement_rate() + bonus;
+                }
+            }
+        }
+        MutationAdaptation::Softmax { temperature } => {
+            // Shifting by the best rate only keeps `exp` in range; sampling normalizes anyway.
+            let best = choices
+                .iter()
+                .map(|(_, counts)| counts.smoothed_improvement_rate())
+                .fold(0.0, f64::max);
+            for &(choice, counts) in &choices {
+                if let Some(w) = choice.weight_mut(weights, custom) {
+                    *w *= ((counts.smoothed_improvement_rate() - best) / temperature).exp();
+                }
+            }
+        }
+    }
+}
@@ -8,7 +8,7 @@ use ndarray::{Array1, Array2};
 
 use crate::constant_optimization::{OptimizeConstantsCtx, optimize_constants};
 use crate::dataset::TaggedDataset;
-use crate::loss_functions::baseline_loss_from_zero_expression;
+use crate::loss_functions::baseline_loss;
 use crate::optim::{BackTracking, EvalBudget, Objective, OptimOptions, bfgs_minimize};
 use crate::pop_member::Evaluator;
 use crate::{Dataset, MemberId, OperatorLibrary, Options, PopMember};
@@ -125,7 +125,7 @@ pub fn run_constant_opt_linear(env: &ConstantOptLinearEnv) -> (bool, f64, Vec<f6
     let mut evaluator = Evaluator::new(env.dataset.n_rows);
     let mut grad_ctx = dynamic_expressions::GradContext::new(env.dataset.n_rows);
     let baseline_loss = if env.options.use_baseline {
-        baseline_loss_from_zero_expression::<T, Ops, D>(&env.dataset, env.options.loss.as_ref())
+        baseline_loss::<T, Ops, D>(&env.dataset, &env.options)
     } else {
         None
     };
@@ -41,18 +41,29 @@ impl NestedConstraints {
     }
 }
 
+/// How many times `curmaxsize` nodes the expanded tree of a graph expression may have: every new
+/// connection can otherwise double it.
+const MAX_GRAPH_EXPANSION: usize = 4;
+
 pub fn check_constraints<T: Float, Ops, const D: usize>(
     expr: &PostfixExpr<T, Ops, D>,
     options: &Options<T, D>,
     curmaxsize: usize,
 ) -> bool {
     let needs_depth_check = options.maxdepth < curmaxsize  // maxsize is much faster to check, so ideally we skip depth check
-        || !options.uses_default_complexity(); // However, with a custom complexity, we have no idea if we can skip depth check
+        || !options.uses_default_complexity() // However, with a custom complexity, we have no idea if we can skip depth check
+        || options.graph_expressions; // Nor with shared subexpressions, whose tree outgrows the complexity
     if needs_depth_check && node_utils::count_depth(&expr.nodes) > options.maxdepth {
         return false;
     }
+    // With shared subexpressions the tree size overstates complexity; it is checked below instead,
+    // but the tree is still what gets evaluated, so its size stays capped.
+    let tree_size_is_complexity = !options.graph_expressions;
+    if !tree_size_is_complexity && expr.nodes.len() > MAX_GRAPH_EXPANSION * curmaxsize {
+        return false;
+    }
     if options.uses_default_complexity() {
-        if expr.nodes.len() > curmaxsize {
+        if tree_size_is_complexity && expr.nodes.len() > curmaxsize {
             return false;
         }
         if !check_default_op_arg_constraints::<T, D>(&expr.nodes, options) {
@@ -64,10 +75,13 @@ pub fn check_constraints<T: Float, Ops, const D: usize>(
         else {
             return false;
         };
-        if total > curmaxsize {
+        if tree_size_is_complexity && total > curmaxsize {
             return false;
         }
     }
+    if !tree_size_is_complexity && complexity::compute_complexity(expr, options) > curmaxsize {
+        return false;
+    }
 
     check_nested_constraints::<D>(&expr.nodes, &options.nested_constraints)
 }
@@ -1,6 +1,8 @@
 use std::collections::HashMap;
 
 use dynamic_expressions::OpId;
+use dynamic_expressions::expression::PostfixExpr;
+use dynamic_expressions::graph::GraphNode;
 use dynamic_expressions::node::PNode;
 use num_traits::Float;
 
@@ -61,10 +63,45 @@ pub(crate) fn compute_custom_complexity_checked<T: Float, const D: usize>(
     Some(st[0])
 }
 
-pub fn compute_complexity<T: Float, const D: usize>(nodes: &[PNode], options: &Options<T, D>) -> usize {
+/// Complexity with every shared subexpression of `expr` counted once (see
+/// `Options::graph_expressions`).
+fn compute_graph_complexity<T: Float, Ops, const D: usize>(
+    expr: &PostfixExpr<T, Ops, D>,
+    options: &Options<T, D>,
+) -> usize {
+    let graph = dynamic_expressions::graph_nodes::<D>(&expr.nodes, &expr.meta.graph_ids);
+    if options.uses_default_complexity() {
+        return graph.len();
+    }
+    graph
+        .iter()
+        .map(|n| match *n {
+            GraphNode::Var { feature } => options
+                .variable_complexities
+                .as_ref()
+                .and_then(|v| v.get(feature as usize))
+                .copied()
+                .unwrap_or(options.complexity_of_variables) as usize,
+            GraphNode::Const { .. } => options.complexity_of_constants as usize,
+            GraphNode::Op { arity, op, .. } => options
+                .operator_complexity_overrides
+                .get(&OpId { arity, id: op })
+                .copied()
+                .unwrap_or(1) as usize,
+        })
+        .sum()
+}
+
+pub fn compute_complexity<T: Float, Ops, const D: usize>(
+    expr: &PostfixExpr<T, Ops, D>,
+    options: &Options<T, D>,
+) -> usize {
+    if options.graph_expressions {
+        return compute_graph_complexity(expr, options);
+    }
     if options.uses_default_complexity() {
-        return nodes.len();
+        return expr.nodes.len();
     }
 
-    compute_custom_complexity_checked::<T, D>(nodes, options, None).unwrap_or(0)
+    compute_custom_complexity_checked::<T, D>(&expr.nodes, options, None).unwrap_or(0)
 }
@@ -1,16 +1,87 @@
 use std::ops::AddAssign;
 
 use dynamic_expressions::utils::ZipEq;
-use dynamic_expressions::{EvalOptions, GradContext, OperatorSet};
+use dynamic_expressions::{DiffContext, EvalOptions, GradContext, GradMatrix, GraphExpr, OperatorSet};
 use fastrand::Rng;
+use ndarray::ArrayView2;
 use num_traits::{Float, FromPrimitive, ToPrimitive};
 
 use crate::dataset::{Dataset, TaggedDataset};
-use crate::optim::{BackTracking, Objective, OptimOptions, bfgs_minimize, newton_1d_minimize};
-use crate::options::Options;
-use crate::pop_member::{Evaluator, PopMember};
+use crate::full_objective::PostfixExprEvaluator;
+use crate::linear_scaling::LinearScaling;
+use crate::optim::{
+    BackTracking, LeastSquaresObjective, Objective, OptimOptions, bfgs_minimize, cma_es_minimize, gradient_is_reliable,
+    levenberg_marquardt, nelder_mead_minimize, newton_1d_minimize,
+};
+use crate::options::{OptimizerAlgorithm, Options};
+use crate::pop_member::{Evaluator, PopMember, dimensional_penalty};
 use crate::random::standard_normal;
 
+/// With `Options::linear_scaling`, applies the scaling fitted to `yhat` on `dataset` and returns
+/// its scale, by which the gradients of the scaled loss are multiplied (the offset and scale being
+/// optimal, their own dependence on the constants does not contribute); otherwise returns one.
+fn scale_outputs<T: Float, const D: usize>(options: &Options<T, D>, dataset: &Dataset<T>, yhat: &mut [T]) -> T {
+    if !options.linear_scaling {
+        return T::one();
+    }
+    let scaling = LinearScaling::fit(yhat, dataset.y_slice(), dataset.weights_slice(), options.loss.as_ref());
+    scaling.apply(yhat);
+    scaling.scale
+}
+
+/// The graph of `expr` if it shares subexpressions (see `Options::graph_expressions`), evaluated
+/// instead of its plan so each shared node is computed once.
+fn graph_of<T: Float, Ops, const D: usize>(
+    expr: &dynamic_expressions::expression::PostfixExpr<T, Ops, D>,
+) -> Option<GraphExpr<T, Ops, D>> {
+    (!expr.meta.graph_ids.is_empty()).then(|| GraphExpr::from_postfix(expr))
+}
+
+/// Evaluates `expr` into `evaluator.yhat`, through `graph` if it has one.
+fn predict_into<T: Float + AddAssign, Ops, const D: usize>(
+    evaluator: &mut Evaluator<T, D>,
+    eval_opts: &EvalOptions,
+    plan: &dynamic_expressions::EvalPlan<D>,
+    graph: Option<&GraphExpr<T, Ops, D>>,
+    expr: &dynamic_expressions::expression::PostfixExpr<T, Ops, D>,
+    x: ArrayView2<'_, T>,
+) -> bool
+where
+    Ops: OperatorSet<T = T>,
+{
+    let Some(graph) = graph else {
+        return dynamic_expressions::eval_plan_array_into(
+            &mut evaluator.yhat,
+            plan,
+            expr,
+            x,
+            &mut evaluator.scratch,
+            eval_opts,
+        );
+    };
+    let (yhat, ok) = dynamic_expressions::eval_graph_array(graph, x, eval_opts);
+    evaluator.yhat.copy_from_slice(&yhat);
+    ok
+}
+
+/// The outputs of `expr` and their gradient with respect to its constants, through `graph` if it
+/// has one.
+fn grad_consts<T: Float + AddAssign, Ops, const D: usize>(
+    grad_ctx: &mut GradContext<T, D>,
+    eval_opts: &EvalOptions,
+    graph: Option<&GraphExpr<T, Ops, D>>,
+    expr: &dynamic_expressions::expression::PostfixExpr<T, Ops, D>,
+    x: ArrayView2<'_, T>,
+) -> (Vec<T>, GradMatrix<T>, bool)
+where
+    Ops: OperatorSet<T = T>,
+{
+    match graph {
+        Some(graph) => dynamic_expressions::eval_grad_graph_array(graph, x, eval_opts),
+        None => dynamic_expressions::eval_grad_tree_array(expr, x, false, grad_ctx, eval_opts),
+    }
+}
+
 struct EvalWorkspace<'a, T: Float + AddAssign, const D: usize> {
     dataset: &'a Dataset<T>,
     options: &'a Options<T, D>,
@@ -18,6 +89,10 @@ struct EvalWorkspace<'a, T: Float + AddAssign, const D: usize> {
     grad_ctx: &'a mut GradContext<T, D>,
     eval_opts: EvalOptions,
     dloss_dyhat: Vec<T>,
+    /// Current parametric-expression parameters and the dataset extended with their rows (see
+    /// `Dataset::with_parameter_rows`); optimized jointly after the constants.
+    parameters: Vec<T>,
+    parametric: Option<(Dataset<T>, DiffContext<T, D>)>,
 }
 
 impl<'a, T: Float + AddAssign, const D: usize> EvalWorkspace<'a, T, D> {
@@ -39,34 +114,132 @@ impl<'a, T: Float + AddAssign, const D: usize> EvalWorkspace<'a, T, D> {
             grad_ctx,
             eval_opts,
             dloss_dyhat: vec![T::zero(); dataset.n_rows],
+            parameters: Vec::new(),
+            parametric: None,
+        }
+    }
+
+    fn with_parameters(mut self, parameters: &[T]) -> Self {
+        if !parameters.is_empty() && self.dataset.classes.is_some() {
+            self.parameters = parameters.to_vec();
+            let data = self.dataset.with_parameter_rows(parameters);
+            self.parametric = Some((data, DiffContext::new(self.dataset.n_rows)));
+        }
+        self
+    }
+
+    /// The dataset expressions are evaluated on (with parameter rows in parametric mode).
+    fn data(&self) -> &Dataset<T> {
+        self.parametric.as_ref().map_or(self.dataset, |(data, _)| data)
+    }
+
+    fn set_parameters(&mut self, values: &[f64]) -> Option<()>
+    where
+        T: FromPrimitive,
+    {
+        let Some((data, _)) = self.parametric.as_mut() else {
+            return Some(());
+        };
+        for (dst, &src) in self.parameters.iter_mut().zip_eq(values) {
+            *dst = T::from_f64(src)?;
         }
+        data.write_parameter_rows(self.dataset.n_features, &self.parameters);
+        Some(())
+    }
+
+    /// Adds d(loss)/d(parameter) to `grad_out` (one entry per parameter and class).
+    fn parameter_grad<Ops>(
+        &mut self,
+        expr: &dynamic_expressions::expression::PostfixExpr<T, Ops, D>,
+        grad_out: &mut [f64],
+    ) -> Option<()>
+    where
+        Ops: OperatorSet<T = T>,
+    {
+        let Some((data, diff_ctx)) = self.parametric.as_mut() else {
+            return Some(());
+        };
+        let classes = data.classes.as_deref().expect("parametric dataset has classes");
+        let n_classes = data.n_classes;
+        for (k, grad_k) in grad_out.chunks_mut(n_classes).enumerate() {
+            let direction = self.dataset.n_features + k;
+            let (_, der, ok) =
+                dynamic_expressions::eval_diff_tree_array(expr, data.x.view(), direction, diff_ctx, &self.eval_opts);
+            if !ok {
+                return None;
+            }
+            let mut acc = vec![T::zero(); n_classes];
+            for ((&c, &dl), &dd) in classes.iter().zip_eq(&self.dloss_dyhat).zip_eq(&der) {
+                acc[c] += dl * dd;
+            }
+            for (g, a) in grad_k.iter_mut().zip_eq(acc) {
+                *g = a.to_f64().unwrap_or(f64::INFINITY);
+            }
+        }
+        Some(())
+    }
+
+    /// Central differences of the full objective with respect to the parameters.
+    fn parameter_grad_full_objective<Ops>(
+        &mut self,
+        expr: &mut dynamic_expressions::expression::PostfixExpr<T, Ops, D>,
+        grad_out: &mut [f64],
+    ) where
+        Ops: OperatorSet<T = T>,
+    {
+        let Some(objective) = self.options.full_objective.as_ref() else {
+            return;
+        };
+        let Some((data, _)) = self.parametric.as_mut() else {
+            return;
+        };
+        let two = T::one() + T::one();
+        let sqrt_eps = T::epsilon().sqrt();
+        let first_row = self.dataset.n_features;
+        for (j, g) in grad_out.iter_mut().enumerate() {
+            let p = self.parameters[j];
+            let h = sqrt_eps * p.abs().max(T::one());
+            self.parameters[j] = p + h;
+            data.write_parameter_rows(first_row, &self.parameters);
+            let f_plus = objective.loss(&mut PostfixExprEvaluator::new(expr), data);
+            self.parameters[j] = p - h;
+            data.write_parameter_rows(first_row, &self.parameters);
+            let f_minus = objective.loss(&mut PostfixExprEvaluator::new(expr), data);
+            self.parameters[j] = p;
+            *g = ((f_plus - f_minus) / (two * h)).to_f64().unwrap_or(f64::INFINITY);
+        }
+        data.write_parameter_rows(first_row, &self.parameters);
     }
 
     fn loss_only<Ops>(
         &mut self,
         plan: &dynamic_expressions::EvalPlan<D>,
-        expr: &dynamic_expressions::expression::PostfixExpr<T, Ops, D>,
+        graph: Option<&GraphExpr<T, Ops, D>>,
+        expr: &mut dynamic_expressions::expression::PostfixExpr<T, Ops, D>,
     ) -> Option<f64>
     where
         Ops: OperatorSet<T = T>,
     {
-        let ok = dynamic_expressions::eval_plan_array_into(
-            &mut self.evaluator.yhat,
-            plan,
-            expr,
-            self.dataset.x.view(),
-            &mut self.evaluator.scratch,
-            &self.eval_opts,
-        );
-        if !ok {
+        let data = match &self.parametric {
+            Some((data, _)) => data,
+            None => self.dataset,
+        };
+        let penalty = dimensional_penalty(expr, self.dataset, self.options);
+        if let Some(objective) = self.options.full_objective.as_ref() {
+            let loss = objective.loss(&mut PostfixExprEvaluator::new(expr), data) + penalty;
+            return loss.is_finite().then(|| loss.to_f64().unwrap_or(f64::INFINITY));
+        }
+
+        if !predict_into(self.evaluator, &self.eval_opts, plan, graph, expr, data.x.view()) {
             return None;
         }
+        scale_outputs(self.options, self.dataset, &mut self.evaluator.yhat);
 
         let loss = self.options.loss.loss(
             &self.evaluator.yhat,
             self.dataset.y.as_slice().unwrap(),
             self.dataset.weights.as_ref().and_then(|w| w.as_slice()),
-        );
+        ) + penalty;
         if !loss.is_finite() {
             return None;
         }
@@ -74,35 +247,56 @@ impl<'a, T: Float + AddAssign, const D: usize> EvalWorkspace<'a, T, D> {
         Some(loss.to_f64().unwrap_or(f64::INFINITY))
     }
 
+    /// Loss and its gradient with respect to the free constants followed by the parameters. The
+    /// dimensional penalty is piecewise constant in them, so it adds to the loss but not the
+    /// gradient.
     fn loss_and_grad<Ops>(
         &mut self,
-        _plan: &dynamic_expressions::EvalPlan<D>,
-        expr: &dynamic_expressions::expression::PostfixExpr<T, Ops, D>,
+        graph: Option<&GraphExpr<T, Ops, D>>,
+        expr: &mut dynamic_expressions::expression::PostfixExpr<T, Ops, D>,
         grad_out: &mut [f64],
     ) -> Option<f64>
     where
         Ops: OperatorSet<T = T>,
     {
-        let n_params = expr.consts.len();
+        // Constants beyond the free ones are fixed (template combiner constants).
+        let n_consts = grad_out.len() - self.parameters.len();
+        let (const_grad, param_grad) = grad_out.split_at_mut(n_consts);
+        let penalty = dimensional_penalty(expr, self.dataset, self.options);
+        if let Some(objective) = self.options.full_objective.as_ref() {
+            let mut grad = vec![T::zero(); expr.consts.len()];
+            let loss = objective.loss_and_grad(&mut PostfixExprEvaluator::new(expr), self.data(), &mut grad) + penalty;
+            if !loss.is_finite() {
+                return None;
+            }
+            for (gout, &g) in const_grad.iter_mut().zip_eq(&grad[..n_consts]) {
+                *gout = g.to_f64().unwrap_or(f64::INFINITY);
+            }
+            self.parameter_grad_full_objective(expr, param_grad);
+            return Some(loss.to_f64().unwrap_or(f64::INFINITY));
+        }
+
         let n_rows = self.dataset.n_rows;
-        debug_assert_eq!(grad_out.len(), n_params);
         debug_assert_eq!(self.dloss_dyhat.len(), n_rows);
 
-        let x = self.dataset.x.view();
-        let (yhat, dy_dc, ok) =
-            dynamic_expressions::eval_grad_tree_array(expr, x, false, self.grad_ctx, &self.eval_opts);
+        let x = match &self.parametric {
+            Some((data, _)) => data.x.view(),
+            None => self.dataset.x.view(),
+        };
+        let (mut yhat, dy_dc, ok) = grad_consts(self.grad_ctx, &self.eval_opts, graph, expr, x);
         if !ok {
             return None;
         }
         if yhat.iter().any(|v| !v.is_finite()) {
             return None;
         }
+        let output_scale = scale_outputs(self.options, self.dataset, &mut yhat);
 
         let loss = self.options.loss.loss(
             &yhat,
             self.dataset.y.as_slice().unwrap(),
             self.dataset.weights.as_ref().and_then(|w| w.as_slice()),
-        );
+        ) + penalty;
         if !loss.is_finite() {
             return None;
         }
@@ -113,11 +307,11 @@ impl<'a, T: Float + AddAssign, const D: usize> EvalWorkspace<'a, T, D> {
             self.dataset.weights.as_ref().and_then(|w| w.as_slice()),
             &mut self.dloss_dyhat,
         );
+        for dl in &mut self.dloss_dyhat {
+            *dl = *dl * output_scale;
+        }
 
-        for (ci, gout) in grad_out.iter_mut().enumerate() {
-            if ci >= n_params {
-                break;
-            }
+        for (ci, gout) in const_grad.iter_mut().enumerate() {
             let base = ci * n_rows;
             let acc = self
                 .dloss_dyhat
@@ -127,32 +321,164 @@ impl<'a, T: Float + AddAssign, const D: usize> EvalWorkspace<'a, T, D> {
                 .fold(T::zero(), |a, (dl, dc)| a + dl * dc);
             *gout = acc.to_f64().unwrap_or(f64::INFINITY);
         }
+        self.parameter_grad(expr, param_grad)?;
 
         Some(loss.to_f64().unwrap_or(f64::INFINITY))
     }
 
+    /// The algorithm to fit `member` with from `x0`: `options.optimizer_algorithm`, with
+    /// Levenberg–Marquardt falling back to BFGS when the loss does not support it and `Auto`
+    /// resolved. Also returns the evaluations spent checking the gradient for `Auto`.
+    fn algorithm<Ops>(&mut self, member: &mut PopMember<T, Ops, D>, x0: &[f64]) -> (OptimizerAlgorithm, usize)
+    where
+        T: FromPrimitive,
+        Ops: OperatorSet<T = T>,
+    {
+        let derivative_free = if x0.len() <= 4 {
+            OptimizerAlgorithm::NelderMead
+        } else {
+            OptimizerAlgorithm::CmaEs
+        };
+        match self.options.optimizer_algorithm {
+            OptimizerAlgorithm::LevenbergMarquardt
+                if self.options.full_objective.is_some() || !self.options.loss.is_least_squares() =>
+            {
+                (OptimizerAlgorithm::Bfgs, 0)
+            }
+            OptimizerAlgorithm::Auto if self.options.full_objective.is_none() && !self.options.loss.is_smooth() => {
+                (derivative_free, 0)
+            }
+            OptimizerAlgorithm::Auto => {
+                let precision = T::epsilon().to_f64().unwrap_or(f64::EPSILON);
+                let mut budget = crate::optim::EvalBudget::default();
+                let mut obj = ConstObjective::new(&member.plan, &mut member.expr, self);
+                let reliable = gradient_is_reliable(x0, &mut obj, precision, &mut budget);
+                let algorithm = if reliable {
+                    OptimizerAlgorithm::Bfgs
+                } else {
+                    derivative_free
+                };
+                (algorithm, budget.f_calls)
+            }
+            algorithm => (algorithm, 0),
+        }
+    }
+
+    /// Per-row residuals `sqrt(w_i / sum(w)) * (yhat_i - y_i)`, whose squares sum to the mean
+    /// squared error, and optionally their Jacobian over the free constants and the parameters.
+    /// They leave out the dimensional penalty; the minimum is re-reported through `loss_only`.
+    fn residuals<Ops>(
+        &mut self,
+        plan: &dynamic_expressions::EvalPlan<D>,
+        graph: Option<&GraphExpr<T, Ops, D>>,
+        expr: &mut dynamic_expressions::expression::PostfixExpr<T, Ops, D>,
+        r_out: &mut [f64],
+        jac_out: Option<&mut [f64]>,
+    ) -> Option<()>
+    where
+        Ops: OperatorSet<T = T>,
+    {
+        let n_rows = self.dataset.n_rows;
+        let y = self.dataset.y.as_slice().unwrap();
+        let weights = self.dataset.weights.as_ref().and_then(|w| w.as_slice());
+        let sum_w = weights.map_or(n_rows as f64, |w| w.iter().map(|v| v.to_f64().unwrap_or(0.0)).sum());
+        let scale = |i: usize| {
+            let w = weights.map_or(1.0, |w| w[i].to_f64().unwrap_or(0.0));
+            (w / sum_w).sqrt()
+        };
+
+        let x = match &self.parametric {
+            Some((data, _)) => data.x.view(),
+            None => self.dataset.x.view(),
+        };
+        let Some(jac) = jac_out else {
+            if !predict_into(self.evaluator, &self.eval_opts, plan, graph, expr, x) {
+                return None;
+            }
+            scale_outputs(self.options, self.dataset, &mut self.evaluator.yhat);
+            for (i, r) in r_out.iter_mut().enumerate() {
+                *r = scale(i) * (self.evaluator.yhat[i] - y[i]).to_f64()?;
+            }
+            return Some(());
+        };
+
+        let (mut yhat, dy_dc, ok) = grad_consts(self.grad_ctx, &self.eval_opts, graph, expr, x);
+        if !ok || yhat.iter().any(|v| !v.is_finite()) {
+            return None;
+        }
+        let output_scale = scale_outputs(self.options, self.dataset, &mut yhat)
+            .to_f64()
+            .unwrap_or(1.0);
+        let n = jac.len() / n_rows;
+        let n_consts = n - self.parameters.len();
+        jac.fill(0.0);
+        for (i, (r, row)) in r_out.iter_mut().zip_eq(jac.chunks_exact_mut(n)).enumerate() {
+            let s = scale(i);
+            *r = s * (yhat[i] - y[i]).to_f64()?;
+            for (ci, j) in row[..n_consts].iter_mut().enumerate() {
+                *j = s * output_scale * dy_dc.data[ci * n_rows + i].to_f64()?;
+            }
+        }
+
+        if let Some((data, diff_ctx)) = self.parametric.as_mut() {
+            let classes = data.classes.as_deref().expect("parametric dataset has classes");
+            let n_classes = data.n_classes;
+            for k in 0..self.parameters.len() / n_classes {
+                let direction = self.dataset.n_features + k;
+                let (_, der, ok) = dynamic_expressions::eval_diff_tree_array(
+                    expr,
+                    data.x.view(),
+                    direction,
+                    diff_ctx,
+                    &self.eval_opts,
+                );
+                if !ok {
+                    return None;
+                }
+                for (i, (&c, d)) in classes.iter().zip_eq(&der).enumerate() {
+                    jac[i * n + n_consts + k * n_classes + c] = scale(i) * output_scale * d.to_f64()?;
+                }
+            }
+        }
+        Some(())
+    }
+
+    /// Derivative-free algorithms get `n + 1` iterations per configured one, as each of their
+    /// iterations (a simplex step or a CMA-ES generation) learns far less than a gradient step.
     fn optimize_from_start<Ops>(
         &mut self,
         start: &[f64],
-        n_params: usize,
         member: &mut PopMember<T, Ops, D>,
+        algorithm: OptimizerAlgorithm,
         optim_opts: OptimOptions,
         ls: BackTracking,
+        rng: &mut Rng,
     ) -> Option<crate::optim::OptimResult>
     where
         T: FromPrimitive,
         Ops: OperatorSet<T = T>,
     {
-        let mut obj = ConstObjective {
-            plan: &member.plan,
-            expr: &mut member.expr,
-            workspace: self,
-        };
+        let mut obj = ConstObjective::new(&member.plan, &mut member.expr, self);
 
-        if n_params == 1 {
-            newton_1d_minimize(start[0], &mut obj, optim_opts, ls)
-        } else {
-            bfgs_minimize(start, &mut obj, optim_opts, ls)
+        let derivative_free_opts = OptimOptions {
+            iterations: optim_opts.iterations.saturating_mul(start.len() + 1),
+            ..optim_opts
+        };
+        match algorithm {
+            OptimizerAlgorithm::LevenbergMarquardt => {
+                let mut res = levenberg_marquardt(start, &mut obj, optim_opts)?;
+                // Report the search loss (e.g. RMSE), not the residual sum of squares.
+                let mut budget = crate::optim::EvalBudget::default();
+                res.minimum = obj.f_only(&res.minimizer, &mut budget)?;
+                res.f_calls += budget.f_calls;
+                Some(res)
+            }
+            OptimizerAlgorithm::NelderMead => nelder_mead_minimize(start, &mut obj, derivative_free_opts),
+            OptimizerAlgorithm::CmaEs => cma_es_minimize(start, &mut obj, derivative_free_opts, rng),
+            OptimizerAlgorithm::Bfgs | OptimizerAlgorithm::Auto if start.len() == 1 => {
+                newton_1d_minimize(start[0], &mut obj, optim_opts, ls)
+            }
+            OptimizerAlgorithm::Bfgs | OptimizerAlgorithm::Auto => bfgs_minimize(start, &mut obj, optim_opts, ls),
         }
     }
 }
@@ -160,9 +486,41 @@ impl<'a, T: Float + AddAssign, const D: usize> EvalWorkspace<'a, T, D> {
 struct ConstObjective<'plan, 'expr, 'work, 'data, T: Float + AddAssign, Ops, const D: usize> {
     plan: &'plan dynamic_expressions::EvalPlan<D>,
     expr: &'expr mut dynamic_expressions::expression::PostfixExpr<T, Ops, D>,
+    /// The graph of `expr` if it shares subexpressions, built once and evaluated instead of `plan`.
+    graph: Option<GraphExpr<T, Ops, D>>,
     workspace: &'work mut EvalWorkspace<'data, T, D>,
 }
 
+impl<'plan, 'expr, 'work, 'data, T: Float + FromPrimitive + AddAssign, Ops, const D: usize>
+    ConstObjective<'plan, 'expr, 'work, 'data, T, Ops, D>
+{
+    fn new(
+        plan: &'plan dynamic_expressions::EvalPlan<D>,
+        expr: &'expr mut dynamic_expressions::expression::PostfixExpr<T, Ops, D>,
+        workspace: &'work mut EvalWorkspace<'data, T, D>,
+    ) -> Self {
+        Self {
+            plan,
+            graph: graph_of(expr),
+            expr,
+            workspace,
+        }
+    }
+
+    /// Writes `x` (free constants, then parameters) into the expression, its graph and the
+    /// workspace.
+    fn set_params(&mut self, x: &[f64]) -> Option<()> {
+        let (consts, parameters) = x.split_at(x.len() - self.workspace.parameters.len());
+        for (dst, &src) in self.expr.consts[..consts.len()].iter_mut().zip_eq(consts) {
+            *dst = T::from_f64(src)?;
+        }
+        if let Some(graph) = self.graph.as_mut() {
+            graph.consts[..consts.len()].copy_from_slice(&self.expr.consts[..consts.len()]);
+        }
+        self.workspace.set_parameters(parameters)
+    }
+}
+
 impl<'plan, 'expr, 'work, 'data, T: Float + FromPrimitive + AddAssign, Ops, const D: usize> Objective
     for ConstObjective<'plan, 'expr, 'work, 'data, T, Ops, D>
 where
@@ -170,18 +528,39 @@ where
 {
     fn f_only(&mut self, x: &[f64], budget: &mut crate::optim::EvalBudget) -> Option<f64> {
         budget.f_calls += 1;
-        for (dst, &src) in self.expr.consts.iter_mut().zip_eq(x) {
-            *dst = T::from_f64(src)?;
-        }
-        self.workspace.loss_only::<Ops>(self.plan, self.expr)
+        self.set_params(x)?;
+        self.workspace
+            .loss_only::<Ops>(self.plan, self.graph.as_ref(), self.expr)
     }
 
     fn fg(&mut self, x: &[f64], g_out: &mut [f64], budget: &mut crate::optim::EvalBudget) -> Option<f64> {
         budget.f_calls += 1;
-        for (dst, &src) in self.expr.consts.iter_mut().zip_eq(x) {
-            *dst = T::from_f64(src)?;
-        }
-        self.workspace.loss_and_grad::<Ops>(self.plan, self.expr, g_out)
+        self.set_params(x)?;
+        self.workspace
+            .loss_and_grad::<Ops>(self.graph.as_ref(), self.expr, g_out)
+    }
+}
+
+impl<'plan, 'expr, 'work, 'data, T: Float + FromPrimitive + AddAssign, Ops, const D: usize> LeastSquaresObjective
+    for ConstObjective<'plan, 'expr, 'work, 'data, T, Ops, D>
+where
+    Ops: OperatorSet<T = T>,
+{
+    fn n_residuals(&self) -> usize {
+        self.workspace.dataset.n_rows
+    }
+
+    fn residuals(
+        &mut self,
+        x: &[f64],
+        r_out: &mut [f64],
+        jac_out: Option<&mut [f64]>,
+        budget: &mut crate::optim::EvalBudget,
+    ) -> Option<()> {
+        budget.f_calls += 1;
+        self.set_params(x)?;
+        self.workspace
+            .residuals::<Ops>(self.plan, self.graph.as_ref(), self.expr, r_out, jac_out)
     }
 }
 
@@ -205,24 +584,31 @@ where
     if !options.should_optimize_constants {
         return (false, 0.0);
     }
-    let n_params = member.expr.consts.len();
+    let mut workspace =
+        EvalWorkspace::new(dataset_ref, options, evaluator, grad_ctx).with_parameters(&member.parameters);
+    let n_consts = member.n_free_consts();
+    let n_params = n_consts + workspace.parameters.len();
     if n_params == 0 {
         return (false, 0.0);
     }
 
     let orig_consts = member.expr.consts.clone();
+    let orig_parameters = member.parameters.clone();
     let orig_birth = member.birth;
     let orig_loss = member.loss;
     let orig_cost = member.cost;
+    let orig_scaling = member.scaling;
 
-    let mut workspace = EvalWorkspace::new(dataset_ref, options, evaluator, grad_ctx);
-
-    let baseline = match workspace.loss_only::<Ops>(&member.plan, &member.expr) {
+    let baseline = match workspace.loss_only::<Ops>(&member.plan, graph_of(&member.expr).as_ref(), &mut member.expr) {
         Some(v) => v,
         None => return (false, 0.0),
     };
 
-    let x0: Vec<f64> = member.expr.consts.iter().map(|v| v.to_f64().unwrap_or(0.0)).collect();
+    let x0: Vec<f64> = member.expr.consts[..n_consts]
+        .iter()
+        .chain(&workspace.parameters)
+        .map(|v| v.to_f64().unwrap_or(0.0))
+        .collect();
 
     let mut best_x = x0.clone();
     let mut best_f = baseline;
@@ -234,10 +620,11 @@ where
     };
     let ls = BackTracking::default();
 
-    let mut n_evals: u64 = 0;
+    let (algorithm, check_evals) = workspace.algorithm(member, &x0);
+    let mut n_evals = check_evals as u64;
 
     {
-        let res = workspace.optimize_from_start(&x0, n_params, member, optim_opts, ls);
+        let res = workspace.optimize_from_start(&x0, member, algorithm, optim_opts, ls, rng);
         if let Some(res) = res {
             n_evals = n_evals.saturating_add(res.f_calls as u64);
             if res.minimum < best_f {
@@ -255,7 +642,7 @@ where
             *v *= 1.0 + 0.5 * eps;
         }
 
-        let res = workspace.optimize_from_start(&xt, n_params, member, optim_opts, ls);
+        let res = workspace.optimize_from_start(&xt, member, algorithm, optim_opts, ls, rng);
         if let Some(res) = res {
             n_evals = n_evals.saturating_add(res.f_calls as u64);
             if res.minimum < best_f {
@@ -266,17 +653,27 @@ where
     }
 
     if best_f < baseline {
-        for (dst, &src) in member.expr.consts.iter_mut().zip_eq(&best_x) {
+        let (best_consts, best_parameters) = best_x.split_at(n_consts);
+        for (dst, &src) in member.expr.consts[..n_consts].iter_mut().zip_eq(best_consts) {
             *dst = T::from_f64(src).unwrap_or_else(T::zero);
         }
+        if !best_parameters.is_empty() {
+            member.parameters = best_parameters
+                .iter()
+                .map(|&v| T::from_f64(v).unwrap_or_else(T::zero))
+                .collect();
+        }
         let ok = member.evaluate(&dataset, options, evaluator);
         if !ok {
             member.expr.consts = orig_consts;
+            member.parameters = orig_parameters;
             member.birth = orig_birth;
             member.loss = orig_loss;
             member.cost = orig_cost;
+            member.scaling = orig_scaling;
             return (false, n_evals as f64);
         }
+        member.sync_subexpr_consts();
         n_evals = n_evals.saturating_add(1);
         member.birth = *next_birth;
         *next_birth += 1;
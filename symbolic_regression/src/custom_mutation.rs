@@ -0,0 +1,71 @@
+//! Rust-only module (no direct Julia file): user-defined mutation operators.
+//!
+//! A [`Mutation`] registered in `Options::custom_mutations` with a weight is sampled alongside the
+//! built-in mutations (as [`MutationChoice::Custom`](crate::MutationChoice::Custom)) and its child
+//! goes through the same constraint checks, evaluation and annealing acceptance. Since `Options` is
+//! not generic over the operator set, the expression is handed over with the operator type erased;
+//! operators are identified by their `OpId`s, e.g. from `Options::operators`.
+
+use std::sync::Arc;
+
+use dynamic_expressions::expression::PostfixExpr;
+use dynamic_expressions::node::PNode;
+use dynamic_expressions::node_utils::is_valid_postfix;
+use fastrand::Rng;
+use num_traits::Float;
+
+use crate::dataset::Dataset;
+use crate::operators::Operators;
+
+/// What a [`Mutation`] may use besides the expression.
+pub struct MutationContext<'a, T: Float, const D: usize> {
+    pub rng: &'a mut Rng,
+    pub operators: &'a Operators<D>,
+    /// Features the expression may use (a template sub-expression's own subset).
+    pub n_features: usize,
+    /// The current size limit; children above it are rejected after the mutation.
+    pub curmaxsize: usize,
+    /// Annealing temperature, from 1 at the start of a cycle down to 0 with `annealing`.
+    pub temperature: f64,
+    /// The rows the child will be scored on (the current mini-batch with `batching`).
+    pub dataset: &'a Dataset<T>,
+}
+
+/// A domain-specific mutation, e.g. a rewrite between physically equivalent forms.
+pub trait Mutation<T: Float, const D: usize>: Send + Sync {
+    /// Changes `expr` in place and returns whether it changed. The result must be a well-formed
+    /// postfix expression over `ctx.n_features` features, its own constants and `ctx.operators`;
+    /// anything else counts as a failed mutation. Constraints are checked by the caller, which
+    /// retries a failed or constraint-violating mutation a few times like the built-in ones.
+    fn mutate(&self, expr: &mut PostfixExpr<T, (), D>, ctx: &mut MutationContext<'_, T, D>) -> bool;
+
+    /// The weight to sample this mutation with for an expression with `nodes`, given its
+    /// registered `weight`; e.g. zero when the rewrite cannot apply. Defaults to `weight`.
+    fn condition_weight(&self, weight: f64, nodes: &[PNode]) -> f64 {
+        let _ = nodes;
+        weight
+    }
+}
+
+pub type MutationObject<T, const D: usize> = Arc<dyn Mutation<T, D>>;
+
+/// Whether `expr` is a non-empty, balanced postfix expression whose variables, constants and
+/// operators all exist: features below `n_features`, indices into `expr.consts` and operators of
+/// `operators`.
+pub(crate) fn is_well_formed<T: Float, const D: usize>(
+    expr: &PostfixExpr<T, (), D>,
+    operators: &Operators<D>,
+    n_features: usize,
+) -> bool {
+    let known = |node: &PNode| match *node {
+        PNode::Var { feature } => usize::from(feature) < n_features,
+        PNode::Const { idx } => usize::from(idx) < expr.consts.len(),
+        PNode::Op { arity, op } => {
+            (1..=D).contains(&usize::from(arity))
+                && operators.ops_by_arity[usize::from(arity) - 1]
+                    .iter()
+                    .any(|o| o.id == op)
+        }
+    };
+    is_valid_postfix(&expr.nodes) && expr.nodes.iter().all(known)
+}
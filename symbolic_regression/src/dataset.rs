@@ -1,9 +1,60 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
 use dynamic_expressions::utils::ZipEq;
 use fastrand::Rng;
 use ndarray::{Array1, Array2};
 use num_traits::Float;
 
-use crate::random::usize_range;
+use crate::dimensional_analysis::Units;
+use crate::random::{shuffle, usize_range};
+
+/// Row indices of a train/validation split.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SplitIndices {
+    pub train: Vec<usize>,
+    pub val: Vec<usize>,
+}
+
+impl SplitIndices {
+    /// Random split holding out `round(validation_fraction * n_rows)` rows for validation
+    /// (`validation_fraction` is clamped to `[0, 0.9]`).
+    pub fn random(n_rows: usize, validation_fraction: f64, seed: u64) -> Self {
+        Self::random_groups(n_rows, 1, validation_fraction, seed)
+    }
+
+    /// Like [`SplitIndices::random`], but splitting whole groups of `rows_per_group` consecutive
+    /// rows, e.g. the samples of a softmax cross-entropy loss; rows keep their order within a group.
+    pub fn random_groups(n_rows: usize, rows_per_group: usize, validation_fraction: f64, seed: u64) -> Self {
+        assert!(
+            rows_per_group > 0 && n_rows.is_multiple_of(rows_per_group),
+            "n_rows ({n_rows}) must be a multiple of rows_per_group ({rows_per_group})"
+        );
+        let n_groups = n_rows / rows_per_group;
+        let vf = validation_fraction.clamp(0.0, 0.9);
+        let n_val = ((vf * (n_groups as f64)).round() as usize).min(n_groups);
+        if n_val == 0 {
+            return Self {
+                train: (0..n_rows).collect(),
+                val: Vec::new(),
+            };
+        }
+
+        let mut idx: Vec<usize> = (0..n_groups).collect();
+        let mut rng = Rng::with_seed(seed ^ 0x6a09_e667_f3bc_c909);
+        shuffle(&mut rng, &mut idx);
+
+        let rows = |groups: &[usize]| {
+            groups
+                .iter()
+                .flat_map(|&g| g * rows_per_group..(g + 1) * rows_per_group)
+                .collect()
+        };
+        Self {
+            train: rows(&idx[n_val..]),
+            val: rows(&idx[..n_val]),
+        }
+    }
+}
 
 #[derive(Copy, Clone, Debug)]
 pub struct TaggedDataset<'a, T: Float> {
@@ -36,6 +87,38 @@ pub struct Dataset<T: Float> {
     pub variable_names: Vec<String>,
     /// Weighted mean of `y` (or unweighted mean when no weights).
     pub avg_y: T,
+    /// Physical units of each feature, for dimensional-analysis constraints.
+    pub x_units: Option<Vec<Units>>,
+    /// Physical units of `y`.
+    pub y_units: Option<Units>,
+    /// Class (e.g. experiment) of each row, for parametric expressions (see `Options::n_parameters`).
+    pub classes: Option<Vec<usize>>,
+    /// Number of classes; class ids are `0..n_classes`.
+    pub n_classes: usize,
+    /// Identifies the contents for buffers derived from them (see `ParameterRows`); renewed by
+    /// every method that changes the dataset.
+    content_id: u64,
+}
+
+/// A fresh `Dataset::content_id`.
+fn next_content_id() -> u64 {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    NEXT.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Datasets extended with a parametric member's parameter rows (see
+/// [`Dataset::with_parameter_rows`]), one per source dataset, so that evaluating another member on
+/// the same source only rewrites the parameter rows.
+#[derive(Debug)]
+pub struct ParameterRows<T: Float> {
+    /// `(source content id, extended dataset)`.
+    extended: Vec<(u64, Dataset<T>)>,
+}
+
+impl<T: Float> Default for ParameterRows<T> {
+    fn default() -> Self {
+        Self { extended: Vec::new() }
+    }
 }
 
 impl<T: Float> Dataset<T> {
@@ -64,6 +147,11 @@ impl<T: Float> Dataset<T> {
             weights,
             variable_names,
             avg_y,
+            x_units: None,
+            y_units: None,
+            classes: None,
+            n_classes: 0,
+            content_id: next_content_id(),
         }
     }
 
@@ -80,6 +168,154 @@ impl<T: Float> Dataset<T> {
         Self::build_dataset(x, y, weights, variable_names, None)
     }
 
+    /// Attaches physical units to the features and/or target (see [`crate::Units`]).
+    pub fn with_units(mut self, x_units: Option<Vec<Units>>, y_units: Option<Units>) -> Self {
+        if let Some(ref u) = x_units {
+            assert_eq!(u.len(), self.n_features, "x_units must have one entry per feature");
+        }
+        self.x_units = x_units;
+        self.y_units = y_units;
+        self.content_id = next_content_id();
+        self
+    }
+
+    pub fn has_units(&self) -> bool {
+        self.x_units.is_some() || self.y_units.is_some()
+    }
+
+    /// Assigns each row to a class for parametric expressions; classes are `0..=max(classes)`.
+    pub fn with_classes(self, classes: Vec<usize>) -> Self {
+        let n_classes = classes.iter().max().map_or(0, |&c| c + 1);
+        self.with_class_column(Some(classes), n_classes)
+    }
+
+    fn with_class_column(mut self, classes: Option<Vec<usize>>, n_classes: usize) -> Self {
+        if let Some(ref c) = classes {
+            assert_eq!(c.len(), self.n_rows, "classes must have one entry per row");
+        }
+        self.classes = classes;
+        self.n_classes = n_classes;
+        self.content_id = next_content_id();
+        self
+    }
+
+    /// A copy with one extra feature row per parameter, holding each row's class-specific value.
+    ///
+    /// `parameters` is parameter-major: parameter `k` of class `c` is `parameters[k * n_classes + c]`.
+    pub(crate) fn with_parameter_rows(&self, parameters: &[T]) -> Self {
+        let n_parameters = parameters.len() / self.n_classes.max(1);
+        let mut x = Array2::<T>::zeros((self.n_features + n_parameters, self.n_rows));
+        x.slice_mut(ndarray::s![..self.n_features, ..]).assign(&self.x);
+        let mut out = Self {
+            x,
+            y: self.y.clone(),
+            n_features: self.n_features + n_parameters,
+            n_rows: self.n_rows,
+            weights: self.weights.clone(),
+            variable_names: self.variable_names.clone(),
+            avg_y: self.avg_y,
+            x_units: self.x_units.clone(),
+            y_units: self.y_units,
+            classes: self.classes.clone(),
+            n_classes: self.n_classes,
+            content_id: next_content_id(),
+        };
+        out.write_parameter_rows(self.n_features, parameters);
+        out
+    }
+
+    /// [`Dataset::with_parameter_rows`] into `buffers`. The dataset extended from this one is kept
+    /// there, and later calls only rewrite its parameter rows; a new source reuses the allocations
+    /// of one with the same shape.
+    pub(crate) fn parameter_rows_into<'b>(&self, parameters: &[T], buffers: &'b mut ParameterRows<T>) -> &'b Self {
+        let n_features = self.n_features + parameters.len() / self.n_classes.max(1);
+        let extended = &mut buffers.extended;
+        let same_shape = |(_, b): &(u64, Self)| b.n_rows == self.n_rows && b.n_features == n_features;
+        let i = match extended.iter().position(|(source, _)| *source == self.content_id) {
+            Some(i) => i,
+            None => match extended.iter().position(same_shape) {
+                Some(i) => {
+                    let (source, out) = &mut extended[i];
+                    *source = self.content_id;
+                    out.x.slice_mut(ndarray::s![..self.n_features, ..]).assign(&self.x);
+                    out.y.assign(&self.y);
+                    out.weights.clone_from(&self.weights);
+                    out.variable_names.clone_from(&self.variable_names);
+                    out.avg_y = self.avg_y;
+                    out.x_units.clone_from(&self.x_units);
+                    out.y_units = self.y_units;
+                    out.classes.clone_from(&self.classes);
+                    out.n_classes = self.n_classes;
+                    i
+                }
+                None => {
+                    extended.push((self.content_id, self.with_parameter_rows(parameters)));
+                    extended.len() - 1
+                }
+            },
+        };
+        let out = &mut extended[i].1;
+        out.write_parameter_rows(self.n_features, parameters);
+        out
+    }
+
+    /// Overwrites the parameter rows (starting at feature `first_row`) of a dataset built by
+    /// [`Dataset::with_parameter_rows`].
+    pub(crate) fn write_parameter_rows(&mut self, first_row: usize, parameters: &[T]) {
+        self.content_id = next_content_id();
+        let classes = self.classes.as_ref().expect("parametric dataset has classes");
+        for (k, values) in parameters.chunks(self.n_classes.max(1)).enumerate() {
+            for (dst, &c) in self.x.row_mut(first_row + k).iter_mut().zip_eq(classes) {
+                *dst = values[c];
+            }
+        }
+    }
+
+    /// Copies the given rows (in order) into a new dataset with the same features and names.
+    pub fn select_rows(&self, indices: &[usize]) -> Self {
+        let mut x = Array2::<T>::zeros((self.n_features, indices.len()));
+        let mut y = Array1::<T>::zeros(indices.len());
+        let mut weights = self.weights.as_ref().map(|_| Array1::<T>::zeros(indices.len()));
+        for &i in indices {
+            assert!(i < self.n_rows, "row index out of range: {i} (n_rows={})", self.n_rows);
+        }
+        let classes = self.classes.as_ref().map(|c| indices.iter().map(|&i| c[i]).collect());
+        for (i_new, &i_old) in indices.iter().enumerate() {
+            x.column_mut(i_new).assign(&self.x.column(i_old));
+            y[i_new] = self.y[i_old];
+            if let (Some(dst), Some(src)) = (weights.as_mut(), self.weights.as_ref()) {
+                dst[i_new] = src[i_old];
+            }
+        }
+        Self::build_dataset(x, y, weights, self.variable_names.clone(), None)
+            .with_units(self.x_units.clone(), self.y_units)
+            .with_class_column(classes, self.n_classes)
+    }
+
+    /// Copies the given feature rows (in order) into a new dataset with the same rows.
+    pub fn select_features(&self, features: &[usize]) -> Self {
+        let mut x = Array2::<T>::zeros((features.len(), self.n_rows));
+        for (f_new, &f_old) in features.iter().enumerate() {
+            x.row_mut(f_new).assign(&self.x.row(f_old));
+        }
+        let names = if self.variable_names.is_empty() {
+            Vec::new()
+        } else {
+            features.iter().map(|&f| self.variable_names[f].clone()).collect()
+        };
+        let x_units = self.x_units.as_ref().map(|u| features.iter().map(|&f| u[f]).collect());
+        Self::build_dataset(x, self.y.clone(), self.weights.clone(), names, Some(self.avg_y))
+            .with_units(x_units, self.y_units)
+            .with_class_column(self.classes.clone(), self.n_classes)
+    }
+
+    /// Splits into a training dataset and, if `split.val` is non-empty, a validation dataset.
+    pub fn split_train_val(&self, split: &SplitIndices) -> (Self, Option<Self>) {
+        let train = self.select_rows(&split.train);
+        let val = (!split.val.is_empty()).then(|| self.select_rows(&split.val));
+        (train, val)
+    }
+
     pub fn y_slice(&self) -> &[T] {
         self.y.as_slice().expect("y is contiguous")
     }
@@ -117,10 +353,19 @@ impl<T: Float> Dataset<T> {
         let x = Array2::<T>::zeros((full.n_features, batch_size));
         let y = Array1::<T>::zeros(batch_size);
         let weights = full.weights.as_ref().map(|_| Array1::<T>::zeros(batch_size));
+        let classes = full.classes.as_ref().map(|_| vec![0; batch_size]);
         Self::build_dataset(x, y, weights, full.variable_names.clone(), Some(full.avg_y))
+            .with_units(full.x_units.clone(), full.y_units)
+            .with_class_column(classes, full.n_classes)
     }
 
     pub fn resample_from(&mut self, full: &Dataset<T>, rng: &mut Rng) {
+        self.resample_groups_from(full, 1, rng);
+    }
+
+    /// Like [`Dataset::resample_from`], drawing whole groups of `rows_per_group` consecutive rows
+    /// (e.g. the samples of a softmax cross-entropy loss). The batch size must be a multiple of it.
+    pub fn resample_groups_from(&mut self, full: &Dataset<T>, rows_per_group: usize, rng: &mut Rng) {
         if full.n_rows == 0 {
             panic!("Cannot batch from an empty dataset (n_rows = 0).");
         }
@@ -135,12 +380,121 @@ impl<T: Float> Dataset<T> {
             assert!(full.weights.is_none());
         }
 
-        for (dst_col, src_idx) in (0..self.n_rows).map(|i| (i, usize_range(rng, 0..full.n_rows))) {
+        assert!(
+            rows_per_group > 0
+                && self.n_rows.is_multiple_of(rows_per_group)
+                && full.n_rows.is_multiple_of(rows_per_group),
+            "batch and dataset sizes must be multiples of rows_per_group ({rows_per_group})"
+        );
+
+        let n_groups = full.n_rows / rows_per_group;
+        let mut group_start = 0;
+        for dst_col in 0..self.n_rows {
+            let offset = dst_col % rows_per_group;
+            if offset == 0 {
+                group_start = usize_range(rng, 0..n_groups) * rows_per_group;
+            }
+            let src_idx = group_start + offset;
             self.x.column_mut(dst_col).assign(&full.x.column(src_idx));
             self.y[dst_col] = full.y[src_idx];
             if let (Some(dst), Some(src)) = (self.weights.as_mut(), full.weights.as_ref()) {
                 dst[dst_col] = src[src_idx];
             }
+            if let (Some(dst), Some(src)) = (self.classes.as_mut(), full.classes.as_ref()) {
+                dst[dst_col] = src[src_idx];
+            }
+        }
+        self.content_id = next_content_id();
+    }
+}
+
+/// Features shared by several targets, for [`crate::equation_search_multi_output`].
+#[derive(Clone, Debug)]
+pub struct MultiOutputDataset<T: Float> {
+    /// Column-major features with shape `(n_features, n_rows)`, as in [`Dataset::x`].
+    pub x: Array2<T>,
+    /// Targets with shape `(n_outputs, n_rows)`; row `k` is the target of output `k`.
+    pub y: Array2<T>,
+    /// Row weights of each output, with the same shape as `y`.
+    pub weights: Option<Array2<T>>,
+    pub variable_names: Vec<String>,
+    /// Physical units of each feature, shared by all outputs.
+    pub x_units: Option<Vec<Units>>,
+    /// Physical units of each output's target.
+    pub y_units: Option<Vec<Units>>,
+    /// Class of each row, shared by all outputs (see [`Dataset::classes`]).
+    pub classes: Option<Vec<usize>>,
+    pub n_classes: usize,
+}
+
+impl<T: Float> MultiOutputDataset<T> {
+    pub fn new(x: Array2<T>, y: Array2<T>) -> Self {
+        Self::with_weights_and_names(x, y, None, Vec::new())
+    }
+
+    pub fn with_weights_and_names(
+        x: Array2<T>,
+        y: Array2<T>,
+        weights: Option<Array2<T>>,
+        variable_names: Vec<String>,
+    ) -> Self {
+        assert_eq!(y.ncols(), x.ncols(), "y must have one column per row");
+        if let Some(ref w) = weights {
+            assert_eq!(w.dim(), y.dim(), "weights must have the same shape as y");
+        }
+        Self {
+            x,
+            y,
+            weights,
+            variable_names,
+            x_units: None,
+            y_units: None,
+            classes: None,
+            n_classes: 0,
+        }
+    }
+
+    /// Attaches physical units to the features and/or each output's target (see [`crate::Units`]).
+    pub fn with_units(mut self, x_units: Option<Vec<Units>>, y_units: Option<Vec<Units>>) -> Self {
+        if let Some(ref u) = x_units {
+            assert_eq!(u.len(), self.x.nrows(), "x_units must have one entry per feature");
+        }
+        if let Some(ref u) = y_units {
+            assert_eq!(u.len(), self.n_outputs(), "y_units must have one entry per output");
         }
+        self.x_units = x_units;
+        self.y_units = y_units;
+        self
+    }
+
+    /// Assigns each row to a class for parametric expressions, as [`Dataset::with_classes`].
+    pub fn with_classes(mut self, classes: Vec<usize>) -> Self {
+        assert_eq!(classes.len(), self.x.ncols(), "classes must have one entry per row");
+        self.n_classes = classes.iter().max().map_or(0, |&c| c + 1);
+        self.classes = Some(classes);
+        self
+    }
+
+    pub fn n_outputs(&self) -> usize {
+        self.y.nrows()
+    }
+
+    /// One single-output dataset per output, each with its own target, weights and target units.
+    pub fn outputs(&self) -> Vec<Dataset<T>> {
+        (0..self.n_outputs())
+            .map(|k| {
+                let weights = self.weights.as_ref().map(|w| w.row(k).to_owned());
+                let y_units = self.y_units.as_ref().map(|u| u[k]);
+                Dataset::build_dataset(
+                    self.x.clone(),
+                    self.y.row(k).to_owned(),
+                    weights,
+                    self.variable_names.clone(),
+                    None,
+                )
+                .with_units(self.x_units.clone(), y_units)
+                .with_class_column(self.classes.clone(), self.n_classes)
+            })
+            .collect()
     }
 }
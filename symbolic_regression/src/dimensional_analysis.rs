@@ -0,0 +1,257 @@
+//! Dimensional analysis over physical units (port of SymbolicRegression.jl's
+//! `DimensionalAnalysis`).
+//!
+//! Units are exponent vectors over the SI base dimensions. [`violates_dimensional_constraints`]
+//! propagates them through an expression: `+`/`-` (and `max`/`min`/`clamp`) require matching units,
+//! `*`/`/` combine them, `sqrt`/`cbrt`/`pow` scale them, and other functions require dimensionless
+//! arguments. Constants are dimensionless; unless `dimensionless_constants_only` is set they are
+//! also wildcards that take on whatever units their context requires.
+
+use dynamic_expressions::node::PNode;
+use dynamic_expressions::{OpId, OperatorSet, PostfixExpr};
+use num_traits::Float;
+
+/// Symbols of the SI base dimensions, in exponent-vector order.
+pub const SI_BASE_SYMBOLS: [&str; 7] = ["m", "kg", "s", "A", "K", "mol", "cd"];
+
+const EXPONENT_TOL: f64 = 1e-9;
+
+/// Physical units as exponents of the SI base dimensions (see [`SI_BASE_SYMBOLS`]).
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct Units {
+    pub exponents: [f64; 7],
+}
+
+impl Units {
+    pub const DIMENSIONLESS: Self = Self { exponents: [0.0; 7] };
+
+    pub fn new(exponents: [f64; 7]) -> Self {
+        Self { exponents }
+    }
+
+    /// Parses products of unit symbols with optional exponents, e.g. `"kg*m^2/s^2"` or `"m s^-1"`.
+    ///
+    /// Accepts the SI base symbols plus `g`, `N`, `J`, `W`, `Pa`, `Hz`, `C` and `V`; prefixes and
+    /// scale factors are not supported. `""` and `"1"` are dimensionless.
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut out = Self::DIMENSIONLESS;
+        let mut divide = false;
+        let mut rest = s.trim();
+        while !rest.is_empty() {
+            let end = rest.find(['*', '/', ' ']).unwrap_or(rest.len());
+            let factor = &rest[..end];
+            if !factor.is_empty() {
+                let (symbol, power) = match factor.split_once('^') {
+                    Some((symbol, power)) => (symbol, power.trim_matches(['(', ')']).parse::<f64>().ok()?),
+                    None => (factor, 1.0),
+                };
+                let units = Self::from_symbol(symbol)?.powf(power);
+                out = if divide { out / units } else { out * units };
+                divide = false;
+            }
+            if rest[end..].starts_with('/') {
+                divide = true;
+            }
+            rest = rest.get(end + 1..).unwrap_or("").trim_start();
+        }
+        Some(out)
+    }
+
+    fn from_symbol(symbol: &str) -> Option<Self> {
+        if let Some(i) = SI_BASE_SYMBOLS.iter().position(|&b| b == symbol) {
+            let mut exponents = [0.0; 7];
+            exponents[i] = 1.0;
+            return Some(Self { exponents });
+        }
+        let e = |m: f64, kg: f64, s: f64, a: f64| Some(Self::new([m, kg, s, a, 0.0, 0.0, 0.0]));
+        match symbol {
+            "1" => Some(Self::DIMENSIONLESS),
+            "g" => e(0.0, 1.0, 0.0, 0.0),
+            "N" => e(1.0, 1.0, -2.0, 0.0),
+            "J" => e(2.0, 1.0, -2.0, 0.0),
+            "W" => e(2.0, 1.0, -3.0, 0.0),
+            "Pa" => e(-1.0, 1.0, -2.0, 0.0),
+            "Hz" => e(0.0, 0.0, -1.0, 0.0),
+            "C" => e(0.0, 0.0, 1.0, 1.0),
+            "V" => e(2.0, 1.0, -3.0, -1.0),
+            _ => None,
+        }
+    }
+
+    pub fn is_dimensionless(&self) -> bool {
+        self.matches(&Self::DIMENSIONLESS)
+    }
+
+    /// Equality up to floating-point error in the exponents.
+    pub fn matches(&self, other: &Self) -> bool {
+        self.exponents
+            .iter()
+            .zip(other.exponents.iter())
+            .all(|(a, b)| (a - b).abs() <= EXPONENT_TOL)
+    }
+
+    pub fn powf(self, p: f64) -> Self {
+        Self::new(self.exponents.map(|e| e * p))
+    }
+}
+
+impl std::ops::Mul for Units {
+    type Output = Self;
+    fn mul(self, other: Self) -> Self {
+        Self::new(std::array::from_fn(|i| self.exponents[i] + other.exponents[i]))
+    }
+}
+
+impl std::ops::Div for Units {
+    type Output = Self;
+    fn div(self, other: Self) -> Self {
+        Self::new(std::array::from_fn(|i| self.exponents[i] - other.exponents[i]))
+    }
+}
+
+/// Units of one subexpression during propagation.
+#[derive(Copy, Clone, Debug)]
+struct DimState {
+    units: Units,
+    /// Free units (an unconstrained constant, or built only from such constants).
+    wildcard: bool,
+    /// The value, if this subexpression is a single constant (needed for `pow`).
+    constant: Option<f64>,
+}
+
+impl DimState {
+    fn dimensionless(wildcard: bool) -> Self {
+        Self {
+            units: Units::DIMENSIONLESS,
+            wildcard,
+            constant: None,
+        }
+    }
+
+    fn with_units(units: Units, wildcard: bool) -> Self {
+        Self {
+            units,
+            wildcard,
+            constant: None,
+        }
+    }
+
+    fn accepts_dimensionless(&self) -> bool {
+        self.wildcard || self.units.is_dimensionless()
+    }
+}
+
+/// Returns the combined state when `args` must all share units, or `None` on a mismatch.
+fn unify(args: &[DimState]) -> Option<DimState> {
+    let fixed: Vec<&DimState> = args.iter().filter(|a| !a.wildcard).collect();
+    match fixed.split_first() {
+        None => Some(DimState::dimensionless(true)),
+        Some((first, rest)) => rest
+            .iter()
+            .all(|a| a.units.matches(&first.units))
+            .then(|| DimState::with_units(first.units, false)),
+    }
+}
+
+/// Applies one operator; `None` means its arguments violate the unit rules.
+fn apply_op<Ops: OperatorSet>(op: OpId, args: &[DimState]) -> Option<DimState> {
+    let product = |a: &DimState, b: &DimState, divide: bool| {
+        let units = if divide { a.units / b.units } else { a.units * b.units };
+        DimState::with_units(units, a.wildcard || b.wildcard)
+    };
+    let scaled = |a: &DimState, p: f64| DimState::with_units(a.units.powf(p), a.wildcard);
+    match (Ops::name(op), args) {
+        ("neg" | "abs" | "identity", [a]) => Some(DimState { constant: None, ..*a }),
+        ("abs2" | "square", [a]) => Some(scaled(a, 2.0)),
+        ("cube", [a]) => Some(scaled(a, 3.0)),
+        ("sqrt", [a]) => Some(scaled(a, 0.5)),
+        ("cbrt", [a]) => Some(scaled(a, 1.0 / 3.0)),
+        ("inv", [a]) => Some(scaled(a, -1.0)),
+        ("sign", [_]) => Some(DimState::dimensionless(false)),
+        ("add" | "sub" | "max" | "min", [_, _]) => unify(args),
+        ("clamp", [_, _, _]) => unify(args),
+        ("mul", [a, b]) => Some(product(a, b, false)),
+        ("div", [a, b]) => Some(product(a, b, true)),
+        ("atan2", [_, _]) => unify(args).map(|_| DimState::dimensionless(false)),
+        ("fma", [a, b, c]) => unify(&[product(a, b, false), *c]),
+        ("pow", [base, exponent]) => {
+            if !exponent.accepts_dimensionless() {
+                return None;
+            }
+            match exponent.constant {
+                Some(p) if !base.wildcard => Some(scaled(base, p)),
+                _ if base.accepts_dimensionless() => Some(DimState::dimensionless(base.wildcard)),
+                _ => None,
+            }
+        }
+        _ => args
+            .iter()
+            .all(DimState::accepts_dimensionless)
+            .then(|| DimState::dimensionless(false)),
+    }
+}
+
+/// Whether `expr` is dimensionally inconsistent with the given feature and target units.
+///
+/// Features without units (or beyond `x_units`) are dimensionless; `y_units = None` leaves the
+/// output unconstrained.
+pub fn violates_dimensional_constraints<T, Ops, const D: usize>(
+    expr: &PostfixExpr<T, Ops, D>,
+    x_units: Option<&[Units]>,
+    y_units: Option<&Units>,
+    dimensionless_constants_only: bool,
+) -> bool
+where
+    T: Float,
+    Ops: OperatorSet,
+{
+    violates_parametric_dimensional_constraints(expr, x_units, usize::MAX, y_units, dimensionless_constants_only)
+}
+
+/// [`violates_dimensional_constraints`] for a parametric expression (see `Options::n_parameters`):
+/// features from `n_features` on are its parameters, which take any units, like constants.
+pub(crate) fn violates_parametric_dimensional_constraints<T, Ops, const D: usize>(
+    expr: &PostfixExpr<T, Ops, D>,
+    x_units: Option<&[Units]>,
+    n_features: usize,
+    y_units: Option<&Units>,
+    dimensionless_constants_only: bool,
+) -> bool
+where
+    T: Float,
+    Ops: OperatorSet,
+{
+    let mut stack: Vec<DimState> = Vec::with_capacity(expr.nodes.len());
+    for node in &expr.nodes {
+        let state = match *node {
+            PNode::Var { feature } if usize::from(feature) >= n_features => {
+                DimState::dimensionless(!dimensionless_constants_only)
+            }
+            PNode::Var { feature } => {
+                let units = x_units
+                    .and_then(|u| u.get(usize::from(feature)))
+                    .copied()
+                    .unwrap_or(Units::DIMENSIONLESS);
+                DimState::with_units(units, false)
+            }
+            PNode::Const { idx } => DimState {
+                constant: expr.consts[usize::from(idx)].to_f64(),
+                ..DimState::dimensionless(!dimensionless_constants_only)
+            },
+            PNode::Op { arity, op } => {
+                let args = stack.split_off(stack.len() - usize::from(arity));
+                match apply_op::<Ops>(OpId { arity, id: op }, &args) {
+                    Some(state) => state,
+                    None => return true,
+                }
+            }
+        };
+        stack.push(state);
+    }
+    let root = stack.pop().expect("expression has a root");
+    match y_units {
+        Some(y) => !root.wildcard && !root.units.matches(y),
+        None => false,
+    }
+}
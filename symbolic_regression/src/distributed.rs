@@ -0,0 +1,676 @@
+//! Multi-process search over TCP or Unix sockets.
+//!
+//! A coordinator ([`crate::equation_search_distributed`]) owns the hall of fame, the running
+//! statistics and migration, and hands one population at a time to worker processes
+//! ([`run_worker`]). Each task is the unit `equation_search_parallel` schedules on its Rayon pool:
+//! one evolution phase plus one optimize/simplify phase of a single population.
+//!
+//! Closures (losses, objectives) cannot cross process boundaries, so every worker is started with
+//! the same dataset and `Options` as the coordinator and only populations (plus the hall-of-fame
+//! crossover donors) travel over the wire.
+//!
+//! Messages are length-prefixed UTF-8 frames (a big-endian `u32` byte count, then the text). A
+//! worker opens with `hello <version> <n_features> <n_rows>`, then answers each `task` frame with a
+//! `result` frame until it reads `shutdown` or the connection closes. Members are one tab-separated
+//! line each, with expressions written by operator name (see [`postfix_string`]) and scalars as the
+//! hex bits of their `f64` value, so a round trip is exact.
+
+use std::fmt::{self, Display};
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::ops::AddAssign;
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use dynamic_expressions::{ParseError, parse_postfix_string, postfix_string};
+use fastrand::Rng;
+use num_traits::{Float, FromPrimitive, ToPrimitive};
+
+use crate::adaptive_mutation::{MutationCounts, MutationStats};
+use crate::adaptive_parsimony::RunningSearchStatistics;
+use crate::dataset::{Dataset, TaggedDataset};
+use crate::feature_selection::preselected_features;
+use crate::genealogy::{Genealogy, GenealogyEvent, GenealogyEventKind};
+use crate::hall_of_fame::HallOfFame;
+use crate::linear_scaling::LinearScaling;
+use crate::loss_functions::baseline_loss;
+use crate::mutate::MutationChoice;
+use crate::options::Options;
+use crate::pop_member::{Evaluator, MemberId, PopMember};
+use crate::population::Population;
+use crate::search_utils::{PopState, SearchTaskResult, execute_task};
+
+const PROTOCOL_VERSION: u32 = 7;
+
+#[derive(Debug)]
+pub enum DistributedError {
+    Io(io::Error),
+    /// A frame could not be decoded, or arrived out of order.
+    Protocol(String),
+    /// A member's expression failed to parse.
+    Expression(ParseError),
+    /// A worker was started with a different protocol version or dataset shape.
+    WorkerMismatch {
+        expected: String,
+        found: String,
+    },
+}
+
+impl fmt::Display for DistributedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DistributedError::Io(e) => write!(f, "{e}"),
+            DistributedError::Protocol(msg) => write!(f, "protocol error: {msg}"),
+            DistributedError::Expression(e) => write!(f, "invalid expression: {e}"),
+            DistributedError::WorkerMismatch { expected, found } => {
+                write!(f, "worker mismatch: expected {expected:?}, found {found:?}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DistributedError {}
+
+impl From<io::Error> for DistributedError {
+    fn from(e: io::Error) -> Self {
+        DistributedError::Io(e)
+    }
+}
+
+fn protocol(msg: impl Into<String>) -> DistributedError {
+    DistributedError::Protocol(msg.into())
+}
+
+/// Where the coordinator listens. Parses from and prints as `tcp:<addr>` or `unix:<path>`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Endpoint {
+    Tcp(SocketAddr),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Endpoint::Tcp(addr) => write!(f, "tcp:{addr}"),
+            #[cfg(unix)]
+            Endpoint::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+impl FromStr for Endpoint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(addr) = s.strip_prefix("tcp:") {
+            return addr.parse().map(Endpoint::Tcp).map_err(|e| format!("{s:?}: {e}"));
+        }
+        #[cfg(unix)]
+        if let Some(path) = s.strip_prefix("unix:") {
+            return Ok(Endpoint::Unix(path.into()));
+        }
+        Err(format!("{s:?}: expected tcp:<addr> or unix:<path>"))
+    }
+}
+
+/// A bound coordinator socket, waiting for workers to connect.
+pub struct WorkerListener {
+    inner: ListenerKind,
+}
+
+enum ListenerKind {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener, PathBuf),
+}
+
+impl WorkerListener {
+    /// Binds `endpoint`. Use port 0 for an ephemeral TCP port and read it back with
+    /// [`WorkerListener::endpoint`]. A Unix socket file is removed again when the listener drops.
+    pub fn bind(endpoint: &Endpoint) -> io::Result<Self> {
+        let inner = match endpoint {
+            Endpoint::Tcp(addr) => ListenerKind::Tcp(TcpListener::bind(addr)?),
+            #[cfg(unix)]
+            Endpoint::Unix(path) => ListenerKind::Unix(UnixListener::bind(path)?, path.clone()),
+        };
+        Ok(Self { inner })
+    }
+
+    /// The endpoint workers should connect to.
+    pub fn endpoint(&self) -> io::Result<Endpoint> {
+        match &self.inner {
+            ListenerKind::Tcp(l) => l.local_addr().map(Endpoint::Tcp),
+            #[cfg(unix)]
+            ListenerKind::Unix(_, path) => Ok(Endpoint::Unix(path.clone())),
+        }
+    }
+
+    pub(crate) fn accept(&self) -> io::Result<Connection> {
+        match &self.inner {
+            ListenerKind::Tcp(l) => {
+                let (stream, _) = l.accept()?;
+                stream.set_nodelay(true)?;
+                Ok(Connection::Tcp(stream))
+            }
+            #[cfg(unix)]
+            ListenerKind::Unix(l, _) => l.accept().map(|(stream, _)| Connection::Unix(stream)),
+        }
+    }
+}
+
+impl Drop for WorkerListener {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let ListenerKind::Unix(_, path) = &self.inner {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+pub(crate) enum Connection {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Connection {
+    fn connect(endpoint: &Endpoint) -> io::Result<Self> {
+        match endpoint {
+            Endpoint::Tcp(addr) => {
+                let stream = TcpStream::connect(addr)?;
+                stream.set_nodelay(true)?;
+                Ok(Connection::Tcp(stream))
+            }
+            #[cfg(unix)]
+            Endpoint::Unix(path) => UnixStream::connect(path).map(Connection::Unix),
+        }
+    }
+
+    pub(crate) fn write_frame(&mut self, text: &str) -> io::Result<()> {
+        let len =
+            u32::try_from(text.len()).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "frame too large"))?;
+        self.write_all(&len.to_be_bytes())?;
+        self.write_all(text.as_bytes())?;
+        self.flush()
+    }
+
+    /// The next frame, or `None` if the peer closed the connection between frames.
+    pub(crate) fn read_frame(&mut self) -> io::Result<Option<String>> {
+        let mut len = [0u8; 4];
+        match self.read_exact(&mut len) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        let mut buf = vec![0u8; u32::from_be_bytes(len) as usize];
+        self.read_exact(&mut buf)?;
+        String::from_utf8(buf)
+            .map(Some)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Connection::Tcp(s) => s.read(buf),
+            #[cfg(unix)]
+            Connection::Unix(s) => s.read(buf),
+        }
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Connection::Tcp(s) => s.write(buf),
+            #[cfg(unix)]
+            Connection::Unix(s) => s.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Connection::Tcp(s) => s.flush(),
+            #[cfg(unix)]
+            Connection::Unix(s) => s.flush(),
+        }
+    }
+}
+
+pub(crate) const SHUTDOWN: &str = "shutdown";
+
+pub(crate) fn hello_line(dataset: &Dataset<impl Float>) -> String {
+    format!("hello {PROTOCOL_VERSION} {} {}", dataset.n_features, dataset.n_rows)
+}
+
+/// Reads a worker's `hello` frame and checks it against the coordinator's dataset.
+pub(crate) fn expect_hello(conn: &mut Connection, dataset: &Dataset<impl Float>) -> Result<(), DistributedError> {
+    let found = conn
+        .read_frame()?
+        .ok_or_else(|| protocol("worker disconnected before saying hello"))?;
+    let expected = hello_line(dataset);
+    if found != expected {
+        return Err(DistributedError::WorkerMismatch { expected, found });
+    }
+    Ok(())
+}
+
+fn encode_scalar<T: ToPrimitive>(x: T) -> String {
+    format!("{:x}", x.to_f64().unwrap_or(f64::NAN).to_bits())
+}
+
+fn decode_scalar<T: FromPrimitive>(s: &str) -> Result<T, DistributedError> {
+    u64::from_str_radix(s, 16)
+        .ok()
+        .and_then(|bits| T::from_f64(f64::from_bits(bits)))
+        .ok_or_else(|| protocol(format!("invalid scalar {s:?}")))
+}
+
+fn decode_int<I: FromStr>(s: Option<&str>) -> Result<I, DistributedError> {
+    let s = s.ok_or_else(|| protocol("missing field"))?;
+    s.parse().map_err(|_| protocol(format!("invalid integer {s:?}")))
+}
+
+fn encode_scalars(values: impl IntoIterator<Item = impl ToPrimitive>) -> String {
+    values.into_iter().map(encode_scalar).collect::<Vec<_>>().join(",")
+}
+
+fn decode_scalars<T: FromPrimitive>(s: &str) -> Result<Vec<T>, DistributedError> {
+    s.split(',').filter(|v| !v.is_empty()).map(decode_scalar).collect()
+}
+
+/// `id parent birth complexity loss cost parameters scaling graph_ids expr subexprs...`,
+/// tab-separated; `scaling` is `scale,offset`, or empty without one, and `graph_ids` the
+/// expression's shared-node ids (`Metadata::graph_ids`), empty for a tree.
+fn encode_member<T, Ops, const D: usize>(tag: &str, m: &PopMember<T, Ops, D>) -> String
+where
+    T: Float + Display,
+    Ops: dynamic_expressions::OperatorSet<T = T>,
+{
+    let parent = m.parent.map_or_else(|| "-".to_string(), |p| p.0.to_string());
+    let mut fields = vec![
+        tag.to_string(),
+        m.id.0.to_string(),
+        parent,
+        m.birth.to_string(),
+        m.complexity.to_string(),
+        encode_scalar(m.loss),
+        encode_scalar(m.cost),
+        encode_scalars(m.parameters.iter().copied()),
+        encode_scalars(m.scaling.iter().flat_map(|s| [s.scale, s.offset])),
+        m.expr
+            .meta
+            .graph_ids
+            .iter()
+            .map(u32::to_string)
+            .collect::<Vec<_>>()
+            .join(","),
+        postfix_string(&m.expr),
+    ];
+    fields.extend(m.subexprs.iter().map(postfix_string));
+    fields.join("\t")
+}
+
+fn decode_member<T, Ops, const D: usize>(
+    fields: &mut std::str::Split<'_, char>,
+    n_expr_features: usize,
+) -> Result<PopMember<T, Ops, D>, DistributedError>
+where
+    T: Float + FromPrimitive,
+    Ops: dynamic_expressions::OperatorSet<T = T>,
+{
+    let id = MemberId(decode_int(fields.next())?);
+    let parent = match fields.next() {
+        Some("-") => None,
+        other => Some(MemberId(decode_int(other)?)),
+    };
+    let birth = decode_int(fields.next())?;
+    let complexity = decode_int(fields.next())?;
+    let loss = decode_scalar(fields.next().ok_or_else(|| protocol("missing loss"))?)?;
+    let cost = decode_scalar(fields.next().ok_or_else(|| protocol("missing cost"))?)?;
+    let parameters = decode_scalars(fields.next().ok_or_else(|| protocol("missing parameters"))?)?;
+    let scaling = match decode_scalars(fields.next().ok_or_else(|| protocol("missing scaling"))?)?[..] {
+        [] => None,
+        [scale, offset] => Some(LinearScaling { scale, offset }),
+        _ => return Err(protocol("invalid scaling")),
+    };
+    let graph_ids = fields
+        .next()
+        .ok_or_else(|| protocol("missing graph ids"))?
+        .split(',')
+        .filter(|v| !v.is_empty())
+        .map(|v| v.parse().map_err(|_| protocol("invalid graph ids")))
+        .collect::<Result<Vec<u32>, _>>()?;
+    let parse = |s: &str| parse_postfix_string::<T, Ops, D>(s).map_err(DistributedError::Expression);
+    let mut expr = parse(fields.next().ok_or_else(|| protocol("missing expression"))?)?;
+    expr.meta.graph_ids = graph_ids;
+    let subexprs = fields.map(parse).collect::<Result<Vec<_>, _>>()?;
+
+    let mut m = PopMember::from_expr(id, parent, birth, expr, n_expr_features);
+    m.complexity = complexity;
+    m.loss = loss;
+    m.cost = cost;
+    m.parameters = parameters;
+    m.scaling = scaling;
+    m.subexprs = subexprs;
+    Ok(m)
+}
+
+fn header<'a>(
+    lines: &mut impl Iterator<Item = &'a str>,
+    tag: &str,
+) -> Result<std::str::SplitWhitespace<'a>, DistributedError> {
+    let line = lines.next().ok_or_else(|| protocol("empty frame"))?;
+    let mut tokens = line.split_whitespace();
+    if tokens.next() != Some(tag) {
+        return Err(protocol(format!("expected {tag:?} frame, got {line:?}")));
+    }
+    Ok(tokens)
+}
+
+fn population_header<T: Float + AddAssign, Ops, const D: usize>(st: &PopState<T, Ops, D>) -> String {
+    format!("{} {} {}", st.rng.get_seed(), st.next_id, st.next_birth)
+}
+
+fn decode_population_header<T: Float + AddAssign, Ops, const D: usize>(
+    tokens: &mut std::str::SplitWhitespace<'_>,
+    st: &mut PopState<T, Ops, D>,
+) -> Result<(), DistributedError> {
+    st.rng = Rng::with_seed(decode_int(tokens.next())?);
+    st.next_id = decode_int(tokens.next())?;
+    st.next_birth = decode_int(tokens.next())?;
+    Ok(())
+}
+
+/// `mutations attempts,accepted,improved,...` for every built-in `MutationChoice` in declaration
+/// order, then for each custom mutation recorded so far.
+fn encode_mutation_stats(stats: &MutationStats) -> String {
+    let counts: Vec<String> = stats
+        .iter()
+        .flat_map(|(_, c)| [c.attempts, c.accepted, c.improved])
+        .map(|v| v.to_string())
+        .collect();
+    format!("mutations {}", counts.join(","))
+}
+
+fn decode_mutation_stats(line: Option<&str>) -> Result<MutationStats, DistributedError> {
+    let line = line.ok_or_else(|| protocol("missing mutations"))?;
+    let rest = line
+        .strip_prefix("mutations ")
+        .ok_or_else(|| protocol(format!("expected mutations, got {line:?}")))?;
+    let values: Vec<u64> = rest.split(',').map(|v| decode_int(Some(v))).collect::<Result<_, _>>()?;
+    if !values.len().is_multiple_of(3) || values.len() < 3 * MutationChoice::ALL.len() {
+        return Err(protocol(format!("invalid mutation counts {rest:?}")));
+    }
+    Ok(MutationStats::from_counts(values.chunks(3).map(|c| MutationCounts {
+        attempts: c[0],
+        accepted: c[1],
+        improved: c[2],
+    })))
+}
+
+/// `event kind child parents accepted loss_before loss_after`, tab-separated; `kind` is `Crossover`,
+/// `Migration` or a `MutationChoice` name (`Custom(i)` for custom mutations) and missing losses are
+/// `-`.
+fn encode_event(e: &GenealogyEvent) -> String {
+    let kind = match e.kind {
+        GenealogyEventKind::Mutation(choice) => format!("{choice:?}"),
+        other => format!("{other:?}"),
+    };
+    let parents: Vec<String> = e.parents.iter().map(|p| p.0.to_string()).collect();
+    let loss = |l: Option<f64>| l.map_or_else(|| "-".to_string(), encode_scalar);
+    [
+        "event".to_string(),
+        kind,
+        e.child.0.to_string(),
+        parents.join(","),
+        u8::from(e.accepted).to_string(),
+        loss(e.loss_before),
+        loss(e.loss_after),
+    ]
+    .join("\t")
+}
+
+fn decode_event(fields: &mut std::str::Split<'_, char>) -> Result<GenealogyEvent, DistributedError> {
+    let kind = match fields.next().ok_or_else(|| protocol("missing event kind"))? {
+        "Crossover" => GenealogyEventKind::Crossover,
+        "Migration" => GenealogyEventKind::Migration,
+        name => match name.strip_prefix("Custom(").and_then(|i| i.strip_suffix(')')) {
+            Some(i) => GenealogyEventKind::Mutation(MutationChoice::Custom(decode_int(Some(i))?)),
+            None => GenealogyEventKind::Mutation(
+                MutationChoice::ALL
+                    .into_iter()
+                    .find(|c| format!("{c:?}") == name)
+                    .ok_or_else(|| protocol(format!("invalid event kind {name:?}")))?,
+            ),
+        },
+    };
+    let child = MemberId(decode_int(fields.next())?);
+    let parents = fields
+        .next()
+        .ok_or_else(|| protocol("missing event parents"))?
+        .split(',')
+        .map(|p| decode_int(Some(p)).map(MemberId))
+        .collect::<Result<_, _>>()?;
+    let accepted = decode_int::<u8>(fields.next())? != 0;
+    let mut loss = || match fields.next() {
+        Some("-") => Ok(None),
+        other => decode_scalar(other.ok_or_else(|| protocol("missing event loss"))?).map(Some),
+    };
+    Ok(GenealogyEvent {
+        kind,
+        child,
+        parents,
+        accepted,
+        loss_before: loss()?,
+        loss_after: loss()?,
+    })
+}
+
+pub(crate) fn encode_task<T, Ops, const D: usize>(
+    pop_idx: usize,
+    curmaxsize: usize,
+    stats: &RunningSearchStatistics,
+    donors: &[PopMember<T, Ops, D>],
+    st: &PopState<T, Ops, D>,
+) -> String
+where
+    T: Float + Display + AddAssign,
+    Ops: dynamic_expressions::OperatorSet<T = T>,
+{
+    let mut lines = vec![
+        format!("task {pop_idx} {curmaxsize} {}", population_header(st)),
+        format!("window {}", encode_scalar(stats.window_size)),
+        format!("frequencies {}", encode_scalars(stats.frequencies.iter().copied())),
+        format!(
+            "normalized {}",
+            encode_scalars(stats.normalized_frequencies.iter().copied())
+        ),
+        encode_mutation_stats(&st.mutation_stats),
+    ];
+    lines.extend(st.pop.members.iter().map(|m| encode_member("member", m)));
+    lines.extend(donors.iter().map(|m| encode_member("donor", m)));
+    lines.join("\n")
+}
+
+/// A decoded task's `(pop_idx, curmaxsize, stats, donors)`.
+type TaskParams<T, Ops, const D: usize> = (usize, usize, RunningSearchStatistics, Vec<PopMember<T, Ops, D>>);
+
+/// Decodes a task into the worker's reusable `st`, returning its [`TaskParams`].
+pub(crate) fn decode_task<T, Ops, const D: usize>(
+    text: &str,
+    st: &mut PopState<T, Ops, D>,
+    n_expr_features: usize,
+) -> Result<TaskParams<T, Ops, D>, DistributedError>
+where
+    T: Float + FromPrimitive + AddAssign,
+    Ops: dynamic_expressions::OperatorSet<T = T>,
+{
+    let mut lines = text.lines();
+    let mut tokens = header(&mut lines, "task")?;
+    let pop_idx = decode_int(tokens.next())?;
+    let curmaxsize = decode_int(tokens.next())?;
+    decode_population_header(&mut tokens, st)?;
+
+    let mut vector = |tag: &str| -> Result<Vec<f64>, DistributedError> {
+        let line = lines.next().ok_or_else(|| protocol(format!("missing {tag}")))?;
+        let rest = line
+            .strip_prefix(tag)
+            .ok_or_else(|| protocol(format!("expected {tag}, got {line:?}")))?;
+        decode_scalars(rest.trim())
+    };
+    let window_size = vector("window")?
+        .first()
+        .copied()
+        .ok_or_else(|| protocol("missing window"))?;
+    let stats = RunningSearchStatistics {
+        window_size,
+        frequencies: vector("frequencies")?,
+        normalized_frequencies: vector("normalized")?,
+    };
+    st.mutation_stats = decode_mutation_stats(lines.next())?;
+    if let Some(genealogy) = st.genealogy.as_mut() {
+        genealogy.clear();
+    }
+
+    st.pop.members.clear();
+    let mut donors = Vec::new();
+    for line in lines {
+        let mut fields = line.split('\t');
+        match fields.next() {
+            Some("member") => st.pop.members.push(decode_member(&mut fields, n_expr_features)?),
+            Some("donor") => donors.push(decode_member(&mut fields, n_expr_features)?),
+            _ => return Err(protocol(format!("unexpected line {line:?}"))),
+        }
+    }
+    Ok((pop_idx, curmaxsize, stats, donors))
+}
+
+pub(crate) fn encode_result<T, Ops, const D: usize>(res: &SearchTaskResult<T, Ops, D>) -> String
+where
+    T: Float + Display + AddAssign,
+    Ops: dynamic_expressions::OperatorSet<T = T>,
+{
+    let mut lines = vec![format!(
+        "result {} {} {} {}",
+        res.pop_idx,
+        res.curmaxsize,
+        res.evals,
+        population_header(&res.pop_state)
+    )];
+    lines.push(encode_mutation_stats(&res.pop_state.mutation_stats));
+    lines.extend(res.pop_state.pop.members.iter().map(|m| encode_member("member", m)));
+    lines.extend(res.best_sub_pop.iter().map(|m| encode_member("migrant", m)));
+    lines.extend(res.best_seen.members().map(|m| encode_member("seen", m)));
+    if let Some(genealogy) = &res.pop_state.genealogy {
+        lines.extend(genealogy.events().iter().map(encode_event));
+    }
+    lines.join("\n")
+}
+
+/// Decodes a worker's result, moving the population back into the coordinator's `st`.
+pub(crate) fn decode_result<T, Ops, const D: usize>(
+    text: &str,
+    mut st: PopState<T, Ops, D>,
+    n_expr_features: usize,
+) -> Result<SearchTaskResult<T, Ops, D>, DistributedError>
+where
+    T: Float + FromPrimitive + AddAssign,
+    Ops: dynamic_expressions::OperatorSet<T = T>,
+{
+    let mut lines = text.lines();
+    let mut tokens = header(&mut lines, "result")?;
+    let pop_idx = decode_int(tokens.next())?;
+    let curmaxsize = decode_int(tokens.next())?;
+    let evals = decode_int(tokens.next())?;
+    decode_population_header(&mut tokens, &mut st)?;
+    st.mutation_stats = decode_mutation_stats(lines.next())?;
+
+    let mut members = Vec::new();
+    let mut best_sub_pop = Vec::new();
+    let mut best_seen = HallOfFame::new(0);
+    for line in lines {
+        let mut fields = line.split('\t');
+        let tag = fields.next();
+        if tag == Some("event") {
+            let event = decode_event(&mut fields)?;
+            st.genealogy.get_or_insert_with(Genealogy::default).record(event);
+            continue;
+        }
+        let m = decode_member(&mut fields, n_expr_features)?;
+        match tag {
+            Some("member") => members.push(m),
+            Some("migrant") => best_sub_pop.push(m),
+            Some("seen") => {
+                if best_seen.best_by_complexity.len() <= m.complexity {
+                    best_seen.best_by_complexity.resize(m.complexity + 1, None);
+                }
+                let c = m.complexity;
+                best_seen.best_by_complexity[c] = Some(m);
+            }
+            _ => return Err(protocol(format!("unexpected line {line:?}"))),
+        }
+    }
+    st.pop = Population::new(members);
+    Ok(SearchTaskResult {
+        pop_idx,
+        curmaxsize,
+        evals,
+        best_seen,
+        best_sub_pop,
+        pop_state: st,
+    })
+}
+
+/// Serves population tasks for the coordinator at `endpoint` until it shuts the worker down.
+///
+/// `dataset` and `options` must match the coordinator's (the worker checks the dataset shape).
+pub fn run_worker<T, Ops, const D: usize>(
+    endpoint: &Endpoint,
+    dataset: &Dataset<T>,
+    options: &Options<T, D>,
+) -> Result<(), DistributedError>
+where
+    T: Float + AddAssign + FromPrimitive + ToPrimitive + Display,
+    Ops: dynamic_expressions::OperatorSet<T = T>,
+{
+    let reduced = preselected_features(dataset, options).map(|selected| dataset.select_features(&selected));
+    let dataset = reduced.as_ref().unwrap_or(dataset);
+    let baseline_loss = if options.use_baseline {
+        baseline_loss::<T, Ops, D>(dataset, options)
+    } else {
+        None
+    };
+    let full_dataset = TaggedDataset::new(dataset, baseline_loss);
+    let n_expr_features = options.n_expr_features(dataset);
+
+    let mut conn = Connection::connect(endpoint)?;
+    conn.write_frame(&hello_line(dataset))?;
+
+    let mut st: PopState<T, Ops, D> = PopState {
+        pop: Population::new(Vec::new()),
+        evaluator: Evaluator::new(dataset.n_rows),
+        grad_ctx: dynamic_expressions::GradContext::new(dataset.n_rows),
+        rng: Rng::with_seed(0),
+        batch_dataset: None,
+        next_id: 0,
+        next_birth: 0,
+        mutation_stats: MutationStats::default(),
+        genealogy: options.use_recorder.then(Genealogy::default),
+    };
+    while let Some(text) = conn.read_frame()? {
+        if text == SHUTDOWN {
+            break;
+        }
+        let (pop_idx, curmaxsize, stats, donors) = decode_task(&text, &mut st, n_expr_features)?;
+        let res = execute_task(full_dataset, options, pop_idx, curmaxsize, stats, &donors, st);
+        conn.write_frame(&encode_result(&res))?;
+        st = res.pop_state;
+    }
+    Ok(())
+}
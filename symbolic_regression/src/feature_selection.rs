@@ -0,0 +1,112 @@
+//! Feature pre-selection (like PySR's `select_k_features`).
+//!
+//! With `Options::select_k_features = k` (and fewer than `n_features` columns requested), the
+//! `equation_search*` functions rank the features by their estimated mutual information with `y`,
+//! search on a dataset reduced to the best `k`, and map `PNode::Var` indices in the result back to
+//! the original column numbering. Pre-selection is skipped when `Options::template` is set, since
+//! templates already name the features of each sub-expression.
+
+use std::cmp::Ordering;
+
+use dynamic_expressions::node::PNode;
+use num_traits::Float;
+
+use crate::dataset::Dataset;
+use crate::options::Options;
+use crate::pop_member::PopMember;
+
+/// Equal-frequency bin of each value, with `n_bins` bins (ties share a bin).
+fn quantile_bins<T: Float>(values: impl Iterator<Item = T>, n_bins: usize) -> Vec<usize> {
+    let values: Vec<T> = values.collect();
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&a, &b| values[a].partial_cmp(&values[b]).unwrap_or(Ordering::Equal));
+    let mut bins = vec![0; values.len()];
+    let mut bin = 0;
+    for (rank, &i) in order.iter().enumerate() {
+        // Only start a new bin between distinct values.
+        if rank > 0 && values[i] != values[order[rank - 1]] {
+            bin = rank * n_bins / values.len();
+        }
+        bins[i] = bin;
+    }
+    bins
+}
+
+fn mutual_information(x_bins: &[usize], y_bins: &[usize], n_bins: usize) -> f64 {
+    let n = x_bins.len() as f64;
+    let mut joint = vec![0.0; n_bins * n_bins];
+    let mut px = vec![0.0; n_bins];
+    let mut py = vec![0.0; n_bins];
+    for (&bx, &by) in x_bins.iter().zip(y_bins) {
+        joint[bx * n_bins + by] += 1.0;
+        px[bx] += 1.0;
+        py[by] += 1.0;
+    }
+    let mut mi = 0.0;
+    for bx in 0..n_bins {
+        for by in 0..n_bins {
+            let c = joint[bx * n_bins + by];
+            if c > 0.0 {
+                mi += (c / n) * (c * n / (px[bx] * py[by])).ln();
+            }
+        }
+    }
+    mi
+}
+
+/// Estimated mutual information (in nats) between each feature and `y`, from equal-frequency
+/// histograms with about `cbrt(n_rows)` bins per axis.
+pub fn mutual_information_scores<T: Float>(dataset: &Dataset<T>) -> Vec<f64> {
+    let n_bins = ((dataset.n_rows as f64).cbrt().ceil() as usize).clamp(2, 32);
+    let y_bins = quantile_bins(dataset.y.iter().copied(), n_bins);
+    (0..dataset.n_features)
+        .map(|f| {
+            mutual_information(
+                &quantile_bins(dataset.x.row(f).iter().copied(), n_bins),
+                &y_bins,
+                n_bins,
+            )
+        })
+        .collect()
+}
+
+/// Indices (ascending) of the `k` features with the highest mutual information with `y`.
+pub fn select_k_features<T: Float>(dataset: &Dataset<T>, k: usize) -> Vec<usize> {
+    let scores = mutual_information_scores(dataset);
+    let mut order: Vec<usize> = (0..scores.len()).collect();
+    order.sort_by(|&a, &b| scores[b].partial_cmp(&scores[a]).unwrap_or(Ordering::Equal));
+    let mut selected = order[..k.min(order.len())].to_vec();
+    selected.sort_unstable();
+    selected
+}
+
+/// The features a search on `dataset` is restricted to, or `None` if pre-selection is off.
+pub(crate) fn preselected_features<T: Float, const D: usize>(
+    dataset: &Dataset<T>,
+    options: &Options<T, D>,
+) -> Option<Vec<usize>> {
+    let k = options.select_k_features;
+    (k > 0 && k < dataset.n_features && options.template.is_none()).then(|| select_k_features(dataset, k))
+}
+
+/// Rewrites a member found on `dataset.select_features(selected)` in terms of the original
+/// columns. Indices past the selection (parametric-expression parameters) follow the original
+/// `n_features` columns.
+pub(crate) fn restore_feature_indices<T, Ops, const D: usize>(
+    member: &mut PopMember<T, Ops, D>,
+    selected: &[usize],
+    n_features: usize,
+    n_expr_features: usize,
+) where
+    T: Float,
+    Ops: dynamic_expressions::OperatorSet<T = T>,
+{
+    for node in &mut member.expr.nodes {
+        if let PNode::Var { feature } = node {
+            let f = usize::from(*feature);
+            let original = selected.get(f).copied().unwrap_or(n_features + f - selected.len());
+            *feature = original as u16;
+        }
+    }
+    member.rebuild_plan(n_expr_features);
+}
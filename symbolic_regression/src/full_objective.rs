@@ -0,0 +1,125 @@
+use std::ops::AddAssign;
+use std::sync::Arc;
+
+use dynamic_expressions::expression::PostfixExpr;
+use dynamic_expressions::node::PNode;
+use dynamic_expressions::{DiffContext, EvalOptions, GradContext, GradMatrix, GraphExpr, OperatorSet};
+use ndarray::ArrayView2;
+use num_traits::Float;
+
+use crate::dataset::Dataset;
+
+/// Type-erased handle to the expression being scored, passed to [`FullObjective`].
+///
+/// Evaluation methods return `None` when the expression produces non-finite values.
+pub trait ExprEvaluator<T: Float> {
+    fn nodes(&self) -> &[PNode];
+    fn consts(&self) -> &[T];
+    fn consts_mut(&mut self) -> &mut [T];
+    /// Evaluates the expression on `x` with shape `(n_features, n_rows)`.
+    fn eval(&self, x: ArrayView2<'_, T>) -> Option<Vec<T>>;
+    /// Evaluates the expression and its derivative with respect to feature `direction`.
+    fn eval_diff(&self, x: ArrayView2<'_, T>, direction: usize) -> Option<(Vec<T>, Vec<T>)>;
+    /// Evaluates the expression and its gradient with respect to the constants
+    /// (direction-major, `n_consts * n_rows`).
+    fn eval_grad_consts(&self, x: ArrayView2<'_, T>) -> Option<(Vec<T>, GradMatrix<T>)>;
+}
+
+/// A loss computed from the whole expression rather than only its predictions.
+///
+/// When set in `Options::full_objective`, it replaces `Options::loss` for member evaluation, the
+/// baseline loss and constant optimization. The loss is treated as invalid when non-finite.
+pub trait FullObjective<T: Float>: Send + Sync {
+    fn loss(&self, expr: &mut dyn ExprEvaluator<T>, dataset: &Dataset<T>) -> T;
+
+    /// Loss and its gradient with respect to `expr.consts()`, written to `grad_out`.
+    ///
+    /// Defaults to central finite differences over [`FullObjective::loss`].
+    fn loss_and_grad(&self, expr: &mut dyn ExprEvaluator<T>, dataset: &Dataset<T>, grad_out: &mut [T]) -> T {
+        let loss = self.loss(expr, dataset);
+        if !loss.is_finite() {
+            return loss;
+        }
+        let two = T::one() + T::one();
+        let sqrt_eps = T::epsilon().sqrt();
+        for (i, g) in grad_out.iter_mut().enumerate() {
+            let c = expr.consts()[i];
+            let h = sqrt_eps * c.abs().max(T::one());
+            expr.consts_mut()[i] = c + h;
+            let f_plus = self.loss(expr, dataset);
+            expr.consts_mut()[i] = c - h;
+            let f_minus = self.loss(expr, dataset);
+            expr.consts_mut()[i] = c;
+            *g = (f_plus - f_minus) / (two * h);
+        }
+        loss
+    }
+}
+
+pub type FullObjectiveObject<T> = Arc<dyn FullObjective<T> + Send + Sync>;
+
+pub(crate) struct PostfixExprEvaluator<'a, T: Float, Ops, const D: usize> {
+    pub(crate) expr: &'a mut PostfixExpr<T, Ops, D>,
+    pub(crate) eval_opts: EvalOptions,
+}
+
+impl<'a, T: Float, Ops, const D: usize> PostfixExprEvaluator<'a, T, Ops, D> {
+    pub(crate) fn new(expr: &'a mut PostfixExpr<T, Ops, D>) -> Self {
+        Self {
+            expr,
+            eval_opts: EvalOptions {
+                check_finite: true,
+                early_exit: true,
+            },
+        }
+    }
+
+    /// The graph of `expr` if it shares subexpressions, so shared nodes are evaluated once.
+    fn graph(&self) -> Option<GraphExpr<T, Ops, D>> {
+        (!self.expr.meta.graph_ids.is_empty()).then(|| GraphExpr::from_postfix(self.expr))
+    }
+}
+
+impl<T, Ops, const D: usize> ExprEvaluator<T> for PostfixExprEvaluator<'_, T, Ops, D>
+where
+    T: Float + AddAssign,
+    Ops: OperatorSet<T = T>,
+{
+    fn nodes(&self) -> &[PNode] {
+        &self.expr.nodes
+    }
+
+    fn consts(&self) -> &[T] {
+        &self.expr.consts
+    }
+
+    fn consts_mut(&mut self) -> &mut [T] {
+        &mut self.expr.consts
+    }
+
+    fn eval(&self, x: ArrayView2<'_, T>) -> Option<Vec<T>> {
+        let (out, ok) = match self.graph() {
+            Some(graph) => dynamic_expressions::eval_graph_array(&graph, x, &self.eval_opts),
+            None => dynamic_expressions::eval_tree_array(self.expr, x, &self.eval_opts),
+        };
+        ok.then_some(out)
+    }
+
+    fn eval_diff(&self, x: ArrayView2<'_, T>, direction: usize) -> Option<(Vec<T>, Vec<T>)> {
+        let mut ctx = DiffContext::<T, D>::new(x.ncols());
+        let (out, der, ok) =
+            dynamic_expressions::eval_diff_tree_array(self.expr, x, direction, &mut ctx, &self.eval_opts);
+        ok.then_some((out, der))
+    }
+
+    fn eval_grad_consts(&self, x: ArrayView2<'_, T>) -> Option<(Vec<T>, GradMatrix<T>)> {
+        let (out, grad, ok) = match self.graph() {
+            Some(graph) => dynamic_expressions::eval_grad_graph_array(&graph, x, &self.eval_opts),
+            None => {
+                let mut ctx = GradContext::<T, D>::new(x.ncols());
+                dynamic_expressions::eval_grad_tree_array(self.expr, x, false, &mut ctx, &self.eval_opts)
+            }
+        };
+        ok.then_some((out, grad))
+    }
+}
@@ -0,0 +1,153 @@
+//! Rust-only analogue of SymbolicRegression.jl's `use_recorder`: a log of how members were born.
+//!
+//! With `Options::use_recorder` set, every population buffers one [`GenealogyEvent`] per child it
+//! breeds (mutation or crossover), and the coordinator appends them, together with the migrations
+//! it performs, to a search-wide [`Genealogy`] whenever it applies a population's result. Since every
+//! member created during the search has a unique [`MemberId`] whose `parent` names the member it
+//! was derived from, the log is enough to walk any member back to its initial ancestors.
+//!
+//! Constant optimization and simplification update members in place, keeping their id, so they
+//! are not logged.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::mutate::MutationChoice;
+use crate::pop_member::MemberId;
+
+/// How a [`GenealogyEvent`]'s child came to be.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum GenealogyEventKind {
+    /// `next_generation` mutated the parent (or, when rejected, copied it).
+    Mutation(MutationChoice),
+    /// `crossover_generation` recombined the two parents.
+    Crossover,
+    /// Migration copied the parent into another population (or from the hall of fame).
+    Migration,
+}
+
+/// The birth of one member.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct GenealogyEvent {
+    pub kind: GenealogyEventKind,
+    /// The new member's id.
+    pub child: MemberId,
+    /// The member the child descends from (its `PopMember::parent`), followed by the other parent
+    /// of a crossover.
+    pub parents: Vec<MemberId>,
+    /// Whether the child carries the change; a rejected mutation's child is an unchanged copy of
+    /// its parent.
+    pub accepted: bool,
+    /// The first parent's loss, if finite.
+    pub loss_before: Option<f64>,
+    /// The loss of the changed expression, if it was evaluated and finite. For a rejected mutation
+    /// this is the loss of the candidate that was discarded.
+    pub loss_after: Option<f64>,
+}
+
+/// Birth events recorded during a search, in the order the coordinator applied them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Genealogy {
+    events: Vec<GenealogyEvent>,
+}
+
+impl Genealogy {
+    pub fn events(&self) -> &[GenealogyEvent] {
+        &self.events
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// The event that created `id`, if it was born during the search.
+    pub fn birth_of(&self, id: MemberId) -> Option<&GenealogyEvent> {
+        self.events.iter().find(|e| e.child == id)
+    }
+
+    /// The ancestry tree of `id`: the events that created it and, recursively, its parents, in
+    /// recording order (so every parent's birth precedes its children's). Members without an event
+    /// are leaves, normally from the initial populations.
+    pub fn ancestry(&self, id: MemberId) -> Genealogy {
+        let by_child: HashMap<MemberId, usize> = self.events.iter().enumerate().map(|(i, e)| (e.child, i)).collect();
+        let mut seen = HashSet::from([id]);
+        let mut stack = vec![id];
+        let mut picked = Vec::new();
+        while let Some(member) = stack.pop() {
+            let Some(&i) = by_child.get(&member) else {
+                continue;
+            };
+            picked.push(i);
+            for &p in &self.events[i].parents {
+                if seen.insert(p) {
+                    stack.push(p);
+                }
+            }
+        }
+        picked.sort_unstable();
+        Genealogy {
+            events: picked.into_iter().map(|i| self.events[i].clone()).collect(),
+        }
+    }
+
+    /// `id` followed by its first parent, that member's first parent, and so on back to the
+    /// earliest recorded ancestor.
+    pub fn lineage(&self, id: MemberId) -> Vec<MemberId> {
+        let by_child: HashMap<MemberId, &GenealogyEvent> = self.events.iter().map(|e| (e.child, e)).collect();
+        let mut chain = vec![id];
+        let mut seen = HashSet::from([id]);
+        while let Some(&p) = by_child
+            .get(chain.last().expect("non-empty"))
+            .and_then(|e| e.parents.first())
+        {
+            if !seen.insert(p) {
+                break;
+            }
+            chain.push(p);
+        }
+        chain
+    }
+
+    /// One JSON-serialized [`GenealogyEvent`] per line.
+    #[cfg(feature = "serde_json")]
+    pub fn to_json_lines(&self) -> String {
+        self.events
+            .iter()
+            .map(|e| serde_json::to_string(e).expect("events are serializable") + "\n")
+            .collect()
+    }
+
+    /// Reads events written by [`Genealogy::to_json_lines`]; blank lines are skipped.
+    #[cfg(feature = "serde_json")]
+    pub fn from_json_lines(text: &str) -> Result<Self, serde_json::Error> {
+        let events = text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?;
+        Ok(Genealogy { events })
+    }
+
+    pub(crate) fn record(&mut self, event: GenealogyEvent) {
+        self.events.push(event);
+    }
+
+    /// Moves `other`'s events to the end of this log.
+    pub(crate) fn append(&mut self, other: &mut Genealogy) {
+        self.events.append(&mut other.events);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.events.clear();
+    }
+}
+
+/// `loss` as an event field: `None` when not finite, which JSON cannot represent.
+pub(crate) fn finite_loss<T: num_traits::ToPrimitive>(loss: T) -> Option<f64> {
+    loss.to_f64().filter(|l| l.is_finite())
+}
@@ -1,8 +1,11 @@
+use std::ops::AddAssign;
+
+use dynamic_expressions::OperatorSet;
 use num_traits::Float;
 
-use crate::check_constraints::check_constraints;
+use crate::dataset::Dataset;
 use crate::options::Options;
-use crate::pop_member::PopMember;
+use crate::pop_member::{Evaluator, PopMember};
 
 pub struct HallOfFame<T: Float, Ops, const D: usize> {
     pub best_by_complexity: Vec<Option<PopMember<T, Ops, D>>>,
@@ -16,27 +19,48 @@ impl<T: Float, Ops, const D: usize> HallOfFame<T, Ops, D> {
     }
 
     pub fn consider(&mut self, member: &PopMember<T, Ops, D>, options: &Options<T, D>, curmaxsize: usize) {
-        if !check_constraints(&member.expr, options, curmaxsize) {
+        if !member.satisfies_constraints(options, curmaxsize) {
             return;
         }
         let c = member.complexity;
-        if c == 0 {
+        if c == 0 || c >= self.best_by_complexity.len() {
             return;
         }
-        if c >= self.best_by_complexity.len() {
-            return;
+        if self.beats_slot(member) {
+            self.best_by_complexity[c] = Some(member.clone());
+        }
+    }
+
+    /// Whether `member` has a lower loss than the member in its complexity's slot, or the slot is
+    /// empty.
+    fn beats_slot(&self, member: &PopMember<T, Ops, D>) -> bool {
+        match &self.best_by_complexity[member.complexity] {
+            None => true,
+            // Within one complexity, cost orders like loss; loss also compares members costed
+            // under different islands' parsimony.
+            Some(best) => member.loss.partial_cmp(&best.loss) == Some(std::cmp::Ordering::Less),
         }
-        match &self.best_by_complexity[c] {
-            None => self.best_by_complexity[c] = Some(member.clone()),
-            Some(best) => {
-                if member
-                    .cost
-                    .partial_cmp(&best.cost)
-                    .unwrap_or(std::cmp::Ordering::Greater)
-                    == std::cmp::Ordering::Less
-                {
-                    self.best_by_complexity[c] = Some(member.clone());
-                }
+    }
+
+    /// Folds the linear scaling of every member into its expression (see
+    /// [`PopMember::fold_linear_scaling`]) and moves it to the slot of its new complexity, growing
+    /// the hall past `Options::maxsize` if needed. Of two members landing in one slot, the one with
+    /// the lower loss is kept.
+    pub(crate) fn fold_linear_scaling(&mut self, n_features: usize, options: &Options<T, D>, baseline_loss: Option<T>)
+    where
+        Ops: OperatorSet<T = T>,
+    {
+        let members: Vec<_> = self.best_by_complexity.iter_mut().filter_map(Option::take).collect();
+        for mut m in members {
+            if m.fold_linear_scaling(n_features, options) {
+                m.rescore(options, baseline_loss);
+            }
+            if m.complexity >= self.best_by_complexity.len() {
+                self.best_by_complexity.resize_with(m.complexity + 1, || None);
+            }
+            if self.beats_slot(&m) {
+                let c = m.complexity;
+                self.best_by_complexity[c] = Some(m);
             }
         }
     }
@@ -52,19 +76,155 @@ impl<T: Float, Ops, const D: usize> HallOfFame<T, Ops, D> {
         }
     }
 
+    /// Recomputes every member's cost under `options`.
+    pub(crate) fn rescore(&mut self, options: &Options<T, D>, baseline_loss: Option<T>) {
+        for m in self.best_by_complexity.iter_mut().flatten() {
+            m.rescore(options, baseline_loss);
+        }
+    }
+
     pub fn members(&self) -> impl Iterator<Item = &PopMember<T, Ops, D>> {
         self.best_by_complexity.iter().flatten()
     }
 
     pub fn pareto_front(&self) -> Vec<PopMember<T, Ops, D>> {
+        self.pareto_front_by(LossSource::Training)
+    }
+
+    /// Members whose `source` loss strictly improves on every simpler member.
+    ///
+    /// With [`LossSource::Validation`], members without a validation loss are skipped.
+    pub fn pareto_front_by(&self, source: LossSource) -> Vec<PopMember<T, Ops, D>> {
         let mut out = Vec::new();
         let mut best_loss = T::infinity();
         for m in self.best_by_complexity.iter().flatten() {
-            if m.loss < best_loss {
-                best_loss = m.loss;
+            let Some(loss) = source.loss_of(m) else {
+                continue;
+            };
+            if loss < best_loss {
+                best_loss = loss;
                 out.push(m.clone());
             }
         }
         out
     }
+
+    /// The Pareto front with each member's score, `-d ln(loss) / d complexity` relative to the
+    /// previous (simpler) front member. The simplest member scores zero.
+    pub fn scored_pareto_front(&self) -> Vec<(PopMember<T, Ops, D>, T)> {
+        let front = self.pareto_front();
+        let scores = pareto_scores(&front);
+        front.into_iter().zip(scores).collect()
+    }
+
+    /// Picks one member of the Pareto front according to `selection`.
+    pub fn select_model(&self, selection: ModelSelection) -> Option<PopMember<T, Ops, D>> {
+        let scored = self.scored_pareto_front();
+        let by_score = |a: &&(PopMember<T, Ops, D>, T), b: &&(PopMember<T, Ops, D>, T)| {
+            a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal)
+        };
+        let chosen = match selection {
+            // The front is sorted by decreasing loss, so the last member is the most accurate.
+            ModelSelection::Accuracy => scored.last(),
+            ModelSelection::Score => scored.iter().max_by(by_score),
+            ModelSelection::Best => {
+                let min_loss = scored.iter().map(|(m, _)| m.loss).fold(T::infinity(), T::min);
+                // `min_loss + 0.5 |min_loss|` is 1.5x the minimum for positive losses and stays above
+                // it for negative ones (e.g. log-likelihood objectives).
+                let threshold = min_loss + T::from(0.5).unwrap() * min_loss.abs();
+                scored.iter().filter(|(m, _)| m.loss <= threshold).max_by(by_score)
+            }
+        };
+        chosen.map(|(m, _)| m.clone())
+    }
+
+    /// The member with the lowest validation loss, if any entry has one.
+    pub fn best_by_validation(&self) -> Option<&PopMember<T, Ops, D>> {
+        self.members()
+            .filter(|m| m.validation_loss.is_some_and(|v| v.is_finite()))
+            .min_by(|a, b| {
+                a.validation_loss
+                    .partial_cmp(&b.validation_loss)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+    }
+
+    /// Computes the validation loss of every entry that does not have one yet.
+    pub(crate) fn update_validation_losses(
+        &mut self,
+        dataset: &Dataset<T>,
+        options: &Options<T, D>,
+        evaluator: &mut Evaluator<T, D>,
+    ) where
+        T: AddAssign,
+        Ops: OperatorSet<T = T>,
+    {
+        for m in self.best_by_complexity.iter_mut().flatten() {
+            if m.validation_loss.is_none() {
+                m.evaluate_validation(dataset, options, evaluator);
+            }
+        }
+    }
+}
+
+/// Scores for a Pareto front sorted by increasing complexity (see
+/// [`HallOfFame::scored_pareto_front`]).
+pub fn pareto_scores<T: Float, Ops, const D: usize>(front: &[PopMember<T, Ops, D>]) -> Vec<T> {
+    let tiny = T::min_positive_value();
+    let mut scores = Vec::with_capacity(front.len());
+    let mut prev: Option<(T, usize)> = None;
+    for m in front {
+        let log_loss = m.loss.max(tiny).ln();
+        let score = match prev {
+            Some((prev_log_loss, prev_complexity)) if m.complexity > prev_complexity => {
+                let dc = T::from(m.complexity - prev_complexity).unwrap();
+                (-(log_loss - prev_log_loss) / dc).max(T::zero())
+            }
+            _ => T::zero(),
+        };
+        scores.push(score);
+        prev = Some((log_loss, m.complexity));
+    }
+    scores
+}
+
+/// PySR-style strategies for choosing a single model from the Pareto front.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum ModelSelection {
+    /// Highest score among members whose loss exceeds the minimum loss by at most half its
+    /// magnitude (1.5x the minimum, for positive losses).
+    #[default]
+    Best,
+    /// Lowest loss.
+    Accuracy,
+    /// Highest score.
+    Score,
+}
+
+impl ModelSelection {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "best" => Some(Self::Best),
+            "accuracy" => Some(Self::Accuracy),
+            "score" => Some(Self::Score),
+            _ => None,
+        }
+    }
+}
+
+/// Which loss a hall-of-fame query ranks members by.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum LossSource {
+    #[default]
+    Training,
+    Validation,
+}
+
+impl LossSource {
+    fn loss_of<T: Float, Ops, const D: usize>(self, m: &PopMember<T, Ops, D>) -> Option<T> {
+        match self {
+            Self::Training => Some(m.loss),
+            Self::Validation => m.validation_loss,
+        }
+    }
 }
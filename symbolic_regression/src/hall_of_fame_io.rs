@@ -0,0 +1,335 @@
+//! Hall-of-fame export and re-import.
+//!
+//! The CSV layout starts with PySR's `hall_of_fame.csv` columns (`Complexity,Loss,Equation`) and
+//! adds `Cost`, `Score` and `Expression`, the exact [`postfix_string`] serialization. The readers
+//! prefer `Expression` and fall back to parsing `Equation`, so PySR's own files can be loaded too.
+//! `Parameters` (space-separated) and `Subexpressions` (`;`-separated postfix strings) carry the
+//! per-class parameters of parametric members and the sub-expressions of template members.
+
+use std::fmt::{self, Display};
+
+use dynamic_expressions::expression::PostfixExpr;
+use dynamic_expressions::strings::{StringTreeOptions, string_tree};
+use dynamic_expressions::{
+    GraphExpr, OperatorSet, ParseError, parse_expr, parse_postfix_string, postfix_string, string_graph,
+};
+use num_traits::Float;
+
+use crate::hall_of_fame::HallOfFame;
+use crate::pop_member::{MemberId, PopMember};
+
+const CSV_COLUMNS: [&str; 8] = [
+    "Complexity",
+    "Loss",
+    "Equation",
+    "Cost",
+    "Score",
+    "Expression",
+    "Parameters",
+    "Subexpressions",
+];
+
+/// `expr` in infix form; a graph expression gets a let-binding for each shared subexpression (see
+/// `Options::graph_expressions`).
+pub(crate) fn string_expr<T, Ops, const D: usize>(expr: &PostfixExpr<T, Ops, D>, opts: StringTreeOptions<'_>) -> String
+where
+    T: Clone + Display,
+    Ops: OperatorSet,
+{
+    if expr.meta.graph_ids.is_empty() {
+        string_tree(expr, opts)
+    } else {
+        string_graph(&GraphExpr::from_postfix(expr), opts)
+    }
+}
+
+/// One exported Pareto-front entry.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct HallOfFameRecord {
+    pub complexity: usize,
+    pub loss: f64,
+    pub cost: f64,
+    pub score: f64,
+    /// Human-readable infix form, as printed by `string_tree` (`string_graph` for graph expressions).
+    pub equation: String,
+    /// Exact serialization (see [`postfix_string`]); may be empty for files written by PySR.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub expression: String,
+    /// Per-class parameter values of a parametric member (see `PopMember::parameters`).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub parameters: Vec<f64>,
+    /// Exact serializations of a template member's sub-expressions (see `PopMember::subexprs`).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub subexpressions: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum HallOfFameIoError {
+    MissingColumn(&'static str),
+    InvalidField {
+        row: usize,
+        column: &'static str,
+        value: String,
+    },
+    Expression {
+        row: usize,
+        error: ParseError,
+    },
+    Csv {
+        row: usize,
+        message: String,
+    },
+    #[cfg(feature = "serde_json")]
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for HallOfFameIoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HallOfFameIoError::MissingColumn(col) => write!(f, "missing column {col:?}"),
+            HallOfFameIoError::InvalidField { row, column, value } => {
+                write!(f, "row {row}: invalid {column} value {value:?}")
+            }
+            HallOfFameIoError::Expression { row, error } => write!(f, "row {row}: {error}"),
+            HallOfFameIoError::Csv { row, message } => write!(f, "row {row}: {message}"),
+            #[cfg(feature = "serde_json")]
+            HallOfFameIoError::Json(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for HallOfFameIoError {}
+
+impl<T, Ops, const D: usize> HallOfFame<T, Ops, D>
+where
+    T: Float + Display,
+    Ops: OperatorSet<T = T>,
+{
+    /// The scored Pareto front as export records, in increasing complexity.
+    pub fn to_records(&self, variable_names: &[String]) -> Vec<HallOfFameRecord> {
+        let names = (!variable_names.is_empty()).then_some(variable_names);
+        self.scored_pareto_front()
+            .into_iter()
+            .map(|(m, score)| {
+                // Members of a running search may still carry their linear scaling; once folded in,
+                // a template member's sub-expressions no longer compose the expression.
+                let scaled = m.scaling.and_then(|s| s.fold_into(&m.expr));
+                let expr = scaled.as_ref().unwrap_or(&m.expr);
+                HallOfFameRecord {
+                    complexity: m.complexity,
+                    loss: m.loss.to_f64().unwrap_or(f64::NAN),
+                    cost: m.cost.to_f64().unwrap_or(f64::NAN),
+                    score: score.to_f64().unwrap_or(0.0),
+                    equation: string_expr(
+                        expr,
+                        StringTreeOptions {
+                            variable_names: names,
+                            pretty: false,
+                        },
+                    ),
+                    expression: postfix_string(expr),
+                    parameters: m.parameters.iter().map(|p| p.to_f64().unwrap_or(f64::NAN)).collect(),
+                    subexpressions: match scaled {
+                        Some(_) => Vec::new(),
+                        None => m.subexprs.iter().map(postfix_string).collect(),
+                    },
+                }
+            })
+            .collect()
+    }
+
+    /// Rebuilds a hall of fame from records, recompiling each member's `EvalPlan` for
+    /// `n_features` inputs (for parametric members, see `Options::n_expr_features`). Complexity,
+    /// loss and cost are taken from the records as-is.
+    pub fn from_records(
+        records: &[HallOfFameRecord],
+        variable_names: &[String],
+        n_features: usize,
+    ) -> Result<Self, HallOfFameIoError> {
+        let max_complexity = records.iter().map(|r| r.complexity).max().unwrap_or(0);
+        let mut hall = Self::new(max_complexity);
+        for (row, r) in records.iter().enumerate() {
+            let expr = if r.expression.trim().is_empty() {
+                parse_expr(&r.equation, variable_names)
+            } else {
+                parse_postfix_string(&r.expression)
+            }
+            .map_err(|error| HallOfFameIoError::Expression { row, error })?;
+            let mut m = PopMember::from_expr(MemberId(row as u64), None, 0, expr, n_features);
+            m.complexity = r.complexity;
+            m.loss = T::from(r.loss).unwrap_or(T::infinity());
+            m.cost = T::from(r.cost).unwrap_or(m.loss);
+            m.parameters = r.parameters.iter().map(|&p| T::from(p).unwrap_or(T::nan())).collect();
+            m.subexprs = r
+                .subexpressions
+                .iter()
+                .map(|s| parse_postfix_string(s))
+                .collect::<Result<_, _>>()
+                .map_err(|error| HallOfFameIoError::Expression { row, error })?;
+            hall.best_by_complexity[r.complexity] = Some(m);
+        }
+        Ok(hall)
+    }
+
+    /// Writes the scored Pareto front as CSV (see the module docs for the columns).
+    pub fn to_csv(&self, variable_names: &[String]) -> String {
+        let mut out = CSV_COLUMNS.join(",");
+        out.push('\n');
+        for r in self.to_records(variable_names) {
+            let fields = [
+                r.complexity.to_string(),
+                r.loss.to_string(),
+                r.equation,
+                r.cost.to_string(),
+                r.score.to_string(),
+                r.expression,
+                r.parameters.iter().map(f64::to_string).collect::<Vec<_>>().join(" "),
+                r.subexpressions.join(";"),
+            ];
+            let fields: Vec<String> = fields.iter().map(|f| csv_quote(f)).collect();
+            out.push_str(&fields.join(","));
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Reads CSV written by [`HallOfFame::to_csv`] or by PySR. Only `Complexity`, `Loss` and one of
+    /// `Expression`/`Equation` are required; `Cost` defaults to the loss.
+    pub fn from_csv(text: &str, variable_names: &[String], n_features: usize) -> Result<Self, HallOfFameIoError> {
+        let rows = parse_csv(text)?;
+        let Some((header, rows)) = rows.split_first() else {
+            return Err(HallOfFameIoError::MissingColumn("Complexity"));
+        };
+        let col = |name: &'static str| header.iter().position(|h| h.trim() == name);
+        let complexity_col = col("Complexity").ok_or(HallOfFameIoError::MissingColumn("Complexity"))?;
+        let loss_col = col("Loss").ok_or(HallOfFameIoError::MissingColumn("Loss"))?;
+        let (equation_col, expression_col) = (col("Equation"), col("Expression"));
+        if equation_col.is_none() && expression_col.is_none() {
+            return Err(HallOfFameIoError::MissingColumn("Equation"));
+        }
+        let (cost_col, parameters_col, subexpressions_col) = (col("Cost"), col("Parameters"), col("Subexpressions"));
+
+        let records = rows
+            .iter()
+            .enumerate()
+            .map(|(row, fields)| {
+                let get = |c: Option<usize>| c.and_then(|c| fields.get(c)).map(|s| s.trim()).unwrap_or("");
+                let number = |c: usize, column: &'static str| {
+                    get(Some(c))
+                        .parse::<f64>()
+                        .map_err(|_| HallOfFameIoError::InvalidField {
+                            row,
+                            column,
+                            value: get(Some(c)).to_string(),
+                        })
+                };
+                let complexity =
+                    get(Some(complexity_col))
+                        .parse::<usize>()
+                        .map_err(|_| HallOfFameIoError::InvalidField {
+                            row,
+                            column: "Complexity",
+                            value: get(Some(complexity_col)).to_string(),
+                        })?;
+                let loss = number(loss_col, "Loss")?;
+                let cost = match cost_col {
+                    Some(c) if !get(Some(c)).is_empty() => number(c, "Cost")?,
+                    _ => loss,
+                };
+                let parameters = get(parameters_col)
+                    .split_whitespace()
+                    .map(|p| {
+                        p.parse::<f64>().map_err(|_| HallOfFameIoError::InvalidField {
+                            row,
+                            column: "Parameters",
+                            value: p.to_string(),
+                        })
+                    })
+                    .collect::<Result<_, _>>()?;
+                let subexpressions = get(subexpressions_col)
+                    .split(';')
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                Ok(HallOfFameRecord {
+                    complexity,
+                    loss,
+                    cost,
+                    score: 0.0,
+                    equation: get(equation_col).to_string(),
+                    expression: get(expression_col).to_string(),
+                    parameters,
+                    subexpressions,
+                })
+            })
+            .collect::<Result<Vec<_>, HallOfFameIoError>>()?;
+        Self::from_records(&records, variable_names, n_features)
+    }
+
+    /// Writes the scored Pareto front as a JSON array of [`HallOfFameRecord`]s.
+    #[cfg(feature = "serde_json")]
+    pub fn to_json(&self, variable_names: &[String]) -> String {
+        serde_json::to_string_pretty(&self.to_records(variable_names)).expect("records are serializable")
+    }
+
+    /// Reads JSON written by [`HallOfFame::to_json`].
+    #[cfg(feature = "serde_json")]
+    pub fn from_json(text: &str, variable_names: &[String], n_features: usize) -> Result<Self, HallOfFameIoError> {
+        let records: Vec<HallOfFameRecord> = serde_json::from_str(text).map_err(HallOfFameIoError::Json)?;
+        Self::from_records(&records, variable_names, n_features)
+    }
+}
+
+fn csv_quote(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Minimal RFC 4180 reader: quoted fields may contain commas, doubled quotes and newlines.
+fn parse_csv(text: &str) -> Result<Vec<Vec<String>>, HallOfFameIoError> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' => in_quotes = true,
+            ',' => row.push(std::mem::take(&mut field)),
+            '\r' => {}
+            '\n' => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            _ => field.push(c),
+        }
+    }
+    if in_quotes {
+        return Err(HallOfFameIoError::Csv {
+            row: rows.len().saturating_sub(1),
+            message: "unterminated quoted field".to_string(),
+        });
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows.retain(|r| !(r.len() == 1 && r[0].trim().is_empty()));
+    Ok(rows)
+}
@@ -0,0 +1,130 @@
+//! Cooperative early stopping for `equation_search_parallel`.
+//!
+//! Searches that opt in (`Options::handle_interrupts` / `Options::watch_stdin`) register a stop flag
+//! in a process-wide list. A SIGINT, or a line reading `q` on an interactive stdin, sets every
+//! registered flag; the search loop then stops dispatching new cycles, drains in-flight tasks,
+//! and returns the partial result. A SIGINT with no registered search (or a second SIGINT while
+//! stopping) terminates the process as usual.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use num_traits::Float;
+
+use crate::options::Options;
+
+static ACTIVE_SEARCHES: Mutex<Vec<Arc<AtomicBool>>> = Mutex::new(Vec::new());
+
+pub(crate) struct StopSignal {
+    flag: Arc<AtomicBool>,
+    registered: bool,
+}
+
+impl StopSignal {
+    pub(crate) fn new<T: Float, const D: usize>(options: &Options<T, D>) -> Self {
+        let flag = Arc::new(AtomicBool::new(false));
+        let mut registered = false;
+        if options.handle_interrupts {
+            registered |= imp::install_sigint_handler();
+        }
+        if options.watch_stdin {
+            registered |= imp::spawn_stdin_watcher();
+        }
+        if registered {
+            active_searches().push(flag.clone());
+        }
+        Self { flag, registered }
+    }
+
+    /// Stops this search only; [`request_stop`] stops every registered one.
+    #[cfg(test)]
+    pub(crate) fn request(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+
+    pub(crate) fn is_set(&self) -> bool {
+        self.flag.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for StopSignal {
+    fn drop(&mut self) {
+        if self.registered {
+            active_searches().retain(|f| !Arc::ptr_eq(f, &self.flag));
+        }
+    }
+}
+
+fn active_searches() -> std::sync::MutexGuard<'static, Vec<Arc<AtomicBool>>> {
+    ACTIVE_SEARCHES.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Sets the stop flag of every registered search.
+///
+/// Returns `false` if no search was registered, or if all of them were already stopping.
+pub(crate) fn request_stop() -> bool {
+    let mut any_new = false;
+    for f in active_searches().iter() {
+        any_new |= !f.swap(true, Ordering::Relaxed);
+    }
+    any_new
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod imp {
+    use std::io::{BufRead, IsTerminal};
+    use std::sync::OnceLock;
+
+    static SIGINT_INSTALLED: OnceLock<bool> = OnceLock::new();
+    static STDIN_WATCHER: OnceLock<bool> = OnceLock::new();
+
+    /// Installs the process-wide SIGINT handler once. Returns `false` if another handler
+    /// was already installed by the host application.
+    pub(super) fn install_sigint_handler() -> bool {
+        *SIGINT_INSTALLED.get_or_init(|| {
+            ctrlc::set_handler(|| {
+                if super::request_stop() {
+                    eprintln!("\nInterrupt received; stopping search early (press Ctrl-C again to abort)...");
+                } else {
+                    std::process::exit(130);
+                }
+            })
+            .is_ok()
+        })
+    }
+
+    /// Spawns the stdin watcher thread once, only when stdin is an interactive terminal.
+    pub(super) fn spawn_stdin_watcher() -> bool {
+        *STDIN_WATCHER.get_or_init(|| {
+            if !std::io::stdin().is_terminal() {
+                return false;
+            }
+            eprintln!("Started! Press 'q' and then <enter> to stop execution early.");
+            std::thread::Builder::new()
+                .name("sr-stdin-watcher".to_string())
+                .spawn(|| {
+                    let stdin = std::io::stdin();
+                    for line in stdin.lock().lines() {
+                        let Ok(line) = line else {
+                            break;
+                        };
+                        if line.trim().eq_ignore_ascii_case("q") && super::request_stop() {
+                            eprintln!("Stopping search early...");
+                        }
+                    }
+                })
+                .is_ok()
+        })
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod imp {
+    pub(super) fn install_sigint_handler() -> bool {
+        false
+    }
+
+    pub(super) fn spawn_stdin_watcher() -> bool {
+        false
+    }
+}
@@ -0,0 +1,108 @@
+//! Rust-only module (no direct Julia file): lexicase and epsilon-lexicase parent selection.
+//!
+//! Lexicase selection treats every row of the (mini-)batch as a separate test case. Starting from
+//! the whole population, it visits the cases in random order and keeps only the candidates with
+//! the lowest loss on each (see [`LossFn::row_losses`]), until one candidate is left or the
+//! cases run out. Members that fit some rows unusually well can therefore win even with a mediocre
+//! aggregate loss. Epsilon-lexicase (La Cava et al., 2016) also keeps candidates whose row loss is
+//! within `eps` of the best, where `eps` is the median absolute deviation of the population's
+//! row losses on that case; this suits continuous-valued errors, where exact ties are rare.
+
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::ops::AddAssign;
+
+use fastrand::Rng;
+use num_traits::Float;
+
+use crate::dataset::Dataset;
+use crate::loss_functions::LossFn;
+use crate::pop_member::{Evaluator, MemberId};
+use crate::population::Population;
+use crate::random::shuffle;
+
+/// Per-row losses of population members on one dataset, keyed by member id.
+///
+/// Members are evaluated once when first seen, so the cache must not outlive the dataset (or
+/// mini-batch) it was filled on, nor in-place changes to members such as constant optimization.
+#[derive(Default)]
+pub struct CaseErrors {
+    errors: HashMap<MemberId, Vec<f64>>,
+}
+
+impl CaseErrors {
+    /// Evaluates the members of `pop` not seen yet and forgets those no longer in it. Returns the
+    /// number of evaluations.
+    pub(crate) fn update<T, Ops, const D: usize>(
+        &mut self,
+        pop: &Population<T, Ops, D>,
+        dataset: &Dataset<T>,
+        loss: &dyn LossFn<T>,
+        evaluator: &mut Evaluator<T, D>,
+    ) -> f64
+    where
+        T: Float + AddAssign,
+        Ops: dynamic_expressions::OperatorSet<T = T>,
+    {
+        let live: HashSet<MemberId> = pop.members.iter().map(|m| m.id).collect();
+        self.errors.retain(|id, _| live.contains(id));
+        let mut evals = 0.0;
+        for m in &pop.members {
+            if let Entry::Vacant(e) = self.errors.entry(m.id) {
+                e.insert(m.row_errors(dataset, loss, evaluator));
+                evals += 1.0;
+            }
+        }
+        evals
+    }
+
+    /// The row losses of every member of `pop`, which must be up to date (see [`Self::update`]).
+    pub(crate) fn of<T: Float, Ops, const D: usize>(&self, pop: &Population<T, Ops, D>) -> Vec<&[f64]> {
+        pop.members.iter().map(|m| self.errors[&m.id].as_slice()).collect()
+    }
+}
+
+/// Index of the candidate lexicase selection picks from `errors` (one row of errors per
+/// candidate, all of the same length).
+pub(crate) fn lexicase_select(rng: &mut Rng, errors: &[&[f64]], epsilon: bool) -> usize {
+    assert!(!errors.is_empty(), "lexicase selection needs candidates");
+    let mut cases: Vec<usize> = (0..errors[0].len()).collect();
+    shuffle(rng, &mut cases);
+    let mut candidates: Vec<usize> = (0..errors.len()).collect();
+    for case in cases {
+        if candidates.len() <= 1 {
+            break;
+        }
+        let best = candidates
+            .iter()
+            .map(|&c| errors[c][case])
+            .fold(f64::INFINITY, f64::min);
+        let threshold = if epsilon {
+            best + median_absolute_deviation(errors.iter().map(|e| e[case]).collect())
+        } else {
+            best
+        };
+        candidates.retain(|&c| errors[c][case] <= threshold);
+    }
+    candidates[rng.usize(..candidates.len())]
+}
+
+/// The median of `|v - median(v)|` over the finite values, zero if there are none.
+fn median_absolute_deviation(mut values: Vec<f64>) -> f64 {
+    values.retain(|v| v.is_finite());
+    if values.is_empty() {
+        return 0.0;
+    }
+    let median = |v: &mut Vec<f64>| {
+        v.sort_by(f64::total_cmp);
+        let n = v.len();
+        if n % 2 == 1 {
+            v[n / 2]
+        } else {
+            0.5 * (v[n / 2 - 1] + v[n / 2])
+        }
+    };
+    let m = median(&mut values);
+    let mut deviations: Vec<f64> = values.iter().map(|v| (v - m).abs()).collect();
+    median(&mut deviations)
+}
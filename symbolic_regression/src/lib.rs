@@ -4,14 +4,26 @@ compile_error!(
 Build with RUSTFLAGS='-C target-feature=+atomics,+bulk-memory,+mutable-globals'."
 );
 
+pub(crate) mod adaptive_mutation;
 pub(crate) mod adaptive_parsimony;
 pub(crate) mod check_constraints;
 pub(crate) mod complexity;
 pub(crate) mod constant_optimization;
+pub(crate) mod custom_mutation;
 pub(crate) mod dataset;
+pub(crate) mod dimensional_analysis;
+pub(crate) mod distributed;
+pub(crate) mod feature_selection;
+pub(crate) mod full_objective;
+pub(crate) mod genealogy;
 pub(crate) mod hall_of_fame;
+pub(crate) mod hall_of_fame_io;
+pub(crate) mod interrupt;
+pub(crate) mod lexicase;
+pub(crate) mod linear_scaling;
 pub(crate) mod loss_functions;
 pub(crate) mod migration;
+pub(crate) mod multi_objective;
 pub(crate) mod mutate;
 pub(crate) mod mutation_functions;
 pub(crate) mod operator_library;
@@ -27,23 +39,45 @@ pub(crate) mod regularized_evolution;
 pub(crate) mod search_utils;
 pub(crate) mod selection;
 pub(crate) mod single_iteration;
+pub(crate) mod template;
 pub(crate) mod warmup;
 
 #[cfg(feature = "bench")]
 pub mod bench;
 
+pub use adaptive_mutation::{MutationAdaptation, MutationCounts, MutationStats};
 pub use check_constraints::{NestedConstraints, OpConstraints};
 pub use complexity::compute_complexity;
-pub use dataset::{Dataset, TaggedDataset};
+pub use custom_mutation::{Mutation, MutationContext, MutationObject};
+pub use dataset::{Dataset, MultiOutputDataset, SplitIndices, TaggedDataset};
+pub use dimensional_analysis::{SI_BASE_SYMBOLS, Units, violates_dimensional_constraints};
+pub use distributed::{DistributedError, Endpoint, WorkerListener, run_worker};
 #[doc(hidden)]
 pub use dynamic_expressions::custom_opset as __dynamic_expressions_custom_opset;
-pub use hall_of_fame::HallOfFame;
-pub use loss_functions::{LossKind, epsilon_insensitive, huber, log_cosh, lp, mae, make_loss, mse, quantile, rmse};
+pub use feature_selection::{mutual_information_scores, select_k_features};
+pub use full_objective::{ExprEvaluator, FullObjective, FullObjectiveObject};
+pub use genealogy::{Genealogy, GenealogyEvent, GenealogyEventKind};
+pub use hall_of_fame::{HallOfFame, LossSource, ModelSelection, pareto_scores};
+pub use hall_of_fame_io::{HallOfFameIoError, HallOfFameRecord};
+pub use linear_scaling::LinearScaling;
+pub use loss_functions::{
+    LossKind, epsilon_insensitive, gamma_deviance, hinge, huber, log_cosh, logistic, lp, mae, make_loss, mse,
+    poisson_deviance, quantile, rmse, softmax_cross_entropy, tweedie_deviance,
+};
+pub use migration::MigrationTopology;
+pub use multi_objective::{SelectionMode, SelectionObjective};
+pub use mutate::MutationChoice;
 pub use operator_library::OperatorLibrary;
 pub use operators::{OperatorSelectError, Operators};
-pub use options::{MutationWeights, Options, OutputStyle, WasmOptionsShim};
+pub use options::{
+    CrossoverWeights, IslandOptions, MutationWeights, OptimizerAlgorithm, Options, OutputStyle, WasmOptionsShim,
+};
 pub use pop_member::{MemberId, PopMember};
-pub use search_utils::{SearchEngine, SearchResult, equation_search};
+pub use search_utils::{
+    MultiOutputSearchResult, SearchEngine, SearchResult, equation_search, equation_search_distributed,
+    equation_search_multi_output, equation_search_with_validation,
+};
+pub use template::{ExpressionTemplate, TemplateError};
 #[cfg(feature = "bench")]
 pub use {
     crate::mutation_functions::{insert_random_op_in_place, random_expr, random_expr_append_ops, rotate_tree_in_place},
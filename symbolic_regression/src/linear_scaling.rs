@@ -0,0 +1,150 @@
+//! Rust-only module (no direct Julia file): Keijzer-style linear scaling of expression outputs.
+//!
+//! With `Options::linear_scaling`, a member is scored on `scale * f(x) + offset`, using the scale
+//! and offset that minimize the loss for its current outputs `f(x)`. The search then only has to
+//! find the shape of the target, not its scale or offset (Keijzer, 2003). For least-squares losses
+//! the pair is the closed-form weighted linear regression of `y` on `f(x)`; other losses refine that
+//! fit with a few iteratively reweighted least-squares (IRLS) steps. The fit is stored on the member
+//! (`PopMember::scaling`) and folded into its expression when results are exported.
+
+use dynamic_expressions::OperatorSet;
+use dynamic_expressions::expression::PostfixExpr;
+use dynamic_expressions::node::PNode;
+use num_traits::Float;
+
+use crate::loss_functions::LossFn;
+
+const IRLS_STEPS: usize = 5;
+
+/// The affine map `scale * f(x) + offset` applied to an expression's outputs.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LinearScaling<T> {
+    pub scale: T,
+    pub offset: T,
+}
+
+impl<T: Float> LinearScaling<T> {
+    pub fn identity() -> Self {
+        Self {
+            scale: T::one(),
+            offset: T::zero(),
+        }
+    }
+
+    /// The scaling minimizing `loss` for outputs `yhat` on targets `y` with row weights `w`.
+    pub fn fit(yhat: &[T], y: &[T], w: Option<&[T]>, loss: &dyn LossFn<T>) -> Self {
+        let fitted = weighted_least_squares(yhat, y, |i| w.map_or(T::one(), |w| w[i]));
+        if loss.is_least_squares() {
+            return fitted;
+        }
+
+        let mut scaled = vec![T::zero(); yhat.len()];
+        let mut grad = vec![T::zero(); yhat.len()];
+        let loss_of = |s: &Self, scaled: &mut [T]| {
+            scaled.copy_from_slice(yhat);
+            s.apply(scaled);
+            let l = loss.loss(scaled, y, w);
+            if l.is_nan() { T::infinity() } else { l }
+        };
+        // Start from the least-squares fit, unless the loss prefers the outputs as they are.
+        let (mut best, mut best_loss) = (fitted, loss_of(&fitted, &mut scaled));
+        let identity_loss = loss_of(&Self::identity(), &mut scaled);
+        if identity_loss < best_loss {
+            (best, best_loss) = (Self::identity(), identity_loss);
+        }
+        for _ in 0..IRLS_STEPS {
+            // `dloss/dyhat / residual` is the IRLS weight `psi(r) / r` (including the row
+            // weights); rows without a usable ratio, e.g. fitted exactly, get the largest one.
+            scaled.copy_from_slice(yhat);
+            best.apply(&mut scaled);
+            loss.dloss_dyhat(&scaled, y, w, &mut grad);
+            let ratios: Vec<Option<T>> = scaled
+                .iter()
+                .zip(y)
+                .zip(&grad)
+                .map(|((&p, &t), &g)| Some(g / (p - t)).filter(|v| v.is_finite() && *v >= T::zero()))
+                .collect();
+            let Some(max) = ratios.iter().flatten().copied().reduce(T::max) else {
+                break;
+            };
+            let candidate = weighted_least_squares(yhat, y, |i| ratios[i].unwrap_or(max));
+            let candidate_loss = loss_of(&candidate, &mut scaled);
+            if candidate_loss >= best_loss {
+                break;
+            }
+            (best, best_loss) = (candidate, candidate_loss);
+        }
+        best
+    }
+
+    pub fn apply(&self, yhat: &mut [T]) {
+        for v in yhat {
+            *v = self.scale * *v + self.offset;
+        }
+    }
+
+    /// `expr` with this scaling folded in as `expr * scale + offset`, leaving out a unit scale and a
+    /// zero offset, and keeping the shared subexpressions of `expr`; `None` if `Ops` has no binary
+    /// `*` or `+`.
+    pub fn fold_into<Ops, const D: usize>(&self, expr: &PostfixExpr<T, Ops, D>) -> Option<PostfixExpr<T, Ops, D>>
+    where
+        Ops: OperatorSet<T = T>,
+    {
+        let mut out = expr.clone();
+        for (token, value, neutral) in [("*", self.scale, T::one()), ("+", self.offset, T::zero())] {
+            if value == neutral {
+                continue;
+            }
+            let op = Ops::lookup_with_arity(token, 2).ok()?;
+            let idx: u16 = out
+                .consts
+                .len()
+                .try_into()
+                .unwrap_or_else(|_| panic!("too many constants to index in u16"));
+            out.consts.push(value);
+            out.nodes.push(PNode::Const { idx });
+            out.nodes.push(PNode::Op { arity: 2, op: op.id });
+        }
+        dynamic_expressions::inherit_sharing(expr, &mut out);
+        Some(out)
+    }
+
+    /// Whether [`Self::fold_into`] can fold any scaling into `Ops` expressions.
+    pub(crate) fn can_fold<Ops: OperatorSet<T = T>>() -> bool {
+        Ops::lookup_with_arity("*", 2).is_ok() && Ops::lookup_with_arity("+", 2).is_ok()
+    }
+}
+
+/// Weighted linear regression of `y` on `f`; a constant `f` keeps a unit scale and only gets an
+/// offset.
+fn weighted_least_squares<T: Float>(f: &[T], y: &[T], w: impl Fn(usize) -> T) -> LinearScaling<T> {
+    let (mut sw, mut sf, mut sy) = (T::zero(), T::zero(), T::zero());
+    for (i, (&fi, &yi)) in f.iter().zip(y).enumerate() {
+        let wi = w(i);
+        sw = sw + wi;
+        sf = sf + wi * fi;
+        sy = sy + wi * yi;
+    }
+    if !sw.is_finite() || sw <= T::zero() {
+        return LinearScaling::identity();
+    }
+    let (mean_f, mean_y) = (sf / sw, sy / sw);
+    let (mut sff, mut sfy) = (T::zero(), T::zero());
+    for (i, (&fi, &yi)) in f.iter().zip(y).enumerate() {
+        let df = fi - mean_f;
+        sff = sff + w(i) * df * df;
+        sfy = sfy + w(i) * df * (yi - mean_y);
+    }
+    let resolution = T::epsilon() * (T::one() + mean_f.abs());
+    let scale = if sff / sw > resolution * resolution {
+        sfy / sff
+    } else {
+        T::one()
+    };
+    let offset = mean_y - scale * mean_f;
+    if scale.is_finite() && offset.is_finite() {
+        LinearScaling { scale, offset }
+    } else {
+        LinearScaling::identity()
+    }
+}
@@ -1,3 +1,4 @@
+use std::ops::AddAssign;
 use std::sync::Arc;
 
 use dynamic_expressions::utils::ZipEq;
@@ -6,10 +7,64 @@ use ndarray::Array2;
 use num_traits::Float;
 
 use crate::dataset::Dataset;
+use crate::full_objective::PostfixExprEvaluator;
+use crate::options::Options;
 
 pub trait LossFn<T: Float>: Send + Sync {
     fn loss(&self, yhat: &[T], y: &[T], w: Option<&[T]>) -> T;
     fn dloss_dyhat(&self, yhat: &[T], y: &[T], w: Option<&[T]>, out: &mut [T]);
+
+    /// Constant prediction used for the baseline loss, when the zero expression is not a
+    /// meaningful null model for this loss. `None` (the default) uses the zero expression.
+    fn baseline_prediction(&self, _y: &[T], _w: Option<&[T]>) -> Option<T> {
+        None
+    }
+
+    /// Whether the loss increases monotonically with the (weighted) mean squared error, so that
+    /// least-squares solvers such as Levenberg–Marquardt minimize it too.
+    fn is_least_squares(&self) -> bool {
+        false
+    }
+
+    /// Whether the loss is differentiable in `yhat` everywhere, so gradient-based constant
+    /// optimizers can rely on `dloss_dyhat`.
+    fn is_smooth(&self) -> bool {
+        true
+    }
+
+    /// Number of consecutive rows forming one sample. Batches and validation splits keep these
+    /// groups whole (see [`crate::SplitIndices::random_groups`]).
+    fn rows_per_sample(&self) -> usize {
+        1
+    }
+
+    /// The error of every row, as lexicase selection compares them: the loss of each sample (see
+    /// [`Self::rows_per_sample`]) on its own, scaled by its mean row weight, for each of its rows.
+    fn row_losses(&self, yhat: &[T], y: &[T], w: Option<&[T]>, out: &mut [T]) {
+        assert_eq!(yhat.len(), y.len());
+        assert_eq!(out.len(), y.len());
+        let rows = self.rows_per_sample().max(1);
+        for (start, o) in (0..y.len()).step_by(rows).zip(out.chunks_mut(rows)) {
+            let sample = start..start + o.len();
+            let w = w.map(|w| &w[sample.clone()]);
+            let weight = w.map_or(T::one(), |w| {
+                w.iter().copied().fold(T::zero(), |a, b| a + b) / T::from(w.len()).unwrap()
+            });
+            o.fill(weight * self.loss(&yhat[sample.clone()], &y[sample], w));
+        }
+    }
+}
+
+/// Panics with a clear message unless `dataset` (named `what` in the message) consists of whole
+/// samples of `options.loss` (see [`LossFn::rows_per_sample`]).
+pub(crate) fn check_whole_samples<T: Float, const D: usize>(dataset: &Dataset<T>, options: &Options<T, D>, what: &str) {
+    let rows = options.loss.rows_per_sample();
+    assert!(rows > 0, "the loss must group at least one row per sample");
+    assert!(
+        dataset.n_rows.is_multiple_of(rows),
+        "the {what} dataset has {} rows, which is not a multiple of the loss's {rows} rows per sample",
+        dataset.n_rows
+    );
 }
 
 pub fn baseline_loss_from_zero_expression<T: Float, Ops, const D: usize>(
@@ -19,6 +74,12 @@ pub fn baseline_loss_from_zero_expression<T: Float, Ops, const D: usize>(
 where
     Ops: OperatorSet<T = T>,
 {
+    if let Some(c) = loss.baseline_prediction(dataset.y_slice(), dataset.weights_slice()) {
+        let yhat = vec![c; dataset.n_rows];
+        let base = loss.loss(&yhat, dataset.y_slice(), dataset.weights_slice());
+        return base.is_finite().then_some(base);
+    }
+
     let expr: dynamic_expressions::expression::PostfixExpr<T, Ops, D> =
         dynamic_expressions::expression::PostfixExpr::zero();
     let plan = dynamic_expressions::compile_plan(&expr.nodes, dataset.n_features, expr.consts.len());
@@ -40,6 +101,24 @@ where
     base.is_finite().then_some(base)
 }
 
+/// Baseline loss of the zero expression under the configured objective
+/// (`Options::full_objective` if set, otherwise `Options::loss`).
+pub fn baseline_loss<T: Float + AddAssign, Ops, const D: usize>(
+    dataset: &Dataset<T>,
+    options: &Options<T, D>,
+) -> Option<T>
+where
+    Ops: OperatorSet<T = T>,
+{
+    let Some(objective) = options.full_objective.as_ref() else {
+        return baseline_loss_from_zero_expression::<T, Ops, D>(dataset, options.loss.as_ref());
+    };
+    let mut expr: dynamic_expressions::expression::PostfixExpr<T, Ops, D> =
+        dynamic_expressions::expression::PostfixExpr::zero();
+    let base = objective.loss(&mut PostfixExprEvaluator::new(&mut expr), dataset);
+    base.is_finite().then_some(base)
+}
+
 pub fn loss_to_cost<T: Float>(
     loss: T,
     complexity: usize,
@@ -74,11 +153,29 @@ pub enum LossKind {
     Lp { p: f64 },
     Quantile { tau: f64 },
     EpsilonInsensitive { eps: f64 },
+    Logistic,
+    Hinge,
+    SoftmaxCrossEntropy { n_classes: usize },
+    Poisson,
+    Gamma,
+    Tweedie { power: f64 },
 }
 
 impl LossKind {
+    /// Parses a loss name with default parameters. Softmax cross-entropy needs its class count,
+    /// as in `"softmax:3"`; without it, the result is `None`.
     pub fn parse(s: &str) -> Option<Self> {
-        match s.trim().to_ascii_lowercase().as_str() {
+        let s = s.trim().to_ascii_lowercase();
+        if let Some((name, n_classes)) = s.split_once(':') {
+            return match name.trim() {
+                "softmax" | "softmax-cross-entropy" | "softmax_cross_entropy" | "cross-entropy" | "cross_entropy" => {
+                    let n_classes = n_classes.trim().parse().ok().filter(|&n| n > 0)?;
+                    Some(Self::SoftmaxCrossEntropy { n_classes })
+                }
+                _ => None,
+            };
+        }
+        match s.as_str() {
             "mse" => Some(Self::Mse),
             "mae" => Some(Self::Mae),
             "rmse" => Some(Self::Rmse),
@@ -89,6 +186,11 @@ impl LossKind {
             "epsilon-insensitive" | "epsilon_insensitive" | "eps-insensitive" | "eps_insensitive" => {
                 Some(Self::EpsilonInsensitive { eps: 0.1 })
             }
+            "logistic" | "bce" | "binary-cross-entropy" | "binary_cross_entropy" => Some(Self::Logistic),
+            "hinge" => Some(Self::Hinge),
+            "poisson" => Some(Self::Poisson),
+            "gamma" => Some(Self::Gamma),
+            "tweedie" => Some(Self::Tweedie { power: 1.5 }),
             _ => None,
         }
     }
@@ -104,18 +206,51 @@ pub fn make_loss<T: Float + Send + Sync + 'static>(kind: LossKind) -> LossObject
         LossKind::Lp { p } => lp::<T>(p),
         LossKind::Quantile { tau } => quantile::<T>(tau),
         LossKind::EpsilonInsensitive { eps } => epsilon_insensitive::<T>(eps),
+        LossKind::Logistic => logistic::<T>(),
+        LossKind::Hinge => hinge::<T>(),
+        LossKind::SoftmaxCrossEntropy { n_classes } => softmax_cross_entropy::<T>(n_classes),
+        LossKind::Poisson => poisson_deviance::<T>(),
+        LossKind::Gamma => gamma_deviance::<T>(),
+        LossKind::Tweedie { power } => tweedie_deviance::<T>(power),
     }
 }
 
 pub trait PointwiseLoss<T: Float> {
     fn point_loss(&self, yhat: T, y: T) -> T;
     fn point_dloss_dyhat(&self, yhat: T, y: T) -> T;
+
+    /// See [`LossFn::baseline_prediction`].
+    fn baseline_prediction(&self, _y: &[T], _w: Option<&[T]>) -> Option<T> {
+        None
+    }
+
+    /// See [`LossFn::is_least_squares`].
+    fn is_squared_error(&self) -> bool {
+        false
+    }
+
+    /// See [`LossFn::is_smooth`].
+    fn is_smooth(&self) -> bool {
+        true
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct MeanLoss<L>(pub L);
 
 impl<T: Float, L: PointwiseLoss<T> + Send + Sync> LossFn<T> for MeanLoss<L> {
+    fn baseline_prediction(&self, y: &[T], w: Option<&[T]>) -> Option<T> {
+        self.0.baseline_prediction(y, w)
+    }
+
+    fn is_least_squares(&self) -> bool {
+        self.0.is_squared_error()
+    }
+
+    fn is_smooth(&self) -> bool {
+        self.0.is_smooth()
+    }
+
     fn loss(&self, yhat: &[T], y: &[T], w: Option<&[T]>) -> T {
         assert_eq!(yhat.len(), y.len());
         match w {
@@ -188,6 +323,10 @@ impl<T: Float> PointwiseLoss<T> for SquaredLoss {
     fn point_dloss_dyhat(&self, yhat: T, y: T) -> T {
         T::from(2.0).unwrap() * (yhat - y)
     }
+
+    fn is_squared_error(&self) -> bool {
+        true
+    }
 }
 
 #[derive(Clone, Debug, Default)]
@@ -208,6 +347,10 @@ impl<T: Float> PointwiseLoss<T> for AbsLoss {
             T::zero()
         }
     }
+
+    fn is_smooth(&self) -> bool {
+        false
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -278,6 +421,10 @@ impl<T: Float> PointwiseLoss<T> for LpLoss<T> {
         let s = if r > T::zero() { T::one() } else { -T::one() };
         p * ar.powf(p - T::one()) * s
     }
+
+    fn is_smooth(&self) -> bool {
+        self.p > T::one()
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -305,6 +452,10 @@ impl<T: Float> PointwiseLoss<T> for QuantileLoss<T> {
             T::zero()
         }
     }
+
+    fn is_smooth(&self) -> bool {
+        false
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -333,6 +484,220 @@ impl<T: Float> PointwiseLoss<T> for EpsilonInsensitiveLoss<T> {
             T::zero()
         }
     }
+
+    fn is_smooth(&self) -> bool {
+        false
+    }
+}
+
+/// Binary cross-entropy on a sigmoid link: `yhat` is a logit and `y` is in `{0, 1}`
+/// (or a probability).
+#[derive(Clone, Debug, Default)]
+pub struct LogisticLoss;
+
+impl<T: Float> PointwiseLoss<T> for LogisticLoss {
+    fn point_loss(&self, yhat: T, y: T) -> T {
+        // softplus(z) - y*z, written to avoid overflow for large |z|.
+        yhat.max(T::zero()) - y * yhat + (-yhat.abs()).exp().ln_1p()
+    }
+
+    fn point_dloss_dyhat(&self, yhat: T, y: T) -> T {
+        T::one() / (T::one() + (-yhat).exp()) - y
+    }
+}
+
+/// Hinge loss on a raw margin `yhat`; labels `y > 0` are the positive class, everything else
+/// (`0` or `-1`) is negative.
+#[derive(Clone, Debug, Default)]
+pub struct HingeLoss;
+
+impl<T: Float> PointwiseLoss<T> for HingeLoss {
+    fn point_loss(&self, yhat: T, y: T) -> T {
+        let s = if y > T::zero() { T::one() } else { -T::one() };
+        (T::one() - s * yhat).max(T::zero())
+    }
+
+    fn point_dloss_dyhat(&self, yhat: T, y: T) -> T {
+        let s = if y > T::zero() { T::one() } else { -T::one() };
+        if T::one() - s * yhat > T::zero() { -s } else { T::zero() }
+    }
+
+    fn is_smooth(&self) -> bool {
+        false
+    }
+}
+
+/// Multi-class softmax cross-entropy over groups of `n_classes` consecutive rows.
+///
+/// Each sample occupies `n_classes` rows (one per class, e.g. with the class index as a feature),
+/// `yhat` holds the logit of each class and `y` the one-hot (or soft) label. The weight of a group
+/// is the weight of its first row. Batching resamples whole groups; validation datasets must also
+/// consist of whole groups (see [`crate::SplitIndices::random_groups`]).
+#[derive(Clone, Debug)]
+pub struct SoftmaxCrossEntropy {
+    pub n_classes: usize,
+}
+
+impl SoftmaxCrossEntropy {
+    fn groups<'a, T: Float>(&self, yhat: &'a [T], y: &'a [T]) -> impl ExactSizeIterator<Item = (&'a [T], &'a [T])> {
+        assert_eq!(yhat.len(), y.len());
+        assert!(self.n_classes > 0, "softmax cross-entropy requires n_classes > 0");
+        assert_eq!(
+            y.len() % self.n_classes,
+            0,
+            "softmax cross-entropy requires n_rows to be a multiple of n_classes"
+        );
+        yhat.chunks_exact(self.n_classes).zip_eq(y.chunks_exact(self.n_classes))
+    }
+
+    fn group_weight<T: Float>(&self, w: Option<&[T]>, g: usize) -> T {
+        w.map_or(T::one(), |w| w[g * self.n_classes])
+    }
+
+    fn sum_group_weights<T: Float>(&self, w: Option<&[T]>, n_groups: usize) -> T {
+        (0..n_groups)
+            .map(|g| self.group_weight(w, g))
+            .fold(T::zero(), |a, b| a + b)
+    }
+}
+
+fn log_sum_exp<T: Float>(z: &[T]) -> T {
+    let m = z.iter().copied().fold(T::neg_infinity(), T::max);
+    if !m.is_finite() {
+        return m;
+    }
+    m + z.iter().map(|&v| (v - m).exp()).fold(T::zero(), |a, b| a + b).ln()
+}
+
+impl<T: Float> LossFn<T> for SoftmaxCrossEntropy {
+    fn rows_per_sample(&self) -> usize {
+        self.n_classes
+    }
+
+    fn loss(&self, yhat: &[T], y: &[T], w: Option<&[T]>) -> T {
+        let n_groups = y.len() / self.n_classes.max(1);
+        if n_groups == 0 {
+            return T::zero();
+        }
+        let sum_w = self.sum_group_weights(w, n_groups);
+        if sum_w == T::zero() {
+            return T::zero();
+        }
+        self.groups(yhat, y)
+            .enumerate()
+            .map(|(g, (z, yg))| {
+                let lse = log_sum_exp(z);
+                let ce = z
+                    .iter()
+                    .zip_eq(yg)
+                    .map(|(&zk, &yk)| yk * (lse - zk))
+                    .fold(T::zero(), |a, b| a + b);
+                self.group_weight(w, g) * ce
+            })
+            .fold(T::zero(), |a, b| a + b)
+            / sum_w
+    }
+
+    fn dloss_dyhat(&self, yhat: &[T], y: &[T], w: Option<&[T]>, out: &mut [T]) {
+        assert_eq!(out.len(), y.len());
+        let n_groups = y.len() / self.n_classes.max(1);
+        let sum_w = self.sum_group_weights(w, n_groups);
+        if n_groups == 0 || sum_w == T::zero() {
+            out.fill(T::zero());
+            return;
+        }
+        for ((g, (z, yg)), o) in self
+            .groups(yhat, y)
+            .enumerate()
+            .zip_eq(out.chunks_exact_mut(self.n_classes))
+        {
+            let lse = log_sum_exp(z);
+            let y_total = yg.iter().copied().fold(T::zero(), |a, b| a + b);
+            let scale = self.group_weight(w, g) / sum_w;
+            for ((ok, &zk), &yk) in o.iter_mut().zip_eq(z).zip_eq(yg) {
+                *ok = scale * ((zk - lse).exp() * y_total - yk);
+            }
+        }
+    }
+}
+
+/// Intercept-only null model under a log link: `ln` of the (weighted) mean target.
+fn log_mean_prediction<T: Float>(y: &[T], w: Option<&[T]>) -> Option<T> {
+    let m = Dataset::compute_avg_y(y, w);
+    (m > T::zero()).then(|| m.ln())
+}
+
+/// Poisson deviance with a log link: `yhat` is the linear predictor `eta`, `mu = exp(eta)`,
+/// and `y >= 0`.
+#[derive(Clone, Debug, Default)]
+pub struct PoissonDeviance;
+
+impl<T: Float> PointwiseLoss<T> for PoissonDeviance {
+    fn point_loss(&self, yhat: T, y: T) -> T {
+        let two = T::from(2.0).unwrap();
+        let y_ln_y = if y > T::zero() { y * y.ln() } else { T::zero() };
+        two * (y_ln_y - y * yhat - y + yhat.exp())
+    }
+
+    fn point_dloss_dyhat(&self, yhat: T, y: T) -> T {
+        T::from(2.0).unwrap() * (yhat.exp() - y)
+    }
+
+    fn baseline_prediction(&self, y: &[T], w: Option<&[T]>) -> Option<T> {
+        log_mean_prediction(y, w)
+    }
+}
+
+/// Gamma deviance with a log link: `yhat` is the linear predictor `eta`, `mu = exp(eta)`,
+/// and `y > 0`.
+#[derive(Clone, Debug, Default)]
+pub struct GammaDeviance;
+
+impl<T: Float> PointwiseLoss<T> for GammaDeviance {
+    fn point_loss(&self, yhat: T, y: T) -> T {
+        let two = T::from(2.0).unwrap();
+        two * (yhat - y.ln() + y * (-yhat).exp() - T::one())
+    }
+
+    fn point_dloss_dyhat(&self, yhat: T, y: T) -> T {
+        T::from(2.0).unwrap() * (T::one() - y * (-yhat).exp())
+    }
+
+    fn baseline_prediction(&self, y: &[T], w: Option<&[T]>) -> Option<T> {
+        log_mean_prediction(y, w)
+    }
+}
+
+/// Tweedie deviance with a log link and variance power `power` (not 1 or 2; use
+/// [`PoissonDeviance`] / [`GammaDeviance`] for those). `yhat` is the linear predictor `eta`.
+#[derive(Clone, Debug)]
+pub struct TweedieDeviance<T: Float> {
+    pub power: T,
+}
+
+impl<T: Float> PointwiseLoss<T> for TweedieDeviance<T> {
+    fn point_loss(&self, yhat: T, y: T) -> T {
+        let two = T::from(2.0).unwrap();
+        let p = self.power;
+        let one_m_p = T::one() - p;
+        let two_m_p = two - p;
+        let y_term = if y > T::zero() {
+            y.powf(two_m_p) / (one_m_p * two_m_p)
+        } else {
+            T::zero()
+        };
+        two * (y_term - y * (one_m_p * yhat).exp() / one_m_p + (two_m_p * yhat).exp() / two_m_p)
+    }
+
+    fn point_dloss_dyhat(&self, yhat: T, y: T) -> T {
+        let two = T::from(2.0).unwrap();
+        let p = self.power;
+        two * ((two - p) * yhat).exp() - two * y * ((T::one() - p) * yhat).exp()
+    }
+
+    fn baseline_prediction(&self, y: &[T], w: Option<&[T]>) -> Option<T> {
+        log_mean_prediction(y, w)
+    }
 }
 
 #[derive(Clone, Debug, Default)]
@@ -343,6 +708,10 @@ impl<T: Float> LossFn<T> for Rmse {
         mse::<T>().loss(yhat, y, w).sqrt()
     }
 
+    fn is_least_squares(&self) -> bool {
+        true
+    }
+
     fn dloss_dyhat(&self, yhat: &[T], y: &[T], w: Option<&[T]>, out: &mut [T]) {
         assert_eq!(yhat.len(), y.len());
         assert_eq!(out.len(), y.len());
@@ -417,3 +786,36 @@ pub fn epsilon_insensitive<T: Float + Send + Sync + 'static>(eps: f64) -> LossOb
         eps: T::from(eps).unwrap_or_else(|| T::from(0.1).unwrap()),
     }))
 }
+
+pub fn logistic<T: Float>() -> LossObject<T> {
+    Arc::new(MeanLoss(LogisticLoss))
+}
+
+pub fn hinge<T: Float>() -> LossObject<T> {
+    Arc::new(MeanLoss(HingeLoss))
+}
+
+pub fn softmax_cross_entropy<T: Float>(n_classes: usize) -> LossObject<T> {
+    assert!(n_classes > 0, "softmax cross-entropy requires n_classes > 0");
+    Arc::new(SoftmaxCrossEntropy { n_classes })
+}
+
+pub fn poisson_deviance<T: Float>() -> LossObject<T> {
+    Arc::new(MeanLoss(PoissonDeviance))
+}
+
+pub fn gamma_deviance<T: Float>() -> LossObject<T> {
+    Arc::new(MeanLoss(GammaDeviance))
+}
+
+pub fn tweedie_deviance<T: Float + Send + Sync + 'static>(power: f64) -> LossObject<T> {
+    if power == 1.0 {
+        return poisson_deviance::<T>();
+    }
+    if power == 2.0 {
+        return gamma_deviance::<T>();
+    }
+    Arc::new(MeanLoss(TweedieDeviance {
+        power: T::from(power).unwrap_or_else(|| T::from(1.5).unwrap()),
+    }))
+}
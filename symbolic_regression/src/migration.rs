@@ -3,9 +3,67 @@ use std::cmp::Ordering;
 use fastrand::Rng;
 use num_traits::Float;
 
+use crate::genealogy::{Genealogy, GenealogyEvent, GenealogyEventKind, finite_loss};
 use crate::pop_member::{MemberId, PopMember};
 use crate::population::Population;
-use crate::random::{choose, poisson_sample, usize_range};
+use crate::random::{choose, poisson_sample, shuffle, usize_range};
+
+/// Which populations ("islands") exchange their best members during migration.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum MigrationTopology {
+    /// Every population draws from the pooled best members of all the others.
+    #[default]
+    Global,
+    /// Population `i` draws from population `i - 1` (wrapping around).
+    Ring,
+    /// Populations sit on a `width`-column grid with wrap-around and draw from their four
+    /// neighbours.
+    Torus { width: usize },
+    /// Each migration draws from `k` other populations chosen at random.
+    RandomNeighbours { k: usize },
+    /// Population 0 is a hub drawing from all the others; the others draw only from the hub.
+    Star,
+}
+
+impl MigrationTopology {
+    /// Populations that may send migrants to population `dst` (never `dst` itself).
+    pub(crate) fn sources(&self, dst: usize, n_pops: usize, rng: &mut Rng) -> Vec<usize> {
+        if n_pops <= 1 {
+            return Vec::new();
+        }
+        let others = || (0..n_pops).filter(move |&i| i != dst);
+        match *self {
+            MigrationTopology::Global => others().collect(),
+            MigrationTopology::Ring => vec![(dst + n_pops - 1) % n_pops],
+            MigrationTopology::Torus { width } => {
+                let width = width.clamp(1, n_pops);
+                let rows = n_pops.div_ceil(width);
+                let (r, c) = (dst / width, dst % width);
+                let mut v: Vec<usize> = [
+                    ((r + rows - 1) % rows, c),
+                    ((r + 1) % rows, c),
+                    (r, (c + width - 1) % width),
+                    (r, (c + 1) % width),
+                ]
+                .into_iter()
+                .map(|(r, c)| r * width + c)
+                .filter(|&i| i < n_pops && i != dst)
+                .collect();
+                v.sort_unstable();
+                v.dedup();
+                v
+            }
+            MigrationTopology::RandomNeighbours { k } => {
+                let mut v: Vec<usize> = others().collect();
+                shuffle(rng, &mut v);
+                v.truncate(k);
+                v
+            }
+            MigrationTopology::Star if dst == 0 => others().collect(),
+            MigrationTopology::Star => vec![0],
+        }
+    }
+}
 
 pub fn best_sub_pop<T: Float, Ops, const D: usize>(
     pop: &Population<T, Ops, D>,
@@ -29,6 +87,7 @@ pub fn migrate_into<T: Float, Ops, const D: usize>(
     rng: &mut Rng,
     next_id: &mut u64,
     next_birth: &mut u64,
+    mut genealogy: Option<&mut Genealogy>,
 ) {
     if migrants.is_empty() {
         return;
@@ -59,6 +118,16 @@ pub fn migrate_into<T: Float, Ops, const D: usize>(
         *next_id += 1;
         m.birth = *next_birth;
         *next_birth += 1;
+        if let Some(genealogy) = genealogy.as_deref_mut() {
+            genealogy.record(GenealogyEvent {
+                kind: GenealogyEventKind::Migration,
+                child: m.id,
+                parents: vec![src.id],
+                accepted: true,
+                loss_before: finite_loss(src.loss),
+                loss_after: finite_loss(m.loss),
+            });
+        }
         dst.members[loc] = m;
     }
 }
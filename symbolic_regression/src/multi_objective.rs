@@ -0,0 +1,244 @@
+//! Rust-only module (no direct Julia file): NSGA-II style selection and replacement.
+//!
+//! By default tournaments compare members by `cost`, which folds complexity into the loss through
+//! `parsimony` (and frequency scaling), and children replace the oldest member. With
+//! `Options::selection` set to [`SelectionMode::Nsga2`], members are instead ranked on several
+//! objectives at once: by non-dominated front first, then by crowding distance within the front,
+//! so tournaments prefer members that are Pareto-optimal and far from their neighbours. A child
+//! then joins the population by displacing its worst-ranked member, or is discarded if it ranks
+//! worst itself.
+
+use std::cmp::Ordering;
+
+use num_traits::Float;
+
+use crate::pop_member::PopMember;
+
+/// A quantity NSGA-II selection minimizes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SelectionObjective {
+    Loss,
+    Complexity,
+    /// Number of constants in the expression.
+    Constants,
+    /// `PopMember::validation_loss`, falling back to the training loss for members without one
+    /// (population members only carry it after hall-of-fame migration).
+    ValidationLoss,
+}
+
+impl SelectionObjective {
+    /// `m`'s value for this objective; NaN counts as infinitely bad.
+    pub fn value<T: Float, Ops, const D: usize>(self, m: &PopMember<T, Ops, D>) -> f64 {
+        let v = match self {
+            SelectionObjective::Loss => m.loss.to_f64().unwrap_or(f64::NAN),
+            SelectionObjective::Complexity => m.complexity as f64,
+            SelectionObjective::Constants => m.expr.consts.len() as f64,
+            SelectionObjective::ValidationLoss => m.validation_loss.unwrap_or(m.loss).to_f64().unwrap_or(f64::NAN),
+        };
+        if v.is_nan() { f64::INFINITY } else { v }
+    }
+}
+
+/// How tournaments pick parents and which member a child replaces.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum SelectionMode {
+    /// Rank by `cost`; children replace the oldest member.
+    #[default]
+    Cost,
+    /// Rank by non-dominated front and crowding distance over `objectives`; children replace the
+    /// worst-ranked member (the oldest among ties).
+    Nsga2 { objectives: Vec<SelectionObjective> },
+    /// Pick parents by lexicase selection, with the rows of the current (mini-)batch as test cases
+    /// and their losses (see `LossFn::row_losses`) as errors; children replace the
+    /// oldest member. Not available with `Options::full_objective`.
+    Lexicase,
+    /// Like `Lexicase`, but keep every candidate within the median absolute deviation of the best
+    /// on each row.
+    EpsilonLexicase,
+}
+
+impl SelectionMode {
+    /// NSGA-II over loss and complexity.
+    pub fn nsga2() -> Self {
+        SelectionMode::Nsga2 {
+            objectives: vec![SelectionObjective::Loss, SelectionObjective::Complexity],
+        }
+    }
+}
+
+/// Non-dominated front (0 is the Pareto front) and crowding distance of every point.
+///
+/// [`Self::push`] and [`Self::swap_remove`] mirror the same calls on the ranked members and only
+/// revisit the points dominated by the one added or removed, so a search cycle ranks its
+/// population once rather than once per child.
+pub(crate) struct Nsga2Ranking {
+    points: Vec<Vec<f64>>,
+    pub(crate) ranks: Vec<usize>,
+    pub(crate) crowding: Vec<f64>,
+}
+
+impl Nsga2Ranking {
+    pub(crate) fn new(points: Vec<Vec<f64>>) -> Self {
+        let ranks = nondominated_ranks(&points);
+        let crowding = crowding_distances(&points, &ranks);
+        Self {
+            points,
+            ranks,
+            crowding,
+        }
+    }
+
+    pub(crate) fn of_members<'a, T: Float + 'a, Ops: 'a, const D: usize>(
+        members: impl IntoIterator<Item = &'a PopMember<T, Ops, D>>,
+        objectives: &[SelectionObjective],
+    ) -> Self {
+        Self::new(members.into_iter().map(|m| point_of(m, objectives)).collect())
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    /// Ranks `m` as the last point. Its front is one past the worst front among its dominators,
+    /// and only the points it dominates can move to a later front.
+    pub(crate) fn push<T: Float, Ops, const D: usize>(
+        &mut self,
+        m: &PopMember<T, Ops, D>,
+        objectives: &[SelectionObjective],
+    ) {
+        let point = point_of(m, objectives);
+        let rank = (0..self.points.len())
+            .filter(|&i| dominates(&self.points[i], &point))
+            .map(|i| self.ranks[i] + 1)
+            .max()
+            .unwrap_or(0);
+        let affected = self.dominated_by(&point);
+        self.points.push(point);
+        self.ranks.push(rank);
+        let new = self.points.len() - 1;
+        for (k, &q) in affected.iter().enumerate() {
+            // `affected` is ordered by front, so every dominator of `q` among the points whose
+            // front changed has already been updated.
+            let raised = std::iter::once(new)
+                .chain(affected[..k].iter().copied())
+                .filter(|&r| dominates(&self.points[r], &self.points[q]))
+                .map(|r| self.ranks[r] + 1)
+                .max()
+                .unwrap_or(0);
+            self.ranks[q] = self.ranks[q].max(raised);
+        }
+        self.crowding = crowding_distances(&self.points, &self.ranks);
+    }
+
+    /// Drops point `i`, moving the last point into its place. Only the points it dominated can
+    /// move to an earlier front.
+    pub(crate) fn swap_remove(&mut self, i: usize) {
+        let removed = self.points.swap_remove(i);
+        self.ranks.swap_remove(i);
+        for q in self.dominated_by(&removed) {
+            self.ranks[q] = (0..self.points.len())
+                .filter(|&r| dominates(&self.points[r], &self.points[q]))
+                .map(|r| self.ranks[r] + 1)
+                .max()
+                .unwrap_or(0);
+        }
+        self.crowding = crowding_distances(&self.points, &self.ranks);
+    }
+
+    /// The points `point` dominates, by front.
+    fn dominated_by(&self, point: &[f64]) -> Vec<usize> {
+        let mut dominated: Vec<usize> = (0..self.points.len())
+            .filter(|&q| dominates(point, &self.points[q]))
+            .collect();
+        dominated.sort_by_key(|&q| self.ranks[q]);
+        dominated
+    }
+
+    /// `Less` when point `i` ranks better than point `j`: a lower front, then a larger crowding
+    /// distance.
+    pub(crate) fn compare(&self, i: usize, j: usize) -> Ordering {
+        self.ranks[i]
+            .cmp(&self.ranks[j])
+            .then_with(|| self.crowding[j].total_cmp(&self.crowding[i]))
+    }
+}
+
+fn point_of<T: Float, Ops, const D: usize>(m: &PopMember<T, Ops, D>, objectives: &[SelectionObjective]) -> Vec<f64> {
+    objectives.iter().map(|o| o.value(m)).collect()
+}
+
+fn dominates(a: &[f64], b: &[f64]) -> bool {
+    a.iter().zip(b).all(|(x, y)| x <= y) && a.iter().zip(b).any(|(x, y)| x < y)
+}
+
+/// The front of every point under Deb et al.'s fast non-dominated sort.
+pub(crate) fn nondominated_ranks(points: &[Vec<f64>]) -> Vec<usize> {
+    let n = points.len();
+    let mut n_dominators = vec![0usize; n];
+    let mut dominated: Vec<Vec<usize>> = vec![Vec::new(); n];
+    for i in 0..n {
+        for j in i + 1..n {
+            if dominates(&points[i], &points[j]) {
+                dominated[i].push(j);
+                n_dominators[j] += 1;
+            } else if dominates(&points[j], &points[i]) {
+                dominated[j].push(i);
+                n_dominators[i] += 1;
+            }
+        }
+    }
+
+    let mut ranks = vec![0; n];
+    let mut front: Vec<usize> = (0..n).filter(|&i| n_dominators[i] == 0).collect();
+    let mut rank = 0;
+    while !front.is_empty() {
+        let mut next = Vec::new();
+        for &i in &front {
+            ranks[i] = rank;
+            for &j in &dominated[i] {
+                n_dominators[j] -= 1;
+                if n_dominators[j] == 0 {
+                    next.push(j);
+                }
+            }
+        }
+        front = next;
+        rank += 1;
+    }
+    ranks
+}
+
+/// Crowding distance of every point within its front: the sum over objectives of the normalized
+/// gap between its two neighbours, infinite at the ends of each objective's range.
+pub(crate) fn crowding_distances(points: &[Vec<f64>], ranks: &[usize]) -> Vec<f64> {
+    let mut distance = vec![0.0; points.len()];
+    let n_objectives = points.first().map_or(0, Vec::len);
+    let columns: Vec<Vec<f64>> = (0..n_objectives)
+        .map(|k| points.iter().map(|p| p[k]).collect())
+        .collect();
+    let n_fronts = ranks.iter().max().map_or(0, |r| r + 1);
+    let mut fronts: Vec<Vec<usize>> = vec![Vec::new(); n_fronts];
+    for (i, &r) in ranks.iter().enumerate() {
+        fronts[r].push(i);
+    }
+
+    for front in &mut fronts {
+        for values in &columns {
+            front.sort_by(|&a, &b| values[a].total_cmp(&values[b]));
+            let (first, last) = (front[0], front[front.len() - 1]);
+            distance[first] = f64::INFINITY;
+            distance[last] = f64::INFINITY;
+            let range = values[last] - values[first];
+            if !(range.is_finite() && range > 0.0) {
+                continue;
+            }
+            for w in front.windows(3) {
+                let gap = (values[w[2]] - values[w[0]]) / range;
+                if !gap.is_nan() {
+                    distance[w[1]] += gap;
+                }
+            }
+        }
+    }
+    distance
+}
@@ -6,19 +6,24 @@ use dynamic_expressions::node::PNode;
 use fastrand::Rng;
 use num_traits::Float;
 
+use crate::adaptive_mutation::{MutationStats, adapt_mutation_weights};
 use crate::adaptive_parsimony::RunningSearchStatistics;
 use crate::check_constraints::check_constraints;
 use crate::complexity::compute_complexity;
 use crate::constant_optimization::{OptimizeConstantsCtx, optimize_constants};
+use crate::custom_mutation::{MutationContext, is_well_formed};
 use crate::dataset::TaggedDataset;
+use crate::genealogy::{Genealogy, GenealogyEvent, GenealogyEventKind, finite_loss};
 use crate::loss_functions::loss_to_cost;
 use crate::mutation_functions;
-use crate::options::{MutationWeights, Options};
+use crate::options::{CrossoverWeights, MutationWeights, Options};
 use crate::pop_member::{Evaluator, MemberId, PopMember};
 use crate::random::usize_range_inclusive;
 use crate::selection::weighted_index;
+use crate::template::{check_template_constraints, template_complexity};
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 pub enum MutationChoice {
     MutateConstant,
     MutateOperator,
@@ -32,6 +37,52 @@ pub enum MutationChoice {
     Randomize,
     DoNothing,
     Optimize,
+    FormConnection,
+    BreakConnection,
+    /// `Options::custom_mutations[i]`.
+    Custom(u16),
+}
+
+impl MutationChoice {
+    /// Every built-in choice, in declaration order.
+    pub const ALL: [MutationChoice; 14] = [
+        MutationChoice::MutateConstant,
+        MutationChoice::MutateOperator,
+        MutationChoice::MutateFeature,
+        MutationChoice::SwapOperands,
+        MutationChoice::RotateTree,
+        MutationChoice::AddNode,
+        MutationChoice::InsertNode,
+        MutationChoice::DeleteNode,
+        MutationChoice::Simplify,
+        MutationChoice::Randomize,
+        MutationChoice::DoNothing,
+        MutationChoice::Optimize,
+        MutationChoice::FormConnection,
+        MutationChoice::BreakConnection,
+    ];
+
+    /// This choice's entry in `weights`, or in `custom` (the weights of `Options::custom_mutations`)
+    /// for a custom mutation; `None` if `custom` has no such entry.
+    pub fn weight_mut<'w>(self, weights: &'w mut MutationWeights, custom: &'w mut [f64]) -> Option<&'w mut f64> {
+        Some(match self {
+            MutationChoice::MutateConstant => &mut weights.mutate_constant,
+            MutationChoice::MutateOperator => &mut weights.mutate_operator,
+            MutationChoice::MutateFeature => &mut weights.mutate_feature,
+            MutationChoice::SwapOperands => &mut weights.swap_operands,
+            MutationChoice::RotateTree => &mut weights.rotate_tree,
+            MutationChoice::AddNode => &mut weights.add_node,
+            MutationChoice::InsertNode => &mut weights.insert_node,
+            MutationChoice::DeleteNode => &mut weights.delete_node,
+            MutationChoice::Simplify => &mut weights.simplify,
+            MutationChoice::Randomize => &mut weights.randomize,
+            MutationChoice::DoNothing => &mut weights.do_nothing,
+            MutationChoice::Optimize => &mut weights.optimize,
+            MutationChoice::FormConnection => &mut weights.form_connection,
+            MutationChoice::BreakConnection => &mut weights.break_connection,
+            MutationChoice::Custom(i) => return custom.get_mut(i as usize),
+        })
+    }
 }
 
 pub struct NextGenerationCtx<'a, T: Float + AddAssign, Ops, const D: usize> {
@@ -44,6 +95,10 @@ pub struct NextGenerationCtx<'a, T: Float + AddAssign, Ops, const D: usize> {
     pub evaluator: &'a mut Evaluator<T, D>,
     pub next_id: &'a mut u64,
     pub next_birth: &'a mut u64,
+    /// The population's mutation record; updated with this child's outcome.
+    pub mutation_stats: &'a mut MutationStats,
+    /// The population's event buffer when `Options::use_recorder` is set; receives this child's birth.
+    pub genealogy: Option<&'a mut Genealogy>,
     pub _ops: core::marker::PhantomData<Ops>,
 }
 
@@ -55,6 +110,11 @@ pub struct CrossoverCtx<'a, T: Float, Ops, const D: usize> {
     pub evaluator: &'a mut Evaluator<T, D>,
     pub next_id: &'a mut u64,
     pub next_birth: &'a mut u64,
+    /// The population's event buffer when `Options::use_recorder` is set; receives the children's births.
+    pub genealogy: Option<&'a mut Genealogy>,
+    /// Donors for `CrossoverChoice::HallOfFame`: the search's hall of fame as of dispatch. Without
+    /// entries it falls back to the second parent.
+    pub hall_of_fame: &'a [PopMember<T, Ops, D>],
     pub _ops: core::marker::PhantomData<Ops>,
 }
 
@@ -66,13 +126,28 @@ fn has_binary_op(nodes: &[PNode]) -> bool {
     nodes.iter().any(|n| matches!(n, PNode::Op { arity: 2, .. }))
 }
 
+/// Zeroes the weights of mutations that cannot apply to `member`; `custom` holds the weights of
+/// `Options::custom_mutations`, in order.
 pub fn condition_mutation_weights<T: Float + AddAssign, Ops, const D: usize>(
     weights: &mut MutationWeights,
+    custom: &mut [f64],
     member: &PopMember<T, Ops, D>,
     options: &Options<T, D>,
     curmaxsize: usize,
     nfeatures: usize,
 ) {
+    for (w, (mutation, _)) in custom.iter_mut().zip(&options.custom_mutations) {
+        *w = mutation.condition_weight(*w, &member.expr.nodes);
+    }
+    if !options.graph_expressions {
+        weights.form_connection = 0.0;
+        weights.break_connection = 0.0;
+    }
+    if options.template.is_some() {
+        // Sub-expressions cannot be scored on their own; constants are still optimized per member.
+        weights.optimize = 0.0;
+    }
+
     let tree_is_leaf = member
         .expr
         .nodes
@@ -80,6 +155,8 @@ pub fn condition_mutation_weights<T: Float + AddAssign, Ops, const D: usize>(
         .all(|n| matches!(n, PNode::Var { .. } | PNode::Const { .. }));
     if tree_is_leaf {
         weights.mutate_operator = 0.0;
+        weights.form_connection = 0.0;
+        weights.break_connection = 0.0;
         weights.swap_operands = 0.0;
         weights.delete_node = 0.0;
         weights.simplify = 0.0;
@@ -113,13 +190,17 @@ pub fn condition_mutation_weights<T: Float + AddAssign, Ops, const D: usize>(
         weights.simplify = 0.0;
     }
 
-    if !options.should_optimize_constants || options.optimizer_probability == 0.0 || member.expr.consts.is_empty() {
+    if !options.should_optimize_constants
+        || options.optimizer_probability == 0.0
+        || (member.expr.consts.is_empty() && member.parameters.is_empty())
+    {
         weights.optimize = 0.0;
     }
 }
 
-pub fn sample_mutation(rng: &mut Rng, weights: &MutationWeights) -> MutationChoice {
-    let choices = [
+/// Draws a mutation by weight; `custom` holds the weights of `Options::custom_mutations`, in order.
+pub fn sample_mutation(rng: &mut Rng, weights: &MutationWeights, custom: &[f64]) -> MutationChoice {
+    let builtin = [
         (MutationChoice::MutateConstant, weights.mutate_constant),
         (MutationChoice::MutateOperator, weights.mutate_operator),
         (MutationChoice::MutateFeature, weights.mutate_feature),
@@ -132,17 +213,71 @@ pub fn sample_mutation(rng: &mut Rng, weights: &MutationWeights) -> MutationChoi
         (MutationChoice::Randomize, weights.randomize),
         (MutationChoice::DoNothing, weights.do_nothing),
         (MutationChoice::Optimize, weights.optimize),
+        (MutationChoice::FormConnection, weights.form_connection),
+        (MutationChoice::BreakConnection, weights.break_connection),
     ];
+    let custom = custom
+        .iter()
+        .enumerate()
+        .map(|(i, &w)| (MutationChoice::Custom(i as u16), w));
+    let choices: Vec<(MutationChoice, f64)> = builtin.into_iter().chain(custom).collect();
     let w: Vec<f64> = choices.iter().map(|(_, v)| *v).collect();
     let idx = weighted_index(rng, &w);
     choices[idx].0
 }
 
+/// A crossover operator (see [`CrossoverWeights`]).
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum CrossoverChoice {
+    Subtree,
+    SizeFair,
+    OnePoint,
+    Homologous,
+    HallOfFame,
+}
+
+pub fn sample_crossover(rng: &mut Rng, weights: &CrossoverWeights) -> CrossoverChoice {
+    let choices = [
+        (CrossoverChoice::Subtree, weights.subtree),
+        (CrossoverChoice::SizeFair, weights.size_fair),
+        (CrossoverChoice::OnePoint, weights.one_point),
+        (CrossoverChoice::Homologous, weights.homologous),
+        (CrossoverChoice::HallOfFame, weights.hall_of_fame),
+    ];
+    let w: Vec<f64> = choices.iter().map(|(_, v)| *v).collect();
+    let idx = weighted_index(rng, &w);
+    choices[idx].0
+}
+
+impl CrossoverChoice {
+    /// The two children of recombining `a` and `b`; `HallOfFame` recombines like `Subtree`, the
+    /// caller picks its second parent.
+    fn apply<T: Clone, Ops, const D: usize>(
+        self,
+        rng: &mut Rng,
+        a: &PostfixExpr<T, Ops, D>,
+        b: &PostfixExpr<T, Ops, D>,
+    ) -> (PostfixExpr<T, Ops, D>, PostfixExpr<T, Ops, D>) {
+        let (mut child_a, mut child_b) = match self {
+            CrossoverChoice::Subtree | CrossoverChoice::HallOfFame => mutation_functions::crossover_trees(rng, a, b),
+            CrossoverChoice::SizeFair => mutation_functions::size_fair_crossover_trees(rng, a, b),
+            CrossoverChoice::OnePoint => mutation_functions::one_point_crossover_trees(rng, a, b),
+            CrossoverChoice::Homologous => mutation_functions::homologous_crossover_trees(rng, a, b),
+        };
+        // Each child keeps the sharing of the parent it was grown from, outside the swapped part.
+        dynamic_expressions::inherit_sharing(a, &mut child_a);
+        dynamic_expressions::inherit_sharing(b, &mut child_b);
+        (child_a, child_b)
+    }
+}
+
 struct MutationOutcome<T: Float + AddAssign, Ops, const D: usize> {
     expr: PostfixExpr<T, Ops, D>,
     mutated: bool,
     evals: f64,
     return_immediately: bool,
+    /// Refitted parametric-expression parameters, if the mutation changed them.
+    parameters: Option<Vec<T>>,
 }
 
 struct MutationApplyCtx<'a, 'd, T: Float + AddAssign, Ops, const D: usize> {
@@ -152,6 +287,8 @@ struct MutationApplyCtx<'a, 'd, T: Float + AddAssign, Ops, const D: usize> {
     dataset: TaggedDataset<'d, T>,
     temperature: f64,
     curmaxsize: usize,
+    /// Features the mutated expression may use (a template sub-expression's own subset).
+    n_features: usize,
     options: &'a Options<T, D>,
     evaluator: &'a mut Evaluator<T, D>,
 }
@@ -172,58 +309,66 @@ impl MutationChoice {
             dataset,
             temperature,
             curmaxsize,
+            n_features,
             options,
             evaluator,
         } = ctx;
-        let n_features = dataset.n_features;
         match self {
             MutationChoice::MutateConstant => MutationOutcome {
                 mutated: mutation_functions::mutate_constant_in_place(rng, &mut expr, temperature, options),
                 expr,
                 evals: 0.0,
                 return_immediately: false,
+                parameters: None,
             },
             MutationChoice::MutateOperator => MutationOutcome {
                 mutated: mutation_functions::mutate_operator_in_place(rng, &mut expr, &options.operators),
                 expr,
                 evals: 0.0,
                 return_immediately: false,
+                parameters: None,
             },
             MutationChoice::MutateFeature => MutationOutcome {
                 mutated: mutation_functions::mutate_feature_in_place(rng, &mut expr, n_features),
                 expr,
                 evals: 0.0,
                 return_immediately: false,
+                parameters: None,
             },
             MutationChoice::SwapOperands => MutationOutcome {
                 mutated: mutation_functions::swap_operands_in_place(rng, &mut expr),
                 expr,
                 evals: 0.0,
                 return_immediately: false,
+                parameters: None,
             },
             MutationChoice::RotateTree => MutationOutcome {
                 mutated: mutation_functions::rotate_tree_in_place(rng, &mut expr),
                 expr,
                 evals: 0.0,
                 return_immediately: false,
+                parameters: None,
             },
             MutationChoice::AddNode => MutationOutcome {
                 mutated: mutation_functions::add_node_in_place(rng, &mut expr, &options.operators, n_features),
                 expr,
                 evals: 0.0,
                 return_immediately: false,
+                parameters: None,
             },
             MutationChoice::InsertNode => MutationOutcome {
                 mutated: mutation_functions::insert_random_op_in_place(rng, &mut expr, &options.operators, n_features),
                 expr,
                 evals: 0.0,
                 return_immediately: false,
+                parameters: None,
             },
             MutationChoice::DeleteNode => MutationOutcome {
                 mutated: mutation_functions::delete_random_op_in_place(rng, &mut expr),
                 expr,
                 evals: 0.0,
                 return_immediately: false,
+                parameters: None,
             },
             MutationChoice::Simplify => {
                 let _ = dynamic_expressions::simplify_in_place(&mut expr, &evaluator.eval_opts);
@@ -232,6 +377,7 @@ impl MutationChoice {
                     expr,
                     evals: 0.0,
                     return_immediately: true,
+                    parameters: None,
                 }
             }
             MutationChoice::Randomize => {
@@ -243,6 +389,48 @@ impl MutationChoice {
                     expr: mutation_functions::random_expr(rng, &options.operators, n_features, target_size),
                     evals: 0.0,
                     return_immediately: false,
+                    parameters: None,
+                }
+            }
+            MutationChoice::FormConnection => MutationOutcome {
+                mutated: mutation_functions::form_connection_in_place(rng, &mut expr),
+                expr,
+                evals: 0.0,
+                return_immediately: false,
+                parameters: None,
+            },
+            MutationChoice::BreakConnection => MutationOutcome {
+                mutated: mutation_functions::break_connection_in_place(rng, &mut expr),
+                expr,
+                evals: 0.0,
+                return_immediately: false,
+                parameters: None,
+            },
+            MutationChoice::Custom(i) => {
+                let mutation = &options.custom_mutations[i as usize].0;
+                let mut erased = PostfixExpr::<T, (), D>::new(
+                    std::mem::take(&mut expr.nodes),
+                    std::mem::take(&mut expr.consts),
+                    std::mem::take(&mut expr.meta),
+                );
+                let mutated = mutation.mutate(
+                    &mut erased,
+                    &mut MutationContext {
+                        rng,
+                        operators: &options.operators,
+                        n_features,
+                        curmaxsize,
+                        temperature,
+                        dataset: dataset.data,
+                    },
+                ) && is_well_formed(&erased, &options.operators, n_features);
+                (expr.nodes, expr.consts, expr.meta) = (erased.nodes, erased.consts, erased.meta);
+                MutationOutcome {
+                    mutated,
+                    expr,
+                    evals: 0.0,
+                    return_immediately: false,
+                    parameters: None,
                 }
             }
             MutationChoice::DoNothing => MutationOutcome {
@@ -250,6 +438,7 @@ impl MutationChoice {
                 expr,
                 evals: 0.0,
                 return_immediately: false,
+                parameters: None,
             },
             MutationChoice::Optimize => {
                 // Match SymbolicRegression.jl: `:optimize` is a mutation that runs constant
@@ -264,6 +453,7 @@ impl MutationChoice {
                 tmp.complexity = member.complexity;
                 tmp.loss = member.loss;
                 tmp.cost = member.cost;
+                tmp.parameters = member.parameters.clone();
 
                 let mut grad_ctx = dynamic_expressions::GradContext::new(dataset.n_rows);
                 let (_improved, evals) = optimize_constants(
@@ -284,6 +474,7 @@ impl MutationChoice {
                     expr: tmp.expr,
                     evals,
                     return_immediately: false,
+                    parameters: Some(tmp.parameters),
                 }
             }
         }
@@ -311,31 +502,67 @@ where
         evaluator,
         next_id,
         next_birth,
+        mutation_stats,
+        mut genealogy,
         ..
     } = ctx;
 
     let before_cost = member.cost.to_f64().unwrap_or(f64::INFINITY);
-    let _before_loss = member.loss.to_f64().unwrap_or(f64::INFINITY);
-    let n_features = dataset.n_features;
+    let before_loss = finite_loss(member.loss);
+    let n_features = options.n_expr_features(&dataset);
+
+    // Template members mutate one randomly chosen sub-expression, within the size budget left
+    // over by the others.
+    let template = options.template.as_ref().filter(|_| !member.subexprs.is_empty());
+    let slot = template.map(|_| rng.usize(..member.subexprs.len()));
+    let mut subexprs = member.subexprs.clone();
+    let focus_member;
+    let (focus, focus_features, focus_maxsize) = match (template, slot) {
+        (Some(template), Some(k)) => {
+            let n = template.features(k).len();
+            let mut m = PopMember::from_expr(member.id, None, member.birth, subexprs[k].clone(), n);
+            m.complexity = compute_complexity(&m.expr, options);
+            let others = template_complexity(&subexprs, options) - m.complexity;
+            focus_member = m;
+            (&focus_member, n, curmaxsize.saturating_sub(others))
+        }
+        _ => (member, n_features, curmaxsize),
+    };
 
     let mut weights = options.mutation_weights.clone();
-    condition_mutation_weights(&mut weights, member, options, curmaxsize, n_features);
-    let choice = sample_mutation(rng, &weights);
+    let mut custom_weights: Vec<f64> = options.custom_mutations.iter().map(|&(_, w)| w).collect();
+    condition_mutation_weights(
+        &mut weights,
+        &mut custom_weights,
+        focus,
+        options,
+        focus_maxsize,
+        focus_features,
+    );
+    adapt_mutation_weights(
+        &mut weights,
+        &mut custom_weights,
+        mutation_stats,
+        options.mutation_adaptation,
+    );
+    let choice = sample_mutation(rng, &weights, &custom_weights);
 
     let max_attempts = 10;
     let mut successful = false;
     let mut return_immediately = false;
     let mut tree = member.expr.clone();
+    let mut parameters = member.parameters.clone();
     let mut evals = 0.0f64;
 
     for _ in 0..max_attempts {
         let outcome = choice.apply(MutationApplyCtx {
             rng,
-            member,
-            expr: member.expr.clone(),
+            member: focus,
+            expr: focus.expr.clone(),
             dataset,
             temperature,
-            curmaxsize,
+            curmaxsize: focus_maxsize,
+            n_features: focus_features,
             options,
             evaluator,
         });
@@ -344,10 +571,24 @@ where
             continue;
         }
         tree = outcome.expr;
+        if !matches!(choice, MutationChoice::FormConnection | MutationChoice::BreakConnection) {
+            // The nodes the mutation touched are no longer shared; the rest keep their sharing.
+            dynamic_expressions::inherit_sharing(&focus.expr, &mut tree);
+        }
         compress_constants(&mut tree);
-        if check_constraints(&tree, options, curmaxsize) {
+        let satisfied = match slot {
+            Some(k) => {
+                subexprs[k] = tree.clone();
+                check_template_constraints(&subexprs, options, curmaxsize)
+            }
+            None => check_constraints(&tree, options, curmaxsize),
+        };
+        if satisfied {
             successful = true;
             return_immediately = outcome.return_immediately;
+            if let Some(p) = outcome.parameters {
+                parameters = p;
+            }
             break;
         }
     }
@@ -357,19 +598,38 @@ where
     let birth = *next_birth;
     *next_birth += 1;
 
+    let mut record = |accepted: bool, improved: bool, loss_after: Option<f64>| {
+        mutation_stats.record(choice, accepted, improved);
+        if let Some(genealogy) = genealogy.as_deref_mut() {
+            genealogy.record(GenealogyEvent {
+                kind: GenealogyEventKind::Mutation(choice),
+                child: id,
+                parents: vec![member.id],
+                accepted,
+                loss_before: before_loss,
+                loss_after,
+            });
+        }
+    };
+
     if !successful {
-        let mut baby = PopMember::from_expr(id, Some(member.id), birth, member.expr.clone(), n_features);
-        baby.complexity = member.complexity;
-        baby.loss = member.loss;
-        baby.cost = member.cost;
-        return (baby, false, 0.0);
+        record(false, false, None);
+        return (unchanged_copy(member, id, birth, n_features), false, 0.0);
     }
 
+    let make_baby = |tree, subexprs| {
+        let mut baby = match template {
+            Some(template) => PopMember::from_template(id, Some(member.id), birth, template, subexprs, n_features),
+            None => PopMember::from_expr(id, Some(member.id), birth, tree, n_features),
+        };
+        baby.parameters = parameters;
+        baby
+    };
+
     if return_immediately {
-        let mut baby = PopMember::from_expr(id, Some(member.id), birth, tree, n_features);
-        baby.rebuild_plan(n_features);
+        let mut baby = make_baby(tree, subexprs);
         baby.loss = member.loss;
-        baby.complexity = compute_complexity(&baby.expr.nodes, options);
+        baby.complexity = baby.compute_complexity(options);
         baby.cost = loss_to_cost(
             baby.loss,
             baby.complexity,
@@ -377,21 +637,19 @@ where
             options.use_baseline,
             dataset.baseline_loss,
         );
+        let improved = baby.cost.to_f64().is_some_and(|c| c < before_cost);
+        record(true, improved, before_loss);
         return (baby, true, 0.0);
     }
 
-    let mut baby = PopMember::from_expr(id, Some(member.id), birth, tree, n_features);
+    let mut baby = make_baby(tree, subexprs);
     let ok = baby.evaluate(&dataset, options, evaluator);
     evals += 1.0;
     let after_cost = baby.cost.to_f64().unwrap_or(f64::INFINITY);
-    let after_loss = baby.loss.to_f64().unwrap_or(f64::INFINITY);
-    let _ = after_loss;
+    let after_loss = finite_loss(baby.loss);
     if !ok || !after_cost.is_finite() {
-        let mut reject = PopMember::from_expr(id, Some(member.id), birth, member.expr.clone(), n_features);
-        reject.complexity = member.complexity;
-        reject.loss = member.loss;
-        reject.cost = member.cost;
-        return (reject, false, 0.0);
+        record(false, false, after_loss);
+        return (unchanged_copy(member, id, birth, n_features), false, 0.0);
     }
 
     let mut prob = 1.0f64;
@@ -416,16 +674,33 @@ where
     }
 
     if prob < rng.f64() {
-        let mut reject = PopMember::from_expr(id, Some(member.id), birth, member.expr.clone(), n_features);
-        reject.complexity = member.complexity;
-        reject.loss = member.loss;
-        reject.cost = member.cost;
-        return (reject, false, evals);
+        record(false, false, after_loss);
+        return (unchanged_copy(member, id, birth, n_features), false, evals);
     }
 
+    record(true, after_cost < before_cost, after_loss);
     (baby, true, evals)
 }
 
+/// A copy of `member` under a new id that keeps its cached loss (used when a mutation is rejected).
+fn unchanged_copy<T: Float, Ops, const D: usize>(
+    member: &PopMember<T, Ops, D>,
+    id: MemberId,
+    birth: u64,
+    n_features: usize,
+) -> PopMember<T, Ops, D>
+where
+    Ops: dynamic_expressions::OperatorSet<T = T>,
+{
+    let mut copy = PopMember::from_expr(id, Some(member.id), birth, member.expr.clone(), n_features);
+    copy.subexprs = member.subexprs.clone();
+    copy.parameters = member.parameters.clone();
+    copy.complexity = member.complexity;
+    copy.loss = member.loss;
+    copy.cost = member.cost;
+    copy
+}
+
 pub fn crossover_generation<T: Float + AddAssign, Ops, const D: usize>(
     member1: &PopMember<T, Ops, D>,
     member2: &PopMember<T, Ops, D>,
@@ -442,28 +717,78 @@ where
         evaluator,
         next_id,
         next_birth,
+        genealogy,
+        hall_of_fame,
         ..
     } = ctx;
 
+    let choice = sample_crossover(rng, &options.crossover_weights);
+    let donor = match choice {
+        CrossoverChoice::HallOfFame if !hall_of_fame.is_empty() => Some(&hall_of_fame[rng.usize(..hall_of_fame.len())]),
+        _ => None,
+    };
+    let member2 = donor.unwrap_or(member2);
+
     let max_tries = 10;
     let mut tries = 0;
+    let n_features = options.n_expr_features(&dataset);
+    // Template members only exchange material between matching sub-expressions.
+    let template = options
+        .template
+        .as_ref()
+        .filter(|_| !member1.subexprs.is_empty() && !member2.subexprs.is_empty());
     loop {
-        let (c1_expr, c2_expr) = mutation_functions::crossover_trees(rng, &member1.expr, &member2.expr);
         tries += 1;
-        if check_constraints(&c1_expr, options, curmaxsize) && check_constraints(&c2_expr, options, curmaxsize) {
-            let id1 = MemberId(*next_id);
-            *next_id += 1;
-            let b1 = *next_birth;
-            *next_birth += 1;
-            let id2 = MemberId(*next_id);
-            *next_id += 1;
-            let b2 = *next_birth;
-            *next_birth += 1;
-
-            let mut baby1 = PopMember::from_expr(id1, Some(member1.id), b1, c1_expr, dataset.n_features);
-            let mut baby2 = PopMember::from_expr(id2, Some(member2.id), b2, c2_expr, dataset.n_features);
+        let babies = match template {
+            Some(template) => {
+                let k = rng.usize(..member1.subexprs.len());
+                let (c1, c2) = choice.apply(rng, &member1.subexprs[k], &member2.subexprs[k]);
+                let mut subs1 = member1.subexprs.clone();
+                let mut subs2 = member2.subexprs.clone();
+                subs1[k] = c1;
+                subs2[k] = c2;
+                (check_template_constraints(&subs1, options, curmaxsize)
+                    && check_template_constraints(&subs2, options, curmaxsize))
+                .then(|| {
+                    let (id1, b1) = take_member_id(next_id, next_birth);
+                    let (id2, b2) = take_member_id(next_id, next_birth);
+                    (
+                        PopMember::from_template(id1, Some(member1.id), b1, template, subs1, n_features),
+                        PopMember::from_template(id2, Some(member2.id), b2, template, subs2, n_features),
+                    )
+                })
+            }
+            None => {
+                let (c1, c2) = choice.apply(rng, &member1.expr, &member2.expr);
+                (check_constraints(&c1, options, curmaxsize) && check_constraints(&c2, options, curmaxsize)).then(
+                    || {
+                        let (id1, b1) = take_member_id(next_id, next_birth);
+                        let (id2, b2) = take_member_id(next_id, next_birth);
+                        (
+                            PopMember::from_expr(id1, Some(member1.id), b1, c1, n_features),
+                            PopMember::from_expr(id2, Some(member2.id), b2, c2, n_features),
+                        )
+                    },
+                )
+            }
+        };
+        if let Some((mut baby1, mut baby2)) = babies {
+            baby1.parameters = member1.parameters.clone();
+            baby2.parameters = member2.parameters.clone();
             let _ = baby1.evaluate(&dataset, options, evaluator);
             let _ = baby2.evaluate(&dataset, options, evaluator);
+            if let Some(genealogy) = genealogy {
+                for (baby, parent, other) in [(&baby1, member1, member2), (&baby2, member2, member1)] {
+                    genealogy.record(GenealogyEvent {
+                        kind: GenealogyEventKind::Crossover,
+                        child: baby.id,
+                        parents: vec![parent.id, other.id],
+                        accepted: true,
+                        loss_before: finite_loss(parent.loss),
+                        loss_after: finite_loss(baby.loss),
+                    });
+                }
+            }
             return (baby1, baby2, true, 2.0);
         }
         if tries >= max_tries {
@@ -473,3 +798,11 @@ where
         }
     }
 }
+
+fn take_member_id(next_id: &mut u64, next_birth: &mut u64) -> (MemberId, u64) {
+    let id = MemberId(*next_id);
+    *next_id += 1;
+    let birth = *next_birth;
+    *next_birth += 1;
+    (id, birth)
+}
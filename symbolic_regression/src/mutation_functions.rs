@@ -1,4 +1,5 @@
 use dynamic_expressions::expression::PostfixExpr;
+use dynamic_expressions::graph::{GraphExpr, GraphNode};
 use dynamic_expressions::node::PNode;
 use dynamic_expressions::node_utils;
 use fastrand::Rng;
@@ -522,47 +523,43 @@ pub(crate) fn delete_random_op_in_place<T: Clone, Ops, const D: usize>(
     true
 }
 
-pub(crate) fn crossover_trees<T: Clone, Ops, const D: usize>(
-    rng: &mut Rng,
-    a: &PostfixExpr<T, Ops, D>,
-    b: &PostfixExpr<T, Ops, D>,
-) -> (PostfixExpr<T, Ops, D>, PostfixExpr<T, Ops, D>) {
-    fn remap_subtree_consts<T: Clone>(
-        donor_nodes: &[PNode],
-        donor_consts: &[T],
-        dst_consts: &mut Vec<T>,
-    ) -> Vec<PNode> {
-        let mut map: Vec<Option<u16>> = vec![None; donor_consts.len()];
-        let mut out: Vec<PNode> = Vec::with_capacity(donor_nodes.len());
-        for n in donor_nodes {
-            match *n {
-                PNode::Const { idx } => {
-                    let old = usize::from(idx);
-                    let new_idx = match map[old] {
-                        Some(v) => v,
-                        None => {
-                            let v: u16 = dst_consts
-                                .len()
-                                .try_into()
-                                .unwrap_or_else(|_| panic!("too many constants to index in u16"));
-                            dst_consts.push(donor_consts[old].clone());
-                            map[old] = Some(v);
-                            v
-                        }
-                    };
-                    out.push(PNode::Const { idx: new_idx });
-                }
-                PNode::Var { feature } => out.push(PNode::Var { feature }),
-                PNode::Op { arity, op } => out.push(PNode::Op { arity, op }),
+fn remap_subtree_consts<T: Clone>(donor_nodes: &[PNode], donor_consts: &[T], dst_consts: &mut Vec<T>) -> Vec<PNode> {
+    let mut map: Vec<Option<u16>> = vec![None; donor_consts.len()];
+    let mut out: Vec<PNode> = Vec::with_capacity(donor_nodes.len());
+    for n in donor_nodes {
+        match *n {
+            PNode::Const { idx } => {
+                let old = usize::from(idx);
+                let new_idx = match map[old] {
+                    Some(v) => v,
+                    None => {
+                        let v: u16 = dst_consts
+                            .len()
+                            .try_into()
+                            .unwrap_or_else(|_| panic!("too many constants to index in u16"));
+                        dst_consts.push(donor_consts[old].clone());
+                        map[old] = Some(v);
+                        v
+                    }
+                };
+                out.push(PNode::Const { idx: new_idx });
             }
+            PNode::Var { feature } => out.push(PNode::Var { feature }),
+            PNode::Op { arity, op } => out.push(PNode::Op { arity, op }),
         }
-        out
     }
+    out
+}
 
+/// The children of exchanging the subtree rooted at `a_root` in `a` with the one at `b_root` in `b`.
+fn swap_subtrees<T: Clone, Ops, const D: usize>(
+    a: &PostfixExpr<T, Ops, D>,
+    b: &PostfixExpr<T, Ops, D>,
+    a_root: usize,
+    b_root: usize,
+) -> (PostfixExpr<T, Ops, D>, PostfixExpr<T, Ops, D>) {
     let a_sizes = node_utils::subtree_sizes(&a.nodes);
     let b_sizes = node_utils::subtree_sizes(&b.nodes);
-    let a_root = usize_range(rng, 0..a.nodes.len());
-    let b_root = usize_range(rng, 0..b.nodes.len());
     let (a_start, a_end) = node_utils::subtree_range(&a_sizes, a_root);
     let (b_start, b_end) = node_utils::subtree_range(&b_sizes, b_root);
 
@@ -589,3 +586,213 @@ pub(crate) fn crossover_trees<T: Clone, Ops, const D: usize>(
     dynamic_expressions::compress_constants(&mut child_b);
     (child_a, child_b)
 }
+
+pub(crate) fn crossover_trees<T: Clone, Ops, const D: usize>(
+    rng: &mut Rng,
+    a: &PostfixExpr<T, Ops, D>,
+    b: &PostfixExpr<T, Ops, D>,
+) -> (PostfixExpr<T, Ops, D>, PostfixExpr<T, Ops, D>) {
+    let a_root = usize_range(rng, 0..a.nodes.len());
+    let b_root = usize_range(rng, 0..b.nodes.len());
+    swap_subtrees(a, b, a_root, b_root)
+}
+
+/// Size-fair crossover (Langdon, 2000): a random subtree of `a` is exchanged with a random subtree
+/// of `b` of at most `2 * size + 1` nodes, which limits how fast children outgrow their parents.
+pub(crate) fn size_fair_crossover_trees<T: Clone, Ops, const D: usize>(
+    rng: &mut Rng,
+    a: &PostfixExpr<T, Ops, D>,
+    b: &PostfixExpr<T, Ops, D>,
+) -> (PostfixExpr<T, Ops, D>, PostfixExpr<T, Ops, D>) {
+    let a_sizes = node_utils::subtree_sizes(&a.nodes);
+    let b_sizes = node_utils::subtree_sizes(&b.nodes);
+    let a_root = usize_range(rng, 0..a.nodes.len());
+    let limit = 2 * a_sizes[a_root] + 1;
+    // Never empty: every leaf of `b` qualifies.
+    let candidates: Vec<usize> = (0..b.nodes.len()).filter(|&j| b_sizes[j] <= limit).collect();
+    let b_root = candidates[usize_range(rng, 0..candidates.len())];
+    swap_subtrees(a, b, a_root, b_root)
+}
+
+/// Aligned node pairs `(i, j, interior)` of the common region of two postfix trees (Poli & Langdon,
+/// 1998): the roots, plus the children of every aligned pair of operators with the same arity.
+/// Such pairs are `interior`; the others lie on the region's boundary.
+fn common_region(a: &[PNode], b: &[PNode]) -> Vec<(usize, usize, bool)> {
+    let a_sizes = node_utils::subtree_sizes(a);
+    let b_sizes = node_utils::subtree_sizes(b);
+    let mut out = Vec::new();
+    let mut stack = vec![(a.len() - 1, b.len() - 1)];
+    while let Some((i, j)) = stack.pop() {
+        let arity = match (a[i], b[j]) {
+            (PNode::Op { arity: x, .. }, PNode::Op { arity: y, .. }) if x == y => usize::from(x),
+            _ => 0,
+        };
+        out.push((i, j, arity > 0));
+        let a_children = child_ranges(&a_sizes, i, arity);
+        let b_children = child_ranges(&b_sizes, j, arity);
+        stack.extend(
+            a_children
+                .into_iter()
+                .zip(b_children)
+                .map(|((_, ie), (_, je))| (ie, je)),
+        );
+    }
+    out
+}
+
+/// One-point crossover: exchanges the subtrees at a random point of the parents' common region, so
+/// both children keep the shape the parents share above it.
+pub(crate) fn one_point_crossover_trees<T: Clone, Ops, const D: usize>(
+    rng: &mut Rng,
+    a: &PostfixExpr<T, Ops, D>,
+    b: &PostfixExpr<T, Ops, D>,
+) -> (PostfixExpr<T, Ops, D>, PostfixExpr<T, Ops, D>) {
+    let region = common_region(&a.nodes, &b.nodes);
+    let (i, j, _) = region[usize_range(rng, 0..region.len())];
+    swap_subtrees(a, b, i, j)
+}
+
+/// Homologous (uniform) crossover over the parents' common region: each aligned operator, and each
+/// aligned subtree on the region's boundary, is exchanged with probability 1/2. Children keep the
+/// shared shape and only mix material from matching positions.
+pub(crate) fn homologous_crossover_trees<T: Clone, Ops, const D: usize>(
+    rng: &mut Rng,
+    a: &PostfixExpr<T, Ops, D>,
+    b: &PostfixExpr<T, Ops, D>,
+) -> (PostfixExpr<T, Ops, D>, PostfixExpr<T, Ops, D>) {
+    struct Parents<'a> {
+        a: &'a [PNode],
+        b: &'a [PNode],
+        a_sizes: Vec<usize>,
+        b_sizes: Vec<usize>,
+        /// Offset of `b`'s constants in the children's shared constant list.
+        shift: u16,
+    }
+
+    fn build(p: &Parents<'_>, rng: &mut Rng, i: usize, j: usize, out_a: &mut Vec<PNode>, out_b: &mut Vec<PNode>) {
+        match (p.a[i], p.b[j]) {
+            (PNode::Op { arity: x, .. }, PNode::Op { arity: y, .. }) if x == y => {
+                let arity = usize::from(x);
+                let a_children = child_ranges(&p.a_sizes, i, arity);
+                let b_children = child_ranges(&p.b_sizes, j, arity);
+                for ((_, ie), (_, je)) in a_children.into_iter().zip(b_children) {
+                    build(p, rng, ie, je, out_a, out_b);
+                }
+                let (na, nb) = if rng.bool() { (p.b[j], p.a[i]) } else { (p.a[i], p.b[j]) };
+                out_a.push(na);
+                out_b.push(nb);
+            }
+            _ => {
+                let (a_start, a_end) = node_utils::subtree_range(&p.a_sizes, i);
+                let (b_start, b_end) = node_utils::subtree_range(&p.b_sizes, j);
+                let a_sub = &p.a[a_start..=a_end];
+                let b_sub: Vec<PNode> = p.b[b_start..=b_end]
+                    .iter()
+                    .map(|&n| match n {
+                        PNode::Const { idx } => PNode::Const { idx: idx + p.shift },
+                        n => n,
+                    })
+                    .collect();
+                if rng.bool() {
+                    out_a.extend_from_slice(&b_sub);
+                    out_b.extend_from_slice(a_sub);
+                } else {
+                    out_a.extend_from_slice(a_sub);
+                    out_b.extend_from_slice(&b_sub);
+                }
+            }
+        }
+    }
+
+    // Both children index into `a`'s constants followed by `b`'s; unused ones are dropped below.
+    let mut consts = a.consts.clone();
+    consts.extend_from_slice(&b.consts);
+    let _: u16 = consts
+        .len()
+        .try_into()
+        .unwrap_or_else(|_| panic!("too many constants to index in u16"));
+    let parents = Parents {
+        a: &a.nodes,
+        b: &b.nodes,
+        a_sizes: node_utils::subtree_sizes(&a.nodes),
+        b_sizes: node_utils::subtree_sizes(&b.nodes),
+        shift: a.consts.len() as u16,
+    };
+    let mut child_a_nodes = Vec::with_capacity(a.nodes.len());
+    let mut child_b_nodes = Vec::with_capacity(b.nodes.len());
+    build(
+        &parents,
+        rng,
+        a.nodes.len() - 1,
+        b.nodes.len() - 1,
+        &mut child_a_nodes,
+        &mut child_b_nodes,
+    );
+
+    let mut child_a = PostfixExpr::new(child_a_nodes, consts.clone(), a.meta.clone());
+    let mut child_b = PostfixExpr::new(child_b_nodes, consts, b.meta.clone());
+    dynamic_expressions::compress_constants(&mut child_a);
+    dynamic_expressions::compress_constants(&mut child_b);
+    (child_a, child_b)
+}
+
+/// Points a random operator argument at another existing operator node, so that subexpression
+/// becomes shared (see `Options::graph_expressions`); the sharing is recorded in
+/// `expr.meta.graph_ids`.
+/// Leaves are never targets: sharing them saves no work.
+pub(crate) fn form_connection_in_place<T: Clone, Ops, const D: usize>(
+    rng: &mut Rng,
+    expr: &mut PostfixExpr<T, Ops, D>,
+) -> bool {
+    let mut graph = GraphExpr::from_postfix(expr);
+    let ops: Vec<usize> = graph
+        .nodes
+        .iter()
+        .enumerate()
+        .filter_map(|(i, n)| matches!(n, GraphNode::Op { .. }).then_some(i))
+        .collect();
+    if ops.len() < 2 {
+        return false;
+    }
+    // Nodes are topologically ordered, so any earlier operator is a valid target.
+    let k = usize_range(rng, 1..ops.len());
+    let parent = ops[k];
+    let slot = usize_range(rng, 0..graph.nodes[parent].children().len());
+    let target = ops[usize_range(rng, 0..k)];
+    if !graph.connect(parent, slot, target) {
+        return false;
+    }
+    *expr = graph.to_postfix();
+    true
+}
+
+/// Gives one use of a shared subexpression its own copy, including the constants inside it.
+pub(crate) fn break_connection_in_place<T: Clone, Ops, const D: usize>(
+    rng: &mut Rng,
+    expr: &mut PostfixExpr<T, Ops, D>,
+) -> bool {
+    let mut graph = GraphExpr::from_postfix(expr);
+    let parents = graph.parent_counts();
+    let edges: Vec<(usize, usize)> = graph
+        .nodes
+        .iter()
+        .enumerate()
+        .flat_map(|(i, n)| {
+            n.children()
+                .iter()
+                .enumerate()
+                .map(move |(slot, &c)| (i, slot, c as usize))
+        })
+        .filter(|&(_, _, c)| parents[c] > 1)
+        .map(|(i, slot, _)| (i, slot))
+        .collect();
+    if edges.is_empty() {
+        return false;
+    }
+    let (parent, slot) = edges[usize_range(rng, 0..edges.len())];
+    if !graph.disconnect(parent, slot) {
+        return false;
+    }
+    *expr = graph.to_postfix();
+    true
+}
@@ -1,6 +1,9 @@
 //! Rust-only module (no direct Julia file): lightweight optimization routines.
 
 use dynamic_expressions::utils::ZipEq;
+use fastrand::Rng;
+
+use crate::random::standard_normal;
 
 #[derive(Clone, Copy, Debug)]
 pub(crate) struct OptimOptions {
@@ -59,6 +62,20 @@ pub(crate) trait Objective {
     fn fg(&mut self, x: &[f64], g_out: &mut [f64], budget: &mut EvalBudget) -> Option<f64>;
 }
 
+/// An objective of the form `sum_i r_i(x)^2`, for [`levenberg_marquardt`].
+pub(crate) trait LeastSquaresObjective {
+    fn n_residuals(&self) -> usize;
+    /// Writes the residuals at `x` into `r_out`, and their Jacobian (row-major,
+    /// `n_residuals x x.len()`) into `jac_out` if given.
+    fn residuals(
+        &mut self,
+        x: &[f64],
+        r_out: &mut [f64],
+        jac_out: Option<&mut [f64]>,
+        budget: &mut EvalBudget,
+    ) -> Option<()>;
+}
+
 pub(crate) fn inf_norm(v: &[f64]) -> f64 {
     v.iter().copied().map(f64::abs).fold(0.0, |a, b| a.max(b))
 }
@@ -455,6 +472,422 @@ pub(crate) fn newton_1d_minimize(
     })
 }
 
+/// Overwrites the lower triangle of the symmetric `n x n` matrix `a` with its Cholesky factor `L`
+/// (`a = L L^T`); the strict upper triangle is left as is. Returns `None` if `a` is not positive
+/// definite.
+fn cholesky_factor(a: &mut [f64], n: usize) -> Option<()> {
+    debug_assert_eq!(a.len(), n * n);
+    for j in 0..n {
+        let mut d = a[j * n + j];
+        for k in 0..j {
+            d -= a[j * n + k] * a[j * n + k];
+        }
+        if !(d.is_finite() && d > 0.0) {
+            return None;
+        }
+        let d = d.sqrt();
+        a[j * n + j] = d;
+        for i in (j + 1)..n {
+            let mut v = a[i * n + j];
+            for k in 0..j {
+                v -= a[i * n + k] * a[j * n + k];
+            }
+            a[i * n + j] = v / d;
+        }
+    }
+    Some(())
+}
+
+/// Solves `a x = b` in place (`b` becomes `x`) for a symmetric positive definite `n x n` matrix `a`,
+/// which is overwritten by its Cholesky factor. Returns `None` if `a` is not positive definite.
+fn cholesky_solve(a: &mut [f64], b: &mut [f64]) -> Option<()> {
+    let n = b.len();
+    cholesky_factor(a, n)?;
+    for i in 0..n {
+        let mut v = b[i];
+        for k in 0..i {
+            v -= a[i * n + k] * b[k];
+        }
+        b[i] = v / a[i * n + i];
+    }
+    for i in (0..n).rev() {
+        let mut v = b[i];
+        for k in (i + 1)..n {
+            v -= a[k * n + i] * b[k];
+        }
+        b[i] = v / a[i * n + i];
+    }
+    Some(())
+}
+
+/// Levenberg–Marquardt with Marquardt's diagonal scaling. `minimum` is the residual sum of squares.
+///
+/// Each accepted step costs one Jacobian evaluation and each rejected one a residual evaluation;
+/// both count towards `opts.f_calls_limit`.
+pub(crate) fn levenberg_marquardt(
+    x0: &[f64],
+    obj: &mut impl LeastSquaresObjective,
+    opts: OptimOptions,
+) -> Option<OptimResult> {
+    const LAMBDA_MAX: f64 = 1e12;
+    let n = x0.len();
+    let m = obj.n_residuals();
+    let mut budget = EvalBudget::default();
+    let over_budget = |budget: &EvalBudget| opts.f_calls_limit != 0 && budget.f_calls >= opts.f_calls_limit;
+
+    let mut x = x0.to_vec();
+    let mut x_trial = vec![0.0; n];
+    let mut r = vec![0.0; m];
+    let mut r_trial = vec![0.0; m];
+    let mut jac = vec![0.0; m * n];
+    let mut jtj = vec![0.0; n * n];
+    let mut a = vec![0.0; n * n];
+    let mut g = vec![0.0; n];
+    let mut step = vec![0.0; n];
+
+    obj.residuals(&x, &mut r, Some(&mut jac), &mut budget)?;
+    let mut fx = dot(&r, &r);
+    if !fx.is_finite() {
+        return None;
+    }
+    let mut lambda = 1e-3;
+
+    'outer: for _ in 0..opts.iterations {
+        // g = J^T r and J^T J.
+        g.fill(0.0);
+        jtj.fill(0.0);
+        for (row, &ri) in jac.chunks_exact(n).zip_eq(&r) {
+            for i in 0..n {
+                g[i] += row[i] * ri;
+                for j in 0..=i {
+                    jtj[i * n + j] += row[i] * row[j];
+                }
+            }
+        }
+        for i in 0..n {
+            for j in 0..i {
+                jtj[j * n + i] = jtj[i * n + j];
+            }
+        }
+        let g_inf = inf_norm(&g);
+        if !g_inf.is_finite() || g_inf <= opts.g_abstol {
+            break;
+        }
+
+        loop {
+            if over_budget(&budget) || lambda > LAMBDA_MAX {
+                break 'outer;
+            }
+            a.copy_from_slice(&jtj);
+            for i in 0..n {
+                a[i * n + i] += lambda * jtj[i * n + i].max(1e-12);
+            }
+            for (si, gi) in step.iter_mut().zip_eq(&g) {
+                *si = -gi;
+            }
+            if cholesky_solve(&mut a, &mut step).is_none() {
+                lambda *= 10.0;
+                continue;
+            }
+            axpy_into(&mut x_trial, &x, 1.0, &step);
+            let f_trial = obj
+                .residuals(&x_trial, &mut r_trial, None, &mut budget)
+                .map(|()| dot(&r_trial, &r_trial))
+                .filter(|f| f.is_finite());
+            match f_trial {
+                Some(f_trial) if f_trial < fx => {
+                    let converged = fx - f_trial <= 1e-12 * fx;
+                    x.copy_from_slice(&x_trial);
+                    fx = f_trial;
+                    lambda = (lambda / 10.0).max(1e-12);
+                    if converged || over_budget(&budget) {
+                        break 'outer;
+                    }
+                    obj.residuals(&x, &mut r, Some(&mut jac), &mut budget)?;
+                    break;
+                }
+                _ => lambda *= 10.0,
+            }
+        }
+    }
+
+    Some(OptimResult {
+        minimizer: x,
+        minimum: fx,
+        f_calls: budget.f_calls,
+    })
+}
+
+/// Whether the gradient of `obj` at `x` is finite and agrees with a central difference of `f_only`
+/// along it (along the diagonal if the gradient is zero). `precision` is the machine epsilon the
+/// objective is evaluated in, which sets the step and the rounding tolerance.
+///
+/// Costs one gradient and two function evaluations.
+pub(crate) fn gradient_is_reliable(
+    x: &[f64],
+    obj: &mut impl Objective,
+    precision: f64,
+    budget: &mut EvalBudget,
+) -> bool {
+    let n = x.len();
+    let mut g = vec![0.0; n];
+    let Some(f) = obj.fg(x, &mut g, budget).filter(|f| f.is_finite()) else {
+        return false;
+    };
+    let norm = dot(&g, &g).sqrt();
+    if !norm.is_finite() {
+        return false;
+    }
+    let direction: Vec<f64> = if norm > 0.0 {
+        g.iter().map(|gi| gi / norm).collect()
+    } else {
+        vec![1.0 / (n as f64).sqrt(); n]
+    };
+
+    let h = precision.cbrt() * (1.0 + inf_norm(x));
+    let mut x_step = vec![0.0; n];
+    axpy_into(&mut x_step, x, h, &direction);
+    let f_plus = obj.f_only(&x_step, budget);
+    axpy_into(&mut x_step, x, -h, &direction);
+    let f_minus = obj.f_only(&x_step, budget);
+    let (Some(f_plus), Some(f_minus)) = (f_plus, f_minus) else {
+        return false;
+    };
+    let finite_difference = (f_plus - f_minus) / (2.0 * h);
+    if !finite_difference.is_finite() {
+        return false;
+    }
+    let tolerance = 1e-2 * norm.max(finite_difference.abs()) + 10.0 * precision * (1.0 + f.abs()) / h;
+    (finite_difference - norm).abs() <= tolerance
+}
+
+/// Nelder–Mead with the dimension-adaptive coefficients of Gao and Han (2012), started from a
+/// simplex of 5% steps along each axis (0.00025 for zero coordinates). Uses only `f_only`; failed
+/// or non-finite evaluations count as `+inf`. Returns `None` if no vertex ever had a finite value.
+pub(crate) fn nelder_mead_minimize(x0: &[f64], obj: &mut impl Objective, opts: OptimOptions) -> Option<OptimResult> {
+    let n = x0.len();
+    let nf = n as f64;
+    let (reflect, expand) = (1.0, (1.0 + 2.0 / nf).min(2.0));
+    let (contract, shrink) = ((0.75 - 0.5 / nf).max(0.5), (1.0 - 1.0 / nf).max(0.5));
+    let mut budget = EvalBudget::default();
+    let over_budget = |budget: &EvalBudget| opts.f_calls_limit != 0 && budget.f_calls >= opts.f_calls_limit;
+    let mut eval =
+        |x: &[f64], budget: &mut EvalBudget| obj.f_only(x, budget).filter(|f| f.is_finite()).unwrap_or(f64::INFINITY);
+
+    let mut simplex: Vec<(f64, Vec<f64>)> = Vec::with_capacity(n + 1);
+    simplex.push((eval(x0, &mut budget), x0.to_vec()));
+    for i in 0..n {
+        let mut v = x0.to_vec();
+        v[i] = if v[i] != 0.0 { 1.05 * v[i] } else { 0.00025 };
+        simplex.push((eval(&v, &mut budget), v));
+    }
+
+    let mut centroid = vec![0.0; n];
+    let mut x_r = vec![0.0; n];
+    let mut x_t = vec![0.0; n];
+    for _ in 0..opts.iterations {
+        simplex.sort_by(|a, b| a.0.total_cmp(&b.0));
+        if over_budget(&budget) {
+            break;
+        }
+        let (f_best, x_best) = (simplex[0].0, &simplex[0].1);
+        let f_worst = simplex[n].0;
+        let x_scale = 1e-10 * (1.0 + inf_norm(x_best));
+        let flat = f_worst - f_best <= 1e-12 * (1.0 + f_best.abs());
+        let small = simplex[1..]
+            .iter()
+            .all(|(_, v)| v.iter().zip_eq(x_best).all(|(a, b)| (a - b).abs() <= x_scale));
+        if flat && small {
+            break;
+        }
+
+        centroid.fill(0.0);
+        for (_, v) in &simplex[..n] {
+            for (c, vi) in centroid.iter_mut().zip_eq(v) {
+                *c += vi / nf;
+            }
+        }
+        let worst = &simplex[n].1;
+        for ((r, c), w) in x_r.iter_mut().zip_eq(&centroid).zip_eq(worst) {
+            *r = c + reflect * (c - w);
+        }
+        let f_r = eval(&x_r, &mut budget);
+
+        if f_r < f_best {
+            for ((t, c), r) in x_t.iter_mut().zip_eq(&centroid).zip_eq(&x_r) {
+                *t = c + expand * (r - c);
+            }
+            let f_e = eval(&x_t, &mut budget);
+            simplex[n] = if f_e < f_r {
+                (f_e, x_t.clone())
+            } else {
+                (f_r, x_r.clone())
+            };
+            continue;
+        }
+        if f_r < simplex[n - 1].0 {
+            simplex[n] = (f_r, x_r.clone());
+            continue;
+        }
+        // Contract outside (towards the reflected point) or inside (towards the worst vertex).
+        let (towards, f_bound) = if f_r < f_worst { (&x_r, f_r) } else { (worst, f_worst) };
+        for ((t, c), p) in x_t.iter_mut().zip_eq(&centroid).zip_eq(towards) {
+            *t = c + contract * (p - c);
+        }
+        let f_c = eval(&x_t, &mut budget);
+        if f_c < f_bound || (f_r < f_worst && f_c <= f_bound) {
+            simplex[n] = (f_c, x_t.clone());
+            continue;
+        }
+        let x_best = simplex[0].1.clone();
+        for (f, v) in &mut simplex[1..] {
+            for (vi, b) in v.iter_mut().zip_eq(&x_best) {
+                *vi = b + shrink * (*vi - b);
+            }
+            *f = eval(v, &mut budget);
+        }
+    }
+
+    simplex.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let (minimum, minimizer) = simplex.swap_remove(0);
+    minimum.is_finite().then_some(OptimResult {
+        minimizer,
+        minimum,
+        f_calls: budget.f_calls,
+    })
+}
+
+/// A small (mu/mu_w, lambda) CMA-ES with cumulative step-size adaptation and rank-one plus rank-mu
+/// covariance updates, using the default strategy parameters of Hansen's tutorial. Samples are
+/// drawn through the Cholesky factor of the covariance instead of its eigendecomposition, so the
+/// step-size path accumulates the standard-normal draws directly.
+///
+/// Starts from `x0` with step size `0.25 * max(|x0|_inf, 1)`; `opts.iterations` counts generations
+/// of `4 + 3 ln(n)` samples. Uses only `f_only`; failed or non-finite evaluations rank last.
+pub(crate) fn cma_es_minimize(
+    x0: &[f64],
+    obj: &mut impl Objective,
+    opts: OptimOptions,
+    rng: &mut Rng,
+) -> Option<OptimResult> {
+    let n = x0.len();
+    let nf = n as f64;
+    let lambda = 4 + (3.0 * nf.ln()).floor() as usize;
+    let mu = lambda / 2;
+    let mut weights: Vec<f64> = (0..mu)
+        .map(|i| (mu as f64 + 0.5).ln() - ((i + 1) as f64).ln())
+        .collect();
+    let sum_w: f64 = weights.iter().sum();
+    weights.iter_mut().for_each(|w| *w /= sum_w);
+    let mu_eff = 1.0 / weights.iter().map(|w| w * w).sum::<f64>();
+
+    let c_sigma = (mu_eff + 2.0) / (nf + mu_eff + 5.0);
+    let d_sigma = 1.0 + 2.0 * (((mu_eff - 1.0) / (nf + 1.0)).sqrt() - 1.0).max(0.0) + c_sigma;
+    let c_c = (4.0 + mu_eff / nf) / (nf + 4.0 + 2.0 * mu_eff / nf);
+    let c_1 = 2.0 / ((nf + 1.3).powi(2) + mu_eff);
+    let c_mu = (1.0 - c_1).min(2.0 * (mu_eff - 2.0 + 1.0 / mu_eff) / ((nf + 2.0).powi(2) + mu_eff));
+    let chi_n = nf.sqrt() * (1.0 - 1.0 / (4.0 * nf) + 1.0 / (21.0 * nf * nf));
+
+    let mut budget = EvalBudget::default();
+    let over_budget = |budget: &EvalBudget| opts.f_calls_limit != 0 && budget.f_calls >= opts.f_calls_limit;
+    let mut eval =
+        |x: &[f64], budget: &mut EvalBudget| obj.f_only(x, budget).filter(|f| f.is_finite()).unwrap_or(f64::INFINITY);
+
+    let mut best_x = x0.to_vec();
+    let mut best_f = eval(x0, &mut budget);
+    let mut mean = x0.to_vec();
+    let mut sigma = 0.25 * inf_norm(x0).max(1.0);
+    let mut cov = vec![0.0; n * n];
+    for i in 0..n {
+        cov[i * n + i] = 1.0;
+    }
+    let mut chol = cov.clone();
+    let mut p_sigma = vec![0.0; n];
+    let mut p_c = vec![0.0; n];
+    let mut z = vec![vec![0.0; n]; lambda];
+    let mut y = vec![vec![0.0; n]; lambda];
+    let mut x = vec![0.0; n];
+    let mut f = vec![0.0; lambda];
+    let mut order: Vec<usize> = (0..lambda).collect();
+    let mut y_w = vec![0.0; n];
+    let mut z_w = vec![0.0; n];
+
+    'generations: for generation in 0..opts.iterations {
+        for k in 0..lambda {
+            if over_budget(&budget) {
+                break 'generations;
+            }
+            for zi in &mut z[k] {
+                *zi = standard_normal(rng);
+            }
+            for i in 0..n {
+                y[k][i] = (0..=i).map(|j| chol[i * n + j] * z[k][j]).sum();
+            }
+            axpy_into(&mut x, &mean, sigma, &y[k]);
+            f[k] = eval(&x, &mut budget);
+            if f[k] < best_f {
+                best_f = f[k];
+                best_x.copy_from_slice(&x);
+            }
+        }
+        order.sort_by(|&a, &b| f[a].total_cmp(&f[b]));
+
+        y_w.fill(0.0);
+        z_w.fill(0.0);
+        for (&k, &w) in order.iter().zip(&weights) {
+            for i in 0..n {
+                y_w[i] += w * y[k][i];
+                z_w[i] += w * z[k][i];
+            }
+        }
+        for (m, yi) in mean.iter_mut().zip_eq(&y_w) {
+            *m += sigma * yi;
+        }
+
+        let ps_scale = (c_sigma * (2.0 - c_sigma) * mu_eff).sqrt();
+        for (p, zi) in p_sigma.iter_mut().zip_eq(&z_w) {
+            *p = (1.0 - c_sigma) * *p + ps_scale * zi;
+        }
+        let ps_norm = dot(&p_sigma, &p_sigma).sqrt();
+        let ps_bias = (1.0 - (1.0 - c_sigma).powi(2 * (generation as i32 + 1))).sqrt();
+        let h_sigma = ps_norm / ps_bias < (1.4 + 2.0 / (nf + 1.0)) * chi_n;
+        let pc_scale = if h_sigma {
+            (c_c * (2.0 - c_c) * mu_eff).sqrt()
+        } else {
+            0.0
+        };
+        for (p, yi) in p_c.iter_mut().zip_eq(&y_w) {
+            *p = (1.0 - c_c) * *p + pc_scale * yi;
+        }
+
+        let delta_h = if h_sigma { 0.0 } else { c_c * (2.0 - c_c) };
+        for i in 0..n {
+            for j in 0..=i {
+                let rank_mu: f64 = order.iter().zip(&weights).map(|(&k, w)| w * y[k][i] * y[k][j]).sum();
+                let v = (1.0 - c_1 - c_mu + c_1 * delta_h) * cov[i * n + j] + c_1 * p_c[i] * p_c[j] + c_mu * rank_mu;
+                cov[i * n + j] = v;
+                cov[j * n + i] = v;
+            }
+        }
+        sigma *= ((c_sigma / d_sigma) * (ps_norm / chi_n - 1.0)).exp();
+
+        chol.copy_from_slice(&cov);
+        if !sigma.is_finite() || cholesky_factor(&mut chol, n).is_none() {
+            break;
+        }
+        let max_sd = (0..n).map(|i| cov[i * n + i]).fold(0.0, f64::max).sqrt();
+        if sigma * max_sd <= 1e-12 * (1.0 + inf_norm(&mean)) {
+            break;
+        }
+    }
+
+    best_f.is_finite().then_some(OptimResult {
+        minimizer: best_x,
+        minimum: best_f,
+        f_calls: budget.f_calls,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -624,4 +1057,144 @@ mod tests {
         assert!((res.minimizer[2] - (2.0 / 3.0)).abs() < 1e-6);
         assert!(res.minimum.is_finite());
     }
+
+    /// Residuals of fitting `y = a * exp(b * t)` to exact data with `a = 2`, `b = -0.5`.
+    struct ExpFit;
+
+    impl LeastSquaresObjective for ExpFit {
+        fn n_residuals(&self) -> usize {
+            10
+        }
+
+        fn residuals(
+            &mut self,
+            x: &[f64],
+            r_out: &mut [f64],
+            mut jac_out: Option<&mut [f64]>,
+            budget: &mut EvalBudget,
+        ) -> Option<()> {
+            budget.f_calls += 1;
+            for (i, r) in r_out.iter_mut().enumerate() {
+                let t = i as f64 / 3.0;
+                let e = (x[1] * t).exp();
+                *r = x[0] * e - 2.0 * (-0.5 * t).exp();
+                if let Some(jac) = jac_out.as_deref_mut() {
+                    jac[2 * i] = e;
+                    jac[2 * i + 1] = x[0] * t * e;
+                }
+            }
+            Some(())
+        }
+    }
+
+    #[test]
+    fn levenberg_marquardt_fits_exponential() {
+        let opts = OptimOptions {
+            iterations: 100,
+            f_calls_limit: 0,
+            g_abstol: 1e-12,
+        };
+        let res = levenberg_marquardt(&[1.0, 0.0], &mut ExpFit, opts).unwrap();
+        assert!((res.minimizer[0] - 2.0).abs() < 1e-6, "{:?}", res.minimizer);
+        assert!((res.minimizer[1] + 0.5).abs() < 1e-6, "{:?}", res.minimizer);
+        assert!(res.minimum < 1e-12);
+    }
+
+    #[test]
+    fn levenberg_marquardt_respects_f_calls_limit() {
+        let opts = OptimOptions {
+            iterations: 100,
+            f_calls_limit: 3,
+            g_abstol: 0.0,
+        };
+        let res = levenberg_marquardt(&[1.0, 0.0], &mut ExpFit, opts).unwrap();
+        assert!(res.f_calls <= 4, "f_calls = {}", res.f_calls);
+    }
+
+    /// `|x0 - 1| + 2 |x1 + 2|`, whose "gradient" is only correct in sign (scaled by 1/10).
+    struct Kinked;
+
+    impl Objective for Kinked {
+        fn f_only(&mut self, x: &[f64], budget: &mut EvalBudget) -> Option<f64> {
+            budget.f_calls += 1;
+            Some((x[0] - 1.0).abs() + 2.0 * (x[1] + 2.0).abs())
+        }
+
+        fn fg(&mut self, x: &[f64], g_out: &mut [f64], budget: &mut EvalBudget) -> Option<f64> {
+            g_out[0] = 0.1 * (x[0] - 1.0).signum();
+            g_out[1] = 0.2 * (x[1] + 2.0).signum();
+            self.f_only(x, budget)
+        }
+    }
+
+    #[test]
+    fn gradient_check_accepts_exact_and_rejects_wrong_gradients() {
+        let mut budget = EvalBudget::default();
+        assert!(gradient_is_reliable(
+            &[0.3, 0.7],
+            &mut Quad2D,
+            f64::EPSILON,
+            &mut budget
+        ));
+        assert!(gradient_is_reliable(
+            &[0.5, -0.5, 0.0],
+            &mut Quad3DOffDiag,
+            f64::EPSILON,
+            &mut budget
+        ));
+        assert!(!gradient_is_reliable(
+            &[0.3, 0.7],
+            &mut Kinked,
+            f64::EPSILON,
+            &mut budget
+        ));
+        assert_eq!(budget.f_calls, 9);
+    }
+
+    #[test]
+    fn nelder_mead_minimizes_smooth_and_kinked_objectives() {
+        let opts = OptimOptions {
+            iterations: 500,
+            f_calls_limit: 0,
+            g_abstol: 0.0,
+        };
+        let res = nelder_mead_minimize(&[0.5, -0.5, 0.0], &mut Quad3DOffDiag, opts).unwrap();
+        for (x, expected) in res.minimizer.iter().zip([2.0 / 3.0, -1.0 / 3.0, 2.0 / 3.0]) {
+            assert!((x - expected).abs() < 1e-5, "{:?}", res.minimizer);
+        }
+        let res = nelder_mead_minimize(&[0.0, 0.0], &mut Kinked, opts).unwrap();
+        assert!(res.minimum < 1e-6, "{res:?}");
+        let res = nelder_mead_minimize(&[0.0], &mut Quad1D, opts).unwrap();
+        assert!((res.minimizer[0] - 3.0).abs() < 1e-5, "{res:?}");
+    }
+
+    #[test]
+    fn cma_es_minimizes_smooth_and_kinked_objectives() {
+        let opts = OptimOptions {
+            iterations: 300,
+            f_calls_limit: 0,
+            g_abstol: 0.0,
+        };
+        let mut rng = Rng::with_seed(3);
+        let res = cma_es_minimize(&[0.5, -0.5, 0.0], &mut Quad3DOffDiag, opts, &mut rng).unwrap();
+        for (x, expected) in res.minimizer.iter().zip([2.0 / 3.0, -1.0 / 3.0, 2.0 / 3.0]) {
+            assert!((x - expected).abs() < 1e-5, "{:?}", res.minimizer);
+        }
+        let res = cma_es_minimize(&[0.0, 0.0], &mut Kinked, opts, &mut rng).unwrap();
+        assert!(res.minimum < 1e-6, "{res:?}");
+    }
+
+    #[test]
+    fn derivative_free_methods_respect_f_calls_limit() {
+        let opts = OptimOptions {
+            iterations: 1000,
+            f_calls_limit: 20,
+            g_abstol: 0.0,
+        };
+        let res = nelder_mead_minimize(&[0.0, 0.0], &mut Quad2D, opts).unwrap();
+        // One iteration may finish with a shrink of the whole simplex.
+        assert!(res.f_calls <= 20 + 2, "f_calls = {}", res.f_calls);
+        let res = cma_es_minimize(&[0.0, 0.0], &mut Quad2D, opts, &mut Rng::with_seed(0)).unwrap();
+        assert!(res.f_calls <= 20, "f_calls = {}", res.f_calls);
+    }
 }
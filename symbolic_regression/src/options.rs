@@ -1,7 +1,16 @@
+use std::borrow::Cow;
+
 use num_traits::Float;
 
+use crate::adaptive_mutation::MutationAdaptation;
+use crate::custom_mutation::MutationObject;
+use crate::dataset::Dataset;
+use crate::full_objective::FullObjectiveObject;
 use crate::loss_functions::{LossObject, mse};
+use crate::migration::MigrationTopology;
+use crate::multi_objective::SelectionMode;
 use crate::operators::Operators;
+use crate::template::ExpressionTemplate;
 
 #[rustfmt::skip]
 macro_rules! sr_mutation_weights_spec {
@@ -98,6 +107,12 @@ macro_rules! sr_options_spec {
                     (f64, 0.001, "fraction-replaced-guesses"),
                 topn:
                     (usize, 12, "topn"),
+                dimensional_constraint_penalty:
+                    (f64, 1000.0, "dimensional-constraint-penalty"),
+                n_parameters:
+                    (usize, 0, "n-parameters"),
+                select_k_features:
+                    (usize, 0, "select-k-features"),
             }
             neg_flags {
                 use_frequency:
@@ -124,6 +139,22 @@ macro_rules! sr_options_spec {
                     (true, should_simplify, "should-simplify"),
                 batching:
                     (false, batching, "batching"),
+                handle_interrupts:
+                    (false, handle_interrupts, "handle-interrupts"),
+                watch_stdin:
+                    (false, watch_stdin, "watch-stdin"),
+                select_best_by_validation:
+                    (false, select_best_by_validation, "select-best-by-validation"),
+                dimensionless_constants_only:
+                    (false, dimensionless_constants_only, "dimensionless-constants-only"),
+                graph_expressions:
+                    (false, graph_expressions, "graph-expressions"),
+                deterministic:
+                    (false, deterministic, "deterministic"),
+                use_recorder:
+                    (false, use_recorder, "use-recorder"),
+                linear_scaling:
+                    (false, linear_scaling, "linear-scaling"),
             }
         }
     };
@@ -149,6 +180,66 @@ macro_rules! __define_mutation_weights {
 
 sr_mutation_weights_spec!(__define_mutation_weights);
 
+/// Relative weights of the crossover operators; one is sampled for each crossover.
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[derive(Clone, Debug, PartialEq)]
+pub struct CrossoverWeights {
+    /// Exchange two random subtrees.
+    pub subtree: f64,
+    /// Exchange a random subtree for one of the other parent's with at most `2 * size + 1` nodes.
+    pub size_fair: f64,
+    /// Exchange the subtrees at one random point of the region where both parents have the same
+    /// shape.
+    pub one_point: f64,
+    /// Exchange each operator and boundary subtree of that common region with probability 1/2.
+    pub homologous: f64,
+    /// Subtree crossover between the first parent and a random entry of the population's hall of
+    /// fame for the current iteration (which `hof_migration` feeds from the global one).
+    pub hall_of_fame: f64,
+}
+
+impl Default for CrossoverWeights {
+    fn default() -> Self {
+        Self {
+            subtree: 1.0,
+            size_fair: 0.0,
+            one_point: 0.0,
+            homologous: 0.0,
+            hall_of_fame: 0.0,
+        }
+    }
+}
+
+/// Per-population overrides of the global options (see `Options::islands`).
+#[derive(Clone, Debug, Default)]
+pub struct IslandOptions {
+    pub mutation_weights: Option<MutationWeights>,
+    pub parsimony: Option<f64>,
+    /// Clamped to the global `maxsize`.
+    pub maxsize: Option<usize>,
+}
+
+/// Algorithm `optimize_constants` fits constants (and parametric-expression parameters) with.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Default)]
+pub enum OptimizerAlgorithm {
+    /// BFGS with a backtracking line search (Newton's method for a single constant).
+    #[default]
+    Bfgs,
+    /// Levenberg–Marquardt on the per-row residuals. Only applies to least-squares losses (see
+    /// `LossFn::is_least_squares`) without a `full_objective`; other searches fall back to BFGS.
+    LevenbergMarquardt,
+    /// Derivative-free Nelder–Mead simplex search; ignores operator and loss derivatives.
+    NelderMead,
+    /// Derivative-free CMA-ES, seeded from the search RNG; ignores operator and loss derivatives.
+    CmaEs,
+    /// BFGS when the gradient is usable, otherwise a derivative-free method (Nelder–Mead for up to
+    /// four parameters, CMA-ES beyond). The gradient is not used when the loss is not smooth (see
+    /// `LossFn::is_smooth`), or when, at the member's current constants, it is non-finite or
+    /// disagrees with a finite difference (e.g. a `custom_opset!` operator with a wrong `partial`).
+    Auto,
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Default)]
 pub enum OutputStyle {
     /// Enable ANSI styles only when stderr supports it (and `NO_COLOR` is not set).
@@ -174,7 +265,25 @@ macro_rules! __define_options {
 
             pub operators: Operators<D>,
             pub mutation_weights: MutationWeights,
+            /// User-defined mutations, each sampled with the given weight next to
+            /// `mutation_weights`.
+            pub custom_mutations: Vec<(MutationObject<T, D>, f64)>,
+            /// Which crossover operator a crossover (see `crossover_probability`) uses.
+            pub crossover_weights: CrossoverWeights,
+            /// Online reweighting of `mutation_weights` from each population's mutation record.
+            pub mutation_adaptation: MutationAdaptation,
+            /// How tournaments rank members and which member a child replaces.
+            pub selection: SelectionMode,
             pub loss: LossObject<T>,
+            /// Expression-level objective; replaces `loss` when set.
+            pub full_objective: Option<FullObjectiveObject<T>>,
+            pub optimizer_algorithm: OptimizerAlgorithm,
+            /// Fixed outer structure; when set, only its sub-expressions are searched.
+            pub template: Option<ExpressionTemplate<T>>,
+            /// Which populations exchange members when `migration` is enabled.
+            pub migration_topology: MigrationTopology,
+            /// Overrides for population `i` are `islands[i % islands.len()]`; empty means none.
+            pub islands: Vec<IslandOptions>,
 
             pub output_style: OutputStyle,
 
@@ -195,7 +304,16 @@ macro_rules! __define_options {
                     $($pname: $pdefault,)*
                     operators: Operators::new(),
                     mutation_weights: MutationWeights::default(),
+                    custom_mutations: Vec::new(),
+                    crossover_weights: CrossoverWeights::default(),
+                    mutation_adaptation: MutationAdaptation::Off,
+                    selection: SelectionMode::Cost,
                     loss: mse::<T>(),
+                    full_objective: None,
+                    optimizer_algorithm: OptimizerAlgorithm::Bfgs,
+                    template: None,
+                    migration_topology: MigrationTopology::Global,
+                    islands: Vec::new(),
                     output_style: OutputStyle::Auto,
                     variable_complexities: None,
                     operator_complexity_overrides: std::collections::HashMap::new(),
@@ -255,6 +373,35 @@ macro_rules! __define_wasm_options_shim {
 sr_options_spec!(__define_wasm_options_shim);
 
 impl<T: Float, const D: usize> Options<T, D> {
+    /// Features an expression may reference on `dataset`: its own plus, when the dataset has a
+    /// class column, one per parametric-expression parameter (`x{n_features + k}` is parameter `k`).
+    pub fn n_expr_features(&self, dataset: &Dataset<T>) -> usize {
+        dataset.n_features
+            + if dataset.classes.is_some() {
+                self.n_parameters
+            } else {
+                0
+            }
+    }
+
+    /// The options population `pop_idx` evolves under, with its `islands` overrides applied.
+    pub fn for_island(&self, pop_idx: usize) -> Cow<'_, Self> {
+        let Some(island) = self.islands.get(pop_idx % self.islands.len().max(1)) else {
+            return Cow::Borrowed(self);
+        };
+        let mut opts = self.clone();
+        if let Some(w) = &island.mutation_weights {
+            opts.mutation_weights = w.clone();
+        }
+        if let Some(p) = island.parsimony {
+            opts.parsimony = p;
+        }
+        if let Some(m) = island.maxsize {
+            opts.maxsize = m.min(self.maxsize);
+        }
+        Cow::Owned(opts)
+    }
+
     pub fn uses_default_complexity(&self) -> bool {
         self.complexity_of_constants == 1
             && self.complexity_of_variables == 1
@@ -1,13 +1,22 @@
+use std::ops::AddAssign;
+
 use dynamic_expressions::expression::PostfixExpr;
-use dynamic_expressions::{EvalOptions, EvalPlan};
+use dynamic_expressions::{EvalOptions, EvalPlan, GraphExpr};
 use num_traits::Float;
 
+use crate::check_constraints::check_constraints;
 use crate::complexity::compute_complexity;
-use crate::dataset::TaggedDataset;
-use crate::loss_functions::loss_to_cost;
+use crate::dataset::{Dataset, ParameterRows, TaggedDataset};
+use crate::dimensional_analysis::violates_parametric_dimensional_constraints;
+use crate::full_objective::PostfixExprEvaluator;
+use crate::linear_scaling::LinearScaling;
+use crate::loss_functions::{LossFn, loss_to_cost};
 use crate::options::Options;
+use crate::random::standard_normal;
+use crate::template::{ExpressionTemplate, check_template_constraints, template_complexity};
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize), serde(transparent))]
 pub struct MemberId(pub u64);
 
 #[derive(Debug)]
@@ -20,6 +29,19 @@ pub struct PopMember<T: Float, Ops, const D: usize> {
     pub complexity: usize,
     pub loss: T,
     pub cost: T,
+    /// Loss on the validation dataset, if one was given to the search. Only filled in for
+    /// hall-of-fame entries, and reset whenever the member is re-evaluated.
+    pub validation_loss: Option<T>,
+    /// Sub-expressions of a template member (see `Options::template`), empty otherwise. `expr` is
+    /// always their composition.
+    pub subexprs: Vec<PostfixExpr<T, Ops, D>>,
+    /// Per-class parameter values of a parametric member (see `Options::n_parameters`), empty
+    /// otherwise. Parameter `k` of class `c` is `parameters[k * n_classes + c]`.
+    pub parameters: Vec<T>,
+    /// Scale and offset applied to the outputs of `expr` with `Options::linear_scaling`, fitted
+    /// whenever the member is evaluated; `None` without it, and once folded into `expr` (see
+    /// [`PopMember::fold_linear_scaling`]).
+    pub scaling: Option<LinearScaling<T>>,
 }
 
 impl<T: Float, Ops, const D: usize> Clone for PopMember<T, Ops, D> {
@@ -33,6 +55,10 @@ impl<T: Float, Ops, const D: usize> Clone for PopMember<T, Ops, D> {
             complexity: self.complexity,
             loss: self.loss,
             cost: self.cost,
+            validation_loss: self.validation_loss,
+            subexprs: self.subexprs.clone(),
+            parameters: self.parameters.clone(),
+            scaling: self.scaling,
         }
     }
 }
@@ -41,6 +67,8 @@ pub struct Evaluator<T: Float, const D: usize> {
     pub eval_opts: EvalOptions,
     pub yhat: Vec<T>,
     pub scratch: ndarray::Array2<T>,
+    /// Datasets extended with a parametric member's parameter rows, reused across evaluations.
+    pub parametric: ParameterRows<T>,
 }
 
 impl<T: Float, const D: usize> Evaluator<T, D> {
@@ -52,6 +80,7 @@ impl<T: Float, const D: usize> Evaluator<T, D> {
             },
             yhat: vec![T::zero(); n_rows],
             scratch: ndarray::Array2::zeros((0, 0)),
+            parametric: ParameterRows::default(),
         }
     }
 
@@ -62,6 +91,74 @@ impl<T: Float, const D: usize> Evaluator<T, D> {
     }
 }
 
+impl<T: Float, Ops, const D: usize> PopMember<T, Ops, D> {
+    /// Number of leading constants of `expr` that belong to the member itself; the rest are fixed
+    /// template-combiner constants.
+    pub(crate) fn n_free_consts(&self) -> usize {
+        if self.subexprs.is_empty() {
+            self.expr.consts.len()
+        } else {
+            self.subexprs.iter().map(|s| s.consts.len()).sum()
+        }
+    }
+
+    /// Draws standard-normal starting values for `n_parameters` parameters per class of `dataset`
+    /// (none without a class column).
+    pub(crate) fn randomize_parameters(&mut self, rng: &mut fastrand::Rng, n_parameters: usize, dataset: &Dataset<T>) {
+        let n = if dataset.classes.is_some() {
+            n_parameters * dataset.n_classes
+        } else {
+            0
+        };
+        self.parameters = (0..n)
+            .map(|_| T::from(standard_normal(rng)).unwrap_or_else(T::zero))
+            .collect();
+    }
+
+    /// Copies the leading constants of `expr` back into the sub-expressions after `expr.consts` was
+    /// changed directly (e.g. by constant optimization).
+    pub(crate) fn sync_subexpr_consts(&mut self)
+    where
+        T: Copy,
+    {
+        let mut rest = &self.expr.consts[..];
+        for sub in &mut self.subexprs {
+            let (head, tail) = rest.split_at(sub.consts.len());
+            sub.consts.copy_from_slice(head);
+            rest = tail;
+        }
+    }
+
+    /// Recomputes `cost` from `loss` under `options` (e.g. another island's parsimony).
+    pub(crate) fn rescore(&mut self, options: &Options<T, D>, baseline_loss: Option<T>) {
+        if self.loss.is_finite() {
+            self.cost = loss_to_cost(
+                self.loss,
+                self.complexity,
+                options.parsimony,
+                options.use_baseline,
+                baseline_loss,
+            );
+        }
+    }
+
+    pub(crate) fn compute_complexity(&self, options: &Options<T, D>) -> usize {
+        if self.subexprs.is_empty() {
+            compute_complexity(&self.expr, options)
+        } else {
+            template_complexity(&self.subexprs, options)
+        }
+    }
+
+    pub(crate) fn satisfies_constraints(&self, options: &Options<T, D>, curmaxsize: usize) -> bool {
+        if self.subexprs.is_empty() {
+            check_constraints(&self.expr, options, curmaxsize)
+        } else {
+            check_template_constraints(&self.subexprs, options, curmaxsize)
+        }
+    }
+}
+
 impl<T: Float, Ops, const D: usize> PopMember<T, Ops, D>
 where
     Ops: dynamic_expressions::OperatorSet<T = T>,
@@ -83,41 +180,185 @@ where
             complexity: 0,
             loss: T::infinity(),
             cost: T::infinity(),
+            validation_loss: None,
+            subexprs: Vec::new(),
+            parameters: Vec::new(),
+            scaling: None,
         }
     }
 
+    /// A template member built from its sub-expressions.
+    pub fn from_template(
+        id: MemberId,
+        parent: Option<MemberId>,
+        birth: u64,
+        template: &ExpressionTemplate<T>,
+        subexprs: Vec<PostfixExpr<T, Ops, D>>,
+        n_features: usize,
+    ) -> Self {
+        let mut member = Self::from_expr(id, parent, birth, template.compose(&subexprs), n_features);
+        member.subexprs = subexprs;
+        member
+    }
+
+    /// Recomposes `expr` after the sub-expressions changed.
+    pub(crate) fn recompose(&mut self, template: &ExpressionTemplate<T>, n_features: usize) {
+        self.expr = template.compose(&self.subexprs);
+        self.rebuild_plan(n_features);
+    }
+
     pub fn rebuild_plan(&mut self, n_features: usize) {
         self.plan = dynamic_expressions::compile_plan(&self.expr.nodes, n_features, self.expr.consts.len());
     }
 
+    /// Folds `scaling` into `expr` (see [`LinearScaling::fold_into`]) and recomputes `complexity`
+    /// for the folded nodes, keeping the losses; `cost` is left as it was (see `rescore`). A
+    /// template member's `subexprs` are cleared, as the folded `expr` is no longer their
+    /// composition. Returns false, changing nothing, if there is no scaling or it cannot be folded.
+    pub fn fold_linear_scaling(&mut self, n_features: usize, options: &Options<T, D>) -> bool {
+        let Some(expr) = self.scaling.and_then(|s| s.fold_into(&self.expr)) else {
+            return false;
+        };
+        self.expr = expr;
+        self.subexprs.clear();
+        self.scaling = None;
+        self.complexity = compute_complexity(&self.expr, options);
+        self.rebuild_plan(n_features);
+        true
+    }
+
     pub fn evaluate(
         &mut self,
         dataset: &TaggedDataset<'_, T>,
         options: &Options<T, D>,
         evaluator: &mut Evaluator<T, D>,
-    ) -> bool {
-        let ok = dynamic_expressions::eval_plan_array_into(
-            &mut evaluator.yhat,
-            &self.plan,
-            &self.expr,
+    ) -> bool
+    where
+        T: AddAssign,
+    {
+        self.complexity = self.compute_complexity(options);
+        self.validation_loss = None;
+        let loss = self.loss_on(dataset.data, options, evaluator, true)
+            + dimensional_penalty(&self.expr, dataset.data, options);
+        self.set_loss(loss, options, dataset.baseline_loss)
+    }
+
+    /// Computes `validation_loss` on `dataset` (infinite if the expression fails to evaluate), with
+    /// the scaling fitted on the training data. Unlike the training loss, it carries no
+    /// dimensional penalty, so it measures the fit alone.
+    pub fn evaluate_validation(
+        &mut self,
+        dataset: &Dataset<T>,
+        options: &Options<T, D>,
+        evaluator: &mut Evaluator<T, D>,
+    ) where
+        T: AddAssign,
+    {
+        let loss = self.loss_on(dataset, options, evaluator, false);
+        self.validation_loss = Some(if loss.is_finite() { loss } else { T::infinity() });
+    }
+
+    /// The loss on `dataset`; with `fit_scaling`, `scaling` is first refitted to it.
+    fn loss_on(
+        &mut self,
+        dataset: &Dataset<T>,
+        options: &Options<T, D>,
+        evaluator: &mut Evaluator<T, D>,
+        fit_scaling: bool,
+    ) -> T
+    where
+        T: AddAssign,
+    {
+        if fit_scaling {
+            self.scaling = None;
+        }
+        evaluator.ensure_n_rows(dataset.n_rows);
+        let dataset = if self.parameters.is_empty() || dataset.classes.is_none() {
+            dataset
+        } else {
+            dataset.parameter_rows_into(&self.parameters, &mut evaluator.parametric)
+        };
+        if let Some(objective) = options.full_objective.as_ref() {
+            return objective.loss(&mut PostfixExprEvaluator::new(&mut self.expr), dataset);
+        }
+
+        if !self.predict_into(
             dataset.x.view(),
+            &mut evaluator.yhat,
             &mut evaluator.scratch,
             &evaluator.eval_opts,
-        );
+        ) {
+            return T::infinity();
+        }
+        if fit_scaling && options.linear_scaling {
+            self.scaling = Some(LinearScaling::fit(
+                &evaluator.yhat,
+                dataset.y_slice(),
+                dataset.weights_slice(),
+                options.loss.as_ref(),
+            ));
+        }
+        if let Some(scaling) = self.scaling {
+            scaling.apply(&mut evaluator.yhat);
+        }
 
-        self.complexity = compute_complexity(&self.expr.nodes, options);
+        options
+            .loss
+            .loss(&evaluator.yhat, dataset.y_slice(), dataset.weights_slice())
+    }
 
-        if !ok {
-            self.loss = T::infinity();
-            self.cost = T::infinity();
-            return false;
+    /// Evaluates `expr` on `x` into `yhat`: through its graph when it shares subexpressions (see
+    /// `Options::graph_expressions`), so each shared node is computed once, and through `plan`
+    /// otherwise. Returns false if the expression fails to evaluate.
+    fn predict_into(
+        &self,
+        x: ndarray::ArrayView2<'_, T>,
+        yhat: &mut [T],
+        scratch: &mut ndarray::Array2<T>,
+        eval_opts: &EvalOptions,
+    ) -> bool {
+        if self.expr.meta.graph_ids.is_empty() {
+            return dynamic_expressions::eval_plan_array_into(yhat, &self.plan, &self.expr, x, scratch, eval_opts);
         }
+        let (out, ok) = dynamic_expressions::eval_graph_array(&GraphExpr::from_postfix(&self.expr), x, eval_opts);
+        yhat.copy_from_slice(&out);
+        ok
+    }
 
-        let loss = options.loss.loss(
-            &evaluator.yhat,
-            dataset.y.as_slice().unwrap(),
-            dataset.weights.as_ref().and_then(|w| w.as_slice()),
-        );
+    /// The loss on every row of `dataset` (see [`LossFn::row_losses`]), with NaN as infinity;
+    /// infinite everywhere if the expression fails to evaluate.
+    pub(crate) fn row_errors(
+        &self,
+        dataset: &Dataset<T>,
+        loss: &dyn LossFn<T>,
+        evaluator: &mut Evaluator<T, D>,
+    ) -> Vec<f64> {
+        evaluator.ensure_n_rows(dataset.n_rows);
+        let dataset = if self.parameters.is_empty() || dataset.classes.is_none() {
+            dataset
+        } else {
+            dataset.parameter_rows_into(&self.parameters, &mut evaluator.parametric)
+        };
+        if !self.predict_into(
+            dataset.x.view(),
+            &mut evaluator.yhat,
+            &mut evaluator.scratch,
+            &evaluator.eval_opts,
+        ) {
+            return vec![f64::INFINITY; dataset.n_rows];
+        }
+        if let Some(scaling) = self.scaling {
+            scaling.apply(&mut evaluator.yhat);
+        }
+        let mut losses = vec![T::zero(); dataset.n_rows];
+        loss.row_losses(&evaluator.yhat, dataset.y_slice(), dataset.weights_slice(), &mut losses);
+        losses
+            .into_iter()
+            .map(|e| e.to_f64().filter(|e| !e.is_nan()).unwrap_or(f64::INFINITY))
+            .collect()
+    }
+
+    fn set_loss(&mut self, loss: T, options: &Options<T, D>, baseline_loss: Option<T>) -> bool {
         if !loss.is_finite() {
             self.loss = T::infinity();
             self.cost = T::infinity();
@@ -130,8 +371,31 @@ where
             self.complexity,
             options.parsimony,
             options.use_baseline,
-            dataset.baseline_loss,
+            baseline_loss,
         );
         true
     }
 }
+
+/// `options.dimensional_constraint_penalty` if the dataset has units and `expr` violates them, zero
+/// otherwise. Added to the training loss and to the objective the constant optimizer minimizes.
+/// `dataset` is the one without parameter rows: features past its `n_features` are parameters.
+pub(crate) fn dimensional_penalty<T: Float, Ops: dynamic_expressions::OperatorSet, const D: usize>(
+    expr: &PostfixExpr<T, Ops, D>,
+    dataset: &Dataset<T>,
+    options: &Options<T, D>,
+) -> T {
+    if dataset.has_units()
+        && violates_parametric_dimensional_constraints(
+            expr,
+            dataset.x_units.as_deref(),
+            dataset.n_features,
+            dataset.y_units.as_ref(),
+            options.dimensionless_constants_only,
+        )
+    {
+        T::from(options.dimensional_constraint_penalty).unwrap()
+    } else {
+        T::zero()
+    }
+}
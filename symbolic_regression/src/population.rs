@@ -1,5 +1,6 @@
 use num_traits::Float;
 
+use crate::multi_objective::{Nsga2Ranking, SelectionObjective};
 use crate::pop_member::PopMember;
 
 pub struct Population<T: Float, Ops, const D: usize> {
@@ -63,6 +64,30 @@ impl<T: Float, Ops, const D: usize> Population<T, Ops, D> {
         self.members[idx] = child;
     }
 
+    /// Ranks `child` among the members (see [`Nsga2Ranking`]) and drops the worst, the oldest
+    /// among ties. `ranking` must rank the current members on `objectives` and is kept up to date.
+    /// Returns whether `child` was kept.
+    pub(crate) fn replace_worst(
+        &mut self,
+        child: PopMember<T, Ops, D>,
+        objectives: &[SelectionObjective],
+        ranking: &mut Nsga2Ranking,
+    ) -> bool {
+        debug_assert_eq!(ranking.len(), self.members.len(), "ranking is out of date");
+        ranking.push(&child, objectives);
+        self.members.push(child);
+        let worst = (0..self.members.len())
+            .max_by(|&i, &j| {
+                ranking
+                    .compare(i, j)
+                    .then_with(|| self.members[j].birth.cmp(&self.members[i].birth))
+            })
+            .expect("population is non-empty");
+        self.members.swap_remove(worst);
+        ranking.swap_remove(worst);
+        worst != self.members.len()
+    }
+
     pub fn replace_two_oldest(&mut self, a: PopMember<T, Ops, D>, b: PopMember<T, Ops, D>) {
         let (i1, i2) = self.two_oldest_indices();
         self.members[i1] = a;
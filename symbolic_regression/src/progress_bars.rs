@@ -5,10 +5,12 @@ mod imp {
     use std::time::{Duration, Instant};
 
     use dynamic_expressions::OperatorSet;
+    use dynamic_expressions::strings::StringTreeOptions;
     use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
     use num_traits::Float;
 
     use crate::hall_of_fame::HallOfFame;
+    use crate::hall_of_fame_io::string_expr;
     use crate::options::{Options, OutputStyle};
 
     struct ProgressTracking {
@@ -113,6 +115,19 @@ mod imp {
                 self.bar.finish();
             }
         }
+
+        pub(crate) fn print_final_hall_of_fame<T, Ops, const D: usize>(&self, hall: &HallOfFame<T, Ops, D>)
+        where
+            T: Float + num_traits::ToPrimitive + Display,
+            Ops: OperatorSet,
+        {
+            let term_width = {
+                let (_, w) = console::Term::stderr().size();
+                (w as usize).max(80)
+            };
+            eprintln!("Final Hall of Fame:");
+            eprintln!("{}", format_hall_of_fame(hall, term_width, usize::MAX, self.render));
+        }
     }
 
     struct ProgressMsgCtx<'a, T: Float, Ops, const D: usize> {
@@ -207,7 +222,7 @@ mod imp {
     {
         let terminal_width = terminal_width.max(80);
         let raw_border = "─".repeat(terminal_width.saturating_sub(1));
-        let raw_header = format!("{:<10}  {:<10}  {}", "Complexity", "Loss", "Equation");
+        let raw_header = format!("{:<10}  {:<10}  {:<10}  {}", "Complexity", "Loss", "Score", "Equation");
 
         let border = raw_border;
         let header = if render.ansi {
@@ -215,6 +230,7 @@ mod imp {
 
             let complexity_plain = "Complexity";
             let loss_plain = "Loss";
+            let score_plain = "Score";
             let equation_plain = "Equation";
 
             let complexity = format!(
@@ -227,9 +243,14 @@ mod imp {
                 s.apply_to(loss_plain),
                 " ".repeat(10_usize.saturating_sub(loss_plain.len()))
             );
+            let score = format!(
+                "{}{}",
+                s.apply_to(score_plain),
+                " ".repeat(10_usize.saturating_sub(score_plain.len()))
+            );
             let equation = s.apply_to(equation_plain).to_string();
 
-            format!("{complexity}  {loss}  {equation}")
+            format!("{complexity}  {loss}  {score}  {equation}")
         } else {
             raw_header
         };
@@ -240,12 +261,13 @@ mod imp {
         out.push_str(&truncate_to_width(&header, terminal_width, render));
         out.push('\n');
 
-        for m in hall.pareto_front().into_iter().take(max_entries) {
+        for (m, score) in hall.scored_pareto_front().into_iter().take(max_entries) {
             let loss = m.loss.to_f64().unwrap_or(f64::INFINITY);
-            let stats = format!("{:<10}  {:<10.3e}  ", m.complexity, loss);
+            let score = score.to_f64().unwrap_or(0.0);
+            let stats = format!("{:<10}  {:<10.3e}  {:<10.3e}  ", m.complexity, loss, score);
             let left_cols_width = stats.chars().count();
 
-            let eqn_plain = m.expr.to_string();
+            let eqn_plain = string_expr(&m.expr, StringTreeOptions::default());
             let eqn_lines = wrap_equation(&eqn_plain, terminal_width, left_cols_width);
 
             if let Some((first, rest)) = eqn_lines.split_first() {
@@ -316,12 +338,13 @@ mod imp {
         fn header_equation_column_aligns_with_rows() {
             let render = RenderOptions { ansi: true };
 
-            let raw_header = format!("{:<10}  {:<10}  {}", "Complexity", "Loss", "Equation");
+            let raw_header = format!("{:<10}  {:<10}  {:<10}  {}", "Complexity", "Loss", "Score", "Equation");
             let header = if render.ansi {
                 let s = console::Style::new().bold().underlined();
 
                 let complexity_plain = "Complexity";
                 let loss_plain = "Loss";
+                let score_plain = "Score";
                 let equation_plain = "Equation";
 
                 let complexity = format!(
@@ -334,8 +357,13 @@ mod imp {
                     s.apply_to(loss_plain),
                     " ".repeat(10_usize.saturating_sub(loss_plain.len()))
                 );
+                let score = format!(
+                    "{}{}",
+                    s.apply_to(score_plain),
+                    " ".repeat(10_usize.saturating_sub(score_plain.len()))
+                );
                 let equation = s.apply_to(equation_plain).to_string();
-                format!("{complexity}  {loss}  {equation}")
+                format!("{complexity}  {loss}  {score}  {equation}")
             } else {
                 raw_header
             };
@@ -343,7 +371,7 @@ mod imp {
             let header_plain = console::strip_ansi_codes(&header);
             let eq_header_start = header_plain.find("Equation").expect("header should contain Equation");
 
-            let stats = format!("{:<10}  {:<10.3e}  ", 5_u32, 1.234_f64);
+            let stats = format!("{:<10}  {:<10.3e}  {:<10.3e}  ", 5_u32, 1.234_f64, 0.5_f64);
             let row = format!("{stats}x0");
             let eq_row_start = row.find("x0").expect("row should contain equation");
 
@@ -357,9 +385,14 @@ mod imp {
 
 #[cfg(not(feature = "progress"))]
 mod imp {
+    use std::fmt::Display;
+
+    use dynamic_expressions::OperatorSet;
+    use dynamic_expressions::strings::StringTreeOptions;
     use num_traits::Float;
 
     use crate::hall_of_fame::HallOfFame;
+    use crate::hall_of_fame_io::string_expr;
     use crate::options::Options;
 
     pub(crate) struct SearchProgress;
@@ -380,6 +413,21 @@ mod imp {
         }
 
         pub(crate) fn finish(&self) {}
+
+        pub(crate) fn print_final_hall_of_fame<T, Ops, const D: usize>(&self, hall: &HallOfFame<T, Ops, D>)
+        where
+            T: Float + num_traits::ToPrimitive + Display,
+            Ops: OperatorSet,
+        {
+            eprintln!("Final Hall of Fame:");
+            eprintln!("{:<10}  {:<10}  {:<10}  Equation", "Complexity", "Loss", "Score");
+            for (m, score) in hall.scored_pareto_front() {
+                let loss = m.loss.to_f64().unwrap_or(f64::INFINITY);
+                let score = score.to_f64().unwrap_or(0.0);
+                let equation = string_expr(&m.expr, StringTreeOptions::default());
+                eprintln!("{:<10}  {:<10.3e}  {:<10.3e}  {equation}", m.complexity, loss, score);
+            }
+        }
     }
 }
 
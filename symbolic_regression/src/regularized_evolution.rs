@@ -3,13 +3,17 @@ use std::ops::AddAssign;
 use fastrand::Rng;
 use num_traits::{Float, FromPrimitive, ToPrimitive};
 
+use crate::adaptive_mutation::MutationStats;
 use crate::adaptive_parsimony::RunningSearchStatistics;
 use crate::dataset::TaggedDataset;
+use crate::genealogy::Genealogy;
+use crate::lexicase::{CaseErrors, lexicase_select};
+use crate::multi_objective::{Nsga2Ranking, SelectionMode};
 use crate::mutate::{self, CrossoverCtx, NextGenerationCtx};
 use crate::options::Options;
-use crate::pop_member::Evaluator;
+use crate::pop_member::{Evaluator, PopMember};
 use crate::population::Population;
-use crate::selection::best_of_sample;
+use crate::selection::{best_of_ranked_sample, best_of_sample};
 
 pub struct RegEvolCtx<'a, T: Float + AddAssign, Ops, const D: usize> {
     pub rng: &'a mut Rng,
@@ -21,20 +25,34 @@ pub struct RegEvolCtx<'a, T: Float + AddAssign, Ops, const D: usize> {
     pub evaluator: &'a mut Evaluator<T, D>,
     pub next_id: &'a mut u64,
     pub next_birth: &'a mut u64,
+    pub mutation_stats: &'a mut MutationStats,
+    pub genealogy: Option<&'a mut Genealogy>,
+    /// Residual cache for lexicase selection, valid for `dataset`.
+    pub case_errors: &'a mut CaseErrors,
+    /// Donors for hall-of-fame crossover: the search's hall of fame as of dispatch.
+    pub hall_of_fame: &'a [PopMember<T, Ops, D>],
     pub _ops: core::marker::PhantomData<Ops>,
 }
 
-pub fn reg_evol_cycle<T, Ops, const D: usize>(pop: &mut Population<T, Ops, D>, ctx: RegEvolCtx<'_, T, Ops, D>) -> f64
+pub fn reg_evol_cycle<T, Ops, const D: usize>(
+    pop: &mut Population<T, Ops, D>,
+    mut ctx: RegEvolCtx<'_, T, Ops, D>,
+) -> f64
 where
     T: Float + FromPrimitive + ToPrimitive + AddAssign,
     Ops: dynamic_expressions::OperatorSet<T = T>,
 {
     let mut num_evals = 0.0;
     let n_evol_cycles = ((pop.len() as f64) / (ctx.options.tournament_selection_n as f64)).ceil() as usize;
+    // NSGA-II ranks the population once per cycle and updates the ranking on each replacement.
+    let mut ranking = match &ctx.options.selection {
+        SelectionMode::Nsga2 { objectives } => Some(Nsga2Ranking::of_members(&pop.members, objectives)),
+        SelectionMode::Cost | SelectionMode::Lexicase | SelectionMode::EpsilonLexicase => None,
+    };
 
     for _ in 0..n_evol_cycles {
         if ctx.rng.f64() > ctx.options.crossover_probability {
-            let allstar = best_of_sample(ctx.rng, pop, ctx.stats, ctx.options);
+            let allstar = select_parent(pop, ranking.as_ref(), &mut ctx, &mut num_evals);
             let (baby, accepted, tmp) = mutate::next_generation(
                 &allstar,
                 NextGenerationCtx {
@@ -47,6 +65,8 @@ where
                     evaluator: ctx.evaluator,
                     next_id: ctx.next_id,
                     next_birth: ctx.next_birth,
+                    mutation_stats: ctx.mutation_stats,
+                    genealogy: ctx.genealogy.as_deref_mut(),
                     _ops: core::marker::PhantomData,
                 },
             );
@@ -54,10 +74,15 @@ where
             if !accepted && ctx.options.skip_mutation_failures {
                 continue;
             }
-            pop.replace_oldest(baby);
+            match (&ctx.options.selection, ranking.as_mut()) {
+                (SelectionMode::Nsga2 { objectives }, Some(ranking)) => {
+                    pop.replace_worst(baby, objectives, ranking);
+                }
+                _ => pop.replace_oldest(baby),
+            }
         } else {
-            let allstar1 = best_of_sample(ctx.rng, pop, ctx.stats, ctx.options);
-            let allstar2 = best_of_sample(ctx.rng, pop, ctx.stats, ctx.options);
+            let allstar1 = select_parent(pop, ranking.as_ref(), &mut ctx, &mut num_evals);
+            let allstar2 = select_parent(pop, ranking.as_ref(), &mut ctx, &mut num_evals);
             let (baby1, baby2, accepted, tmp) = mutate::crossover_generation(
                 &allstar1,
                 &allstar2,
@@ -69,6 +94,8 @@ where
                     evaluator: ctx.evaluator,
                     next_id: ctx.next_id,
                     next_birth: ctx.next_birth,
+                    genealogy: ctx.genealogy.as_deref_mut(),
+                    hall_of_fame: ctx.hall_of_fame,
                     _ops: core::marker::PhantomData,
                 },
             );
@@ -76,9 +103,45 @@ where
             if !accepted && ctx.options.skip_mutation_failures {
                 continue;
             }
-            pop.replace_two_oldest(baby1, baby2);
+            match (&ctx.options.selection, ranking.as_mut()) {
+                (SelectionMode::Nsga2 { objectives }, Some(ranking)) => {
+                    pop.replace_worst(baby1, objectives, ranking);
+                    pop.replace_worst(baby2, objectives, ranking);
+                }
+                _ => pop.replace_two_oldest(baby1, baby2),
+            }
         }
     }
 
     num_evals
 }
+
+/// A tournament winner (under the cycle's `ranking` with NSGA-II selection), or with lexicase
+/// selection the winner on the rows of `ctx.dataset`; the evaluations spent filling the residual
+/// cache are added to `num_evals`.
+fn select_parent<T, Ops, const D: usize>(
+    pop: &Population<T, Ops, D>,
+    ranking: Option<&Nsga2Ranking>,
+    ctx: &mut RegEvolCtx<'_, T, Ops, D>,
+    num_evals: &mut f64,
+) -> PopMember<T, Ops, D>
+where
+    T: Float + FromPrimitive + ToPrimitive + AddAssign,
+    Ops: dynamic_expressions::OperatorSet<T = T>,
+{
+    let epsilon = match ctx.options.selection {
+        SelectionMode::Lexicase => false,
+        SelectionMode::EpsilonLexicase => true,
+        SelectionMode::Cost | SelectionMode::Nsga2 { .. } => {
+            return match ranking {
+                Some(ranking) => best_of_ranked_sample(ctx.rng, pop, ranking, ctx.options),
+                None => best_of_sample(ctx.rng, pop, ctx.stats, ctx.options),
+            };
+        }
+    };
+    *num_evals += ctx
+        .case_errors
+        .update(pop, ctx.dataset.data, ctx.options.loss.as_ref(), ctx.evaluator);
+    let idx = lexicase_select(ctx.rng, &ctx.case_errors.of(pop), epsilon);
+    pop.members[idx].clone()
+}
@@ -5,11 +5,17 @@ use fastrand::Rng;
 use num_traits::Float;
 use progress_bars::SearchProgress;
 
+use crate::adaptive_mutation::MutationStats;
 use crate::adaptive_parsimony::RunningSearchStatistics;
-use crate::check_constraints::check_constraints;
-use crate::dataset::{Dataset, TaggedDataset};
-use crate::hall_of_fame::HallOfFame;
-use crate::loss_functions::baseline_loss_from_zero_expression;
+use crate::dataset::{Dataset, MultiOutputDataset, TaggedDataset};
+use crate::distributed::{self, Connection, DistributedError, WorkerListener};
+use crate::feature_selection::{preselected_features, restore_feature_indices};
+use crate::genealogy::Genealogy;
+use crate::hall_of_fame::{HallOfFame, ModelSelection};
+use crate::interrupt::StopSignal;
+use crate::linear_scaling::LinearScaling;
+use crate::loss_functions::{baseline_loss, check_whole_samples};
+use crate::multi_objective::SelectionMode;
 use crate::options::Options;
 use crate::pop_member::{Evaluator, MemberId, PopMember};
 use crate::population::Population;
@@ -19,6 +25,59 @@ use crate::{migration, progress_bars, single_iteration, warmup};
 pub struct SearchResult<T: Float + AddAssign, Ops, const D: usize> {
     pub hall_of_fame: HallOfFame<T, Ops, D>,
     pub best: PopMember<T, Ops, D>,
+    /// Each population's mutation record, in population order (see [`MutationStats`]).
+    pub mutation_stats: Vec<MutationStats>,
+    /// Every birth during the search, when `Options::use_recorder` is set.
+    pub genealogy: Option<Genealogy>,
+}
+
+impl<T: Float + AddAssign, Ops, const D: usize> SearchResult<T, Ops, D> {
+    /// See [`HallOfFame::scored_pareto_front`].
+    pub fn scored_pareto_front(&self) -> Vec<(PopMember<T, Ops, D>, T)> {
+        self.hall_of_fame.scored_pareto_front()
+    }
+
+    /// See [`HallOfFame::select_model`].
+    pub fn select_model(&self, selection: ModelSelection) -> Option<PopMember<T, Ops, D>> {
+        self.hall_of_fame.select_model(selection)
+    }
+
+    /// See [`Genealogy::ancestry`]; `None` unless the search ran with `Options::use_recorder`.
+    pub fn ancestry(&self, member: &PopMember<T, Ops, D>) -> Option<Genealogy> {
+        self.genealogy.as_ref().map(|g| g.ancestry(member.id))
+    }
+}
+
+impl<T: Float + AddAssign, Ops, const D: usize> SearchResult<T, Ops, D>
+where
+    Ops: dynamic_expressions::OperatorSet<T = T>,
+{
+    /// Folds the linear scaling of every member into its expression, so results evaluate to the
+    /// scaled outputs their losses were computed on. Complexities and costs are recomputed for the
+    /// folded expressions, and hall-of-fame members move to the slots of their new complexities.
+    fn with_folded_scaling(mut self, n_features: usize, options: &Options<T, D>, baseline_loss: Option<T>) -> Self {
+        self.hall_of_fame
+            .fold_linear_scaling(n_features, options, baseline_loss);
+        if self.best.fold_linear_scaling(n_features, options) {
+            self.best.rescore(options, baseline_loss);
+        }
+        self
+    }
+}
+
+/// One search result (and hall of fame) per output of a multi-output dataset, in output order.
+pub struct MultiOutputSearchResult<T: Float + AddAssign, Ops, const D: usize> {
+    pub outputs: Vec<SearchResult<T, Ops, D>>,
+}
+
+impl<T: Float + AddAssign, Ops, const D: usize> MultiOutputSearchResult<T, Ops, D> {
+    pub fn n_outputs(&self) -> usize {
+        self.outputs.len()
+    }
+
+    pub fn pareto_fronts(&self) -> Vec<Vec<PopMember<T, Ops, D>>> {
+        self.outputs.iter().map(|r| r.hall_of_fame.pareto_front()).collect()
+    }
 }
 
 struct SearchCounters {
@@ -44,13 +103,13 @@ impl SearchCounters {
     }
 }
 
-struct SearchTaskResult<T: Float + AddAssign, Ops, const D: usize> {
-    pop_idx: usize,
-    curmaxsize: usize,
-    evals: u64,
-    best_seen: HallOfFame<T, Ops, D>,
-    best_sub_pop: Vec<PopMember<T, Ops, D>>,
-    pop_state: PopState<T, Ops, D>,
+pub(crate) struct SearchTaskResult<T: Float + AddAssign, Ops, const D: usize> {
+    pub(crate) pop_idx: usize,
+    pub(crate) curmaxsize: usize,
+    pub(crate) evals: u64,
+    pub(crate) best_seen: HallOfFame<T, Ops, D>,
+    pub(crate) best_sub_pop: Vec<PopMember<T, Ops, D>>,
+    pub(crate) pop_state: PopState<T, Ops, D>,
 }
 
 pub(crate) struct PopState<T: Float + AddAssign, Ops, const D: usize> {
@@ -61,6 +120,9 @@ pub(crate) struct PopState<T: Float + AddAssign, Ops, const D: usize> {
     pub(crate) batch_dataset: Option<Dataset<T>>,
     pub(crate) next_id: u64,
     pub(crate) next_birth: u64,
+    pub(crate) mutation_stats: MutationStats,
+    /// Births since the coordinator last applied this population's result.
+    pub(crate) genealogy: Option<Genealogy>,
 }
 
 impl<T: Float + AddAssign, Ops, const D: usize> PopState<T, Ops, D> {
@@ -70,6 +132,7 @@ impl<T: Float + AddAssign, Ops, const D: usize> PopState<T, Ops, D> {
         options: &'a Options<T, D>,
         curmaxsize: usize,
         stats: &'a RunningSearchStatistics,
+        hall_of_fame: &'a [PopMember<T, Ops, D>],
         f: F,
     ) -> Ret
     where
@@ -84,7 +147,8 @@ impl<T: Float + AddAssign, Ops, const D: usize> PopState<T, Ops, D> {
             if full_data.n_rows == 0 {
                 panic!("Cannot batch from an empty dataset (n_rows = 0).");
             }
-            let bs = options.batch_size.max(1);
+            let rows_per_sample = options.loss.rows_per_sample();
+            let bs = options.batch_size.max(1).next_multiple_of(rows_per_sample);
             let needs_new = match self.batch_dataset.as_ref() {
                 None => true,
                 Some(b) => b.n_rows != bs || b.n_features != full_data.n_features,
@@ -93,7 +157,7 @@ impl<T: Float + AddAssign, Ops, const D: usize> PopState<T, Ops, D> {
                 self.batch_dataset = Some(Dataset::make_batch_buffer(full_data, bs));
             }
             let batch = self.batch_dataset.as_mut().expect("set above");
-            batch.resample_from(full_data, &mut self.rng);
+            batch.resample_groups_from(full_data, rows_per_sample, &mut self.rng);
             TaggedDataset {
                 data: batch,
                 baseline_loss: full_dataset.baseline_loss,
@@ -112,6 +176,9 @@ impl<T: Float + AddAssign, Ops, const D: usize> PopState<T, Ops, D> {
             grad_ctx: &mut self.grad_ctx,
             next_id: &mut self.next_id,
             next_birth: &mut self.next_birth,
+            mutation_stats: &mut self.mutation_stats,
+            genealogy: self.genealogy.as_mut(),
+            hall_of_fame,
             _ops: core::marker::PhantomData,
         };
 
@@ -124,18 +191,30 @@ struct PopPools<T: Float + AddAssign, Ops, const D: usize> {
     best_sub_pops: Vec<Vec<PopMember<T, Ops, D>>>,
     best: PopMember<T, Ops, D>,
     total_evals: u64,
+    genealogy: Option<Genealogy>,
+}
+
+impl<T: Float + AddAssign, Ops, const D: usize> PopPools<T, Ops, D> {
+    fn mutation_stats(&self) -> Vec<MutationStats> {
+        self.pops
+            .iter()
+            .map(|st| st.as_ref().map(|st| st.mutation_stats.clone()).unwrap_or_default())
+            .collect()
+    }
 }
 
 struct EquationSearchState<'a, T: Float + AddAssign, Ops, const D: usize> {
     full_dataset: TaggedDataset<'a, T>,
     options: &'a Options<T, D>,
-    n_workers: usize,
     counters: SearchCounters,
     stats: RunningSearchStatistics,
     hall: HallOfFame<T, Ops, D>,
     progress: SearchProgress,
     pools: PopPools<T, Ops, D>,
     order_rng: Rng,
+    stop: &'a StopSignal,
+    validation: Option<&'a Dataset<T>>,
+    validation_evaluator: Evaluator<T, D>,
 }
 
 pub fn equation_search<T, Ops, const D: usize>(dataset: &Dataset<T>, options: &Options<T, D>) -> SearchResult<T, Ops, D>
@@ -154,29 +233,115 @@ where
     T: Float + AddAssign + num_traits::FromPrimitive + num_traits::ToPrimitive + Display + Send + Sync,
     Ops: dynamic_expressions::OperatorSet<T = T> + Send + Sync,
 {
-    let baseline_loss = if options.use_baseline {
-        baseline_loss_from_zero_expression::<T, Ops, D>(dataset, options.loss.as_ref())
-    } else {
-        None
-    };
-    let full_dataset = TaggedDataset::new(dataset, baseline_loss);
+    let stop = StopSignal::new(options);
+    search_with_stop_signal(dataset, None, options, &stop)
+}
+
+/// Like [`equation_search`], additionally tracking each hall-of-fame member's loss on
+/// `validation` (see `HallOfFame::pareto_front_by` and `Options::select_best_by_validation`).
+pub fn equation_search_with_validation<T, Ops, const D: usize>(
+    dataset: &Dataset<T>,
+    validation: &Dataset<T>,
+    options: &Options<T, D>,
+) -> SearchResult<T, Ops, D>
+where
+    T: Float + AddAssign + num_traits::FromPrimitive + num_traits::ToPrimitive + Display + Send + Sync,
+    Ops: dynamic_expressions::OperatorSet<T = T> + Send + Sync,
+{
+    assert_eq!(
+        validation.n_features, dataset.n_features,
+        "validation dataset must have the same features as the training dataset"
+    );
+    let stop = StopSignal::new(options);
+    search_with_stop_signal(dataset, Some(validation), options, &stop)
+}
 
-    let counters = SearchCounters {
-        total_cycles: options.niterations * options.populations,
-        cycles_started: 0,
-        cycles_completed: 0,
+/// Searches every output of `dataset` concurrently: one population set and hall of fame per
+/// output (see [`MultiOutputDataset::outputs`]), sharing the operator set, options and the Rayon
+/// pool.
+///
+/// Population tasks of all outputs are interleaved on the pool, and only the first output reports
+/// progress. A stop request (see `Options::handle_interrupts`) ends all outputs early.
+pub fn equation_search_multi_output<T, Ops, const D: usize>(
+    dataset: &MultiOutputDataset<T>,
+    options: &Options<T, D>,
+) -> MultiOutputSearchResult<T, Ops, D>
+where
+    T: Float + AddAssign + num_traits::FromPrimitive + num_traits::ToPrimitive + Display + Send + Sync,
+    Ops: dynamic_expressions::OperatorSet<T = T> + Send + Sync,
+{
+    let datasets = dataset.outputs();
+    assert!(!datasets.is_empty(), "multi-output search requires at least one output");
+    let selections: Vec<_> = datasets.iter().map(|d| preselected_features(d, options)).collect();
+    let reduced: Vec<_> = datasets
+        .iter()
+        .zip(&selections)
+        .map(|(d, selected)| selected.as_ref().map(|s| d.select_features(s)))
+        .collect();
+
+    let quiet = Options {
+        progress: false,
+        ..options.clone()
     };
+    let stop = StopSignal::new(options);
+    let n_workers = usable_threads().min(options.populations * datasets.len()).max(1);
+    let mut states: Vec<_> = datasets
+        .iter()
+        .zip(&reduced)
+        .enumerate()
+        .map(|(k, (d, r))| {
+            let output_options = if k == 0 { options } else { &quiet };
+            EquationSearchState::new(r.as_ref().unwrap_or(d), None, output_options, &stop)
+        })
+        .collect();
+    rayon::scope(|scope| {
+        run_scoped_search(scope, &mut states, n_workers);
+    });
 
-    let stats = RunningSearchStatistics::new(options.maxsize, 100_000);
-    let mut hall = HallOfFame::new(options.maxsize);
+    let outputs = states
+        .into_iter()
+        .zip(&selections)
+        .zip(&datasets)
+        .map(|((state, selected), d)| {
+            let mut result = state.finish();
+            if let Some(selected) = selected {
+                restore_result_features(&mut result, selected, d, options);
+            }
+            result
+        })
+        .collect();
+    MultiOutputSearchResult { outputs }
+}
 
-    let mut progress = SearchProgress::new(options, counters.total_cycles);
+pub(crate) fn search_with_stop_signal<T, Ops, const D: usize>(
+    dataset: &Dataset<T>,
+    validation: Option<&Dataset<T>>,
+    options: &Options<T, D>,
+    stop: &StopSignal,
+) -> SearchResult<T, Ops, D>
+where
+    T: Float + AddAssign + num_traits::FromPrimitive + num_traits::ToPrimitive + Display + Send + Sync,
+    Ops: dynamic_expressions::OperatorSet<T = T> + Send + Sync,
+{
+    if let Some(selected) = preselected_features(dataset, options) {
+        let reduced = dataset.select_features(&selected);
+        let reduced_validation = validation.map(|v| v.select_features(&selected));
+        let mut result = search_with_stop_signal(&reduced, reduced_validation.as_ref(), options, stop);
+        restore_result_features(&mut result, &selected, dataset, options);
+        return result;
+    }
 
-    let pools = init_populations(full_dataset, options, &mut hall);
-    progress.set_initial_evals(pools.total_evals);
+    let n_workers = usable_threads().min(options.populations).max(1);
 
-    let order_rng = Rng::with_seed(options.seed ^ 0x9e37_79b9_7f4a_7c15);
+    let mut state = EquationSearchState::new(dataset, validation, options, stop);
+    rayon::scope(|scope| {
+        run_scoped_search(scope, std::slice::from_mut(&mut state), n_workers);
+    });
+    state.finish()
+}
 
+/// The number of Rayon threads available to run population tasks.
+fn usable_threads() -> usize {
     let pool_threads = rayon::current_num_threads();
     // If we're already running inside Rayon, reserve the current worker thread for orchestration.
     // (Blocking it on `result_rx.recv()` would otherwise reduce the pool capacity by one.)
@@ -191,34 +356,184 @@ where
         usable_threads > 0,
         "equation_search_parallel requires at least 2 Rayon threads when called from inside the Rayon pool"
     );
+    usable_threads
+}
 
-    let n_workers = usable_threads.min(pools.pops.len()).max(1);
+/// Like [`equation_search`], running each population task in a worker process (see
+/// [`crate::run_worker`]) instead of on the Rayon pool.
+///
+/// Waits for `n_workers` workers to connect to `listener` before starting, and shuts them down when
+/// the search ends. Migration, the hall of fame and progress reporting stay in this process.
+pub fn equation_search_distributed<T, Ops, const D: usize>(
+    dataset: &Dataset<T>,
+    options: &Options<T, D>,
+    listener: &WorkerListener,
+    n_workers: usize,
+) -> Result<SearchResult<T, Ops, D>, DistributedError>
+where
+    T: Float + AddAssign + num_traits::FromPrimitive + num_traits::ToPrimitive + Display + Send + Sync,
+    Ops: dynamic_expressions::OperatorSet<T = T> + Send + Sync,
+{
+    assert!(n_workers > 0, "distributed search requires at least one worker");
+    if let Some(selected) = preselected_features(dataset, options) {
+        let reduced = dataset.select_features(&selected);
+        let mut result = equation_search_distributed(&reduced, options, listener, n_workers)?;
+        restore_result_features(&mut result, &selected, dataset, options);
+        return Ok(result);
+    }
 
-    let mut state = EquationSearchState {
-        full_dataset,
-        options,
-        n_workers,
-        counters,
-        stats,
-        hall,
-        progress,
-        pools,
-        order_rng,
-    };
+    let mut connections = Vec::with_capacity(n_workers);
+    for _ in 0..n_workers {
+        let mut conn = listener.accept()?;
+        distributed::expect_hello(&mut conn, dataset)?;
+        connections.push(conn);
+    }
 
-    rayon::scope(|scope| {
-        run_scoped_search(scope, &mut state);
-    });
-    state.progress.finish();
+    let stop = StopSignal::new(options);
+    let mut state = EquationSearchState::new(dataset, None, options, &stop);
+    run_distributed_search(&mut state, connections)?;
+    Ok(state.finish())
+}
 
-    SearchResult {
-        hall_of_fame: state.hall,
-        best: state.pools.best,
+fn restore_result_features<T, Ops, const D: usize>(
+    result: &mut SearchResult<T, Ops, D>,
+    selected: &[usize],
+    dataset: &Dataset<T>,
+    options: &Options<T, D>,
+) where
+    T: Float + AddAssign,
+    Ops: dynamic_expressions::OperatorSet<T = T>,
+{
+    let n_expr_features = options.n_expr_features(dataset);
+    for m in result.hall_of_fame.best_by_complexity.iter_mut().flatten() {
+        restore_feature_indices(m, selected, dataset.n_features, n_expr_features);
     }
+    restore_feature_indices(&mut result.best, selected, dataset.n_features, n_expr_features);
+}
+
+impl<'a, T, Ops, const D: usize> EquationSearchState<'a, T, Ops, D>
+where
+    T: Float + AddAssign + num_traits::FromPrimitive + num_traits::ToPrimitive + Display,
+    Ops: dynamic_expressions::OperatorSet<T = T>,
+{
+    fn new(
+        dataset: &'a Dataset<T>,
+        validation: Option<&'a Dataset<T>>,
+        options: &'a Options<T, D>,
+        stop: &'a StopSignal,
+    ) -> Self {
+        check_whole_samples(dataset, options, "training");
+        if let Some(val) = validation {
+            check_whole_samples(val, options, "validation");
+        }
+        let baseline_loss = if options.use_baseline {
+            baseline_loss::<T, Ops, D>(dataset, options)
+        } else {
+            None
+        };
+        let full_dataset = TaggedDataset::new(dataset, baseline_loss);
+
+        let counters = SearchCounters {
+            total_cycles: options.niterations * options.populations,
+            cycles_started: 0,
+            cycles_completed: 0,
+        };
+
+        let stats = RunningSearchStatistics::new(options.maxsize, 100_000);
+        let mut hall = HallOfFame::new(options.maxsize);
+
+        let mut progress = SearchProgress::new(options, counters.total_cycles);
+
+        let pools = init_populations(full_dataset, options, &mut hall);
+        progress.set_initial_evals(pools.total_evals);
+
+        let mut validation_evaluator = Evaluator::new(validation.map_or(0, |v| v.n_rows));
+        if let Some(val) = validation {
+            hall.update_validation_losses(val, options, &mut validation_evaluator);
+        }
+
+        let order_rng = Rng::with_seed(options.seed ^ 0x9e37_79b9_7f4a_7c15);
+
+        Self {
+            full_dataset,
+            options,
+            counters,
+            stats,
+            hall,
+            progress,
+            pools,
+            order_rng,
+            stop,
+            validation,
+            validation_evaluator,
+        }
+    }
+
+    fn finish(self) -> SearchResult<T, Ops, D> {
+        self.progress.finish();
+        if self.stop.is_set() {
+            self.progress.print_final_hall_of_fame(&self.hall);
+        }
+
+        let best = select_best(&self.hall, &self.pools.best, self.options, self.validation.is_some()).clone();
+        let n_features = self.options.n_expr_features(self.full_dataset.data);
+        SearchResult {
+            mutation_stats: self.pools.mutation_stats(),
+            genealogy: self.pools.genealogy,
+            hall_of_fame: self.hall,
+            best,
+        }
+        .with_folded_scaling(n_features, self.options, self.full_dataset.baseline_loss)
+    }
+
+    /// Reserves the next cycle: its size limit and snapshots of the running statistics and of the
+    /// hall-of-fame crossover donors.
+    fn dispatch_params(&mut self) -> (usize, RunningSearchStatistics, Vec<PopMember<T, Ops, D>>) {
+        let cycles_remaining_start = self.counters.cycles_remaining_start_for_next_dispatch();
+        let curmaxsize = warmup::get_cur_maxsize(self.options, self.counters.total_cycles, cycles_remaining_start);
+        let mut stats_snapshot = self.stats.clone();
+        stats_snapshot.normalize();
+        (curmaxsize, stats_snapshot, crossover_donors(&self.hall, self.options))
+    }
+
+    fn apply(&mut self, res: SearchTaskResult<T, Ops, D>) {
+        apply_task_result(
+            self.options,
+            self.full_dataset.baseline_loss,
+            &mut self.counters,
+            &mut self.stats,
+            &mut self.hall,
+            &mut self.progress,
+            &mut self.pools,
+            res,
+        );
+        if let Some(val) = self.validation {
+            self.hall
+                .update_validation_losses(val, self.options, &mut self.validation_evaluator);
+        }
+    }
+}
+
+/// The final `best`: the lowest-validation-loss hall member when `select_best_by_validation` is set
+/// and a validation dataset exists, otherwise the lowest-training-loss member seen.
+fn select_best<'a, T: Float + AddAssign, Ops, const D: usize>(
+    hall: &'a HallOfFame<T, Ops, D>,
+    best: &'a PopMember<T, Ops, D>,
+    options: &Options<T, D>,
+    has_validation: bool,
+) -> &'a PopMember<T, Ops, D> {
+    if options.select_best_by_validation && has_validation {
+        if let Some(m) = hall.best_by_validation() {
+            return m;
+        }
+    }
+    best
 }
 
 pub struct SearchEngine<T: Float + AddAssign, Ops, const D: usize> {
     dataset: Dataset<T>,
+    validation: Option<Dataset<T>>,
+    validation_evaluator: Evaluator<T, D>,
     baseline_loss: Option<T>,
     options: Options<T, D>,
     counters: SearchCounters,
@@ -239,8 +554,22 @@ where
     Ops: dynamic_expressions::OperatorSet<T = T>,
 {
     pub fn new(dataset: Dataset<T>, options: Options<T, D>) -> Self {
+        Self::with_validation(dataset, None, options)
+    }
+
+    /// Like [`SearchEngine::new`], additionally tracking each hall-of-fame member's loss on
+    /// `validation`.
+    pub fn with_validation(dataset: Dataset<T>, validation: Option<Dataset<T>>, options: Options<T, D>) -> Self {
+        check_whole_samples(&dataset, &options, "training");
+        if let Some(val) = &validation {
+            assert_eq!(
+                val.n_features, dataset.n_features,
+                "validation dataset must have the same features as the training dataset"
+            );
+            check_whole_samples(val, &options, "validation");
+        }
         let baseline_loss = if options.use_baseline {
-            baseline_loss_from_zero_expression::<T, Ops, D>(&dataset, options.loss.as_ref())
+            baseline_loss::<T, Ops, D>(&dataset, &options)
         } else {
             None
         };
@@ -259,10 +588,17 @@ where
         let pools = init_populations(full_dataset, &options, &mut hall);
         progress.set_initial_evals(pools.total_evals);
 
+        let mut validation_evaluator = Evaluator::new(validation.as_ref().map_or(0, |v| v.n_rows));
+        if let Some(val) = &validation {
+            hall.update_validation_losses(val, &options, &mut validation_evaluator);
+        }
+
         let order_rng = Rng::with_seed(options.seed ^ 0x9e37_79b9_7f4a_7c15);
 
         Self {
             dataset,
+            validation,
+            validation_evaluator,
             baseline_loss,
             options,
             counters,
@@ -303,13 +639,27 @@ where
     }
 
     pub fn best(&self) -> &PopMember<T, Ops, D> {
-        &self.pools.best
+        select_best(&self.hall, &self.pools.best, &self.options, self.validation.is_some())
+    }
+
+    /// Each population's mutation record so far.
+    pub fn mutation_stats(&self) -> Vec<MutationStats> {
+        self.pools.mutation_stats()
+    }
+
+    /// Births recorded so far, when `Options::use_recorder` is set.
+    pub fn genealogy(&self) -> Option<&Genealogy> {
+        self.pools.genealogy.as_ref()
     }
 
     pub fn dataset(&self) -> &Dataset<T> {
         &self.dataset
     }
 
+    pub fn validation_dataset(&self) -> Option<&Dataset<T>> {
+        self.validation.as_ref()
+    }
+
     pub fn options(&self) -> &Options<T, D> {
         &self.options
     }
@@ -327,10 +677,15 @@ where
 
     pub fn run_to_completion(mut self) -> SearchResult<T, Ops, D> {
         while self.step_one_cycle() {}
+        let best = self.best().clone();
+        let n_features = self.options.n_expr_features(&self.dataset);
         SearchResult {
+            mutation_stats: self.pools.mutation_stats(),
+            genealogy: self.pools.genealogy,
             hall_of_fame: self.hall,
-            best: self.pools.best,
+            best,
         }
+        .with_folded_scaling(n_features, &self.options, self.baseline_loss)
     }
 
     fn step_one_cycle(&mut self) -> bool {
@@ -360,6 +715,7 @@ where
 
         let mut stats_snapshot = self.stats.clone();
         stats_snapshot.normalize();
+        let donors = crossover_donors(&self.hall, &self.options);
 
         let full_dataset = self.full_dataset_tagged();
         let res = execute_task(
@@ -368,10 +724,12 @@ where
             pop_idx,
             curmaxsize,
             stats_snapshot,
+            &donors,
             pop_state,
         );
         apply_task_result(
             &self.options,
+            self.baseline_loss,
             &mut self.counters,
             &mut self.stats,
             &mut self.hall,
@@ -379,6 +737,10 @@ where
             &mut self.pools,
             res,
         );
+        if let Some(val) = &self.validation {
+            self.hall
+                .update_validation_losses(val, &self.options, &mut self.validation_evaluator);
+        }
 
         if self.is_finished() && !self.progress_finished {
             self.progress.finish();
@@ -403,26 +765,40 @@ where
     }
 }
 
-fn execute_task<T, Ops, const D: usize>(
+pub(crate) fn execute_task<T, Ops, const D: usize>(
     full_dataset: TaggedDataset<'_, T>,
     options: &Options<T, D>,
     pop_idx: usize,
     curmaxsize: usize,
     stats: RunningSearchStatistics,
+    hall_of_fame: &[PopMember<T, Ops, D>],
     mut pop_state: PopState<T, Ops, D>,
 ) -> SearchTaskResult<T, Ops, D>
 where
     T: Float + num_traits::FromPrimitive + num_traits::ToPrimitive + AddAssign,
     Ops: dynamic_expressions::OperatorSet<T = T>,
 {
-    let (evals1, best_seen) =
-        pop_state.run_iteration_phase(full_dataset, options, curmaxsize, &stats, |pop, ctx, eval_dataset| {
-            single_iteration::s_r_cycle(pop, ctx, eval_dataset)
-        });
+    let island_options = options.for_island(pop_idx);
+    let options = &*island_options;
+    let curmaxsize = curmaxsize.min(options.maxsize);
 
-    let evals2 = pop_state.run_iteration_phase(full_dataset, options, curmaxsize, &stats, |pop, ctx, opt_dataset| {
-        single_iteration::optimize_and_simplify_population(pop, ctx, opt_dataset)
-    });
+    let (evals1, best_seen) = pop_state.run_iteration_phase(
+        full_dataset,
+        options,
+        curmaxsize,
+        &stats,
+        hall_of_fame,
+        |pop, ctx, eval_dataset| single_iteration::s_r_cycle(pop, ctx, eval_dataset),
+    );
+
+    let evals2 = pop_state.run_iteration_phase(
+        full_dataset,
+        options,
+        curmaxsize,
+        &stats,
+        hall_of_fame,
+        |pop, ctx, opt_dataset| single_iteration::optimize_and_simplify_population(pop, ctx, opt_dataset),
+    );
     let evals = (evals1.max(0.0) + evals2.max(0.0)) as u64;
 
     let best_sub_pop = migration::best_sub_pop(&pop_state.pop, options.topn);
@@ -437,8 +813,22 @@ where
     }
 }
 
+/// The hall of fame's entries, as donors for hall-of-fame crossover; empty when that operator is off.
+fn crossover_donors<T: Float, Ops, const D: usize>(
+    hall: &HallOfFame<T, Ops, D>,
+    options: &Options<T, D>,
+) -> Vec<PopMember<T, Ops, D>> {
+    if options.crossover_weights.hall_of_fame > 0.0 {
+        hall.members().cloned().collect()
+    } else {
+        Vec::new()
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn apply_task_result<T, Ops, const D: usize>(
     options: &Options<T, D>,
+    baseline_loss: Option<T>,
     counters: &mut SearchCounters,
     stats: &mut RunningSearchStatistics,
     hall: &mut HallOfFame<T, Ops, D>,
@@ -457,6 +847,9 @@ fn apply_task_result<T, Ops, const D: usize>(
     pools.pops[pop_idx] = Some(res.pop_state);
 
     let st = pools.pops[pop_idx].as_mut().expect("pop exists");
+    if let (Some(log), Some(births)) = (pools.genealogy.as_mut(), st.genealogy.as_mut()) {
+        log.append(births);
+    }
 
     stats.update_from_population(st.pop.members.iter().map(|m| m.complexity));
     stats.move_window();
@@ -466,18 +859,28 @@ fn apply_task_result<T, Ops, const D: usize>(
     }
     for m in &st.pop.members {
         hall.consider(m, options, curmaxsize);
-        if check_constraints(&m.expr, options, curmaxsize) && m.loss < pools.best.loss {
+        if m.satisfies_constraints(options, curmaxsize) && m.loss < pools.best.loss {
             pools.best = m.clone();
         }
     }
+    if !options.islands.is_empty() {
+        // The members were costed under the island's parsimony; the hall reports the search's.
+        hall.rescore(options, baseline_loss);
+    }
+
+    // Migrants arrive costed under their source's parsimony; rescore them for this island.
+    let island_options = options.for_island(pop_idx);
+    let rescore = |m: &mut PopMember<T, Ops, D>| m.rescore(&island_options, baseline_loss);
 
     if options.migration {
-        let mut candidates: Vec<PopMember<T, Ops, D>> = Vec::new();
-        for (i, v) in pools.best_sub_pops.iter().enumerate() {
-            if i != pop_idx {
-                candidates.extend(v.iter().cloned());
-            }
-        }
+        let sources = options
+            .migration_topology
+            .sources(pop_idx, pools.best_sub_pops.len(), &mut st.rng);
+        let mut candidates: Vec<PopMember<T, Ops, D>> = sources
+            .into_iter()
+            .flat_map(|i| pools.best_sub_pops[i].iter().cloned())
+            .collect();
+        candidates.iter_mut().for_each(rescore);
         migration::migrate_into(
             &mut st.pop,
             &candidates,
@@ -485,11 +888,13 @@ fn apply_task_result<T, Ops, const D: usize>(
             &mut st.rng,
             &mut st.next_id,
             &mut st.next_birth,
+            pools.genealogy.as_mut(),
         );
     }
 
     if options.hof_migration {
-        let dominating = hall.pareto_front();
+        let mut dominating = hall.pareto_front();
+        dominating.iter_mut().for_each(rescore);
         migration::migrate_into(
             &mut st.pop,
             &dominating,
@@ -497,6 +902,7 @@ fn apply_task_result<T, Ops, const D: usize>(
             &mut st.rng,
             &mut st.next_id,
             &mut st.next_birth,
+            pools.genealogy.as_mut(),
         );
     }
 
@@ -506,61 +912,227 @@ fn apply_task_result<T, Ops, const D: usize>(
 
 fn run_scoped_search<'scope, 'env, T, Ops, const D: usize>(
     scope: &rayon::Scope<'scope>,
-    state: &mut EquationSearchState<'env, T, Ops, D>,
+    states: &mut [EquationSearchState<'env, T, Ops, D>],
+    n_workers: usize,
 ) where
     'env: 'scope,
     T: Float + AddAssign + num_traits::FromPrimitive + num_traits::ToPrimitive + Display + Send + Sync + 'scope,
     Ops: dynamic_expressions::OperatorSet<T = T> + Send + Sync + 'scope,
 {
-    let full_dataset = state.full_dataset;
-    let options = state.options;
-
-    let (result_tx, result_rx) = std::sync::mpsc::channel::<SearchTaskResult<T, Ops, D>>();
-
-    for _iter in 0..options.niterations {
-        let mut task_order: Vec<usize> = (0..state.pools.pops.len()).collect();
-        shuffle(&mut state.order_rng, &mut task_order);
+    // Task results are tagged with the index of the search (output) they belong to.
+    let (result_tx, result_rx) = std::sync::mpsc::channel::<(usize, SearchTaskResult<T, Ops, D>)>();
+    let niterations = states.iter().map(|s| s.options.niterations).max().unwrap_or(0);
+
+    for iter in 0..niterations {
+        let mut rounds: Vec<Option<IterationRound<T, Ops, D>>> = states
+            .iter_mut()
+            .map(|state| {
+                (iter < state.options.niterations && !state.stop.is_set()).then(|| {
+                    let mut task_order: Vec<usize> = (0..state.pools.pops.len()).collect();
+                    shuffle(&mut state.order_rng, &mut task_order);
+                    let deferred = DeferredResults::new(state.options.deterministic, state.pools.pops.len());
+                    IterationRound {
+                        task_order,
+                        next_task: 0,
+                        deferred,
+                    }
+                })
+            })
+            .collect();
+        if rounds.iter().all(Option::is_none) {
+            break;
+        }
 
-        let mut next_task = 0usize;
         let mut in_flight = 0usize;
-
-        while next_task < task_order.len() || in_flight > 0 {
-            while in_flight < state.n_workers && next_task < task_order.len() {
-                let pop_idx = task_order[next_task];
-                next_task += 1;
+        let mut next_search = 0usize;
+        loop {
+            // Dispatch round-robin over the searches. Once a stop is requested, stop dispatching and
+            // only drain the in-flight tasks.
+            let mut idle = 0;
+            while in_flight < n_workers && idle < states.len() {
+                let k = next_search;
+                next_search = (next_search + 1) % states.len();
+                let state = &mut states[k];
+                let Some(round) = rounds[k].as_mut().filter(|r| r.next_task < r.task_order.len()) else {
+                    idle += 1;
+                    continue;
+                };
+                if state.stop.is_set() {
+                    idle += 1;
+                    continue;
+                }
+                idle = 0;
+                let pop_idx = round.task_order[round.next_task];
+                round.next_task += 1;
 
                 let Some(st) = state.pools.pops[pop_idx].take() else {
                     continue;
                 };
 
-                let cycles_remaining_start = state.counters.cycles_remaining_start_for_next_dispatch();
-                let curmaxsize = warmup::get_cur_maxsize(options, state.counters.total_cycles, cycles_remaining_start);
-                let mut stats_snapshot = state.stats.clone();
-                stats_snapshot.normalize();
-
+                let (curmaxsize, stats_snapshot, donors) = state.dispatch_params();
+                let (full_dataset, options) = (state.full_dataset, state.options);
                 let result_tx = result_tx.clone();
                 scope.spawn(move |_| {
-                    let res = execute_task(full_dataset, options, pop_idx, curmaxsize, stats_snapshot, st);
-                    let _ = result_tx.send(res);
+                    let res = execute_task(full_dataset, options, pop_idx, curmaxsize, stats_snapshot, &donors, st);
+                    let _ = result_tx.send((k, res));
                 });
                 in_flight += 1;
             }
+            if in_flight == 0 {
+                break;
+            }
 
-            let res = result_rx.recv().expect("worker result channel closed early");
+            let (k, res) = result_rx.recv().expect("worker result channel closed early");
             in_flight -= 1;
-            apply_task_result(
-                options,
-                &mut state.counters,
-                &mut state.stats,
-                &mut state.hall,
-                &mut state.progress,
-                &mut state.pools,
-                res,
-            );
+            let round = rounds[k]
+                .as_mut()
+                .expect("results only arrive for dispatching searches");
+            round.deferred.apply_or_defer(&mut states[k], res);
+        }
+        for (state, round) in states.iter_mut().zip(rounds) {
+            if let Some(mut round) = round {
+                round.deferred.apply_in_order(state, &round.task_order);
+            }
         }
     }
 }
 
+/// One search's progress through the population tasks of the current iteration.
+struct IterationRound<T: Float + AddAssign, Ops, const D: usize> {
+    task_order: Vec<usize>,
+    next_task: usize,
+    deferred: DeferredResults<T, Ops, D>,
+}
+
+/// In `deterministic` mode, task results of one iteration are held back and applied in the
+/// iteration's (seed-derived) task order once all of them are in. Every task of the iteration then
+/// starts from the same statistics and hall of fame, and migration happens in a fixed order, so
+/// the outcome does not depend on which task finished first.
+struct DeferredResults<T: Float + AddAssign, Ops, const D: usize> {
+    results: Option<Vec<Option<SearchTaskResult<T, Ops, D>>>>,
+}
+
+impl<T: Float + AddAssign, Ops, const D: usize> DeferredResults<T, Ops, D> {
+    fn new(deterministic: bool, n_pops: usize) -> Self {
+        Self {
+            results: deterministic.then(|| (0..n_pops).map(|_| None).collect()),
+        }
+    }
+
+    fn apply_or_defer(&mut self, state: &mut EquationSearchState<'_, T, Ops, D>, res: SearchTaskResult<T, Ops, D>)
+    where
+        T: num_traits::FromPrimitive + num_traits::ToPrimitive + Display,
+        Ops: dynamic_expressions::OperatorSet<T = T>,
+    {
+        match &mut self.results {
+            Some(results) => {
+                let pop_idx = res.pop_idx;
+                results[pop_idx] = Some(res);
+            }
+            None => state.apply(res),
+        }
+    }
+
+    fn apply_in_order(&mut self, state: &mut EquationSearchState<'_, T, Ops, D>, task_order: &[usize])
+    where
+        T: num_traits::FromPrimitive + num_traits::ToPrimitive + Display,
+        Ops: dynamic_expressions::OperatorSet<T = T>,
+    {
+        let Some(results) = &mut self.results else {
+            return;
+        };
+        for &pop_idx in task_order {
+            if let Some(res) = results[pop_idx].take() {
+                state.apply(res);
+            }
+        }
+    }
+}
+
+/// The dispatch loop of [`run_scoped_search`], sending tasks to worker connections instead.
+///
+/// Each connection gets a thread that only moves frames; encoding, decoding and bookkeeping stay on
+/// the calling thread.
+fn run_distributed_search<T, Ops, const D: usize>(
+    state: &mut EquationSearchState<'_, T, Ops, D>,
+    connections: Vec<Connection>,
+) -> Result<(), DistributedError>
+where
+    T: Float + AddAssign + num_traits::FromPrimitive + num_traits::ToPrimitive + Display,
+    Ops: dynamic_expressions::OperatorSet<T = T>,
+{
+    let options = state.options;
+    let n_expr_features = options.n_expr_features(state.full_dataset.data);
+    let (result_tx, result_rx) = std::sync::mpsc::channel::<(usize, std::io::Result<Option<String>>)>();
+
+    std::thread::scope(|scope| {
+        let mut task_txs = Vec::with_capacity(connections.len());
+        for (worker, mut conn) in connections.into_iter().enumerate() {
+            let (task_tx, task_rx) = std::sync::mpsc::channel::<String>();
+            task_txs.push(task_tx);
+            let result_tx = result_tx.clone();
+            scope.spawn(move || {
+                for task in task_rx {
+                    let reply = conn.write_frame(&task).and_then(|()| conn.read_frame());
+                    let failed = !matches!(reply, Ok(Some(_)));
+                    let _ = result_tx.send((worker, reply));
+                    if failed {
+                        return;
+                    }
+                }
+                let _ = conn.write_frame(distributed::SHUTDOWN);
+            });
+        }
+
+        let mut idle: Vec<usize> = (0..task_txs.len()).rev().collect();
+        let mut in_flight: Vec<Option<PopState<T, Ops, D>>> = (0..task_txs.len()).map(|_| None).collect();
+
+        for _iter in 0..options.niterations {
+            if state.stop.is_set() {
+                break;
+            }
+            let mut task_order: Vec<usize> = (0..state.pools.pops.len()).collect();
+            shuffle(&mut state.order_rng, &mut task_order);
+
+            let mut next_task = 0usize;
+            let mut deferred = DeferredResults::new(options.deterministic, state.pools.pops.len());
+            while next_task < task_order.len() || idle.len() < task_txs.len() {
+                while next_task < task_order.len() && !state.stop.is_set() {
+                    let Some(&worker) = idle.last() else {
+                        break;
+                    };
+                    let pop_idx = task_order[next_task];
+                    next_task += 1;
+
+                    let Some(st) = state.pools.pops[pop_idx].take() else {
+                        continue;
+                    };
+                    let (curmaxsize, stats_snapshot, donors) = state.dispatch_params();
+                    let task = distributed::encode_task(pop_idx, curmaxsize, &stats_snapshot, &donors, &st);
+                    if task_txs[worker].send(task).is_err() {
+                        return Err(DistributedError::Protocol(format!("worker {worker} exited")));
+                    }
+                    idle.pop();
+                    in_flight[worker] = Some(st);
+                }
+                if idle.len() == task_txs.len() {
+                    break;
+                }
+
+                let (worker, reply) = result_rx.recv().expect("connection threads hold a sender");
+                let text = reply?
+                    .ok_or_else(|| DistributedError::Protocol(format!("worker {worker} disconnected during a task")))?;
+                let st = in_flight[worker].take().expect("worker had a task in flight");
+                let res = distributed::decode_result(&text, st, n_expr_features)?;
+                deferred.apply_or_defer(state, res);
+                idle.push(worker);
+            }
+            deferred.apply_in_order(state, &task_order);
+        }
+        Ok(())
+    })
+}
+
 fn init_populations<T, Ops, const D: usize>(
     full_dataset: TaggedDataset<'_, T>,
     options: &Options<T, D>,
@@ -570,6 +1142,23 @@ where
     T: Float + num_traits::FromPrimitive + num_traits::ToPrimitive + AddAssign,
     Ops: dynamic_expressions::OperatorSet<T = T>,
 {
+    assert!(
+        !options.linear_scaling || LinearScaling::<T>::can_fold::<Ops>(),
+        "linear_scaling requires binary `*` and `+` in the operator set, to fold the scaling into results"
+    );
+    assert!(
+        options.mutation_adaptation.is_valid(),
+        "mutation_adaptation needs a positive Softmax temperature and a finite, non-negative Ucb exploration, got {:?}",
+        options.mutation_adaptation
+    );
+    assert!(
+        options.full_objective.is_none()
+            || !matches!(
+                options.selection,
+                SelectionMode::Lexicase | SelectionMode::EpsilonLexicase
+            ),
+        "lexicase selection compares per-row losses, which a full_objective does not provide"
+    );
     let dataset = full_dataset.data;
     let mut total_evals: u64 = 0;
     let mut pops: Vec<Option<PopState<T, Ops, D>>> = Vec::with_capacity(options.populations);
@@ -582,20 +1171,33 @@ where
         let mut next_id = (pop_i as u64) << 32;
         let mut next_birth = 0u64;
 
+        let island_options = options.for_island(pop_i);
         let nlength = 3usize;
+        let n_expr_features = options.n_expr_features(dataset);
         let mut members = Vec::with_capacity(options.population_size);
         for _ in 0..options.population_size {
-            let expr = crate::mutation_functions::random_expr_append_ops(
-                &mut rng,
-                &options.operators,
-                dataset.n_features,
-                nlength,
-                options.maxsize,
-            );
-            let mut m = PopMember::from_expr(MemberId(next_id), None, next_birth, expr, dataset.n_features);
+            let id = MemberId(next_id);
+            let mut m = match &options.template {
+                Some(template) => {
+                    let subexprs =
+                        template.random_subexprs(&mut rng, &options.operators, nlength, island_options.maxsize);
+                    PopMember::from_template(id, None, next_birth, template, subexprs, n_expr_features)
+                }
+                None => {
+                    let expr = crate::mutation_functions::random_expr_append_ops(
+                        &mut rng,
+                        &options.operators,
+                        n_expr_features,
+                        nlength,
+                        island_options.maxsize,
+                    );
+                    PopMember::from_expr(id, None, next_birth, expr, n_expr_features)
+                }
+            };
+            m.randomize_parameters(&mut rng, options.n_parameters, dataset);
             next_id += 1;
             next_birth += 1;
-            let _ = m.evaluate(&full_dataset, options, &mut evaluator);
+            let _ = m.evaluate(&full_dataset, &island_options, &mut evaluator);
             total_evals += 1;
             hall.consider(&m, options, options.maxsize);
             members.push(m);
@@ -609,13 +1211,15 @@ where
             batch_dataset: None,
             next_id,
             next_birth,
+            mutation_stats: MutationStats::default(),
+            genealogy: options.use_recorder.then(Genealogy::default),
         }));
     }
 
     let mut best: Option<PopMember<T, Ops, D>> = None;
     for st in pops.iter().flatten() {
         for m in &st.pop.members {
-            if !check_constraints(&m.expr, options, options.maxsize) {
+            if !m.satisfies_constraints(options, options.maxsize) {
                 continue;
             }
             match &best {
@@ -649,5 +1253,6 @@ where
         best_sub_pops,
         best,
         total_evals,
+        genealogy: options.use_recorder.then(Genealogy::default),
     }
 }
@@ -4,6 +4,7 @@ use fastrand::Rng;
 use num_traits::Float;
 
 use crate::adaptive_parsimony::RunningSearchStatistics;
+use crate::multi_objective::{Nsga2Ranking, SelectionMode};
 use crate::options::Options;
 use crate::pop_member::PopMember;
 use crate::population::Population;
@@ -31,6 +32,8 @@ fn sample_tournament_place(rng: &mut Rng, n: usize, p: f32) -> usize {
     weighted_index(rng, &weights) + 1
 }
 
+/// With NSGA-II selection this ranks the whole population first; a search cycle ranks it once
+/// and calls [`best_of_ranked_sample`] instead.
 pub fn best_of_sample<T: Float, Ops, const D: usize>(
     rng: &mut Rng,
     pop: &Population<T, Ops, D>,
@@ -38,6 +41,10 @@ pub fn best_of_sample<T: Float, Ops, const D: usize>(
     options: &Options<T, D>,
 ) -> PopMember<T, Ops, D> {
     let n = options.tournament_selection_n.min(pop.len());
+    if let SelectionMode::Nsga2 { objectives } = &options.selection {
+        let ranking = Nsga2Ranking::of_members(&pop.members, objectives);
+        return best_of_ranked_sample(rng, pop, &ranking, options);
+    }
     let indices = sample_indices(rng, pop.len(), n);
 
     let mut scored: Vec<(f64, usize)> = Vec::with_capacity(n);
@@ -63,6 +70,20 @@ pub fn best_of_sample<T: Float, Ops, const D: usize>(
     pop.members[chosen].clone()
 }
 
+/// A tournament winner under `ranking`, which ranks the members of `pop`.
+pub(crate) fn best_of_ranked_sample<T: Float, Ops, const D: usize>(
+    rng: &mut Rng,
+    pop: &Population<T, Ops, D>,
+    ranking: &Nsga2Ranking,
+    options: &Options<T, D>,
+) -> PopMember<T, Ops, D> {
+    let n = options.tournament_selection_n.min(pop.len());
+    let mut indices = sample_indices(rng, pop.len(), n);
+    indices.sort_by(|&i, &j| ranking.compare(i, j));
+    let place = sample_tournament_place(rng, indices.len(), options.tournament_selection_p);
+    pop.members[indices[(place - 1).min(indices.len() - 1)]].clone()
+}
+
 pub(crate) fn weighted_index(rng: &mut Rng, weights: &[f64]) -> usize {
     if weights.is_empty() {
         panic!("weights must be non-empty");
@@ -3,12 +3,15 @@ use std::ops::AddAssign;
 use fastrand::Rng;
 use num_traits::{Float, FromPrimitive, ToPrimitive};
 
+use crate::adaptive_mutation::MutationStats;
 use crate::adaptive_parsimony::RunningSearchStatistics;
 use crate::constant_optimization::{OptimizeConstantsCtx, optimize_constants};
 use crate::dataset::TaggedDataset;
+use crate::genealogy::Genealogy;
 use crate::hall_of_fame::HallOfFame;
+use crate::lexicase::CaseErrors;
 use crate::options::Options;
-use crate::pop_member::Evaluator;
+use crate::pop_member::{Evaluator, PopMember};
 use crate::population::Population;
 use crate::regularized_evolution::{RegEvolCtx, reg_evol_cycle};
 
@@ -22,6 +25,10 @@ pub struct IterationCtx<'a, T: Float + AddAssign, Ops, const D: usize> {
     pub grad_ctx: &'a mut dynamic_expressions::GradContext<T, D>,
     pub next_id: &'a mut u64,
     pub next_birth: &'a mut u64,
+    pub mutation_stats: &'a mut MutationStats,
+    pub genealogy: Option<&'a mut Genealogy>,
+    /// Snapshot of the search's hall of fame, taken when the task was dispatched.
+    pub hall_of_fame: &'a [PopMember<T, Ops, D>],
     pub _ops: core::marker::PhantomData<Ops>,
 }
 
@@ -40,6 +47,7 @@ where
     let mut num_evals = 0.0;
     let mut best_seen = HallOfFame::new(ctx.options.maxsize);
     best_seen.update_from_members(&pop.members, ctx.options, ctx.curmaxsize);
+    let mut case_errors = CaseErrors::default();
 
     for i in 0..ncycles {
         let temperature = if ncycles <= 1 {
@@ -60,6 +68,10 @@ where
                 evaluator: ctx.evaluator,
                 next_id: ctx.next_id,
                 next_birth: ctx.next_birth,
+                mutation_stats: ctx.mutation_stats,
+                genealogy: ctx.genealogy.as_deref_mut(),
+                case_errors: &mut case_errors,
+                hall_of_fame: ctx.hall_of_fame,
                 _ops: core::marker::PhantomData,
             },
         );
@@ -80,10 +92,24 @@ where
     let mut num_evals = 0.0;
 
     if ctx.options.should_simplify {
+        let n_expr_features = ctx.options.n_expr_features(&ctx.full_dataset);
         for m in &mut pop.members {
-            let changed = dynamic_expressions::simplify_in_place(&mut m.expr, &ctx.evaluator.eval_opts);
-            if changed {
-                m.rebuild_plan(ctx.full_dataset.n_features);
+            match ctx.options.template.as_ref().filter(|_| !m.subexprs.is_empty()) {
+                Some(template) => {
+                    let mut changed = false;
+                    for sub in &mut m.subexprs {
+                        changed |= dynamic_expressions::simplify_in_place(sub, &ctx.evaluator.eval_opts);
+                    }
+                    if changed {
+                        m.recompose(template, n_expr_features);
+                    }
+                }
+                None => {
+                    let changed = dynamic_expressions::simplify_in_place(&mut m.expr, &ctx.evaluator.eval_opts);
+                    if changed {
+                        m.rebuild_plan(n_expr_features);
+                    }
+                }
             }
         }
     }
@@ -0,0 +1,167 @@
+//! Template expressions (port of SymbolicRegression.jl's `TemplateExpression`).
+//!
+//! A fixed combiner composes several sub-expressions, each a function of its own subset of the
+//! features. The search only evolves the sub-expressions; members keep them in
+//! `PopMember::subexprs` and `PopMember::expr` holds their composition, so evaluation, printing
+//! and export work on the full expression unchanged.
+
+use std::fmt;
+
+use dynamic_expressions::expression::{Metadata, PostfixExpr};
+use dynamic_expressions::node::PNode;
+use fastrand::Rng;
+use num_traits::Float;
+
+use crate::check_constraints::check_constraints;
+use crate::complexity::compute_complexity;
+use crate::mutation_functions::random_expr_append_ops;
+use crate::operators::Operators;
+use crate::options::Options;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TemplateError {
+    /// The combiner references sub-expression `index`, but only `n_subexprs` feature lists were given.
+    UnknownSubexpr { index: usize, n_subexprs: usize },
+    /// Sub-expression `index` is never used by the combiner.
+    UnusedSubexpr { index: usize },
+    /// Sub-expression `index` has no features to depend on.
+    NoFeatures { index: usize },
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::UnknownSubexpr { index, n_subexprs } => write!(
+                f,
+                "combiner uses sub-expression {index} but only {n_subexprs} were declared"
+            ),
+            TemplateError::UnusedSubexpr { index } => write!(f, "sub-expression {index} is not used by the combiner"),
+            TemplateError::NoFeatures { index } => write!(f, "sub-expression {index} has no features"),
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+/// The fixed outer structure of a template search.
+///
+/// The combiner is an ordinary expression whose variable `xk` stands for sub-expression `k`, e.g.
+/// `parse_expr("f * exp(-g)", &["f".into(), "g".into()])`. Sub-expression `k` is written over
+/// local variables `x0, x1, ...` which map to the dataset features `features[k]`. Combiner
+/// constants are fixed and never optimized.
+#[derive(Clone, Debug)]
+pub struct ExpressionTemplate<T> {
+    combiner: Vec<PNode>,
+    combiner_consts: Vec<T>,
+    features: Vec<Vec<usize>>,
+}
+
+impl<T: Float> ExpressionTemplate<T> {
+    pub fn new<Ops, const D: usize>(
+        combiner: &PostfixExpr<T, Ops, D>,
+        features: Vec<Vec<usize>>,
+    ) -> Result<Self, TemplateError> {
+        let mut used = vec![false; features.len()];
+        for n in &combiner.nodes {
+            if let PNode::Var { feature } = *n {
+                let index = usize::from(feature);
+                let Some(u) = used.get_mut(index) else {
+                    return Err(TemplateError::UnknownSubexpr {
+                        index,
+                        n_subexprs: features.len(),
+                    });
+                };
+                *u = true;
+            }
+        }
+        if let Some(index) = used.iter().position(|u| !u) {
+            return Err(TemplateError::UnusedSubexpr { index });
+        }
+        if let Some(index) = features.iter().position(Vec::is_empty) {
+            return Err(TemplateError::NoFeatures { index });
+        }
+        Ok(Self {
+            combiner: combiner.nodes.clone(),
+            combiner_consts: combiner.consts.clone(),
+            features,
+        })
+    }
+
+    pub fn n_subexprs(&self) -> usize {
+        self.features.len()
+    }
+
+    /// Dataset features available to sub-expression `k`.
+    pub fn features(&self, k: usize) -> &[usize] {
+        &self.features[k]
+    }
+
+    /// Substitutes `subexprs` into the combiner, mapping their local variables to dataset features.
+    ///
+    /// Constants of the sub-expressions come first, in order, followed by the combiner's.
+    pub fn compose<Ops, const D: usize>(&self, subexprs: &[PostfixExpr<T, Ops, D>]) -> PostfixExpr<T, Ops, D> {
+        assert_eq!(subexprs.len(), self.n_subexprs());
+        let mut offsets = Vec::with_capacity(subexprs.len());
+        let mut consts = Vec::new();
+        for sub in subexprs {
+            offsets.push(consts.len());
+            consts.extend_from_slice(&sub.consts);
+        }
+        let combiner_offset = consts.len();
+        consts.extend_from_slice(&self.combiner_consts);
+
+        let mut nodes = Vec::new();
+        for n in &self.combiner {
+            match *n {
+                PNode::Var { feature } => {
+                    let k = usize::from(feature);
+                    nodes.extend(subexprs[k].nodes.iter().map(|&sn| match sn {
+                        PNode::Var { feature } => PNode::Var {
+                            feature: self.features[k][usize::from(feature)] as u16,
+                        },
+                        PNode::Const { idx } => PNode::Const {
+                            idx: (offsets[k] + usize::from(idx)) as u16,
+                        },
+                        op => op,
+                    }));
+                }
+                PNode::Const { idx } => nodes.push(PNode::Const {
+                    idx: (combiner_offset + usize::from(idx)) as u16,
+                }),
+                op => nodes.push(op),
+            }
+        }
+        PostfixExpr::new(nodes, consts, Metadata::default())
+    }
+
+    pub(crate) fn random_subexprs<Ops, const D: usize>(
+        &self,
+        rng: &mut Rng,
+        operators: &Operators<D>,
+        n_append_ops: usize,
+        max_size: usize,
+    ) -> Vec<PostfixExpr<T, Ops, D>> {
+        let max_size = (max_size / self.n_subexprs()).max(1);
+        self.features
+            .iter()
+            .map(|f| random_expr_append_ops(rng, operators, f.len(), n_append_ops, max_size))
+            .collect()
+    }
+}
+
+/// Total complexity of a template member: the sum over its sub-expressions.
+pub(crate) fn template_complexity<T: Float, Ops, const D: usize>(
+    subexprs: &[PostfixExpr<T, Ops, D>],
+    options: &Options<T, D>,
+) -> usize {
+    subexprs.iter().map(|s| compute_complexity(s, options)).sum()
+}
+
+pub(crate) fn check_template_constraints<T: Float, Ops, const D: usize>(
+    subexprs: &[PostfixExpr<T, Ops, D>],
+    options: &Options<T, D>,
+    curmaxsize: usize,
+) -> bool {
+    subexprs.iter().all(|s| check_constraints(s, options, curmaxsize))
+        && template_complexity(subexprs, options) <= curmaxsize
+}
@@ -1,15 +1,37 @@
 mod common;
+mod test_adaptive_mutation;
 mod test_batching_constraints_complexity;
 mod test_compress_constants;
 mod test_constant_optimization_birth_reset;
 mod test_cost_normalization;
 mod test_count_depth_proptests;
+mod test_crossover;
+mod test_custom_mutation;
+mod test_deterministic;
+mod test_dimensional_analysis;
+mod test_distributed;
 mod test_equation_search_runs;
+mod test_feature_selection;
 mod test_frequency_in_tournament;
+mod test_full_objective;
+mod test_genealogy;
+mod test_graph_expressions;
+mod test_hall_of_fame_io;
+mod test_interrupt;
+mod test_island_topologies;
+mod test_lexicase;
+mod test_linear_scaling;
 mod test_loss;
+mod test_model_selection;
+mod test_multi_objective;
+mod test_multi_output;
 mod test_mutate_constant_regressions;
 mod test_mutation_regressions;
 mod test_next_generation_retry_and_skip;
+mod test_optimizer_algorithms;
+mod test_parametric_expressions;
 mod test_population_replacement;
 mod test_random_distributions;
 mod test_rotate_tree_proptests;
+mod test_template_expressions;
+mod test_validation;
@@ -0,0 +1,140 @@
+use ndarray::{Array1, Array2};
+
+use super::common::{D, T, TestOps};
+use crate::adaptive_mutation::adapt_mutation_weights;
+use crate::dataset::Dataset;
+use crate::operator_library::OperatorLibrary;
+use crate::{MutationAdaptation, MutationChoice, MutationStats, MutationWeights, Options, equation_search};
+
+/// `MutateConstant` improved half of 100 children, `AddNode` none of 100; the rest are untried.
+fn stats() -> MutationStats {
+    let mut stats = MutationStats::default();
+    for i in 0..100 {
+        stats.record(MutationChoice::MutateConstant, true, i % 2 == 0);
+        stats.record(MutationChoice::AddNode, i % 3 == 0, false);
+    }
+    stats
+}
+
+fn adapted(adaptation: MutationAdaptation) -> MutationWeights {
+    let mut weights = MutationWeights {
+        rotate_tree: 0.0,
+        ..Default::default()
+    };
+    adapt_mutation_weights(&mut weights, &mut [], &stats(), adaptation);
+    weights
+}
+
+#[test]
+fn counts_track_attempts_acceptance_and_improvement() {
+    let stats = stats();
+    let c = stats.get(MutationChoice::AddNode);
+    assert_eq!((c.attempts, c.accepted, c.improved), (100, 34, 0));
+    let c = stats.get(MutationChoice::MutateConstant);
+    assert_eq!((c.attempts, c.accepted, c.improved), (100, 100, 50));
+    assert_eq!(stats.total_attempts(), 200);
+
+    let mut merged = stats.clone();
+    merged.merge(&stats);
+    assert_eq!(merged.get(MutationChoice::AddNode).attempts, 200);
+}
+
+#[test]
+fn adaptation_parameters_must_keep_weights_non_negative() {
+    assert!(MutationAdaptation::Off.is_valid());
+    assert!(MutationAdaptation::Ucb { exploration: 0.0 }.is_valid());
+    assert!(!MutationAdaptation::Ucb { exploration: -1.0 }.is_valid());
+    assert!(!MutationAdaptation::Ucb { exploration: f64::NAN }.is_valid());
+    assert!(MutationAdaptation::Softmax { temperature: 0.1 }.is_valid());
+    assert!(!MutationAdaptation::Softmax { temperature: 0.0 }.is_valid());
+    assert!(!MutationAdaptation::Softmax { temperature: -0.1 }.is_valid());
+
+    // Valid parameters give finite, non-negative weights.
+    for adaptation in [
+        MutationAdaptation::Ucb { exploration: 0.0 },
+        MutationAdaptation::Softmax { temperature: 1e-3 },
+    ] {
+        let weights = adapted(adaptation);
+        let mut custom = [];
+        for choice in MutationChoice::ALL {
+            let w = *choice.weight_mut(&mut weights.clone(), &mut custom).unwrap();
+            assert!(w.is_finite() && w >= 0.0, "{choice:?} under {adaptation:?}: {w}");
+        }
+    }
+}
+
+#[test]
+fn bandits_favour_operators_that_improve() {
+    let base = MutationWeights::default();
+    assert_eq!(adapted(MutationAdaptation::Off).mutate_constant, base.mutate_constant);
+
+    for adaptation in [
+        MutationAdaptation::Ucb { exploration: 0.5 },
+        MutationAdaptation::Softmax { temperature: 0.1 },
+    ] {
+        let w = adapted(adaptation);
+        let base_ratio = base.mutate_constant / base.add_node;
+        assert!(
+            w.mutate_constant / w.add_node > 2.0 * base_ratio,
+            "{adaptation:?}: {w:?}"
+        );
+        // Untried operators keep an optimistic prior over a consistently failing one.
+        assert!(
+            w.delete_node / w.add_node > base.delete_node / base.add_node,
+            "{adaptation:?}"
+        );
+        assert_eq!(w.rotate_tree, 0.0);
+    }
+}
+
+fn search(adaptation: MutationAdaptation) -> Vec<MutationStats> {
+    let n_rows = 40;
+    let x = Array2::from_shape_fn((1, n_rows), |(_, i)| i as T / 10.0 - 2.0);
+    let y = Array1::from_shape_fn(n_rows, |i| 1.5 * x[(0, i)] * x[(0, i)] - 0.3);
+    let options = Options {
+        operators: OperatorLibrary::sr_default::<TestOps, D>(),
+        mutation_weights: MutationWeights {
+            do_nothing: 2.0,
+            ..Default::default()
+        },
+        mutation_adaptation: adaptation,
+        seed: 2,
+        populations: 3,
+        population_size: 30,
+        niterations: 4,
+        ncycles_per_iteration: 40,
+        maxsize: 12,
+        progress: false,
+        ..Default::default()
+    };
+    equation_search::<T, TestOps, D>(&Dataset::new(x, y), &options).mutation_stats
+}
+
+fn do_nothing_share(stats: &[MutationStats]) -> f64 {
+    let mut total = MutationStats::default();
+    stats.iter().for_each(|s| total.merge(s));
+    total.get(MutationChoice::DoNothing).attempts as f64 / total.total_attempts() as f64
+}
+
+#[test]
+fn search_reports_per_island_stats() {
+    let stats = search(MutationAdaptation::Off);
+    assert_eq!(stats.len(), 3);
+    for island in &stats {
+        assert!(island.total_attempts() > 0);
+        for (choice, c) in island.iter() {
+            assert!(
+                c.improved <= c.accepted && c.accepted <= c.attempts,
+                "{choice:?}: {c:?}"
+            );
+        }
+        assert_eq!(island.get(MutationChoice::DoNothing).improved, 0);
+    }
+}
+
+#[test]
+fn adaptation_samples_unhelpful_operators_less() {
+    let fixed = do_nothing_share(&search(MutationAdaptation::Off));
+    let adaptive = do_nothing_share(&search(MutationAdaptation::Softmax { temperature: 0.05 }));
+    assert!(adaptive < 0.75 * fixed, "fixed {fixed} vs adaptive {adaptive}");
+}
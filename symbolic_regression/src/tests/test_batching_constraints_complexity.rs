@@ -172,5 +172,5 @@ fn compute_complexity_respects_custom_weights_and_rounding() {
 
     // sin(x0): 3 + 2 = 5
     // add: 1 + 5 + 1 = 7
-    assert_eq!(compute_complexity(&expr.nodes, &options), 7);
+    assert_eq!(compute_complexity(&expr, &options), 7);
 }
@@ -0,0 +1,207 @@
+use std::collections::HashSet;
+
+use dynamic_expressions::expression::PostfixExpr;
+use dynamic_expressions::{GradContext, parse_expr};
+use fastrand::Rng;
+use ndarray::{Array1, Array2};
+
+use super::common::{D, T, TestOps};
+use crate::adaptive_mutation::MutationStats;
+use crate::adaptive_parsimony::RunningSearchStatistics;
+use crate::dataset::{Dataset, TaggedDataset};
+use crate::genealogy::Genealogy;
+use crate::mutate::{self, CrossoverCtx};
+use crate::mutation_functions::{
+    crossover_trees, homologous_crossover_trees, one_point_crossover_trees, size_fair_crossover_trees,
+};
+use crate::operator_library::OperatorLibrary;
+use crate::pop_member::{Evaluator, MemberId, PopMember};
+use crate::population::Population;
+use crate::single_iteration::{IterationCtx, s_r_cycle};
+use crate::{CrossoverWeights, Options, equation_search};
+
+type Expr = PostfixExpr<T, TestOps, D>;
+type Crossover = fn(&mut Rng, &Expr, &Expr) -> (Expr, Expr);
+
+fn expr(eq: &str) -> Expr {
+    parse_expr(eq, &[]).unwrap()
+}
+
+/// The distinct first children of `crossover(a, b)` over 200 seeds, as strings; checks that every
+/// crossover conserves the parents' nodes.
+fn first_children(crossover: Crossover, a: &str, b: &str) -> HashSet<String> {
+    let (a, b) = (expr(a), expr(b));
+    (0..200)
+        .map(|seed| {
+            let (c1, c2) = crossover(&mut Rng::with_seed(seed), &a, &b);
+            assert_eq!(c1.nodes.len() + c2.nodes.len(), a.nodes.len() + b.nodes.len());
+            c1.to_string()
+        })
+        .collect()
+}
+
+fn strings(eqs: impl IntoIterator<Item = String>) -> HashSet<String> {
+    eqs.into_iter().map(|eq| expr(&eq).to_string()).collect()
+}
+
+#[test]
+fn size_fair_crossover_bounds_the_donated_subtree() {
+    let big = "(x0 + x1) * (x1 - 2.0) + sin(x0)";
+    let sizes = |crossover: Crossover| {
+        let (a, b) = (expr("x0"), expr(big));
+        (0..200).map(move |seed| crossover(&mut Rng::with_seed(seed), &a, &b).0.nodes.len())
+    };
+    assert!(sizes(size_fair_crossover_trees).all(|n| n <= 3));
+    assert!(sizes(crossover_trees).any(|n| n > 3));
+}
+
+#[test]
+fn one_point_crossover_swaps_at_one_aligned_position() {
+    let children = first_children(one_point_crossover_trees, "sin(x0) + x1 * 2.0", "cos(x1) - 3.0");
+    let expected = strings(
+        [
+            "cos(x1) - 3.0",
+            "cos(x1) + x1 * 2.0",
+            "sin(x1) + x1 * 2.0",
+            "sin(x0) + 3.0",
+        ]
+        .map(String::from),
+    );
+    assert_eq!(children, expected);
+}
+
+#[test]
+fn homologous_crossover_mixes_every_aligned_position() {
+    let children = first_children(homologous_crossover_trees, "sin(x0) + x1 * 2.0", "cos(x1) - 3.0");
+    let mut expected = Vec::new();
+    for op in ["+", "-"] {
+        for f in ["sin", "cos"] {
+            for x in ["x0", "x1"] {
+                for rhs in ["x1 * 2.0", "3.0"] {
+                    expected.push(format!("{f}({x}) {op} {rhs}"));
+                }
+            }
+        }
+    }
+    assert_eq!(children, strings(expected));
+}
+
+#[test]
+fn hall_of_fame_crossover_takes_the_second_parent_from_the_hall() {
+    let x = Array2::from_shape_fn((2, 8), |(f, i)| (i + f) as T);
+    let dataset = Dataset::new(x, Array1::from_vec(vec![1.0; 8]));
+    let full_dataset = TaggedDataset::new(&dataset, None);
+    let options = Options::<T, D> {
+        operators: OperatorLibrary::sr_default::<TestOps, D>(),
+        crossover_weights: CrossoverWeights {
+            subtree: 0.0,
+            hall_of_fame: 1.0,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let member = |id, eq| PopMember::<T, TestOps, D>::from_expr(MemberId(id), None, id, expr(eq), 2);
+    let hall = [member(99, "cos(x0) * 3.0")];
+    let mut genealogy = Genealogy::default();
+
+    let (_, _, accepted, _) = mutate::crossover_generation(
+        &member(1, "x0 + x1"),
+        &member(2, "x1"),
+        CrossoverCtx {
+            rng: &mut Rng::with_seed(0),
+            dataset: full_dataset,
+            curmaxsize: options.maxsize,
+            options: &options,
+            evaluator: &mut Evaluator::new(dataset.n_rows),
+            next_id: &mut 100,
+            next_birth: &mut 100,
+            genealogy: Some(&mut genealogy),
+            hall_of_fame: &hall,
+            _ops: core::marker::PhantomData,
+        },
+    );
+    assert!(accepted);
+    let parents: Vec<Vec<u64>> = genealogy
+        .events()
+        .iter()
+        .map(|e| e.parents.iter().map(|p| p.0).collect())
+        .collect();
+    assert_eq!(parents, [vec![1, 99], vec![99, 1]]);
+}
+
+#[test]
+fn iterations_draw_hall_of_fame_donors_from_the_dispatched_snapshot() {
+    let x = Array2::from_shape_fn((2, 8), |(f, i)| (i + f) as T);
+    let dataset = Dataset::new(x, Array1::from_vec(vec![1.0; 8]));
+    let full_dataset = TaggedDataset::new(&dataset, None);
+    let options = Options::<T, D> {
+        operators: OperatorLibrary::sr_default::<TestOps, D>(),
+        crossover_probability: 1.0,
+        crossover_weights: CrossoverWeights {
+            subtree: 0.0,
+            hall_of_fame: 1.0,
+            ..Default::default()
+        },
+        ncycles_per_iteration: 4,
+        ..Default::default()
+    };
+    let mut evaluator = Evaluator::new(dataset.n_rows);
+    let mut member = |id, eq| {
+        let mut m = PopMember::<T, TestOps, D>::from_expr(MemberId(id), None, id, expr(eq), 2);
+        assert!(m.evaluate(&full_dataset, &options, &mut evaluator));
+        m
+    };
+    let hall = [member(99, "cos(x0) * 3.0")];
+    let mut pop = Population::new((1..=8).map(|id| member(id, "x0 + x1")).collect());
+    let stats = RunningSearchStatistics::new(options.maxsize, 1000);
+    let mut genealogy = Genealogy::default();
+    let mut ctx = IterationCtx {
+        rng: &mut Rng::with_seed(0),
+        full_dataset,
+        curmaxsize: options.maxsize,
+        stats: &stats,
+        options: &options,
+        evaluator: &mut evaluator,
+        grad_ctx: &mut GradContext::new(dataset.n_rows),
+        next_id: &mut 100,
+        next_birth: &mut 100,
+        mutation_stats: &mut MutationStats::default(),
+        genealogy: Some(&mut genealogy),
+        hall_of_fame: &hall,
+        _ops: core::marker::PhantomData,
+    };
+    s_r_cycle(&mut pop, &mut ctx, full_dataset);
+
+    // The population never holds member 99, so it can only come from the snapshot.
+    assert!(!genealogy.events().is_empty());
+    assert!(genealogy.events().iter().all(|e| e.parents.iter().any(|p| p.0 == 99)));
+}
+
+#[test]
+fn search_with_every_crossover_operator() {
+    let n_rows = 40;
+    let x = Array2::from_shape_fn((2, n_rows), |(f, i)| ((i * (f + 3)) % 13) as T / 6.0 - 1.0);
+    let y = Array1::from_shape_fn(n_rows, |i| x[(0, i)] * x[(1, i)] - x[(0, i)]);
+    let options = Options {
+        operators: OperatorLibrary::sr_default::<TestOps, D>(),
+        crossover_probability: 0.3,
+        crossover_weights: CrossoverWeights {
+            subtree: 1.0,
+            size_fair: 1.0,
+            one_point: 1.0,
+            homologous: 1.0,
+            hall_of_fame: 1.0,
+        },
+        seed: 1,
+        populations: 4,
+        population_size: 20,
+        niterations: 4,
+        ncycles_per_iteration: 100,
+        maxsize: 12,
+        progress: false,
+        ..Default::default()
+    };
+    let result = equation_search::<T, TestOps, D>(&Dataset::new(x, y), &options);
+    assert!(result.best.loss < 1e-12, "loss = {}", result.best.loss);
+    assert!(result.best.complexity <= options.maxsize);
+}
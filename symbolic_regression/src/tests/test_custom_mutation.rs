@@ -0,0 +1,229 @@
+use std::sync::Arc;
+
+use dynamic_expressions::expression::PostfixExpr;
+use dynamic_expressions::node::PNode;
+use dynamic_expressions::parse_expr;
+use fastrand::Rng;
+use ndarray::{Array1, Array2};
+
+use super::common::{D, T, TestOps};
+use crate::adaptive_mutation::MutationStats;
+use crate::adaptive_parsimony::RunningSearchStatistics;
+use crate::dataset::{Dataset, TaggedDataset};
+use crate::mutate::{self, MutationChoice, condition_mutation_weights};
+use crate::operator_library::OperatorLibrary;
+use crate::pop_member::{Evaluator, MemberId, PopMember};
+use crate::{Mutation, MutationContext, MutationWeights, Options, equation_search};
+
+/// Replaces `e` by `e op e` for a random binary operator.
+struct Duplicate;
+
+impl Mutation<T, D> for Duplicate {
+    fn mutate(&self, expr: &mut PostfixExpr<T, (), D>, ctx: &mut MutationContext<'_, T, D>) -> bool {
+        let op = ctx.operators.sample_op(ctx.rng, 2);
+        expr.nodes.extend_from_within(..);
+        expr.nodes.push(PNode::Op { arity: 2, op: op.id });
+        true
+    }
+}
+
+/// Shifts every feature index by one; it cannot apply to expressions without features.
+struct ShiftFeatures;
+
+impl Mutation<T, D> for ShiftFeatures {
+    fn mutate(&self, expr: &mut PostfixExpr<T, (), D>, ctx: &mut MutationContext<'_, T, D>) -> bool {
+        for node in &mut expr.nodes {
+            if let PNode::Var { feature } = node {
+                *feature = ((*feature as usize + 1) % ctx.n_features) as u16;
+            }
+        }
+        ctx.n_features > 1
+    }
+
+    fn condition_weight(&self, weight: f64, nodes: &[PNode]) -> f64 {
+        if nodes.iter().any(|n| matches!(n, PNode::Var { .. })) {
+            weight
+        } else {
+            0.0
+        }
+    }
+}
+
+fn custom_only(mutation: impl Mutation<T, D> + 'static) -> Options<T, D> {
+    Options {
+        operators: OperatorLibrary::sr_default::<TestOps, D>(),
+        mutation_weights: MutationWeights {
+            mutate_constant: 0.0,
+            mutate_operator: 0.0,
+            mutate_feature: 0.0,
+            swap_operands: 0.0,
+            rotate_tree: 0.0,
+            add_node: 0.0,
+            insert_node: 0.0,
+            delete_node: 0.0,
+            simplify: 0.0,
+            randomize: 0.0,
+            do_nothing: 0.0,
+            optimize: 0.0,
+            form_connection: 0.0,
+            break_connection: 0.0,
+        },
+        custom_mutations: vec![(Arc::new(mutation), 1.0)],
+        annealing: false,
+        ..Default::default()
+    }
+}
+
+fn member(id: u64, eq: &str) -> PopMember<T, TestOps, D> {
+    PopMember::from_expr(MemberId(id), None, id, parse_expr(eq, &[]).unwrap(), 2)
+}
+
+#[test]
+fn custom_weights_are_conditioned_per_expression() {
+    let options = custom_only(ShiftFeatures);
+    let conditioned = |eq| {
+        let mut weights = options.mutation_weights.clone();
+        let mut custom = vec![2.0];
+        condition_mutation_weights(&mut weights, &mut custom, &member(0, eq), &options, options.maxsize, 2);
+        custom
+    };
+    assert_eq!(conditioned("x0 * 1.5"), [2.0]);
+    assert_eq!(conditioned("1.5"), [0.0]);
+}
+
+#[test]
+fn custom_weights_are_looked_up_checked() {
+    let mut weights = MutationWeights::default();
+    let mut custom = [1.5];
+    assert_eq!(
+        MutationChoice::Custom(0).weight_mut(&mut weights, &mut custom).copied(),
+        Some(1.5)
+    );
+    assert_eq!(MutationChoice::Custom(1).weight_mut(&mut weights, &mut custom), None);
+    assert_eq!(
+        MutationChoice::AddNode.weight_mut(&mut weights, &mut []).copied(),
+        Some(MutationWeights::default().add_node)
+    );
+}
+
+/// A child of `eq` under `options`, with its size limit `curmaxsize`, and whether it was accepted.
+fn next_child(
+    options: &Options<T, D>,
+    eq: &str,
+    curmaxsize: usize,
+    mutation_stats: &mut MutationStats,
+) -> (PopMember<T, TestOps, D>, bool) {
+    let x = Array2::from_shape_fn((2, 8), |(f, i)| (i + f) as T);
+    let dataset = Dataset::new(x, Array1::from_vec(vec![0.0; 8]));
+    let full_dataset = TaggedDataset::new(&dataset, None);
+    let stats = RunningSearchStatistics::new(options.maxsize, 1000);
+    let mut evaluator = Evaluator::<T, D>::new(dataset.n_rows);
+    let (mut next_id, mut next_birth) = (1u64, 1u64);
+
+    let mut parent = member(0, eq);
+    assert!(parent.evaluate(&full_dataset, options, &mut evaluator));
+    let (child, accepted, _) = mutate::next_generation::<T, TestOps, D>(
+        &parent,
+        mutate::NextGenerationCtx {
+            rng: &mut Rng::with_seed(0),
+            dataset: full_dataset,
+            temperature: 1.0,
+            curmaxsize,
+            stats: &stats,
+            options,
+            evaluator: &mut evaluator,
+            next_id: &mut next_id,
+            next_birth: &mut next_birth,
+            mutation_stats,
+            genealogy: None,
+            _ops: core::marker::PhantomData,
+        },
+    );
+    (child, accepted)
+}
+
+#[test]
+fn custom_mutation_children_go_through_constraint_checks() {
+    let options = custom_only(Duplicate);
+    let mut mutation_stats = MutationStats::default();
+    let mut child_of = |eq: &str| {
+        let (child, accepted) = next_child(&options, eq, 5, &mut mutation_stats);
+        (child.expr.nodes.len(), accepted)
+    };
+    assert_eq!(child_of("x0"), (3, true));
+    assert!(!child_of("x0 + x1").1, "a child of size 7 exceeds curmaxsize = 5");
+
+    let counts = mutation_stats.get(MutationChoice::Custom(0));
+    assert_eq!((counts.attempts, counts.accepted), (2, 1));
+}
+
+/// Appends `nodes` to the expression, which leaves it malformed.
+struct Append(Vec<PNode>);
+
+impl Mutation<T, D> for Append {
+    fn mutate(&self, expr: &mut PostfixExpr<T, (), D>, _ctx: &mut MutationContext<'_, T, D>) -> bool {
+        expr.nodes.extend_from_slice(&self.0);
+        true
+    }
+}
+
+#[test]
+fn malformed_custom_mutation_children_are_rejected() {
+    let add = OperatorLibrary::sr_default::<TestOps, D>().ops_by_arity[1][0].id;
+    for nodes in [
+        vec![PNode::Var { feature: 1 }],
+        vec![PNode::Op { arity: 2, op: add }],
+        vec![PNode::Var { feature: 2 }, PNode::Op { arity: 2, op: add }],
+        vec![PNode::Const { idx: 1 }, PNode::Op { arity: 2, op: add }],
+        vec![PNode::Var { feature: 1 }, PNode::Op { arity: 2, op: u16::MAX }],
+    ] {
+        let (child, accepted) = next_child(
+            &custom_only(Append(nodes.clone())),
+            "x0 * 1.5",
+            10,
+            &mut MutationStats::default(),
+        );
+        assert!(!accepted, "{nodes:?}");
+        assert_eq!(child.expr.nodes, member(0, "x0 * 1.5").expr.nodes);
+    }
+
+    let valid = vec![PNode::Var { feature: 1 }, PNode::Op { arity: 2, op: add }];
+    assert!(
+        next_child(
+            &custom_only(Append(valid)),
+            "x0 * 1.5",
+            10,
+            &mut MutationStats::default()
+        )
+        .1
+    );
+}
+
+#[test]
+fn search_samples_custom_mutations() {
+    let n_rows = 30;
+    let x = Array2::from_shape_fn((2, n_rows), |(f, i)| ((i * (f + 3)) % 11) as T / 5.0 - 1.0);
+    let y = Array1::from_shape_fn(n_rows, |i| x[(1, i)] * x[(1, i)] - x[(0, i)]);
+    let options = Options {
+        operators: OperatorLibrary::sr_default::<TestOps, D>(),
+        custom_mutations: vec![(Arc::new(ShiftFeatures), 1.0)],
+        deterministic: true,
+        seed: 3,
+        populations: 4,
+        population_size: 20,
+        niterations: 4,
+        ncycles_per_iteration: 100,
+        maxsize: 12,
+        progress: false,
+        ..Default::default()
+    };
+    let result = equation_search::<T, TestOps, D>(&Dataset::new(x, y), &options);
+
+    let counts = result
+        .mutation_stats
+        .iter()
+        .map(|s| s.get(MutationChoice::Custom(0)))
+        .fold((0, 0), |(a, b), c| (a + c.attempts, b + c.accepted));
+    assert!(counts.0 > 0 && counts.1 > 0, "{counts:?}");
+    assert!(result.best.loss < 1e-12, "loss = {}", result.best.loss);
+}
@@ -0,0 +1,83 @@
+use dynamic_expressions::postfix_string;
+use ndarray::{Array1, Array2};
+
+use super::common::{D, T, TestOps};
+use crate::dataset::Dataset;
+use crate::operator_library::OperatorLibrary;
+use crate::{
+    Endpoint, Options, SearchResult, WorkerListener, equation_search, equation_search_distributed, run_worker,
+};
+
+fn dataset() -> Dataset<T> {
+    let n_rows = 60;
+    let x = Array2::from_shape_fn((2, n_rows), |(f, i)| ((i * (2 * f + 5)) % 23) as T / 11.0 - 1.0);
+    let y = Array1::from_shape_fn(n_rows, |i| (x[(0, i)] * 1.7).cos() + x[(1, i)] * x[(0, i)]);
+    Dataset::new(x, y)
+}
+
+fn options() -> Options<T, D> {
+    Options {
+        operators: OperatorLibrary::sr_default::<TestOps, D>(),
+        deterministic: true,
+        use_recorder: true,
+        seed: 11,
+        populations: 6,
+        population_size: 20,
+        niterations: 3,
+        ncycles_per_iteration: 20,
+        maxsize: 14,
+        fraction_replaced: 0.1,
+        batching: true,
+        batch_size: 20,
+        progress: false,
+        ..Default::default()
+    }
+}
+
+/// Every hall-of-fame entry, exactly.
+fn fingerprint(result: &SearchResult<T, TestOps, D>) -> Vec<(usize, u64, String)> {
+    result
+        .hall_of_fame
+        .members()
+        .map(|m| (m.complexity, m.loss.to_bits(), postfix_string(&m.expr)))
+        .collect()
+}
+
+fn search_on_pool(n_threads: usize) -> Vec<(usize, u64, String)> {
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(n_threads).build().unwrap();
+    // Inside the pool one thread orchestrates, so 2 threads means a single worker.
+    let result = pool.install(|| equation_search::<T, TestOps, D>(&dataset(), &options()));
+    fingerprint(&result)
+}
+
+#[test]
+fn results_do_not_depend_on_pool_size() {
+    let single_worker = search_on_pool(2);
+    assert!(!single_worker.is_empty());
+    assert_eq!(single_worker, search_on_pool(5));
+    assert_eq!(single_worker, search_on_pool(8));
+}
+
+#[cfg(unix)]
+#[test]
+fn distributed_matches_in_process_search() {
+    let local = equation_search::<T, TestOps, D>(&dataset(), &options());
+
+    let path = std::env::temp_dir().join(format!("sr-deterministic-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let listener = WorkerListener::bind(&Endpoint::Unix(path)).unwrap();
+    let endpoint = listener.endpoint().unwrap();
+    let workers: Vec<_> = (0..3)
+        .map(|_| {
+            let endpoint = endpoint.clone();
+            std::thread::spawn(move || run_worker::<T, TestOps, D>(&endpoint, &dataset(), &options()))
+        })
+        .collect();
+    let remote = equation_search_distributed::<T, TestOps, D>(&dataset(), &options(), &listener, 3).unwrap();
+    for w in workers {
+        w.join().unwrap().unwrap();
+    }
+    assert_eq!(fingerprint(&local), fingerprint(&remote));
+    assert_eq!(local.mutation_stats, remote.mutation_stats);
+    assert_eq!(local.genealogy, remote.genealogy);
+}
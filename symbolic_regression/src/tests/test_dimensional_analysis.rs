@@ -0,0 +1,155 @@
+use dynamic_expressions::expression::PostfixExpr;
+use dynamic_expressions::operator_enum::presets::BuiltinOpsF64;
+use dynamic_expressions::parse_expr;
+use fastrand::Rng;
+use ndarray::{Array1, Array2};
+
+use crate::constant_optimization::{OptimizeConstantsCtx, optimize_constants};
+use crate::dataset::{Dataset, TaggedDataset};
+use crate::dimensional_analysis::{
+    Units, violates_dimensional_constraints, violates_parametric_dimensional_constraints,
+};
+use crate::options::Options;
+use crate::pop_member::{Evaluator, MemberId, PopMember};
+
+type Expr = PostfixExpr<f64, BuiltinOpsF64, 3>;
+
+fn units(s: &str) -> Units {
+    Units::parse(s).unwrap()
+}
+
+/// x0 in metres, x1 in seconds, x2 dimensionless; target is a velocity.
+fn violates(equation: &str, dimensionless_constants_only: bool) -> bool {
+    let expr: Expr = parse_expr(equation, &[]).unwrap();
+    let x_units = [units("m"), units("s"), units("1")];
+    violates_dimensional_constraints(&expr, Some(&x_units), Some(&units("m/s")), dimensionless_constants_only)
+}
+
+#[test]
+fn parses_unit_strings() {
+    assert_eq!(units("kg*m^2/s^2"), units("J"));
+    assert_eq!(units("m s^-1"), units("m/s"));
+    assert_eq!(units("N/m^2"), units("Pa"));
+    assert!(units("").is_dimensionless() && units("1").is_dimensionless());
+    assert_eq!(units("m^0.5").exponents[0], 0.5);
+    assert!(Units::parse("furlong").is_none());
+    assert!(Units::parse("m^x").is_none());
+}
+
+#[test]
+fn propagates_arithmetic_units() {
+    assert!(!violates("x0 / x1", false));
+    assert!(!violates("(x0 / x1) + (x0 / x1)", false));
+    assert!(violates("x0 + x1", false));
+    assert!(violates("x0 * x1", false));
+    assert!(!violates("sqrt(x0 * x0) / x1", false));
+    assert!(!violates("pow(x0, 2.0) / (x0 * x1)", false));
+    assert!(violates("pow(x0, x2) / x1", false));
+}
+
+#[test]
+fn transcendental_functions_require_dimensionless_arguments() {
+    assert!(!violates("(x0 / x1) * cos(x2)", false));
+    assert!(violates("(x0 / x1) * cos(x0)", false));
+    assert!(!violates("(x0 / x1) * exp(x0 / (x1 * (x0 / x1)))", false));
+}
+
+#[test]
+fn constants_are_wildcards_unless_disabled() {
+    assert!(!violates("x0 * 2.5", false));
+    assert!(!violates("(x0 / x1) + 1.0", false));
+    assert!(violates("(x0 / x1) + 1.0", true));
+    assert!(violates("x0 * 2.5", true));
+    assert!(!violates("(x0 / x1) * 2.5", true));
+}
+
+#[test]
+fn parameters_are_wildcards_like_constants() {
+    // x0 in metres, x1 in seconds; x2 is a parametric-expression parameter.
+    let x_units = [units("m"), units("s")];
+    let violates = |equation: &str, dimensionless_constants_only: bool| {
+        let expr: Expr = parse_expr(equation, &[]).unwrap();
+        violates_parametric_dimensional_constraints(
+            &expr,
+            Some(&x_units),
+            2,
+            Some(&units("m/s")),
+            dimensionless_constants_only,
+        )
+    };
+    assert!(!violates("x2 * x0", false));
+    assert!(!violates("(x0 / x1) + x2", false));
+    assert!(violates("(x0 / x1) + x2", true));
+    assert!(violates("x0 + x1", false));
+}
+
+#[test]
+fn evaluate_adds_penalty_for_violations() {
+    let x = Array2::from_shape_vec((2, 3), vec![1.0, 2.0, 3.0, 0.5, 1.0, 2.0]).unwrap();
+    let y = Array1::from_vec(vec![2.0, 2.0, 1.5]);
+    let plain = Dataset::new(x, y);
+    let with_units = plain
+        .clone()
+        .with_units(Some(vec![units("m"), units("s")]), Some(units("m/s")));
+    let options = Options::<f64, 3> {
+        dimensional_constraint_penalty: 100.0,
+        ..Default::default()
+    };
+    let mut evaluator = Evaluator::new(3);
+    let mut losses_of = |equation: &str, dataset: &Dataset<f64>| {
+        let expr: Expr = parse_expr(equation, &[]).unwrap();
+        let mut m = PopMember::from_expr(MemberId(0), None, 0, expr, 2);
+        assert!(m.evaluate(&TaggedDataset::new(dataset, None), &options, &mut evaluator));
+        m.evaluate_validation(dataset, &options, &mut evaluator);
+        (m.loss, m.validation_loss.unwrap())
+    };
+
+    assert_eq!(losses_of("x0 / x1", &with_units).0, 0.0);
+    let (bad_plain, _) = losses_of("x0 - x1", &plain);
+    let (bad_loss, bad_validation_loss) = losses_of("x0 - x1", &with_units);
+    assert_eq!(bad_loss, bad_plain + 100.0);
+    // The validation loss measures the fit alone.
+    assert_eq!(bad_validation_loss, bad_plain);
+
+    // Units survive row selection (used for batching and validation splits).
+    let subset = with_units.select_rows(&[0, 2]);
+    assert_eq!(subset.y_units, Some(units("m/s")));
+    assert_eq!(subset.x_units.as_deref().map(<[Units]>::len), Some(2));
+}
+
+#[test]
+fn constant_optimizer_minimizes_the_penalized_loss() {
+    // y = x0^1.2 / x1: the raw fit pulls the exponent of `pow(x0, 1.0) / x1` away from 1, which
+    // breaks the units and costs far more than it gains.
+    let x = Array2::from_shape_fn((2, 20), |(f, i)| if f == 0 { 1.0 + i as f64 / 4.0 } else { 2.0 });
+    let y = Array1::from_shape_fn(20, |i| x[(0, i)].powf(1.2) / x[(1, i)]);
+    let dataset = Dataset::new(x, y).with_units(Some(vec![units("m"), units("s")]), Some(units("m/s")));
+    let full = TaggedDataset::new(&dataset, None);
+    let options = Options::<f64, 3> {
+        dimensional_constraint_penalty: 100.0,
+        optimizer_nrestarts: 0,
+        ..Default::default()
+    };
+    let mut evaluator = Evaluator::new(dataset.n_rows);
+    let mut grad_ctx = dynamic_expressions::GradContext::new(dataset.n_rows);
+    let expr: Expr = parse_expr("pow(x0, 1.0) / x1", &[]).unwrap();
+    let mut m = PopMember::from_expr(MemberId(0), None, 0, expr, 2);
+    assert!(m.evaluate(&full, &options, &mut evaluator));
+    let before = m.loss;
+    assert!(before < 100.0);
+
+    optimize_constants(
+        &mut Rng::with_seed(0),
+        &mut m,
+        OptimizeConstantsCtx {
+            dataset: full,
+            options: &options,
+            evaluator: &mut evaluator,
+            grad_ctx: &mut grad_ctx,
+            next_birth: &mut 1,
+        },
+    );
+    // Only moves within the unit check's tolerance are worth taking.
+    assert!(m.loss <= before, "loss = {}", m.loss);
+    assert!((m.expr.consts[0] - 1.0).abs() < 1e-6, "{:?}", m.expr.consts);
+}
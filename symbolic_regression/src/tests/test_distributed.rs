@@ -0,0 +1,182 @@
+use std::process::{Command, Stdio};
+
+use dynamic_expressions::expression::PostfixExpr;
+use dynamic_expressions::{GradContext, parse_expr};
+use fastrand::Rng;
+use ndarray::{Array1, Array2};
+
+use super::common::{D, T, TestOps};
+use crate::adaptive_mutation::MutationStats;
+use crate::adaptive_parsimony::RunningSearchStatistics;
+use crate::dataset::Dataset;
+use crate::distributed::{decode_task, encode_task};
+use crate::operator_library::OperatorLibrary;
+use crate::pop_member::{Evaluator, MemberId, PopMember};
+use crate::population::Population;
+use crate::search_utils::PopState;
+use crate::{
+    Endpoint, LinearScaling, MutationChoice, Options, WorkerListener, equation_search_distributed, run_worker,
+};
+
+const WORKER_ENV: &str = "SR_TEST_DISTRIBUTED_ENDPOINT";
+
+fn dataset() -> Dataset<T> {
+    let n_rows = 40;
+    let x = Array2::from_shape_fn((2, n_rows), |(f, i)| ((i * (f + 3)) % 13) as T / 6.0 - 1.0);
+    let y = Array1::from_shape_fn(n_rows, |i| x[(0, i)] * x[(0, i)] - 0.5 * x[(1, i)]);
+    Dataset::new(x, y)
+}
+
+fn options() -> Options<T, D> {
+    Options {
+        operators: OperatorLibrary::sr_default::<TestOps, D>(),
+        seed: 5,
+        populations: 4,
+        population_size: 20,
+        niterations: 2,
+        ncycles_per_iteration: 20,
+        maxsize: 12,
+        progress: false,
+        ..Default::default()
+    }
+}
+
+fn pop_state(members: Vec<PopMember<T, TestOps, D>>, seed: u64) -> PopState<T, TestOps, D> {
+    PopState {
+        pop: Population::new(members),
+        evaluator: Evaluator::new(4),
+        grad_ctx: GradContext::new(4),
+        rng: Rng::with_seed(seed),
+        batch_dataset: None,
+        next_id: 17,
+        next_birth: 23,
+        mutation_stats: MutationStats::default(),
+        genealogy: None,
+    }
+}
+
+#[test]
+fn task_frames_round_trip_exactly() {
+    let expr: PostfixExpr<T, TestOps, D> = parse_expr("sin(x0 * 0.1) - x2 / 3.0", &[]).unwrap();
+    let mut a = PopMember::from_expr(MemberId(7), Some(MemberId(3)), 11, expr, 3);
+    a.expr.meta.graph_ids = vec![0, 1, 2, 3, 4, 1, 5, 6];
+    a.complexity = 8;
+    a.loss = 0.1 + 0.2;
+    a.cost = T::INFINITY;
+    a.parameters = vec![1.0 / 3.0, -2.5];
+    a.scaling = Some(LinearScaling {
+        scale: 0.1 + 0.2,
+        offset: -7.0,
+    });
+    let mut b = PopMember::from_expr(MemberId(8), None, 12, parse_expr("x1", &[]).unwrap(), 3);
+    b.subexprs = vec![parse_expr("x0 + 1.0", &[]).unwrap()];
+
+    let stats = RunningSearchStatistics::new(6, 1000);
+    let mut src = pop_state(vec![a.clone(), b.clone()], 99);
+    src.mutation_stats.record(MutationChoice::AddNode, true, true);
+    src.mutation_stats.record(MutationChoice::Simplify, false, false);
+    src.mutation_stats.record(MutationChoice::Custom(1), true, false);
+    let donor = PopMember::from_expr(MemberId(9), None, 13, parse_expr("cos(x1)", &[]).unwrap(), 3);
+    let text = encode_task(2, 9, &stats, std::slice::from_ref(&donor), &src);
+
+    let mut st = pop_state(Vec::new(), 0);
+    let (pop_idx, curmaxsize, decoded_stats, donors) = decode_task(&text, &mut st, 3).unwrap();
+    assert_eq!((pop_idx, curmaxsize), (2, 9));
+    let [d] = &donors[..] else {
+        panic!("expected one donor");
+    };
+    assert_eq!((d.id, d.expr.nodes.clone()), (donor.id, donor.expr.nodes));
+    assert_eq!(decoded_stats.frequencies, stats.frequencies);
+    assert_eq!(decoded_stats.normalized_frequencies, stats.normalized_frequencies);
+    assert_eq!(st.rng.get_seed(), Rng::with_seed(99).get_seed());
+    assert_eq!((st.next_id, st.next_birth), (17, 23));
+    assert_eq!(st.mutation_stats, src.mutation_stats);
+
+    let [da, db] = &st.pop.members[..] else {
+        panic!("expected two members");
+    };
+    assert_eq!(
+        (da.id, da.parent, da.birth, da.complexity),
+        (a.id, a.parent, a.birth, a.complexity)
+    );
+    assert_eq!(da.loss.to_bits(), a.loss.to_bits());
+    assert_eq!(da.cost, a.cost);
+    assert_eq!(da.parameters, a.parameters);
+    assert_eq!(da.scaling, a.scaling);
+    assert_eq!(da.expr.nodes, a.expr.nodes);
+    assert_eq!(da.expr.consts, a.expr.consts);
+    assert_eq!(da.expr.meta.graph_ids, a.expr.meta.graph_ids);
+    assert_eq!((db.parent, db.scaling), (None, None));
+    assert!(db.expr.meta.graph_ids.is_empty());
+    assert_eq!(db.subexprs[0].nodes, b.subexprs[0].nodes);
+}
+
+#[test]
+fn mismatched_workers_are_rejected() {
+    let listener = WorkerListener::bind(&"tcp:127.0.0.1:0".parse().unwrap()).unwrap();
+    let endpoint = listener.endpoint().unwrap();
+    let worker = std::thread::spawn(move || {
+        let other = Dataset::new(Array2::zeros((3, 5)), Array1::zeros(5));
+        run_worker::<T, TestOps, D>(&endpoint, &other, &options())
+    });
+    let err = equation_search_distributed::<T, TestOps, D>(&dataset(), &options(), &listener, 1)
+        .err()
+        .unwrap();
+    assert!(matches!(err, crate::DistributedError::WorkerMismatch { .. }), "{err}");
+    drop(listener);
+    let _ = worker.join();
+}
+
+#[cfg(unix)]
+#[test]
+fn search_over_unix_socket_with_thread_workers() {
+    let path = std::env::temp_dir().join(format!("sr-distributed-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let listener = WorkerListener::bind(&Endpoint::Unix(path)).unwrap();
+    let endpoint = listener.endpoint().unwrap();
+
+    let workers: Vec<_> = (0..2)
+        .map(|_| {
+            let endpoint = endpoint.clone();
+            std::thread::spawn(move || run_worker::<T, TestOps, D>(&endpoint, &dataset(), &options()))
+        })
+        .collect();
+    let result = equation_search_distributed::<T, TestOps, D>(&dataset(), &options(), &listener, 2).unwrap();
+    for w in workers {
+        w.join().unwrap().unwrap();
+    }
+    assert!(result.best.loss.is_finite());
+    assert!(result.hall_of_fame.members().count() > 0);
+}
+
+/// Worker entry point for `search_over_tcp_with_worker_processes`; a no-op in normal test runs.
+#[test]
+fn worker_process() {
+    if let Ok(endpoint) = std::env::var(WORKER_ENV) {
+        run_worker::<T, TestOps, D>(&endpoint.parse().unwrap(), &dataset(), &options()).unwrap();
+    }
+}
+
+#[test]
+fn search_over_tcp_with_worker_processes() {
+    let listener = WorkerListener::bind(&"tcp:127.0.0.1:0".parse().unwrap()).unwrap();
+    let endpoint = listener.endpoint().unwrap();
+    let exe = std::env::current_exe().unwrap();
+    let mut children: Vec<_> = (0..2)
+        .map(|_| {
+            Command::new(&exe)
+                .args(["--exact", "tests::test_distributed::worker_process", "--test-threads=1"])
+                .env(WORKER_ENV, endpoint.to_string())
+                .stdout(Stdio::null())
+                .spawn()
+                .unwrap()
+        })
+        .collect();
+
+    let result = equation_search_distributed::<T, TestOps, D>(&dataset(), &options(), &listener, 2).unwrap();
+    for child in &mut children {
+        assert!(child.wait().unwrap().success());
+    }
+    assert!(result.best.loss.is_finite());
+    assert!(result.best.loss < 0.5, "loss = {}", result.best.loss);
+}
@@ -0,0 +1,80 @@
+use dynamic_expressions::node::PNode;
+use ndarray::{Array1, Array2};
+
+use super::common::{D, T, TestOps};
+use crate::dataset::Dataset;
+use crate::dimensional_analysis::Units;
+use crate::operator_library::OperatorLibrary;
+use crate::{Options, equation_search, mutual_information_scores, select_k_features};
+
+/// Five features where only `x3` matters: `y = 2 x3^2 - x3`.
+fn dataset() -> Dataset<T> {
+    let n_rows = 200;
+    let mut rng = fastrand::Rng::with_seed(0);
+    let x = Array2::from_shape_fn((5, n_rows), |_| rng.f64() * 4.0 - 2.0);
+    let y = Array1::from_shape_fn(n_rows, |i| 2.0 * x[(3, i)] * x[(3, i)] - x[(3, i)]);
+    let names = (0..5).map(|f| format!("v{f}")).collect();
+    Dataset::with_weights_and_names(x, y, None, names)
+}
+
+#[test]
+fn mutual_information_ranks_the_relevant_feature_first() {
+    let data = dataset();
+    let scores = mutual_information_scores(&data);
+    assert_eq!(scores.len(), 5);
+    for (f, s) in scores.iter().enumerate() {
+        if f != 3 {
+            assert!(scores[3] > 2.0 * s, "{scores:?}");
+        }
+    }
+    assert_eq!(select_k_features(&data, 1), vec![3]);
+    assert_eq!(select_k_features(&data, 9).len(), 5);
+}
+
+#[test]
+fn select_features_keeps_names_and_units() {
+    let units: Vec<Units> = ["m", "s", "kg", "m", "1"]
+        .iter()
+        .map(|u| Units::parse(u).unwrap())
+        .collect();
+    let data = dataset().with_units(Some(units.clone()), None);
+    let reduced = data.select_features(&[1, 3]);
+    assert_eq!(reduced.n_features, 2);
+    assert_eq!(reduced.n_rows, data.n_rows);
+    assert_eq!(reduced.variable_names, vec!["v1".to_string(), "v3".to_string()]);
+    assert_eq!(reduced.x_units, Some(vec![units[1], units[3]]));
+    assert_eq!(reduced.x.row(1), data.x.row(3));
+    assert_eq!(reduced.y, data.y);
+}
+
+#[test]
+fn equation_search_maps_selected_features_back() {
+    let data = dataset();
+    let options = Options {
+        operators: OperatorLibrary::sr_default::<TestOps, D>(),
+        select_k_features: 2,
+        seed: 0,
+        populations: 2,
+        population_size: 30,
+        niterations: 3,
+        ncycles_per_iteration: 30,
+        maxsize: 12,
+        progress: false,
+        ..Default::default()
+    };
+    let selected = select_k_features(&data, 2);
+    assert!(selected.contains(&3));
+
+    let result = equation_search::<T, TestOps, D>(&data, &options);
+    assert!(result.best.loss.is_finite());
+    let mut uses_x3 = false;
+    for m in result.hall_of_fame.members().chain([&result.best]) {
+        for n in &m.expr.nodes {
+            if let PNode::Var { feature } = *n {
+                assert!(selected.contains(&usize::from(feature)), "{:?}", m.expr.nodes);
+                uses_x3 |= feature == 3;
+            }
+        }
+    }
+    assert!(uses_x3);
+}
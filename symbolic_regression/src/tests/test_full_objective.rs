@@ -0,0 +1,111 @@
+use std::sync::Arc;
+
+use dynamic_expressions::expression::{Metadata, PostfixExpr};
+use dynamic_expressions::node::PNode;
+use fastrand::Rng;
+use ndarray::{Array1, Array2};
+
+use super::common::{D, T, TestOps};
+use crate::constant_optimization::{OptimizeConstantsCtx, optimize_constants};
+use crate::dataset::{Dataset, TaggedDataset};
+use crate::loss_functions::baseline_loss;
+use crate::operator_library::OperatorLibrary;
+use crate::pop_member::{Evaluator, MemberId, PopMember};
+use crate::{ExprEvaluator, FullObjective, Options};
+
+fn var(feature: u16) -> PostfixExpr<T, TestOps, D> {
+    PostfixExpr::new(vec![PNode::Var { feature }], Vec::new(), Metadata::default())
+}
+
+fn linear_dataset(slope: T) -> Dataset<T> {
+    let n_rows = 32;
+    let x: Vec<T> = (0..n_rows).map(|i| (i as T) / (n_rows as T)).collect();
+    let y: Vec<T> = x.iter().map(|&xi| slope * xi).collect();
+    Dataset::new(Array2::from_shape_vec((1, n_rows), x).unwrap(), Array1::from_vec(y))
+}
+
+/// MSE plus a penalty pulling d(expr)/dx0 towards `target_slope`.
+struct SlopePenalty {
+    target_slope: T,
+}
+
+impl FullObjective<T> for SlopePenalty {
+    fn loss(&self, expr: &mut dyn ExprEvaluator<T>, dataset: &Dataset<T>) -> T {
+        let Some((yhat, dydx)) = expr.eval_diff(dataset.x.view(), 0) else {
+            return T::INFINITY;
+        };
+        let n = yhat.len() as T;
+        let mse = yhat
+            .iter()
+            .zip(dataset.y_slice())
+            .map(|(a, b)| (a - b).powi(2))
+            .sum::<T>()
+            / n;
+        let penalty = dydx.iter().map(|d| (d - self.target_slope).powi(2)).sum::<T>() / n;
+        mse + penalty
+    }
+}
+
+#[test]
+fn full_objective_replaces_loss_in_evaluate_and_baseline() {
+    let dataset = linear_dataset(2.0);
+    let options = Options::<T, D> {
+        operators: OperatorLibrary::sr_default::<TestOps, D>(),
+        full_objective: Some(Arc::new(SlopePenalty { target_slope: 3.0 })),
+        ..Default::default()
+    };
+
+    // Zero expression: MSE is mean(y^2), derivative is 0 everywhere.
+    let mean_y2 = dataset.y.iter().map(|v| v * v).sum::<T>() / dataset.n_rows as T;
+    let base = baseline_loss::<T, TestOps, D>(&dataset, &options).unwrap();
+    assert!((base - (mean_y2 + 9.0)).abs() < 1e-12);
+
+    // x0 matches the data exactly apart from the slope (1 vs 2), and the penalty (1 - 3)^2.
+    let mut member = PopMember::from_expr(MemberId(0), None, 0, var(0), dataset.n_features);
+    let mut evaluator = Evaluator::<T, D>::new(dataset.n_rows);
+    let full_dataset = TaggedDataset::new(&dataset, Some(base));
+    assert!(member.evaluate(&full_dataset, &options, &mut evaluator));
+    let mean_x2 = dataset.x.iter().map(|v| v * v).sum::<T>() / dataset.n_rows as T;
+    assert!((member.loss - (mean_x2 + 4.0)).abs() < 1e-12);
+}
+
+#[test]
+fn constant_optimization_uses_finite_difference_fallback() {
+    let dataset = linear_dataset(2.0);
+    let options = Options::<T, D> {
+        operators: OperatorLibrary::sr_default::<TestOps, D>(),
+        full_objective: Some(Arc::new(SlopePenalty { target_slope: 2.0 })),
+        should_optimize_constants: true,
+        optimizer_iterations: 100,
+        optimizer_nrestarts: 0,
+        ..Default::default()
+    };
+
+    let expr = PostfixExpr::<T, TestOps, D>::zero() * var(0);
+    let mut member = PopMember::from_expr(MemberId(0), None, 0, expr, dataset.n_features);
+    member.expr.consts[0] = 0.5;
+    let mut evaluator = Evaluator::<T, D>::new(dataset.n_rows);
+    let mut grad_ctx = dynamic_expressions::GradContext::<T, D>::new(dataset.n_rows);
+    let full_dataset = TaggedDataset::new(&dataset, None);
+    assert!(member.evaluate(&full_dataset, &options, &mut evaluator));
+
+    let mut next_birth = 1;
+    let (improved, _) = optimize_constants::<T, TestOps, D>(
+        &mut Rng::with_seed(0),
+        &mut member,
+        OptimizeConstantsCtx {
+            dataset: full_dataset,
+            options: &options,
+            evaluator: &mut evaluator,
+            grad_ctx: &mut grad_ctx,
+            next_birth: &mut next_birth,
+        },
+    );
+    assert!(improved);
+    assert!(
+        (member.expr.consts[0] - 2.0).abs() < 1e-4,
+        "c = {}",
+        member.expr.consts[0]
+    );
+    assert!(member.loss < 1e-8);
+}
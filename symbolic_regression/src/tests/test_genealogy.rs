@@ -0,0 +1,123 @@
+use ndarray::{Array1, Array2};
+
+use super::common::{D, T, TestOps};
+use crate::dataset::Dataset;
+use crate::operator_library::OperatorLibrary;
+use crate::{
+    Genealogy, GenealogyEvent, GenealogyEventKind, MemberId, MutationChoice, Options, SearchResult, equation_search,
+};
+
+fn options() -> Options<T, D> {
+    Options {
+        operators: OperatorLibrary::sr_default::<TestOps, D>(),
+        use_recorder: true,
+        seed: 3,
+        populations: 3,
+        population_size: 20,
+        niterations: 3,
+        ncycles_per_iteration: 20,
+        maxsize: 12,
+        crossover_probability: 0.2,
+        progress: false,
+        ..Default::default()
+    }
+}
+
+fn search(options: &Options<T, D>) -> SearchResult<T, TestOps, D> {
+    let n_rows = 40;
+    let x = Array2::from_shape_fn((2, n_rows), |(f, i)| ((i * (f + 2)) % 11) as T / 5.0 - 1.0);
+    let y = Array1::from_shape_fn(n_rows, |i| x[(0, i)] * x[(1, i)] + 0.5);
+    equation_search::<T, TestOps, D>(&Dataset::new(x, y), options)
+}
+
+fn event(kind: GenealogyEventKind, child: u64, parents: &[u64]) -> GenealogyEvent {
+    GenealogyEvent {
+        kind,
+        child: MemberId(child),
+        parents: parents.iter().copied().map(MemberId).collect(),
+        accepted: true,
+        loss_before: Some(1.0),
+        loss_after: Some(0.5),
+    }
+}
+
+/// 10 and 11 are initial members; 12 is a rejected mutation of 10 and crosses with 11.
+fn genealogy() -> Genealogy {
+    let mut g = Genealogy::default();
+    g.record(GenealogyEvent {
+        accepted: false,
+        loss_after: None,
+        ..event(GenealogyEventKind::Mutation(MutationChoice::AddNode), 12, &[10])
+    });
+    g.record(event(GenealogyEventKind::Mutation(MutationChoice::Simplify), 20, &[11]));
+    g.record(event(GenealogyEventKind::Crossover, 13, &[12, 11]));
+    g.record(event(GenealogyEventKind::Crossover, 14, &[11, 12]));
+    g.record(event(GenealogyEventKind::Migration, 15, &[13]));
+    g
+}
+
+#[test]
+fn ancestry_includes_both_crossover_parents() {
+    let g = genealogy();
+    let children: Vec<u64> = g.ancestry(MemberId(15)).events().iter().map(|e| e.child.0).collect();
+    assert_eq!(children, [12, 13, 15]);
+    assert!(g.ancestry(MemberId(10)).is_empty());
+    assert_eq!(
+        g.lineage(MemberId(15)),
+        [MemberId(15), MemberId(13), MemberId(12), MemberId(10)]
+    );
+    assert_eq!(g.birth_of(MemberId(14)).unwrap().parents, [MemberId(11), MemberId(12)]);
+}
+
+#[test]
+fn search_records_the_ancestry_of_hall_of_fame_members() {
+    let options = options();
+    let result = search(&options);
+    let genealogy = result.genealogy.as_ref().unwrap();
+    let kinds = |f: fn(&GenealogyEventKind) -> bool| genealogy.events().iter().filter(|e| f(&e.kind)).count();
+    assert!(kinds(|k| matches!(k, GenealogyEventKind::Mutation(_))) > 0);
+    assert!(kinds(|k| *k == GenealogyEventKind::Crossover) > 0);
+    assert!(kinds(|k| *k == GenealogyEventKind::Migration) > 0);
+    assert!(genealogy.events().iter().any(|e| !e.accepted));
+
+    let initial = |id: MemberId| (id.0 & 0xffff_ffff) < options.population_size as u64;
+    for m in result.hall_of_fame.members() {
+        let lineage = genealogy.lineage(m.id);
+        assert!(initial(*lineage.last().unwrap()), "{lineage:?}");
+        if let Some(birth) = genealogy.birth_of(m.id) {
+            assert_eq!(Some(birth.parents[0]), m.parent);
+        }
+
+        // Parents are born before their children, back to initial members.
+        let ancestry = result.ancestry(m).unwrap();
+        let mut born = std::collections::HashSet::new();
+        for e in ancestry.events() {
+            for p in &e.parents {
+                assert!(born.contains(p) || initial(*p), "{e:?}");
+            }
+            born.insert(e.child);
+        }
+        assert_eq!(ancestry.events().last().map(|e| e.child), lineage.get(1).map(|_| m.id));
+    }
+}
+
+#[test]
+fn recorder_is_off_by_default() {
+    let options = Options {
+        use_recorder: false,
+        ..options()
+    };
+    let result = search(&options);
+    assert!(result.genealogy.is_none());
+    assert!(result.ancestry(&result.best).is_none());
+}
+
+#[cfg(feature = "serde_json")]
+#[test]
+fn json_lines_round_trip() {
+    let g = genealogy();
+    let text = g.to_json_lines();
+    assert_eq!(text.lines().count(), g.len());
+    assert!(text.contains(r#"{"Mutation":"AddNode"}"#), "{text}");
+    assert_eq!(Genealogy::from_json_lines(&text).unwrap(), g);
+}
@@ -0,0 +1,256 @@
+use dynamic_expressions::expression::PostfixExpr;
+use dynamic_expressions::node::PNode;
+use dynamic_expressions::{inherit_sharing, parse_expr};
+use fastrand::Rng;
+use ndarray::{Array1, Array2};
+
+use super::common::{D, T, TestOps};
+use crate::check_constraints::check_constraints;
+use crate::complexity::compute_complexity;
+use crate::constant_optimization::{OptimizeConstantsCtx, optimize_constants};
+use crate::dataset::TaggedDataset;
+use crate::hall_of_fame::HallOfFame;
+use crate::mutate::condition_mutation_weights;
+use crate::mutation_functions::{break_connection_in_place, form_connection_in_place};
+use crate::operator_library::OperatorLibrary;
+use crate::pop_member::{Evaluator, MemberId, PopMember};
+use crate::{Options, equation_search};
+
+fn parse(s: &str) -> PostfixExpr<T, TestOps, D> {
+    parse_expr(s, &[]).unwrap()
+}
+
+/// `(x0 * c0) - sin(x0 * c0)` with the constant tied and both products one graph node.
+fn tied_expr() -> PostfixExpr<T, TestOps, D> {
+    let mut ex = parse("(x0 * 0.5) - sin(x0 * 0.5)");
+    ex.consts.truncate(1);
+    for n in &mut ex.nodes {
+        if let PNode::Const { idx } = n {
+            *idx = 0;
+        }
+    }
+    ex.meta.graph_ids = vec![0, 1, 2, 0, 1, 2, 3, 4];
+    ex
+}
+
+fn graph_options() -> Options<T, D> {
+    Options {
+        graph_expressions: true,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn complexity_counts_shared_subexpressions_once() {
+    let ex = tied_expr();
+    assert_eq!(compute_complexity(&ex, &Options::<T, D>::default()), 8);
+    let options = graph_options();
+    assert_eq!(compute_complexity(&ex, &options), 5);
+
+    // The tree has 8 nodes but fits a maxsize of 5 as a graph.
+    assert!(check_constraints(&ex, &options, 5));
+    assert!(!check_constraints(&ex, &options, 4));
+    assert!(!check_constraints(&ex, &Options::default(), 5));
+
+    // Repeats that were never connected count in full.
+    assert_eq!(compute_complexity(&parse("x0 * x0"), &options), 3);
+}
+
+#[test]
+fn expanded_tree_size_and_depth_stay_capped() {
+    // Squaring a shared node n times doubles the tree each time but adds one graph node.
+    let mut ex = parse("x0 + 1.0");
+    ex.meta.graph_ids = vec![0, 1, 2];
+    for id in 3..8 {
+        let (base, base_ids) = (ex.nodes.clone(), ex.meta.graph_ids.clone());
+        ex.nodes.extend_from_slice(&base);
+        ex.nodes.push(parse("x0 * x1").nodes[2]);
+        ex.meta.graph_ids.extend_from_slice(&base_ids);
+        ex.meta.graph_ids.push(id);
+    }
+    let options = Options {
+        maxdepth: 20,
+        ..graph_options()
+    };
+    assert_eq!(compute_complexity(&ex, &options), 8);
+    assert_eq!(ex.nodes.len(), 127);
+    assert!(!check_constraints(&ex, &options, 20));
+    assert!(check_constraints(&ex, &options, 32));
+
+    let shallow = Options { maxdepth: 5, ..options };
+    assert!(!check_constraints(&ex, &shallow, 32));
+}
+
+#[test]
+fn sharing_survives_unrelated_mutations() {
+    let options = graph_options();
+    // A new root above the tied expression keeps the product shared.
+    let tied = tied_expr();
+    let mut ex = parse("cos(x1 * 2.0) + ((x0 * 0.5) - sin(x0 * 0.5))");
+    ex.consts = vec![2.0, 0.5];
+    for (n, idx) in ex
+        .nodes
+        .iter_mut()
+        .filter_map(|n| match n {
+            PNode::Const { idx } => Some(idx),
+            _ => None,
+        })
+        .zip([0, 1, 1])
+    {
+        *n = idx;
+    }
+    inherit_sharing(&tied, &mut ex);
+    assert_eq!(compute_complexity(&ex, &options), ex.nodes.len() - 3);
+
+    // Changing one of the two uses gives it its own product; only the tied constant is shared.
+    let mut edited = ex.clone();
+    let last_x0 = edited
+        .nodes
+        .iter()
+        .rposition(|n| *n == PNode::Var { feature: 0 })
+        .unwrap();
+    edited.nodes[last_x0] = PNode::Var { feature: 1 };
+    inherit_sharing(&ex, &mut edited);
+    assert_eq!(compute_complexity(&edited, &options), edited.nodes.len() - 1);
+
+    // Identical subtrees the edit did not inherit are not shared.
+    assert_eq!(compute_complexity(&parse("(x0 * x1) + sin(x0 * x1)"), &options), 8);
+}
+
+#[test]
+fn form_connection_creates_sharing() {
+    let options = graph_options();
+    let mut shared = 0;
+    for seed in 0..50 {
+        let mut rng = Rng::with_seed(seed);
+        let mut ex = parse("sin(x0 * 0.5) + cos(x1 - 2.0)");
+        if form_connection_in_place(&mut rng, &mut ex) && compute_complexity(&ex, &options) < ex.nodes.len() {
+            shared += 1;
+        }
+    }
+    assert!(shared > 0);
+}
+
+#[test]
+fn break_connection_unties_constants() {
+    let mut rng = Rng::with_seed(0);
+    let mut ex = tied_expr();
+    assert!(break_connection_in_place(&mut rng, &mut ex));
+    assert_eq!(ex.consts.len(), 2);
+    assert_eq!(compute_complexity(&ex, &graph_options()), ex.nodes.len());
+
+    // Nothing left to break.
+    assert!(!break_connection_in_place(&mut rng, &mut ex));
+}
+
+#[test]
+fn break_connection_unshares_subexpressions_without_constants() {
+    let mut rng = Rng::with_seed(0);
+    let mut ex = parse("(x0 * x1) - sin(x0 * x1)");
+    ex.meta.graph_ids = vec![0, 1, 2, 0, 1, 2, 3, 4];
+    assert_eq!(compute_complexity(&ex, &graph_options()), 5);
+    assert!(break_connection_in_place(&mut rng, &mut ex));
+    assert!(ex.meta.graph_ids.is_empty());
+    assert_eq!(compute_complexity(&ex, &graph_options()), ex.nodes.len());
+}
+
+#[test]
+fn graph_members_evaluate_through_their_graph() {
+    let n_rows = 16;
+    let x: Vec<T> = (0..n_rows).map(|i| i as T / n_rows as T).collect();
+    let y: Vec<T> = x.iter().map(|&xi| xi * 0.5 - (xi * 0.5).sin()).collect();
+    let dataset = crate::Dataset::new(Array2::from_shape_vec((1, n_rows), x).unwrap(), Array1::from_vec(y));
+    let options = Options {
+        operators: OperatorLibrary::sr_default::<TestOps, D>(),
+        ..graph_options()
+    };
+    let mut member = PopMember::from_expr(MemberId(0), None, 0, tied_expr(), 1);
+    // A plan of another expression: the member must not be evaluated through it.
+    member.plan = dynamic_expressions::compile_plan(&parse("x0").nodes, 1, 0);
+    let full_dataset = TaggedDataset::new(&dataset, None);
+    let mut evaluator = Evaluator::new(n_rows);
+    assert!(member.evaluate(&full_dataset, &options, &mut evaluator));
+    assert!(member.loss < 1e-6);
+
+    // The tied constant is fitted once for both uses of the product.
+    member.expr.consts[0] = 0.3;
+    assert!(member.evaluate(&full_dataset, &options, &mut evaluator));
+    assert!(member.loss > 1e-6);
+    let (improved, _) = optimize_constants(
+        &mut Rng::with_seed(0),
+        &mut member,
+        OptimizeConstantsCtx {
+            dataset: full_dataset,
+            options: &options,
+            evaluator: &mut evaluator,
+            grad_ctx: &mut dynamic_expressions::GradContext::new(n_rows),
+            next_birth: &mut 1,
+        },
+    );
+    assert!(improved);
+    assert!(member.loss < 1e-6);
+    assert_eq!(member.expr.consts.len(), 1);
+    assert!(!member.expr.meta.graph_ids.is_empty());
+}
+
+#[test]
+fn graph_members_print_with_let_bindings() {
+    let mut member = PopMember::from_expr(MemberId(0), None, 0, tied_expr(), 1);
+    (member.complexity, member.loss, member.cost) = (5, 0.5, 0.5);
+    let mut hall = HallOfFame::new(10);
+    hall.best_by_complexity[5] = Some(member);
+    let records = hall.to_records(&[]);
+    assert_eq!(records[0].equation, "let z0 = x0 * 0.5 in z0 - sin(z0)");
+}
+
+#[test]
+fn connection_weights_only_apply_to_graph_searches() {
+    let member = PopMember::from_expr(MemberId(0), None, 0, tied_expr(), 1);
+    let tree_options = Options::<T, D> {
+        operators: OperatorLibrary::sr_default::<TestOps, D>(),
+        ..Default::default()
+    };
+    let mut weights = tree_options.mutation_weights.clone();
+    condition_mutation_weights(&mut weights, &mut [], &member, &tree_options, 20, 1);
+    assert_eq!((weights.form_connection, weights.break_connection), (0.0, 0.0));
+
+    let options = Options {
+        graph_expressions: true,
+        ..tree_options
+    };
+    let mut weights = options.mutation_weights.clone();
+    condition_mutation_weights(&mut weights, &mut [], &member, &options, 20, 1);
+    assert!(weights.form_connection > 0.0 && weights.break_connection > 0.0);
+}
+
+#[test]
+fn equation_search_runs_over_graphs() {
+    let n_rows = 32;
+    let x: Vec<T> = (0..n_rows).map(|i| i as T / n_rows as T).collect();
+    let y: Vec<T> = x.iter().map(|&xi| (xi * xi).sin() + xi * xi).collect();
+    let dataset = crate::Dataset::new(Array2::from_shape_vec((1, n_rows), x).unwrap(), Array1::from_vec(y));
+    let mut options = Options::<T, D> {
+        seed: 7,
+        operators: OperatorLibrary::sr_default::<TestOps, D>(),
+        populations: 2,
+        population_size: 30,
+        niterations: 2,
+        ncycles_per_iteration: 10,
+        maxsize: 10,
+        migration: false,
+        hof_migration: false,
+        optimizer_probability: 0.0,
+        progress: false,
+        graph_expressions: true,
+        ..Default::default()
+    };
+    options.mutation_weights.form_connection = 2.0;
+    options.mutation_weights.break_connection = 1.0;
+
+    let result = equation_search::<T, TestOps, D>(&dataset, &options);
+    assert!(result.best.loss.is_finite());
+    for m in result.hall_of_fame.members() {
+        assert_eq!(m.complexity, compute_complexity(&m.expr, &options));
+        assert!(m.complexity <= options.maxsize);
+    }
+}
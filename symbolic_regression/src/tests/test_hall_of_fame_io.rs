@@ -0,0 +1,131 @@
+use dynamic_expressions::expression::PostfixExpr;
+use dynamic_expressions::parse_expr;
+use ndarray::{Array1, Array2};
+
+use super::common::{D, T, TestOps};
+use crate::dataset::{Dataset, TaggedDataset};
+use crate::hall_of_fame::HallOfFame;
+use crate::options::Options;
+use crate::pop_member::{Evaluator, MemberId, PopMember};
+
+fn names() -> Vec<String> {
+    vec!["a".to_string(), "b".to_string()]
+}
+
+fn member(equation: &str, complexity: usize, loss: T) -> PopMember<T, TestOps, D> {
+    let expr: PostfixExpr<T, TestOps, D> = parse_expr(equation, &names()).unwrap();
+    let mut m = PopMember::from_expr(MemberId(complexity as u64), None, 0, expr, 2);
+    m.complexity = complexity;
+    m.loss = loss;
+    m.cost = loss * 1.5;
+    m
+}
+
+fn hall() -> HallOfFame<T, TestOps, D> {
+    let mut hall = HallOfFame::new(10);
+    for m in [
+        member("a", 1, 4.0),
+        member("a * 0.1", 3, 1.0 / 3.0),
+        member("(a * 0.1) + cos(b - -2.5)", 6, 0.01),
+    ] {
+        let c = m.complexity;
+        hall.best_by_complexity[c] = Some(m);
+    }
+    hall
+}
+
+#[test]
+fn csv_starts_with_pysr_columns() {
+    let csv = hall().to_csv(&names());
+    let mut lines = csv.lines();
+    assert_eq!(
+        lines.next(),
+        Some("Complexity,Loss,Equation,Cost,Score,Expression,Parameters,Subexpressions")
+    );
+    assert_eq!(lines.next(), Some("1,4,a,6,0,x0 |,,"));
+    let third = lines.nth(1).unwrap();
+    assert!(third.starts_with("6,0.01,(a * 0.1) + cos(b - -2.5),"), "{third}");
+    assert_eq!(csv.lines().count(), 4);
+}
+
+#[test]
+fn csv_round_trip_restores_members_exactly() {
+    let hall = hall();
+    let back = HallOfFame::<T, TestOps, D>::from_csv(&hall.to_csv(&names()), &names(), 2).unwrap();
+    let (orig, back): (Vec<_>, Vec<_>) = (hall.pareto_front(), back.pareto_front());
+    assert_eq!(orig.len(), back.len());
+    for (o, b) in orig.iter().zip(&back) {
+        assert_eq!(o.expr.nodes, b.expr.nodes);
+        assert_eq!(o.expr.consts, b.expr.consts);
+        assert_eq!((o.complexity, o.loss, o.cost), (b.complexity, b.loss, b.cost));
+    }
+}
+
+#[test]
+fn csv_round_trip_keeps_parameters_and_subexpressions() {
+    let mut hall = hall();
+    let parametric = hall.best_by_complexity[3].as_mut().unwrap();
+    parametric.parameters = vec![0.1 + 0.2, -1.5e-7];
+    let template = hall.best_by_complexity[6].as_mut().unwrap();
+    template.subexprs = vec![
+        parse_expr("a * 0.1", &names()).unwrap(),
+        parse_expr("b", &names()).unwrap(),
+    ];
+
+    let csv = hall.to_csv(&names());
+    let back = HallOfFame::<T, TestOps, D>::from_csv(&csv, &names(), 3).unwrap();
+    for c in [1, 3, 6] {
+        let (o, b) = (
+            hall.best_by_complexity[c].as_ref().unwrap(),
+            back.best_by_complexity[c].as_ref().unwrap(),
+        );
+        assert_eq!(o.parameters, b.parameters);
+        assert_eq!(o.subexprs.len(), b.subexprs.len());
+        for (os, bs) in o.subexprs.iter().zip(&b.subexprs) {
+            assert_eq!((&os.nodes, &os.consts), (&bs.nodes, &bs.consts));
+        }
+    }
+}
+
+#[test]
+fn reads_pysr_hall_of_fame_csv_and_evaluates() {
+    let csv = "Complexity,Loss,Equation\n\
+               1,2.5,x0\n\
+               5,0.125,\"(x0 * 2.0) + cos(x1)\"\n";
+    let hall = HallOfFame::<T, TestOps, D>::from_csv(csv, &[], 2).unwrap();
+    let mut best = hall.best_by_complexity[5].clone().unwrap();
+    assert_eq!(best.cost, 0.125);
+
+    // The rebuilt plan evaluates: y = 2 * x0 + cos(x1) is fit exactly.
+    let x = Array2::<T>::from_shape_vec((2, 3), vec![0.0, 1.0, 2.0, 0.5, 0.0, -1.0]).unwrap();
+    let y = Array1::from_iter((0..3).map(|i| 2.0 * x[(0, i)] + x[(1, i)].cos()));
+    let dataset = Dataset::new(x, y);
+    let options = Options::<T, D>::default();
+    let mut evaluator = Evaluator::new(dataset.n_rows);
+    assert!(best.evaluate(&TaggedDataset::new(&dataset, None), &options, &mut evaluator));
+    assert!(best.loss < 1e-24);
+}
+
+fn load_error(csv: &str) -> String {
+    match HallOfFame::<T, TestOps, D>::from_csv(csv, &[], 1) {
+        Ok(_) => panic!("expected an error for {csv:?}"),
+        Err(e) => e.to_string(),
+    }
+}
+
+#[test]
+fn from_csv_reports_missing_columns_and_bad_rows() {
+    assert!(load_error("Loss,Equation\n1,x0\n").contains("Complexity"));
+    assert!(load_error("Complexity,Loss,Equation\n1,1,foo(x0)\n").starts_with("row 0"));
+    assert!(load_error("Complexity,Loss,Equation\n1,nope,x0\n").contains("Loss"));
+    assert!(load_error("Complexity,Loss,Equation\n1,1,\"x0\n").contains("unterminated"));
+}
+
+#[cfg(feature = "serde_json")]
+#[test]
+fn json_round_trip_restores_members_exactly() {
+    let hall = hall();
+    let json = hall.to_json(&names());
+    let back = HallOfFame::<T, TestOps, D>::from_json(&json, &names(), 2).unwrap();
+    assert_eq!(hall.to_records(&names()), back.to_records(&names()));
+}
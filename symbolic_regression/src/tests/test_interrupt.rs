@@ -0,0 +1,43 @@
+use std::time::Duration;
+
+use ndarray::{Array1, Array2};
+
+use super::common::{D, T, TestOps};
+use crate::interrupt::StopSignal;
+use crate::operator_library::OperatorLibrary;
+use crate::search_utils::search_with_stop_signal;
+use crate::{Options, SearchResult};
+
+#[test]
+fn stop_request_returns_partial_result() {
+    let n_rows = 32;
+    let x: Vec<T> = (0..n_rows).map(|i| (i as T) / (n_rows as T)).collect();
+    let y: Vec<T> = x.iter().map(|&xi| xi * xi + xi).collect();
+    let dataset = crate::Dataset::new(Array2::from_shape_vec((1, n_rows), x).unwrap(), Array1::from_vec(y));
+
+    let options = Options::<T, D> {
+        seed: 7,
+        operators: OperatorLibrary::sr_default::<TestOps, D>(),
+        populations: 2,
+        population_size: 20,
+        // Far more work than the test could finish without a stop request.
+        niterations: 1_000_000,
+        ncycles_per_iteration: 10,
+        maxsize: 10,
+        optimizer_probability: 0.0,
+        progress: false,
+        ..Default::default()
+    };
+
+    // A signal local to this search: neither registered for SIGINT nor stopping other tests' searches.
+    let stop = StopSignal::new(&options);
+    let result: SearchResult<T, TestOps, D> = std::thread::scope(|scope| {
+        let search = scope.spawn(|| search_with_stop_signal(&dataset, None, &options, &stop));
+        std::thread::sleep(Duration::from_millis(100));
+        stop.request();
+        search.join().unwrap()
+    });
+
+    assert!(result.best.loss.is_finite());
+    assert!(!result.hall_of_fame.pareto_front().is_empty());
+}
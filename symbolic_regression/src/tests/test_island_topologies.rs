@@ -0,0 +1,139 @@
+use fastrand::Rng;
+use ndarray::{Array1, Array2};
+
+use super::common::{D, T, TestOps};
+use crate::dataset::Dataset;
+use crate::hall_of_fame::HallOfFame;
+use crate::loss_functions::{baseline_loss, loss_to_cost};
+use crate::operator_library::OperatorLibrary;
+use crate::pop_member::{MemberId, PopMember};
+use crate::{IslandOptions, MigrationTopology, MutationWeights, Options, equation_search};
+
+fn sources(topology: MigrationTopology, dst: usize, n_pops: usize) -> Vec<usize> {
+    let mut v = topology.sources(dst, n_pops, &mut Rng::with_seed(0));
+    v.sort_unstable();
+    v
+}
+
+#[test]
+fn topologies_pick_expected_sources() {
+    assert_eq!(sources(MigrationTopology::Global, 2, 4), vec![0, 1, 3]);
+    assert_eq!(sources(MigrationTopology::Ring, 0, 4), vec![3]);
+    assert_eq!(sources(MigrationTopology::Ring, 2, 4), vec![1]);
+    assert_eq!(sources(MigrationTopology::Star, 0, 4), vec![1, 2, 3]);
+    assert_eq!(sources(MigrationTopology::Star, 3, 4), vec![0]);
+
+    // 3x3 grid: 4 is in the middle, 0 in a corner wrapping to the far edges.
+    let torus = MigrationTopology::Torus { width: 3 };
+    assert_eq!(sources(torus, 4, 9), vec![1, 3, 5, 7]);
+    assert_eq!(sources(torus, 0, 9), vec![1, 2, 3, 6]);
+    // An incomplete last row only links to existing populations.
+    assert_eq!(sources(torus, 6, 7), vec![0, 3]);
+
+    let random = sources(MigrationTopology::RandomNeighbours { k: 2 }, 1, 6);
+    assert_eq!(random.len(), 2);
+    assert!(!random.contains(&1));
+
+    for topology in [
+        MigrationTopology::Global,
+        MigrationTopology::Ring,
+        MigrationTopology::Star,
+    ] {
+        assert!(sources(topology, 0, 1).is_empty());
+    }
+}
+
+#[test]
+fn island_overrides_apply_cyclically() {
+    let options: Options<T, D> = Options {
+        maxsize: 20,
+        parsimony: 0.01,
+        islands: vec![
+            IslandOptions {
+                mutation_weights: Some(MutationWeights {
+                    randomize: 1.0,
+                    ..Default::default()
+                }),
+                maxsize: Some(40),
+                ..Default::default()
+            },
+            IslandOptions {
+                parsimony: Some(0.5),
+                maxsize: Some(10),
+                ..Default::default()
+            },
+        ],
+        ..Default::default()
+    };
+
+    let explore = options.for_island(2);
+    assert_eq!(explore.mutation_weights.randomize, 1.0);
+    assert_eq!(explore.parsimony, 0.01);
+    assert_eq!(explore.maxsize, 20);
+
+    let exploit = options.for_island(3);
+    assert_eq!(exploit.mutation_weights.randomize, MutationWeights::default().randomize);
+    assert_eq!(exploit.parsimony, 0.5);
+    assert_eq!(exploit.maxsize, 10);
+
+    let plain: Options<T, D> = Options::default();
+    assert!(matches!(plain.for_island(5), std::borrow::Cow::Borrowed(_)));
+}
+
+#[test]
+fn equation_search_runs_with_ring_and_islands() {
+    let n_rows = 40;
+    let x = Array2::from_shape_fn((2, n_rows), |(f, i)| (i * (f + 1)) as T / 20.0 - 1.0);
+    let y = Array1::from_shape_fn(n_rows, |i| x[(0, i)] * x[(1, i)] + 0.3);
+    let data = Dataset::new(x, y);
+    let options = Options {
+        operators: OperatorLibrary::sr_default::<TestOps, D>(),
+        seed: 4,
+        populations: 4,
+        population_size: 20,
+        niterations: 3,
+        ncycles_per_iteration: 20,
+        maxsize: 14,
+        fraction_replaced: 0.2,
+        progress: false,
+        migration_topology: MigrationTopology::Ring,
+        islands: vec![
+            IslandOptions::default(),
+            IslandOptions {
+                parsimony: Some(0.1),
+                maxsize: Some(7),
+                ..Default::default()
+            },
+        ],
+        ..Default::default()
+    };
+    let result = equation_search::<T, TestOps, D>(&data, &options);
+    assert!(result.best.loss.is_finite());
+    assert!(result.hall_of_fame.members().all(|m| m.complexity <= options.maxsize));
+
+    // Whichever island found them, the hall costs its members under the search's parsimony.
+    let baseline = baseline_loss::<T, TestOps, D>(&data, &options);
+    for m in result.hall_of_fame.members() {
+        let cost = loss_to_cost(m.loss, m.complexity, options.parsimony, options.use_baseline, baseline);
+        assert!((m.cost - cost).abs() <= 1e-12 * cost.abs(), "{} vs {cost}", m.cost);
+    }
+}
+
+#[test]
+fn hall_of_fame_keeps_the_lower_loss_across_island_costs() {
+    let options: Options<T, D> = Options {
+        operators: OperatorLibrary::sr_default::<TestOps, D>(),
+        ..Default::default()
+    };
+    let member = |loss: T, cost: T| {
+        let expr = dynamic_expressions::parse_expr("x0 + 1.0", &[]).unwrap();
+        let mut m = PopMember::<T, TestOps, D>::from_expr(MemberId(0), None, 0, expr, 1);
+        (m.complexity, m.loss, m.cost) = (3, loss, cost);
+        m
+    };
+    // The second member was costed under a higher parsimony, so its lower loss costs more.
+    let mut hall = HallOfFame::new(options.maxsize);
+    hall.consider(&member(0.5, 0.53), &options, options.maxsize);
+    hall.consider(&member(0.4, 1.9), &options, options.maxsize);
+    assert_eq!(hall.members().map(|m| m.loss).collect::<Vec<_>>(), [0.4]);
+}
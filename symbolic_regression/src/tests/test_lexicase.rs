@@ -0,0 +1,98 @@
+use dynamic_expressions::parse_expr;
+use fastrand::Rng;
+use ndarray::{Array1, Array2};
+
+use super::common::{D, T, TestOps};
+use crate::dataset::Dataset;
+use crate::lexicase::{CaseErrors, lexicase_select};
+use crate::operator_library::OperatorLibrary;
+use crate::pop_member::{Evaluator, MemberId, PopMember};
+use crate::population::Population;
+use crate::{Options, SelectionMode, equation_search, mae, mse};
+
+/// How often each candidate wins 300 selections.
+fn wins(errors: &[&[f64]], epsilon: bool) -> Vec<usize> {
+    let mut rng = Rng::with_seed(4);
+    let mut wins = vec![0; errors.len()];
+    for _ in 0..300 {
+        wins[lexicase_select(&mut rng, errors, epsilon)] += 1;
+    }
+    wins
+}
+
+#[test]
+fn lexicase_prefers_specialists_over_the_best_average() {
+    let errors: [&[f64]; 3] = [&[0.0, 9.0, 9.0], &[9.0, 0.0, 9.0], &[4.0, 4.0, 9.0]];
+    let w = wins(&errors, false);
+    assert!(w[0] > 100 && w[1] > 100, "{w:?}");
+    assert_eq!(w[2], 0);
+}
+
+#[test]
+fn epsilon_lexicase_keeps_near_ties() {
+    // On row 0 the first two are within one median absolute deviation, so the second always
+    // wins on row 1; exact lexicase picks the first whenever row 0 comes first.
+    let errors: [&[f64]; 3] = [&[0.0, 9.0], &[0.1, 0.0], &[4.0, 4.0]];
+    assert_eq!(wins(&errors, true), [0, 300, 0]);
+    let w = wins(&errors, false);
+    assert!(w[0] > 100 && w[1] > 100, "{w:?}");
+}
+
+#[test]
+fn case_errors_are_evaluated_once_per_member() {
+    let x = Array2::from_shape_vec((1, 3), vec![0.0, 1.0, 2.0]).unwrap();
+    let data = Dataset::new(x, Array1::from_vec(vec![1.0, 1.0, 1.0]));
+    let member =
+        |id, eq| PopMember::<T, TestOps, D>::from_expr(MemberId(id), None, id, parse_expr(eq, &[]).unwrap(), 1);
+    let mut pop = Population::new(vec![member(1, "x0"), member(2, "log(x0)")]);
+    let mut evaluator = Evaluator::new(3);
+
+    let mut cache = CaseErrors::default();
+    let mae = mae::<T>();
+    assert_eq!(cache.update(&pop, &data, mae.as_ref(), &mut evaluator), 2.0);
+    assert_eq!(cache.of(&pop), [&[1.0, 0.0, 1.0][..], &[f64::INFINITY; 3][..]]);
+
+    pop.members[1] = member(3, "x0 * 0.5");
+    assert_eq!(cache.update(&pop, &data, mae.as_ref(), &mut evaluator), 1.0);
+    assert_eq!(cache.of(&pop)[1], [1.0, 0.5, 0.0]);
+}
+
+#[test]
+fn case_errors_use_the_search_loss() {
+    let x = Array2::from_shape_vec((1, 3), vec![0.0, 1.0, 2.0]).unwrap();
+    let y = Array1::from_vec(vec![1.0, 1.0, 1.0]);
+    let data = Dataset::with_weights_and_names(x, y, Some(Array1::from_vec(vec![1.0, 2.0, 3.0])), Vec::new());
+    let pop = Population::new(vec![PopMember::<T, TestOps, D>::from_expr(
+        MemberId(1),
+        None,
+        1,
+        parse_expr("x0", &[]).unwrap(),
+        1,
+    )]);
+    let mut cache = CaseErrors::default();
+    cache.update(&pop, &data, mse::<T>().as_ref(), &mut Evaluator::new(3));
+    assert_eq!(cache.of(&pop), [&[1.0, 0.0, 3.0][..]]);
+}
+
+#[test]
+fn epsilon_lexicase_search_with_batching() {
+    let n_rows = 60;
+    let x = Array2::from_shape_fn((2, n_rows), |(f, i)| ((i * (f + 3)) % 17) as T / 8.0 - 1.0);
+    let y = Array1::from_shape_fn(n_rows, |i| x[(0, i)] * x[(0, i)] + x[(1, i)]);
+    let options = Options {
+        operators: OperatorLibrary::sr_default::<TestOps, D>(),
+        selection: SelectionMode::EpsilonLexicase,
+        batching: true,
+        batch_size: 20,
+        seed: 2,
+        populations: 4,
+        population_size: 20,
+        niterations: 4,
+        ncycles_per_iteration: 100,
+        maxsize: 12,
+        progress: false,
+        ..Default::default()
+    };
+    let result = equation_search::<T, TestOps, D>(&Dataset::new(x, y), &options);
+    assert!(result.best.loss < 1e-12, "loss = {}", result.best.loss);
+}
@@ -0,0 +1,117 @@
+use dynamic_expressions::parse_expr;
+use ndarray::{Array1, Array2};
+
+use super::common::{D, T, TestOps};
+use crate::complexity::compute_complexity;
+use crate::dataset::{Dataset, TaggedDataset};
+use crate::loss_functions::{mae, mse};
+use crate::operator_library::OperatorLibrary;
+use crate::pop_member::{Evaluator, MemberId, PopMember};
+use crate::{LinearScaling, Options, equation_search};
+
+fn assert_close(a: T, b: T, tol: T) {
+    assert!((a - b).abs() <= tol, "{a} != {b}");
+}
+
+#[test]
+fn least_squares_fit_is_exact_for_affine_targets() {
+    let f = [0.0, 1.0, 2.5, -1.0, 4.0];
+    let y = f.map(|v| 3.0 * v - 2.0);
+    let s = LinearScaling::fit(&f, &y, None, mse::<T>().as_ref());
+    assert_close(s.scale, 3.0, 1e-12);
+    assert_close(s.offset, -2.0, 1e-12);
+
+    let flat = LinearScaling::fit(&[2.0; 5], &y, None, mse::<T>().as_ref());
+    assert_eq!(flat.scale, 1.0);
+    assert_close(flat.offset, y.iter().sum::<T>() / 5.0 - 2.0, 1e-12);
+}
+
+#[test]
+fn irls_fit_improves_on_least_squares_for_mae() {
+    let f: Vec<T> = (0..12).map(|i| i as T).collect();
+    let mut y: Vec<T> = f.iter().map(|v| 2.0 * v + 1.0).collect();
+    y[11] += 100.0;
+    let loss = mae::<T>();
+    let mae_of = |s: LinearScaling<T>| {
+        let mut yhat = f.clone();
+        s.apply(&mut yhat);
+        loss.loss(&yhat, &y, None)
+    };
+    let least_squares = LinearScaling::fit(&f, &y, None, mse::<T>().as_ref());
+    let fitted = LinearScaling::fit(&f, &y, None, loss.as_ref());
+    assert!(mae_of(fitted) < mae_of(least_squares));
+    assert_close(fitted.scale, 2.0, 0.5);
+}
+
+#[test]
+fn scaling_is_fitted_on_evaluation_and_folds_into_the_expression() {
+    let x = Array2::from_shape_fn((2, 10), |(f, i)| (i + f) as T / 3.0);
+    let y = Array1::from_shape_fn(10, |i| 5.0 * x[(0, i)] + 3.0);
+    let dataset = Dataset::new(x, y);
+    let full_dataset = TaggedDataset::new(&dataset, None);
+    let mut options = Options::<T, D> {
+        operators: OperatorLibrary::sr_default::<TestOps, D>(),
+        linear_scaling: true,
+        ..Default::default()
+    };
+    let mut evaluator = Evaluator::new(dataset.n_rows);
+    let mut member = PopMember::<T, TestOps, D>::from_expr(MemberId(0), None, 0, parse_expr("x0", &[]).unwrap(), 2);
+
+    assert!(member.evaluate(&full_dataset, &options, &mut evaluator));
+    assert!(member.loss < 1e-20, "loss = {}", member.loss);
+    let s = member.scaling.unwrap();
+    assert_close(s.scale, 5.0, 1e-12);
+    assert_close(s.offset, 3.0, 1e-12);
+
+    assert_eq!(member.complexity, 1);
+    assert!(member.fold_linear_scaling(2, &options));
+    assert_eq!(member.scaling, None);
+    assert_eq!(member.complexity, 5, "x0 * 5 + 3");
+    options.linear_scaling = false;
+    assert!(member.evaluate(&full_dataset, &options, &mut evaluator));
+    assert!(member.loss < 1e-20, "loss = {}", member.loss);
+}
+
+#[test]
+fn search_with_linear_scaling_returns_folded_expressions() {
+    let n_rows = 40;
+    let x = Array2::from_shape_fn((2, n_rows), |(f, i)| ((i * (f + 3)) % 13) as T / 6.0 - 1.0);
+    let y = Array1::from_shape_fn(n_rows, |i| 100.0 * x[(0, i)] * x[(1, i)] + 50.0);
+    let dataset = Dataset::new(x, y);
+    let options = Options {
+        operators: OperatorLibrary::sr_default::<TestOps, D>(),
+        linear_scaling: true,
+        deterministic: true,
+        seed: 2,
+        populations: 4,
+        population_size: 20,
+        niterations: 4,
+        ncycles_per_iteration: 100,
+        maxsize: 12,
+        progress: false,
+        ..Default::default()
+    };
+    let result = equation_search::<T, TestOps, D>(&dataset, &options);
+    assert!(result.best.loss < 1e-12, "loss = {}", result.best.loss);
+    assert_eq!(result.best.scaling, None);
+    for (c, m) in result.hall_of_fame.best_by_complexity.iter().enumerate() {
+        if let Some(m) = m {
+            assert_eq!(m.scaling, None);
+            assert_eq!(m.complexity, c);
+            assert_eq!(c, compute_complexity(&m.expr, &options));
+        }
+    }
+    assert_eq!(result.best.complexity, compute_complexity(&result.best.expr, &options));
+
+    let mut best = result.best.clone();
+    let unscaled = Options {
+        linear_scaling: false,
+        ..options
+    };
+    assert!(best.evaluate(
+        &TaggedDataset::new(&dataset, None),
+        &unscaled,
+        &mut Evaluator::new(n_rows)
+    ));
+    assert!(best.loss < 1e-12, "loss = {}", best.loss);
+}
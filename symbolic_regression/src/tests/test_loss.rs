@@ -58,3 +58,287 @@ fn make_loss_kind_dispatches() {
     let l = loss_functions::make_loss::<f64>(loss_functions::LossKind::Mae).loss(&yhat, &y, None);
     assert_close(l, 4.0 / 3.0, 1e-12);
 }
+
+fn assert_grad_matches_finite_difference(
+    loss: &dyn loss_functions::LossFn<f64>,
+    yhat: &[f64],
+    y: &[f64],
+    w: Option<&[f64]>,
+) {
+    let mut grad = vec![0.0; yhat.len()];
+    loss.dloss_dyhat(yhat, y, w, &mut grad);
+    let h = 1e-6;
+    for i in 0..yhat.len() {
+        let mut plus = yhat.to_vec();
+        let mut minus = yhat.to_vec();
+        plus[i] += h;
+        minus[i] -= h;
+        let fd = (loss.loss(&plus, y, w) - loss.loss(&minus, y, w)) / (2.0 * h);
+        assert_close(grad[i], fd, 1e-6);
+    }
+}
+
+#[test]
+fn logistic_and_hinge_match_known_values() {
+    let y = [1.0_f64, 0.0];
+    let yhat = [0.0_f64, 2.0];
+
+    // logistic: [ln 2, softplus(2)]; hinge: [max(0, 1 - 0), max(0, 1 + 2)]
+    let l = loss_functions::logistic::<f64>().loss(&yhat, &y, None);
+    assert_close(l, (2.0_f64.ln() + (1.0 + 2.0_f64.exp()).ln()) / 2.0, 1e-12);
+    let l = loss_functions::hinge::<f64>().loss(&yhat, &y, None);
+    assert_close(l, (1.0 + 3.0) / 2.0, 1e-12);
+
+    // Large logits must not overflow.
+    let l = loss_functions::logistic::<f64>().loss(&[1000.0, -1000.0], &[1.0, 0.0], None);
+    assert_close(l, 0.0, 1e-12);
+}
+
+#[test]
+fn softmax_cross_entropy_groups_consecutive_rows() {
+    // Two samples with three classes each; true classes are 0 and 2.
+    let y = [1.0_f64, 0.0, 0.0, 0.0, 0.0, 1.0];
+    let yhat = [2.0_f64, 1.0, 0.0, 0.0, 0.0, 0.0];
+    let w = [1.0_f64, 1.0, 1.0, 3.0, 3.0, 3.0];
+    let loss = loss_functions::softmax_cross_entropy::<f64>(3);
+
+    let lse0 = (2.0_f64.exp() + 1.0_f64.exp() + 1.0).ln();
+    let ce0 = lse0 - 2.0;
+    let ce1 = 3.0_f64.ln();
+    assert_close(loss.loss(&yhat, &y, None), (ce0 + ce1) / 2.0, 1e-12);
+    assert_close(loss.loss(&yhat, &y, Some(&w)), (ce0 + 3.0 * ce1) / 4.0, 1e-12);
+}
+
+#[test]
+fn row_losses_are_weighted_per_sample() {
+    let mut out = [0.0; 3];
+    loss_functions::mse::<f64>().row_losses(&[2.0, 0.0, 4.0], &[1.0, 2.0, 3.0], Some(&[1.0, 0.5, 0.0]), &mut out);
+    assert_eq!(out, [1.0, 2.0, 0.0]);
+
+    let y = [1.0_f64, 0.0, 0.0, 0.0, 0.0, 1.0];
+    let yhat = [2.0_f64, 1.0, 0.0, 0.0, 0.0, 0.0];
+    let w = [1.0_f64, 1.0, 1.0, 3.0, 3.0, 3.0];
+    let mut out = [0.0; 6];
+    loss_functions::softmax_cross_entropy::<f64>(3).row_losses(&yhat, &y, Some(&w), &mut out);
+    let ce0 = (2.0_f64.exp() + 1.0_f64.exp() + 1.0).ln() - 2.0;
+    let ce1 = 3.0 * 3.0_f64.ln();
+    for (o, e) in out.iter().zip([ce0, ce0, ce0, ce1, ce1, ce1]) {
+        assert_close(*o, e, 1e-12);
+    }
+}
+
+#[test]
+fn classification_gradients_match_finite_differences() {
+    let y = [1.0_f64, 0.0, 1.0, 0.0];
+    let yhat = [0.3_f64, -1.2, 2.5, 0.7];
+    let w = [1.0_f64, 2.0, 0.5, 1.5];
+    assert_grad_matches_finite_difference(loss_functions::logistic::<f64>().as_ref(), &yhat, &y, Some(&w));
+    assert_grad_matches_finite_difference(loss_functions::hinge::<f64>().as_ref(), &yhat, &y, Some(&w));
+
+    let y = [0.0_f64, 1.0, 1.0, 0.0];
+    let w = [2.0_f64, 2.0, 1.0, 1.0];
+    let softmax = loss_functions::softmax_cross_entropy::<f64>(2);
+    assert_grad_matches_finite_difference(softmax.as_ref(), &yhat, &y, None);
+    assert_grad_matches_finite_difference(softmax.as_ref(), &yhat, &y, Some(&w));
+}
+
+#[test]
+fn classification_baselines_use_zero_logits() {
+    use ndarray::{Array1, Array2};
+
+    use super::common::{D, TestOps};
+
+    let x = Array2::from_shape_vec((1, 4), vec![0.0, 1.0, 0.0, 1.0]).unwrap();
+    let dataset = crate::Dataset::new(x, Array1::from_vec(vec![1.0, 0.0, 0.0, 1.0]));
+
+    let base = |kind| {
+        let loss = loss_functions::make_loss::<f64>(kind);
+        loss_functions::baseline_loss_from_zero_expression::<f64, TestOps, D>(&dataset, loss.as_ref()).unwrap()
+    };
+    assert_close(base(loss_functions::LossKind::Logistic), 2.0_f64.ln(), 1e-12);
+    assert_close(base(loss_functions::LossKind::Hinge), 1.0, 1e-12);
+    assert_close(
+        base(loss_functions::LossKind::SoftmaxCrossEntropy { n_classes: 2 }),
+        2.0_f64.ln(),
+        1e-12,
+    );
+    assert_eq!(
+        loss_functions::LossKind::parse("BCE"),
+        Some(loss_functions::LossKind::Logistic)
+    );
+    assert_eq!(
+        loss_functions::LossKind::parse("softmax:3"),
+        Some(loss_functions::LossKind::SoftmaxCrossEntropy { n_classes: 3 })
+    );
+    assert_eq!(
+        loss_functions::LossKind::parse(" Cross-Entropy : 2 "),
+        Some(loss_functions::LossKind::SoftmaxCrossEntropy { n_classes: 2 })
+    );
+    for s in ["softmax", "softmax:0", "softmax:x", "mse:2"] {
+        assert_eq!(loss_functions::LossKind::parse(s), None, "{s}");
+    }
+}
+
+#[test]
+fn softmax_batches_and_validation_splits_keep_samples_whole() {
+    use ndarray::{Array1, Array2};
+
+    use crate::operator_library::OperatorLibrary;
+    use crate::tests::common::{D, TestOps};
+    use crate::{Dataset, Options, SplitIndices, equation_search_with_validation};
+
+    // 30 samples of 3 classes; row `3 * s + k` holds class `k` of sample `s`.
+    let n_classes = 3;
+    let n_rows = 30 * n_classes;
+    let x = Array2::from_shape_fn((2, n_rows), |(f, i)| {
+        if f == 0 {
+            (i / n_classes) as f64 / 10.0 - 1.5
+        } else {
+            (i % n_classes) as f64
+        }
+    });
+    let label = |i: usize| ((x[(0, i)] + 1.5) * 0.99).floor() as usize;
+    let y = Array1::from_shape_fn(n_rows, |i| if i % n_classes == label(i) { 1.0 } else { 0.0 });
+    let data = Dataset::new(x, y);
+
+    let split = SplitIndices::random_groups(n_rows, n_classes, 0.2, 3);
+    assert_eq!((split.train.len(), split.val.len()), (72, 18));
+    for group in split.train.chunks(n_classes).chain(split.val.chunks(n_classes)) {
+        assert_eq!(group[0] % n_classes, 0);
+        assert_eq!(group, [group[0], group[0] + 1, group[0] + 2]);
+    }
+    assert_eq!(
+        SplitIndices::random_groups(50, 1, 0.2, 7),
+        SplitIndices::random(50, 0.2, 7)
+    );
+
+    let mut batch = Dataset::make_batch_buffer(&data, 12);
+    batch.resample_groups_from(&data, n_classes, &mut fastrand::Rng::with_seed(5));
+    for k in 0..batch.n_rows {
+        assert_eq!(batch.x[(1, k)], (k % n_classes) as f64);
+        assert_eq!(batch.x[(0, k)], batch.x[(0, k - k % n_classes)]);
+    }
+
+    // A batch size that is not a multiple of `n_classes` is rounded up to whole samples.
+    let (train, val) = data.split_train_val(&split);
+    let options = Options::<f64, D> {
+        operators: OperatorLibrary::sr_default::<TestOps, D>(),
+        loss: loss_functions::softmax_cross_entropy(n_classes),
+        batching: true,
+        batch_size: 50,
+        seed: 1,
+        populations: 2,
+        population_size: 20,
+        niterations: 2,
+        ncycles_per_iteration: 20,
+        maxsize: 12,
+        progress: false,
+        ..Default::default()
+    };
+    let result = equation_search_with_validation::<f64, TestOps, D>(&train, &val.unwrap(), &options);
+    assert!(result.best.loss.is_finite());
+}
+
+#[test]
+fn glm_deviances_match_known_values() {
+    let y = [0.0_f64, 2.0, 5.0];
+    let mu = [1.0_f64, 2.0, 4.0];
+    let eta: Vec<f64> = mu.iter().map(|m| m.ln()).collect();
+
+    let poisson: f64 = y
+        .iter()
+        .zip(&mu)
+        .map(|(&yi, &mi)| {
+            let t = if yi > 0.0 { yi * (yi / mi).ln() } else { 0.0 };
+            2.0 * (t - (yi - mi))
+        })
+        .sum::<f64>()
+        / 3.0;
+    assert_close(
+        loss_functions::poisson_deviance::<f64>().loss(&eta, &y, None),
+        poisson,
+        1e-12,
+    );
+
+    let y_pos = [1.0_f64, 2.0, 5.0];
+    let gamma: f64 = y_pos
+        .iter()
+        .zip(&mu)
+        .map(|(&yi, &mi)| 2.0 * (-(yi / mi).ln() + (yi - mi) / mi))
+        .sum::<f64>()
+        / 3.0;
+    assert_close(
+        loss_functions::gamma_deviance::<f64>().loss(&eta, &y_pos, None),
+        gamma,
+        1e-12,
+    );
+
+    let p = 1.5_f64;
+    let tweedie: f64 = y
+        .iter()
+        .zip(&mu)
+        .map(|(&yi, &mi)| {
+            2.0 * (yi.powf(2.0 - p) / ((1.0 - p) * (2.0 - p)) - yi * mi.powf(1.0 - p) / (1.0 - p)
+                + mi.powf(2.0 - p) / (2.0 - p))
+        })
+        .sum::<f64>()
+        / 3.0;
+    assert_close(
+        loss_functions::tweedie_deviance::<f64>(p).loss(&eta, &y, None),
+        tweedie,
+        1e-12,
+    );
+
+    // Tweedie approaches the Poisson deviance as power -> 1.
+    let near_poisson = loss_functions::tweedie_deviance::<f64>(1.0 + 1e-7).loss(&eta, &y, None);
+    assert_close(near_poisson, poisson, 1e-5);
+}
+
+#[test]
+fn glm_deviance_gradients_match_finite_differences() {
+    let y = [0.0_f64, 2.0, 5.0, 1.5];
+    let eta = [0.2_f64, 0.5, 1.1, -0.3];
+    let w = [1.0_f64, 0.5, 2.0, 1.0];
+    for kind in [
+        loss_functions::LossKind::Poisson,
+        loss_functions::LossKind::Tweedie { power: 1.3 },
+    ] {
+        assert_grad_matches_finite_difference(loss_functions::make_loss::<f64>(kind).as_ref(), &eta, &y, Some(&w));
+    }
+    let y_pos = [0.5_f64, 2.0, 5.0, 1.5];
+    assert_grad_matches_finite_difference(loss_functions::gamma_deviance::<f64>().as_ref(), &eta, &y_pos, Some(&w));
+}
+
+#[test]
+fn glm_baselines_use_intercept_only_model() {
+    use ndarray::{Array1, Array2};
+
+    use super::common::{D, TestOps};
+
+    let y = vec![1.0_f64, 2.0, 3.0, 6.0];
+    let w = vec![1.0_f64, 1.0, 2.0, 0.0];
+    let x = Array2::from_shape_vec((1, 4), vec![0.0; 4]).unwrap();
+    let dataset = crate::Dataset::with_weights_and_names(
+        x,
+        Array1::from_vec(y.clone()),
+        Some(Array1::from_vec(w.clone())),
+        Vec::new(),
+    );
+
+    // Weighted mean of y is (1 + 2 + 6) / 4.
+    let eta0 = (9.0_f64 / 4.0).ln();
+    for kind in [
+        loss_functions::LossKind::Poisson,
+        loss_functions::LossKind::Gamma,
+        loss_functions::LossKind::Tweedie { power: 1.5 },
+    ] {
+        let loss = loss_functions::make_loss::<f64>(kind);
+        let base =
+            loss_functions::baseline_loss_from_zero_expression::<f64, TestOps, D>(&dataset, loss.as_ref()).unwrap();
+        assert_close(base, loss.loss(&[eta0; 4], &y, Some(&w)), 1e-12);
+    }
+    assert_eq!(
+        loss_functions::LossKind::parse("tweedie"),
+        Some(loss_functions::LossKind::Tweedie { power: 1.5 })
+    );
+}
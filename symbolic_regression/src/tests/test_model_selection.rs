@@ -0,0 +1,67 @@
+use dynamic_expressions::expression::{Metadata, PostfixExpr};
+use dynamic_expressions::node::PNode;
+
+use super::common::{D, T, TestOps};
+use crate::hall_of_fame::{HallOfFame, ModelSelection, pareto_scores};
+use crate::pop_member::{MemberId, PopMember};
+
+fn member(complexity: usize, loss: T) -> PopMember<T, TestOps, D> {
+    let expr = PostfixExpr::new(vec![PNode::Var { feature: 0 }], Vec::new(), Metadata::default());
+    let mut m = PopMember::from_expr(MemberId(complexity as u64), None, 0, expr, 1);
+    m.complexity = complexity;
+    m.loss = loss;
+    m.cost = loss;
+    m
+}
+
+fn hall(entries: &[(usize, T)]) -> HallOfFame<T, TestOps, D> {
+    let mut hall = HallOfFame::new(20);
+    for &(c, loss) in entries {
+        hall.best_by_complexity[c] = Some(member(c, loss));
+    }
+    hall
+}
+
+#[test]
+fn scores_are_negative_log_loss_slopes() {
+    // Complexity 4 is dominated by complexity 3 and is not on the front.
+    let hall = hall(&[(1, 8.0), (3, 2.0), (4, 3.0), (7, 1.0)]);
+    let scored = hall.scored_pareto_front();
+    let complexities: Vec<usize> = scored.iter().map(|(m, _)| m.complexity).collect();
+    assert_eq!(complexities, vec![1, 3, 7]);
+
+    let scores: Vec<T> = scored.iter().map(|(_, s)| *s).collect();
+    assert_eq!(scores[0], 0.0);
+    assert!((scores[1] - 4.0_f64.ln() / 2.0).abs() < 1e-12);
+    assert!((scores[2] - 2.0_f64.ln() / 4.0).abs() < 1e-12);
+
+    let front: Vec<_> = scored.into_iter().map(|(m, _)| m).collect();
+    assert_eq!(pareto_scores(&front), scores);
+}
+
+#[test]
+fn selection_strategies_pick_expected_members() {
+    // Scores: c=3 -> ln(10)/2 ~ 1.15, c=5 -> ln(5)/2 ~ 0.80, c=9 -> ln(1.25)/4 ~ 0.06.
+    let hall = hall(&[(1, 100.0), (3, 10.0), (5, 2.0), (9, 1.6)]);
+
+    let pick = |sel| hall.select_model(sel).map(|m| m.complexity);
+    assert_eq!(pick(ModelSelection::Accuracy), Some(9));
+    assert_eq!(pick(ModelSelection::Score), Some(3));
+    // Only c=5 (2.0) and c=9 (1.6) are within 1.5x of the minimum loss.
+    assert_eq!(pick(ModelSelection::Best), Some(5));
+
+    // With a negative minimum the threshold is -2.0 + 0.5 * 2.0 = -1.0, keeping c=5 (-1.5) and c=9 (-2.0).
+    let negative = self::hall(&[(1, 5.0), (5, -1.5), (9, -2.0)]);
+    assert_eq!(
+        negative.select_model(ModelSelection::Best).map(|m| m.complexity),
+        Some(5)
+    );
+
+    assert_eq!(ModelSelection::parse(" Accuracy "), Some(ModelSelection::Accuracy));
+    assert_eq!(ModelSelection::parse("nope"), None);
+    assert!(
+        HallOfFame::<T, TestOps, D>::new(5)
+            .select_model(ModelSelection::Best)
+            .is_none()
+    );
+}
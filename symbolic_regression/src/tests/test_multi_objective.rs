@@ -0,0 +1,128 @@
+use dynamic_expressions::expression::{Metadata, PostfixExpr};
+use dynamic_expressions::node::PNode;
+use fastrand::Rng;
+use ndarray::{Array1, Array2};
+
+use super::common::{D, T, TestOps};
+use crate::adaptive_parsimony::RunningSearchStatistics;
+use crate::dataset::Dataset;
+use crate::multi_objective::{Nsga2Ranking, crowding_distances, nondominated_ranks};
+use crate::operator_library::OperatorLibrary;
+use crate::pop_member::{MemberId, PopMember};
+use crate::population::Population;
+use crate::selection::best_of_sample;
+use crate::{Options, SelectionMode, SelectionObjective, equation_search};
+
+/// A member with the given `(loss, complexity)`; its cost favours the most complex one.
+fn member(id: u64, loss: T, complexity: usize) -> PopMember<T, TestOps, D> {
+    let expr = PostfixExpr::new(vec![PNode::Var { feature: 0 }], Vec::new(), Metadata::default());
+    let mut m = PopMember::from_expr(MemberId(id), None, id, expr, 1);
+    m.loss = loss;
+    m.complexity = complexity;
+    m.cost = 1.0 / complexity as T;
+    m
+}
+
+fn options() -> Options<T, D> {
+    Options {
+        operators: OperatorLibrary::sr_default::<TestOps, D>(),
+        selection: SelectionMode::nsga2(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn fronts_and_crowding_distances() {
+    let points = vec![
+        vec![1.0, 5.0],
+        vec![2.0, 3.0],
+        vec![4.0, 1.0],
+        vec![3.0, 4.0],
+        vec![f64::INFINITY, 1.0],
+        vec![5.0, 5.0],
+    ];
+    let ranks = nondominated_ranks(&points);
+    assert_eq!(ranks, [0, 0, 0, 1, 1, 2]);
+
+    let crowding = crowding_distances(&points, &ranks);
+    assert_eq!(crowding[0], f64::INFINITY);
+    assert_eq!(crowding[2], f64::INFINITY);
+    // Neighbours (1, 5) and (4, 1) span the whole front in both objectives.
+    assert_eq!(crowding[1], 2.0);
+    assert!(crowding[3..].iter().all(|d| d.is_infinite()));
+}
+
+#[test]
+fn tournament_prefers_the_pareto_front_over_cost() {
+    let pop = Population::new(vec![member(1, 1.0, 2), member(2, 2.0, 3), member(3, 0.5, 1)]);
+    let options = Options {
+        tournament_selection_n: 3,
+        tournament_selection_p: 1.0,
+        ..options()
+    };
+    let stats = RunningSearchStatistics::new(options.maxsize, 1000);
+    let chosen = best_of_sample(&mut Rng::with_seed(0), &pop, &stats, &options);
+    assert_eq!(chosen.id, MemberId(3));
+
+    let by_cost = Options {
+        selection: SelectionMode::Cost,
+        ..options
+    };
+    let chosen = best_of_sample(&mut Rng::with_seed(0), &pop, &stats, &by_cost);
+    assert_eq!(chosen.id, MemberId(2));
+}
+
+#[test]
+fn children_replace_the_worst_ranked_member() {
+    let objectives = [SelectionObjective::Loss, SelectionObjective::Complexity];
+    let mut pop = Population::new(vec![member(1, 1.0, 5), member(2, 3.0, 6), member(3, 2.0, 1)]);
+
+    let mut ranking = Nsga2Ranking::of_members(&pop.members, &objectives);
+
+    assert!(!pop.replace_worst(member(4, 4.0, 7), &objectives, &mut ranking));
+    assert!(pop.replace_worst(member(5, 0.5, 2), &objectives, &mut ranking));
+    let mut ids: Vec<u64> = pop.members.iter().map(|m| m.id.0).collect();
+    ids.sort_unstable();
+    assert_eq!(ids, [1, 3, 5]);
+}
+
+#[test]
+fn replacements_keep_the_ranking_current() {
+    let objectives = [SelectionObjective::Loss, SelectionObjective::Complexity];
+    let mut rng = Rng::with_seed(3);
+    let mut random_member = |id| member(id, rng.usize(0..6) as T, rng.usize(1..6));
+    let mut pop = Population::new((1..=12).map(&mut random_member).collect());
+    let mut ranking = Nsga2Ranking::of_members(&pop.members, &objectives);
+    for id in 13..60 {
+        pop.replace_worst(random_member(id), &objectives, &mut ranking);
+        let fresh = Nsga2Ranking::of_members(&pop.members, &objectives);
+        assert_eq!(ranking.ranks, fresh.ranks);
+        assert_eq!(ranking.crowding, fresh.crowding);
+    }
+}
+
+#[test]
+fn nsga2_search_finds_the_target() {
+    let n_rows = 40;
+    let x = Array2::from_shape_fn((2, n_rows), |(f, i)| ((i * (f + 3)) % 13) as T / 6.0 - 1.0);
+    let y = Array1::from_shape_fn(n_rows, |i| x[(0, i)] * x[(1, i)] - x[(0, i)]);
+    let options = Options {
+        selection: SelectionMode::Nsga2 {
+            objectives: vec![
+                SelectionObjective::Loss,
+                SelectionObjective::Complexity,
+                SelectionObjective::Constants,
+            ],
+        },
+        seed: 1,
+        populations: 4,
+        population_size: 20,
+        niterations: 4,
+        ncycles_per_iteration: 100,
+        maxsize: 12,
+        progress: false,
+        ..options()
+    };
+    let result = equation_search::<T, TestOps, D>(&Dataset::new(x, y), &options);
+    assert!(result.best.loss < 1e-12, "loss = {}", result.best.loss);
+}
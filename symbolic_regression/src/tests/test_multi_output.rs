@@ -0,0 +1,66 @@
+use ndarray::{Array1, Array2, Axis};
+
+use super::common::{D, T, TestOps};
+use crate::operator_library::OperatorLibrary;
+use crate::{MultiOutputDataset, Options, Units, equation_search_multi_output};
+
+#[test]
+fn outputs_share_x_and_slice_targets() {
+    let x = Array2::from_shape_vec((1, 3), vec![1.0, 2.0, 3.0]).unwrap();
+    let y = Array2::from_shape_vec((2, 3), vec![1.0, 2.0, 3.0, 10.0, 20.0, 30.0]).unwrap();
+    let w = Array2::from_shape_vec((2, 3), vec![1.0, 1.0, 1.0, 0.0, 1.0, 1.0]).unwrap();
+    let (m, s) = (Units::parse("m").unwrap(), Units::parse("s").unwrap());
+
+    let dataset = MultiOutputDataset::with_weights_and_names(x.clone(), y.clone(), Some(w), vec!["a".to_string()])
+        .with_units(None, Some(vec![m, s]))
+        .with_classes(vec![0, 1, 1]);
+    assert_eq!(dataset.n_outputs(), 2);
+
+    let datasets = dataset.outputs();
+    assert_eq!(datasets.len(), 2);
+    assert_eq!(datasets[0].y_slice(), &[1.0, 2.0, 3.0]);
+    assert_eq!(datasets[1].y_slice(), &[10.0, 20.0, 30.0]);
+    assert_eq!(datasets[1].weights_slice(), Some(&[0.0, 1.0, 1.0][..]));
+    assert_eq!(datasets[1].avg_y, 25.0);
+    assert_eq!((datasets[0].y_units, datasets[1].y_units), (Some(m), Some(s)));
+    for d in &datasets {
+        assert_eq!(d.x, x);
+        assert_eq!(d.variable_names, vec!["a".to_string()]);
+        assert_eq!((d.classes.as_deref(), d.n_classes), (Some(&[0, 1, 1][..]), 2));
+    }
+    let unweighted = MultiOutputDataset::new(x, y).outputs();
+    assert_eq!((unweighted[1].weights_slice(), unweighted[1].y_units), (None, None));
+}
+
+#[test]
+fn multi_output_search_reports_one_front_per_output() {
+    let n_rows = 32;
+    let xs: Vec<T> = (0..n_rows).map(|i| (i as T) / (n_rows as T)).collect();
+    let x = Array2::from_shape_vec((1, n_rows), xs.clone()).unwrap();
+    let y0 = Array1::from_iter(xs.iter().map(|&v| v * v));
+    let y1 = Array1::from_iter(xs.iter().map(|&v| v + 1.0));
+    let y = ndarray::stack(Axis(0), &[y0.view(), y1.view()]).unwrap();
+
+    let dataset = MultiOutputDataset::new(x, y);
+    let options = Options::<T, D> {
+        seed: 3,
+        operators: OperatorLibrary::sr_default::<TestOps, D>(),
+        populations: 2,
+        population_size: 20,
+        niterations: 2,
+        ncycles_per_iteration: 10,
+        maxsize: 10,
+        optimizer_probability: 0.0,
+        progress: false,
+        ..Default::default()
+    };
+
+    let result = equation_search_multi_output::<T, TestOps, D>(&dataset, &options);
+    assert_eq!(result.n_outputs(), 2);
+    let fronts = result.pareto_fronts();
+    assert_eq!(fronts.len(), 2);
+    for (front, out) in fronts.iter().zip(&result.outputs) {
+        assert!(!front.is_empty());
+        assert!(out.best.loss.is_finite());
+    }
+}
@@ -6,6 +6,7 @@ use fastrand::Rng;
 use ndarray::{Array1, Array2};
 
 use super::common::{D, T, TestOps};
+use crate::adaptive_mutation::MutationStats;
 use crate::adaptive_parsimony::RunningSearchStatistics;
 use crate::dataset::TaggedDataset;
 use crate::operator_library::OperatorLibrary;
@@ -75,6 +76,8 @@ fn randomize_mutation_can_succeed_below_size_3() {
             evaluator: &mut evaluator,
             next_id: &mut next_id,
             next_birth: &mut next_birth,
+            mutation_stats: &mut MutationStats::default(),
+            genealogy: None,
             _ops: core::marker::PhantomData,
         },
     );
@@ -110,7 +113,7 @@ fn rotate_tree_is_not_disabled_on_non_binary_trees() {
         swap_operands: 1.0,
         ..MutationWeights::default()
     };
-    mutate::condition_mutation_weights(&mut weights, &member, &options, 10, 1);
+    mutate::condition_mutation_weights(&mut weights, &mut [], &member, &options, 10, 1);
 
     assert_eq!(weights.swap_operands, 0.0);
     assert_eq!(weights.rotate_tree, 1.0);
@@ -224,6 +227,8 @@ fn add_node_includes_append_at_leaf_move() {
                 evaluator: &mut evaluator,
                 next_id: &mut next_id,
                 next_birth: &mut next_birth,
+                mutation_stats: &mut MutationStats::default(),
+                genealogy: None,
                 _ops: core::marker::PhantomData,
             },
         );
@@ -319,6 +324,8 @@ fn mutate_operator_can_be_a_noop_and_still_succeeds() {
             evaluator: &mut evaluator,
             next_id: &mut next_id,
             next_birth: &mut next_birth,
+            mutation_stats: &mut MutationStats::default(),
+            genealogy: None,
             _ops: core::marker::PhantomData,
         },
     );
@@ -4,8 +4,10 @@ use fastrand::Rng;
 use ndarray::{Array1, Array2};
 
 use super::common::{D, T, TestOps};
+use crate::adaptive_mutation::MutationStats;
 use crate::adaptive_parsimony::RunningSearchStatistics;
 use crate::dataset::TaggedDataset;
+use crate::lexicase::CaseErrors;
 use crate::operator_library::OperatorLibrary;
 use crate::pop_member::{Evaluator, MemberId, PopMember};
 use crate::population::Population;
@@ -79,6 +81,8 @@ fn next_generation_fails_constraints_after_retries() {
             evaluator: &mut evaluator,
             next_id: &mut next_id,
             next_birth: &mut next_birth,
+            mutation_stats: &mut MutationStats::default(),
+            genealogy: None,
             _ops: core::marker::PhantomData,
         },
     );
@@ -145,6 +149,10 @@ fn reg_evol_cycle_skips_replacement_when_configured() {
         evaluator: &mut evaluator,
         next_id: &mut next_id,
         next_birth: &mut next_birth,
+        mutation_stats: &mut MutationStats::default(),
+        genealogy: None,
+        case_errors: &mut CaseErrors::default(),
+        hall_of_fame: &[],
         temperature: 1.0,
         curmaxsize: 1,
         _ops: core::marker::PhantomData,
@@ -0,0 +1,209 @@
+use dynamic_expressions::{OperatorSet, parse_expr};
+use fastrand::Rng;
+use ndarray::{Array1, Array2};
+
+use super::common::{D, T, TestOps};
+use crate::constant_optimization::{OptimizeConstantsCtx, optimize_constants};
+use crate::dataset::{Dataset, TaggedDataset};
+use crate::operator_library::OperatorLibrary;
+use crate::pop_member::{Evaluator, MemberId, PopMember};
+use crate::{OptimizerAlgorithm, Options, mae, rmse};
+
+/// Weighted rows of `y = 2 exp(-0.7 x0) + 0.3`.
+fn dataset() -> Dataset<T> {
+    let n_rows = 50;
+    let x = Array2::from_shape_fn((1, n_rows), |(_, i)| i as T / 10.0);
+    let y = Array1::from_shape_fn(n_rows, |i| 2.0 * (-0.7 * x[(0, i)]).exp() + 0.3);
+    let w = Array1::from_shape_fn(n_rows, |i| 1.0 + (i % 3) as T);
+    Dataset::with_weights_and_names(x, y, Some(w), Vec::new())
+}
+
+fn options(algorithm: OptimizerAlgorithm) -> Options<T, D> {
+    Options {
+        operators: OperatorLibrary::sr_default::<TestOps, D>(),
+        optimizer_algorithm: algorithm,
+        optimizer_iterations: 100,
+        optimizer_nrestarts: 0,
+        ..Default::default()
+    }
+}
+
+crate::custom_opset! {
+    struct WrongPartialOps<T = f64>;
+
+    1 => {
+        exp { eval: |[x]| x.exp(), partial: |[x]| 0.5 * x.exp() },
+    },
+    2 => {
+        add { infix: "+", eval: |[a, b]| a + b, partial: |[_, _], _i| 1.0 },
+        mul { infix: "*", eval: |[a, b]| a * b, partial: |[a, b], i| if i == 0 { b } else { a } },
+    },
+}
+
+/// Optimizes `c0 * exp(c1 * x0) + c2` from a poor start; returns the member and the evaluations used.
+fn fit(data: &Dataset<T>, options: &Options<T, D>) -> (PopMember<T, TestOps, D>, f64) {
+    fit_with(data, options)
+}
+
+fn fit_with<Ops: OperatorSet<T = T>>(data: &Dataset<T>, options: &Options<T, D>) -> (PopMember<T, Ops, D>, f64) {
+    let expr = parse_expr("1.0 * exp(0.1 * x0) + 0.0", &[]).unwrap();
+    let mut member = PopMember::from_expr(MemberId(0), None, 0, expr, data.n_features);
+    let full = TaggedDataset::new(data, None);
+    let mut evaluator = Evaluator::new(data.n_rows);
+    member.evaluate(&full, options, &mut evaluator);
+    let mut grad_ctx = dynamic_expressions::GradContext::new(data.n_rows);
+    let (improved, evals) = optimize_constants(
+        &mut Rng::with_seed(0),
+        &mut member,
+        OptimizeConstantsCtx {
+            dataset: full,
+            options,
+            evaluator: &mut evaluator,
+            grad_ctx: &mut grad_ctx,
+            next_birth: &mut 1,
+        },
+    );
+    assert!(improved);
+    (member, evals)
+}
+
+#[test]
+fn levenberg_marquardt_fits_weighted_least_squares_in_fewer_evaluations() {
+    let data = dataset();
+    let (lm, lm_evals) = fit(&data, &options(OptimizerAlgorithm::LevenbergMarquardt));
+    assert!(lm.loss < 1e-20, "loss = {}", lm.loss);
+    for (c, expected) in lm.expr.consts.iter().zip([2.0, -0.7, 0.3]) {
+        assert!((c - expected).abs() < 1e-8, "{:?}", lm.expr.consts);
+    }
+
+    let (bfgs, bfgs_evals) = fit(&data, &options(OptimizerAlgorithm::Bfgs));
+    assert!(lm.loss <= bfgs.loss);
+    assert!(lm_evals < bfgs_evals, "LM {lm_evals} vs BFGS {bfgs_evals}");
+}
+
+#[test]
+fn levenberg_marquardt_reports_the_search_loss() {
+    let data = dataset();
+    let options = Options {
+        loss: rmse(),
+        ..options(OptimizerAlgorithm::LevenbergMarquardt)
+    };
+    let (m, _) = fit(&data, &options);
+    assert!(m.loss < 1e-10, "loss = {}", m.loss);
+}
+
+#[test]
+fn levenberg_marquardt_respects_the_call_budget() {
+    let data = dataset();
+    let options = Options {
+        optimizer_f_calls_limit: 5,
+        optimizer_nrestarts: 2,
+        ..options(OptimizerAlgorithm::LevenbergMarquardt)
+    };
+    let (_, evals) = fit(&data, &options);
+    // Per start: the budget plus the final loss evaluation; then the improved member's re-evaluation.
+    assert!(evals <= 3.0 * 6.0 + 1.0, "evals = {evals}");
+}
+
+#[test]
+fn non_least_squares_losses_fall_back_to_bfgs() {
+    let data = dataset();
+    let options = Options {
+        loss: mae(),
+        ..options(OptimizerAlgorithm::LevenbergMarquardt)
+    };
+    let mut bfgs_options = options.clone();
+    bfgs_options.optimizer_algorithm = OptimizerAlgorithm::Bfgs;
+    let (lm, _) = fit(&data, &options);
+    let (bfgs, _) = fit(&data, &bfgs_options);
+    assert_eq!(lm.expr.consts, bfgs.expr.consts);
+}
+
+#[test]
+fn levenberg_marquardt_fits_class_parameters() {
+    let slopes = [2.0, -1.0, 0.5];
+    let n_rows = 30;
+    let classes: Vec<usize> = (0..n_rows).map(|i| i % 3).collect();
+    let x = Array2::from_shape_fn((1, n_rows), |(_, i)| i as T / 10.0 - 1.0);
+    let y = Array1::from_shape_fn(n_rows, |i| slopes[classes[i]] * x[(0, i)] + 0.5);
+    let data = Dataset::new(x, y).with_classes(classes);
+    let options = Options {
+        n_parameters: 1,
+        ..options(OptimizerAlgorithm::LevenbergMarquardt)
+    };
+
+    let expr = parse_expr("(x1 * x0) + 0.0", &[]).unwrap();
+    let mut member: PopMember<T, TestOps, D> = PopMember::from_expr(MemberId(0), None, 0, expr, 2);
+    member.parameters = vec![1.0; 3];
+    let full = TaggedDataset::new(&data, None);
+    let mut evaluator = Evaluator::new(data.n_rows);
+    member.evaluate(&full, &options, &mut evaluator);
+    let mut grad_ctx = dynamic_expressions::GradContext::new(data.n_rows);
+    let (improved, _) = optimize_constants(
+        &mut Rng::with_seed(0),
+        &mut member,
+        OptimizeConstantsCtx {
+            dataset: full,
+            options: &options,
+            evaluator: &mut evaluator,
+            grad_ctx: &mut grad_ctx,
+            next_birth: &mut 1,
+        },
+    );
+    assert!(improved);
+    assert!(member.loss < 1e-20, "loss = {}", member.loss);
+    for (p, s) in member.parameters.iter().zip(slopes) {
+        assert!((p - s).abs() < 1e-8, "{:?}", member.parameters);
+    }
+}
+
+#[test]
+fn derivative_free_optimizers_fit_a_non_smooth_loss() {
+    let data = dataset();
+    for algorithm in [OptimizerAlgorithm::NelderMead, OptimizerAlgorithm::CmaEs] {
+        let options = Options {
+            loss: mae(),
+            ..options(algorithm)
+        };
+        let (m, _) = fit(&data, &options);
+        assert!(m.loss < 1e-6, "{algorithm:?}: loss = {}", m.loss);
+    }
+}
+
+#[test]
+fn auto_keeps_bfgs_when_the_gradient_is_reliable() {
+    let data = dataset();
+    let (auto, auto_evals) = fit(&data, &options(OptimizerAlgorithm::Auto));
+    let (bfgs, bfgs_evals) = fit(&data, &options(OptimizerAlgorithm::Bfgs));
+    assert_eq!(auto.expr.consts, bfgs.expr.consts);
+    // One gradient and two loss evaluations for the check.
+    assert_eq!(auto_evals, bfgs_evals + 3.0);
+}
+
+#[test]
+fn auto_skips_gradients_of_non_smooth_losses() {
+    let data = dataset();
+    let options = Options {
+        loss: mae(),
+        ..options(OptimizerAlgorithm::Auto)
+    };
+    let mut nelder_mead_options = options.clone();
+    nelder_mead_options.optimizer_algorithm = OptimizerAlgorithm::NelderMead;
+    let (auto, auto_evals) = fit(&data, &options);
+    let (nelder_mead, nelder_mead_evals) = fit(&data, &nelder_mead_options);
+    assert_eq!(auto.expr.consts, nelder_mead.expr.consts);
+    assert_eq!(auto_evals, nelder_mead_evals);
+}
+
+#[test]
+fn auto_skips_wrong_operator_partials() {
+    let data = dataset();
+    let (auto, auto_evals) = fit_with::<WrongPartialOps>(&data, &options(OptimizerAlgorithm::Auto));
+    let (nelder_mead, nelder_mead_evals) = fit_with::<WrongPartialOps>(&data, &options(OptimizerAlgorithm::NelderMead));
+    assert_eq!(auto.expr.consts, nelder_mead.expr.consts);
+    assert_eq!(auto_evals, nelder_mead_evals + 3.0);
+    assert!(auto.loss < 1e-10, "loss = {}", auto.loss);
+
+    let (bfgs, _) = fit_with::<WrongPartialOps>(&data, &options(OptimizerAlgorithm::Bfgs));
+    assert!(auto.loss < bfgs.loss, "Auto {} vs BFGS {}", auto.loss, bfgs.loss);
+}
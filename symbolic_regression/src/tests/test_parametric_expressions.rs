@@ -0,0 +1,154 @@
+use dynamic_expressions::expression::PostfixExpr;
+use dynamic_expressions::parse_expr;
+use fastrand::Rng;
+use ndarray::{Array1, Array2};
+
+use super::common::{D, T, TestOps};
+use crate::constant_optimization::{OptimizeConstantsCtx, optimize_constants};
+use crate::dataset::{Dataset, ParameterRows, TaggedDataset};
+use crate::operator_library::OperatorLibrary;
+use crate::pop_member::{Evaluator, MemberId, PopMember};
+use crate::{Options, equation_search};
+
+const SLOPES: [T; 3] = [2.0, -1.0, 0.5];
+
+/// Three experiments of `y = slope_c * x0 + 0.5`.
+fn dataset() -> Dataset<T> {
+    let n_rows = 30;
+    let classes: Vec<usize> = (0..n_rows).map(|i| i % 3).collect();
+    let x = Array2::from_shape_fn((1, n_rows), |(_, i)| i as T / 10.0 - 1.0);
+    let y = Array1::from_shape_fn(n_rows, |i| SLOPES[classes[i]] * x[(0, i)] + 0.5);
+    Dataset::new(x, y).with_classes(classes)
+}
+
+fn options() -> Options<T, D> {
+    Options {
+        operators: OperatorLibrary::sr_default::<TestOps, D>(),
+        n_parameters: 1,
+        ..Default::default()
+    }
+}
+
+/// `p0 * x0 + c`, with the parameter as feature 1.
+fn member(parameters: Vec<T>, c: &str) -> PopMember<T, TestOps, D> {
+    let expr: PostfixExpr<T, TestOps, D> = parse_expr(&format!("(x1 * x0) + {c}"), &[]).unwrap();
+    let mut m = PopMember::from_expr(MemberId(0), None, 0, expr, 2);
+    m.parameters = parameters;
+    m
+}
+
+#[test]
+fn class_column_survives_row_selection_and_adds_parameter_rows() {
+    let data = dataset();
+    assert_eq!(data.n_classes, 3);
+    assert_eq!(options().n_expr_features(&data), 2);
+    assert_eq!(
+        options().n_expr_features(&Dataset::new(data.x.clone(), data.y.clone())),
+        1
+    );
+
+    let subset = data.select_rows(&[0, 3, 4]);
+    assert_eq!(subset.classes, Some(vec![0, 0, 1]));
+    assert_eq!(subset.n_classes, 3);
+
+    let extended = subset.with_parameter_rows(&[10.0, 20.0, 30.0]);
+    assert_eq!(extended.n_features, 2);
+    assert_eq!(extended.x.row(0), subset.x.row(0));
+    assert_eq!(extended.x.row(1).to_vec(), vec![10.0, 10.0, 20.0]);
+
+    // The buffer kept for a source only gets its parameter rows rewritten.
+    let mut buffers = ParameterRows::default();
+    let first: *const Dataset<T> = subset.parameter_rows_into(&[10.0, 20.0, 30.0], &mut buffers);
+    let again = subset.parameter_rows_into(&[4.0, 5.0, 6.0], &mut buffers);
+    assert!(std::ptr::eq(first, again));
+    assert_eq!(again.x.row(1).to_vec(), vec![4.0, 4.0, 5.0]);
+
+    // Another dataset of the same shape refills it.
+    let other = data.select_rows(&[2, 5, 7]);
+    let refilled = other.parameter_rows_into(&[1.0, 2.0, 3.0], &mut buffers);
+    let fresh = other.with_parameter_rows(&[1.0, 2.0, 3.0]);
+    assert_eq!((&refilled.x, &refilled.y), (&fresh.x, &fresh.y));
+    assert_eq!(refilled.classes, Some(vec![2, 2, 1]));
+
+    // So does a batch resampled in place.
+    let mut batch = Dataset::make_batch_buffer(&data, 3);
+    batch.resample_from(&data, &mut Rng::with_seed(0));
+    let _ = batch.parameter_rows_into(&[1.0, 2.0, 3.0], &mut buffers);
+    batch.resample_from(&data, &mut Rng::with_seed(1));
+    let resampled = batch.parameter_rows_into(&[1.0, 2.0, 3.0], &mut buffers);
+    assert_eq!(resampled.x, batch.with_parameter_rows(&[1.0, 2.0, 3.0]).x);
+}
+
+#[test]
+fn evaluation_uses_each_rows_class_values() {
+    let data = dataset();
+    let options = options();
+    let full = TaggedDataset::new(&data, None);
+    let mut evaluator = Evaluator::new(data.n_rows);
+
+    let mut exact = member(SLOPES.to_vec(), "0.5");
+    assert!(exact.evaluate(&full, &options, &mut evaluator));
+    assert!(exact.loss < 1e-24);
+
+    let mut shared = member(vec![1.0; 3], "0.5");
+    assert!(shared.evaluate(&full, &options, &mut evaluator));
+    assert!(shared.loss > 0.1);
+
+    // The evaluator's parameter rows follow the member and the rows it is evaluated on.
+    let reversed: Vec<usize> = (0..data.n_rows).rev().collect();
+    let batch = data.select_rows(&reversed);
+    assert!(exact.evaluate(&TaggedDataset::new(&batch, None), &options, &mut evaluator));
+    assert!(exact.loss < 1e-24);
+}
+
+#[test]
+fn optimizer_fits_constants_and_class_parameters_jointly() {
+    let data = dataset();
+    let options = Options {
+        optimizer_iterations: 100,
+        ..options()
+    };
+    let full = TaggedDataset::new(&data, None);
+    let mut evaluator = Evaluator::new(data.n_rows);
+    let mut m = member(vec![1.0; 3], "0.0");
+    m.evaluate(&full, &options, &mut evaluator);
+
+    let mut grad_ctx = dynamic_expressions::GradContext::new(data.n_rows);
+    let (improved, _) = optimize_constants(
+        &mut Rng::with_seed(0),
+        &mut m,
+        OptimizeConstantsCtx {
+            dataset: full,
+            options: &options,
+            evaluator: &mut evaluator,
+            grad_ctx: &mut grad_ctx,
+            next_birth: &mut 1,
+        },
+    );
+    assert!(improved);
+    assert!(m.loss < 1e-10, "loss = {}", m.loss);
+    assert!((m.expr.consts[0] - 0.5).abs() < 1e-5);
+    for (p, s) in m.parameters.iter().zip(SLOPES) {
+        assert!((p - s).abs() < 1e-5, "{:?}", m.parameters);
+    }
+}
+
+#[test]
+fn equation_search_gives_members_per_class_parameters() {
+    let data = dataset();
+    let options = Options {
+        seed: 2,
+        populations: 2,
+        population_size: 30,
+        niterations: 2,
+        ncycles_per_iteration: 20,
+        maxsize: 10,
+        progress: false,
+        ..options()
+    };
+    let result = equation_search::<T, TestOps, D>(&data, &options);
+    assert!(result.best.loss.is_finite());
+    for m in result.hall_of_fame.members() {
+        assert_eq!(m.parameters.len(), 3);
+    }
+}
@@ -0,0 +1,212 @@
+use dynamic_expressions::expression::PostfixExpr;
+use dynamic_expressions::node::PNode;
+use dynamic_expressions::{EvalOptions, eval_tree_array, parse_expr};
+use fastrand::Rng;
+use ndarray::{Array1, Array2};
+
+use super::common::{D, T, TestOps};
+use crate::adaptive_mutation::MutationStats;
+use crate::adaptive_parsimony::RunningSearchStatistics;
+use crate::constant_optimization::{OptimizeConstantsCtx, optimize_constants};
+use crate::dataset::{Dataset, TaggedDataset};
+use crate::hall_of_fame::HallOfFame;
+use crate::mutate::{NextGenerationCtx, next_generation};
+use crate::operator_library::OperatorLibrary;
+use crate::pop_member::{Evaluator, MemberId, PopMember};
+use crate::template::template_complexity;
+use crate::{ExpressionTemplate, Options, TemplateError, equation_search};
+
+type Expr = PostfixExpr<T, TestOps, D>;
+
+fn parse(s: &str) -> Expr {
+    parse_expr(s, &[]).unwrap()
+}
+
+/// `f(x0, x1) * exp(-g(x2)) + 1.5`.
+fn template() -> ExpressionTemplate<T> {
+    let combiner: Expr = parse_expr("f * exp(-g) + 1.5", &["f".into(), "g".into()]).unwrap();
+    ExpressionTemplate::new(&combiner, vec![vec![0, 1], vec![2]]).unwrap()
+}
+
+/// Rows of `y = (2 x0 - x1) * exp(-x2) + 1.5`.
+fn dataset() -> Dataset<T> {
+    let n_rows = 40;
+    let x = Array2::from_shape_fn((3, n_rows), |(f, i)| ((i * (f + 2)) % 17) as T / 8.0 - 1.0);
+    let y = Array1::from_shape_fn(n_rows, |i| (2.0 * x[(0, i)] - x[(1, i)]) * (-x[(2, i)]).exp() + 1.5);
+    Dataset::new(x, y)
+}
+
+fn options() -> Options<T, D> {
+    Options {
+        operators: OperatorLibrary::sr_default::<TestOps, D>(),
+        template: Some(template()),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn compose_maps_features_and_orders_constants() {
+    let composed = template().compose(&[parse("x1 - 0.5"), parse("x0 * 2.0")]);
+    assert_eq!(composed.consts, vec![0.5, 2.0, 1.5]);
+
+    let expected = parse("((x1 - 0.5) * exp(-(x2 * 2.0))) + 1.5");
+    let data = dataset();
+    let opts = EvalOptions::default();
+    let (a, _) = eval_tree_array::<T, TestOps, D>(&composed, data.x.view(), &opts);
+    let (b, _) = eval_tree_array::<T, TestOps, D>(&expected, data.x.view(), &opts);
+    assert_eq!(a, b);
+}
+
+#[test]
+fn invalid_templates_are_rejected() {
+    let combiner: Expr = parse_expr("f * g", &["f".into(), "g".into()]).unwrap();
+    assert_eq!(
+        ExpressionTemplate::new(&combiner, vec![vec![0]]).unwrap_err(),
+        TemplateError::UnknownSubexpr {
+            index: 1,
+            n_subexprs: 1
+        }
+    );
+    assert_eq!(
+        ExpressionTemplate::new(&combiner, vec![vec![0], vec![1], vec![2]]).unwrap_err(),
+        TemplateError::UnusedSubexpr { index: 2 }
+    );
+    assert_eq!(
+        ExpressionTemplate::new(&combiner, vec![vec![0], vec![]]).unwrap_err(),
+        TemplateError::NoFeatures { index: 1 }
+    );
+}
+
+#[test]
+fn optimizer_fits_subexpression_constants_only() {
+    let data = dataset();
+    let options = Options {
+        optimizer_iterations: 200,
+        ..options()
+    };
+    let template = options.template.as_ref().unwrap();
+    let subexprs = vec![parse("(x0 * 1.0) - x1"), parse("x0 * 0.7")];
+    let mut member = PopMember::from_template(MemberId(0), None, 0, template, subexprs, data.n_features);
+    let full_dataset = TaggedDataset::new(&data, None);
+    let mut evaluator = Evaluator::new(data.n_rows);
+    assert!(member.evaluate(&full_dataset, &options, &mut evaluator));
+    assert_eq!(member.complexity, 8);
+
+    let mut grad_ctx = dynamic_expressions::GradContext::new(data.n_rows);
+    let (improved, _) = optimize_constants(
+        &mut Rng::with_seed(0),
+        &mut member,
+        OptimizeConstantsCtx {
+            dataset: full_dataset,
+            options: &options,
+            evaluator: &mut evaluator,
+            grad_ctx: &mut grad_ctx,
+            next_birth: &mut 1,
+        },
+    );
+    assert!(improved);
+    assert!(member.loss < 1e-10);
+    assert_eq!(member.expr.consts[2], 1.5);
+    assert!((member.subexprs[0].consts[0] - 2.0).abs() < 1e-5);
+    assert!((member.subexprs[1].consts[0] - 1.0).abs() < 1e-5);
+    assert_eq!(member.expr.consts, template.compose(&member.subexprs).consts);
+}
+
+#[test]
+fn mutations_stay_inside_subexpressions() {
+    let data = dataset();
+    let mut options = options();
+    options.annealing = false;
+    options.use_frequency = false;
+    let template = options.template.as_ref().unwrap();
+    let full_dataset = TaggedDataset::new(&data, None);
+    let mut evaluator = Evaluator::new(data.n_rows);
+    let stats = RunningSearchStatistics::new(options.maxsize, 10_000);
+    let mut rng = Rng::with_seed(3);
+    let (mut next_id, mut next_birth) = (1, 1);
+
+    let subexprs = vec![parse("x0 - x1"), parse("x0")];
+    let mut member = PopMember::from_template(MemberId(0), None, 0, template, subexprs, data.n_features);
+    member.evaluate(&full_dataset, &options, &mut evaluator);
+    let curmaxsize = 12;
+    for _ in 0..300 {
+        let (baby, _, _) = next_generation(
+            &member,
+            NextGenerationCtx {
+                rng: &mut rng,
+                dataset: full_dataset,
+                temperature: 1.0,
+                curmaxsize,
+                stats: &stats,
+                options: &options,
+                evaluator: &mut evaluator,
+                next_id: &mut next_id,
+                next_birth: &mut next_birth,
+                mutation_stats: &mut MutationStats::default(),
+                genealogy: None,
+                _ops: core::marker::PhantomData,
+            },
+        );
+        assert_eq!(baby.expr.nodes, template.compose(&baby.subexprs).nodes);
+        assert_eq!(baby.complexity, template_complexity(&baby.subexprs, &options));
+        assert!(baby.complexity <= curmaxsize);
+        for (k, sub) in baby.subexprs.iter().enumerate() {
+            let n = template.features(k).len();
+            assert!(
+                sub.nodes
+                    .iter()
+                    .all(|n_| !matches!(*n_, PNode::Var { feature } if usize::from(feature) >= n))
+            );
+        }
+        member = baby;
+    }
+}
+
+#[test]
+fn equation_search_runs_over_templates() {
+    let data = dataset();
+    let options = Options {
+        seed: 1,
+        populations: 2,
+        population_size: 30,
+        niterations: 2,
+        ncycles_per_iteration: 20,
+        maxsize: 16,
+        progress: false,
+        ..options()
+    };
+    let template = options.template.as_ref().unwrap();
+
+    let result = equation_search::<T, TestOps, D>(&data, &options);
+    assert!(result.best.loss.is_finite());
+    assert!(result.hall_of_fame.members().count() > 0);
+    for m in result.hall_of_fame.members() {
+        assert_eq!(m.subexprs.len(), 2);
+        assert_eq!(m.expr.nodes, template.compose(&m.subexprs).nodes);
+        assert!(m.complexity <= options.maxsize);
+    }
+}
+
+#[test]
+fn folding_linear_scaling_drops_the_subexpressions() {
+    let data = dataset();
+    let full_dataset = TaggedDataset::new(&data, None);
+    let options = Options {
+        linear_scaling: true,
+        ..options()
+    };
+    let subexprs = vec![parse("x0 - x1"), parse("x0")];
+    let expr = options.template.as_ref().unwrap().compose(&subexprs);
+    let mut member = PopMember::<T, TestOps, D>::from_expr(MemberId(0), None, 0, expr, data.n_features);
+    member.subexprs = subexprs;
+    assert!(member.evaluate(&full_dataset, &options, &mut Evaluator::new(data.n_rows)));
+    assert!(member.scaling.is_some());
+
+    let mut hall = HallOfFame::new(options.maxsize);
+    hall.best_by_complexity[member.complexity] = Some(member.clone());
+    assert!(hall.to_records(&[])[0].subexpressions.is_empty());
+
+    assert!(member.fold_linear_scaling(data.n_features, &options));
+    assert!(member.subexprs.is_empty());
+    assert_eq!(member.scaling, None);
+}
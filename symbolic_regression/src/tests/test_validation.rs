@@ -0,0 +1,110 @@
+use ndarray::{Array1, Array2};
+
+use super::common::{D, T, TestOps};
+use crate::operator_library::OperatorLibrary;
+use crate::{Dataset, LossSource, Options, SearchEngine, SplitIndices, equation_search_with_validation};
+
+fn quadratic_dataset(n_rows: usize) -> Dataset<T> {
+    let x: Vec<T> = (0..n_rows).map(|i| (i as T) / (n_rows as T) * 4.0 - 2.0).collect();
+    let y: Vec<T> = x.iter().map(|&xi| xi * xi - xi).collect();
+    let w: Vec<T> = (0..n_rows).map(|i| 1.0 + (i % 3) as T).collect();
+    Dataset::with_weights_and_names(
+        Array2::from_shape_vec((1, n_rows), x).unwrap(),
+        Array1::from_vec(y),
+        Some(Array1::from_vec(w)),
+        vec!["a".to_string()],
+    )
+}
+
+fn options() -> Options<T, D> {
+    Options::<T, D> {
+        seed: 11,
+        operators: OperatorLibrary::sr_default::<TestOps, D>(),
+        populations: 2,
+        population_size: 20,
+        niterations: 3,
+        ncycles_per_iteration: 20,
+        maxsize: 12,
+        optimizer_probability: 0.0,
+        progress: false,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn random_split_partitions_rows_deterministically() {
+    let split = SplitIndices::random(50, 0.2, 7);
+    assert_eq!(split.val.len(), 10);
+    assert_eq!(split.train.len(), 40);
+    let mut all: Vec<usize> = split.train.iter().chain(&split.val).copied().collect();
+    all.sort_unstable();
+    assert_eq!(all, (0..50).collect::<Vec<_>>());
+    assert_eq!(split, SplitIndices::random(50, 0.2, 7));
+
+    let none = SplitIndices::random(50, 0.0, 7);
+    assert!(none.val.is_empty());
+    assert_eq!(none.train, (0..50).collect::<Vec<_>>());
+}
+
+#[test]
+fn split_train_val_copies_rows_and_weights() {
+    let data = quadratic_dataset(6);
+    let split = SplitIndices {
+        train: vec![5, 0, 2],
+        val: vec![4],
+    };
+    let (train, val) = data.split_train_val(&split);
+    let val = val.expect("non-empty validation split");
+
+    assert_eq!(train.n_rows, 3);
+    assert_eq!(train.y_slice(), &[data.y[5], data.y[0], data.y[2]]);
+    assert_eq!(train.x[(0, 0)], data.x[(0, 5)]);
+    assert_eq!(train.weights_slice(), Some(&[3.0, 1.0, 3.0][..]));
+    assert_eq!(val.y_slice(), &[data.y[4]]);
+    assert_eq!(val.variable_names, data.variable_names);
+}
+
+#[test]
+fn search_tracks_validation_losses_and_selects_best_by_them() {
+    let data = quadratic_dataset(64);
+    let (train, val) = data.split_train_val(&SplitIndices::random(data.n_rows, 0.25, 1));
+    let val = val.unwrap();
+
+    let options = Options {
+        select_best_by_validation: true,
+        ..options()
+    };
+    let result = equation_search_with_validation::<T, TestOps, D>(&train, &val, &options);
+
+    assert!(result.hall_of_fame.members().all(|m| m.validation_loss.is_some()));
+    let front = result.hall_of_fame.pareto_front_by(LossSource::Validation);
+    assert!(!front.is_empty());
+    for pair in front.windows(2) {
+        assert!(pair[0].complexity < pair[1].complexity);
+        assert!(pair[1].validation_loss.unwrap() < pair[0].validation_loss.unwrap());
+    }
+
+    let min_val = result
+        .hall_of_fame
+        .members()
+        .filter_map(|m| m.validation_loss)
+        .fold(T::INFINITY, T::min);
+    assert_eq!(result.best.validation_loss, Some(min_val));
+}
+
+#[test]
+fn engine_with_validation_matches_training_only_search() {
+    let data = quadratic_dataset(48);
+    let (train, val) = data.split_train_val(&SplitIndices::random(data.n_rows, 0.25, 2));
+
+    let plain = SearchEngine::<T, TestOps, D>::new(train.clone(), options()).run_to_completion();
+    let engine = SearchEngine::<T, TestOps, D>::with_validation(train, val, options());
+    assert!(engine.validation_dataset().is_some());
+    let with_val = engine.run_to_completion();
+
+    // Validation tracking must not perturb the search itself.
+    let losses = |r: &crate::SearchResult<T, TestOps, D>| r.hall_of_fame.members().map(|m| m.loss).collect::<Vec<_>>();
+    assert_eq!(losses(&plain), losses(&with_val));
+    assert_eq!(plain.best.loss, with_val.best.loss);
+    assert!(with_val.hall_of_fame.members().all(|m| m.validation_loss.is_some()));
+}
@@ -4,17 +4,13 @@ use csv::ReaderBuilder;
 use dynamic_expressions::operator_enum::presets::BuiltinOpsF64;
 use dynamic_expressions::strings::{StringTreeOptions, string_tree};
 use dynamic_expressions::utils::ZipEq;
-use dynamic_expressions::{EvalOptions, OpId, OperatorSet, eval_plan_array_into};
-use fastrand::Rng;
+use dynamic_expressions::{EvalOptions, GraphExpr, OpId, OperatorSet, eval_plan_array_into, string_graph};
 use ndarray::{Array1, Array2};
 use serde::{Deserialize, Serialize};
 use serde_wasm_bindgen::{from_value, to_value};
-use symbolic_regression::{Dataset, LossKind, Operators, Options, SearchEngine, WasmOptionsShim};
+use symbolic_regression::{Dataset, LossKind, Operators, Options, SearchEngine, SplitIndices, WasmOptionsShim};
 use wasm_bindgen::prelude::*;
 
-type FullDatasetParts = (Dataset<f64>, Vec<String>, Option<Array1<f64>>);
-type SelectedRows = (Array2<f64>, Array1<f64>, Option<Array1<f64>>);
-
 #[cfg(feature = "panic-hook")]
 #[wasm_bindgen(start)]
 pub fn start() {
@@ -71,16 +67,6 @@ pub fn default_core_options() -> Result<JsValue, JsValue> {
     to_value(&WasmOptionsShim::default()).map_err(|e| JsValue::from_str(&e.to_string()))
 }
 
-fn shuffle<T>(rng: &mut Rng, values: &mut [T]) {
-    if values.len() <= 1 {
-        return;
-    }
-    for i in (1..values.len()).rev() {
-        let j = rng.usize(0..=i);
-        values.swap(i, j);
-    }
-}
-
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(default)]
 pub struct WasmSearchOptions {
@@ -97,6 +83,7 @@ pub struct WasmSearchOptions {
     pub lp_p: f64,
     pub quantile_tau: f64,
     pub epsilon_insensitive_eps: f64,
+    pub tweedie_power: f64,
 
     #[serde(flatten)]
     pub core: WasmOptionsShim,
@@ -116,6 +103,7 @@ impl Default for WasmSearchOptions {
             lp_p: 2.0,
             quantile_tau: 0.5,
             epsilon_insensitive_eps: 0.1,
+            tweedie_power: 1.5,
             core: WasmOptionsShim::default(),
         }
     }
@@ -171,10 +159,18 @@ pub struct WasmSplitIndices {
     pub val: Vec<usize>,
 }
 
+impl From<SplitIndices> for WasmSplitIndices {
+    fn from(split: SplitIndices) -> Self {
+        Self {
+            train: split.train,
+            val: split.val,
+        }
+    }
+}
+
 #[wasm_bindgen]
 pub struct WasmSearch {
     engine: SearchEngine<f64, BuiltinOpsF64, 3>,
-    val_dataset: Option<Dataset<f64>>,
     split: WasmSplitIndices,
     pareto_k: usize,
 }
@@ -205,21 +201,18 @@ impl WasmSearch {
 
         let (headers, rows) = parse_csv_to_rows(&csv_text, opts.has_headers).map_err(|e| JsValue::from_str(&e))?;
 
-        let (dataset_all, variable_names, weights_all) =
-            build_full_dataset(&headers, &rows, &opts).map_err(|e| JsValue::from_str(&e))?;
+        let dataset_all = build_full_dataset(&headers, &rows, &opts).map_err(|e| JsValue::from_str(&e))?;
 
-        let split = make_split_indices(dataset_all.n_rows, opts.validation_fraction, opts.core.seed);
-        let (train, val) = split_train_val(&dataset_all, weights_all.as_ref(), &variable_names, &split)
-            .map_err(|e| JsValue::from_str(&e))?;
+        let split = SplitIndices::random(dataset_all.n_rows, opts.validation_fraction, opts.core.seed);
+        let (train, val) = dataset_all.split_train_val(&split);
 
         let options = options_from_wasm(&opts, operators)?;
 
-        let engine = SearchEngine::<f64, BuiltinOpsF64, 3>::new(train, options);
+        let engine = SearchEngine::<f64, BuiltinOpsF64, 3>::with_validation(train, val, options);
 
         Ok(WasmSearch {
             engine,
-            val_dataset: val,
-            split,
+            split: split.into(),
             pareto_k: 250,
         })
     }
@@ -261,8 +254,8 @@ impl WasmSearch {
         let dataset = match which.as_str() {
             "train" => self.engine.dataset(),
             "val" => self
-                .val_dataset
-                .as_ref()
+                .engine
+                .validation_dataset()
                 .ok_or_else(|| JsValue::from_str("no validation dataset"))?,
             _ => return Err(JsValue::from_str("which must be \"train\" or \"val\"")),
         };
@@ -292,9 +285,16 @@ fn options_from_wasm(opts: &WasmSearchOptions, operators: Operators<3>) -> Resul
                 eps: opts.epsilon_insensitive_eps,
             }
         }
+        "logistic" => LossKind::Logistic,
+        "hinge" => LossKind::Hinge,
+        "poisson" => LossKind::Poisson,
+        "gamma" => LossKind::Gamma,
+        "tweedie" => LossKind::Tweedie {
+            power: opts.tweedie_power,
+        },
         other => {
             return Err(JsValue::from_str(&format!(
-                "unknown loss_kind {other:?} (expected \"mse\", \"mae\", \"rmse\", \"huber\", \"logcosh\", \"lp\", \"quantile\", or \"epsilon-insensitive\")"
+                "unknown loss_kind {other:?} (expected \"mse\", \"mae\", \"rmse\", \"huber\", \"logcosh\", \"lp\", \"quantile\", \"epsilon-insensitive\", \"logistic\", \"hinge\", \"poisson\", \"gamma\", or \"tweedie\")"
             )));
         }
     };
@@ -342,13 +342,16 @@ fn summary_from_member(
     engine: &SearchEngine<f64, BuiltinOpsF64, 3>,
     m: &symbolic_regression::PopMember<f64, BuiltinOpsF64, 3>,
 ) -> EquationSummary {
-    let equation = string_tree::<f64, BuiltinOpsF64, 3>(
-        &m.expr,
-        StringTreeOptions {
-            variable_names: Some(&engine.dataset().variable_names),
-            pretty: false,
-        },
-    );
+    let opts = StringTreeOptions {
+        variable_names: Some(&engine.dataset().variable_names),
+        pretty: false,
+    };
+    // Graph members print their shared subexpressions as let-bindings.
+    let equation = if m.expr.meta.graph_ids.is_empty() {
+        string_tree::<f64, BuiltinOpsF64, 3>(&m.expr, opts)
+    } else {
+        string_graph(&GraphExpr::from_postfix(&m.expr), opts)
+    };
     EquationSummary {
         id: m.id.0.to_string(),
         complexity: m.complexity,
@@ -558,11 +561,7 @@ fn parse_csv_to_rows(csv_text: &str, has_headers: bool) -> Result<(Vec<String>,
     Ok((headers, rows))
 }
 
-fn build_full_dataset(
-    headers: &[String],
-    rows: &[Vec<f64>],
-    opts: &WasmSearchOptions,
-) -> Result<FullDatasetParts, String> {
+fn build_full_dataset(headers: &[String], rows: &[Vec<f64>], opts: &WasmSearchOptions) -> Result<Dataset<f64>, String> {
     if rows.is_empty() {
         return Err("CSV had no data rows".to_string());
     }
@@ -638,81 +637,7 @@ fn build_full_dataset(
         (0..n_features).map(|i| format!("x{}", i)).collect()
     };
 
-    let dataset = Dataset::with_weights_and_names(x, y, None, variable_names.clone());
-    Ok((dataset, variable_names, w))
-}
-
-fn make_split_indices(n_rows: usize, validation_fraction: f64, seed: u64) -> WasmSplitIndices {
-    if n_rows == 0 {
-        return WasmSplitIndices {
-            train: Vec::new(),
-            val: Vec::new(),
-        };
-    }
-
-    let vf = validation_fraction.clamp(0.0, 0.9);
-    let n_val = ((vf * (n_rows as f64)).round() as usize).min(n_rows);
-    if n_val == 0 {
-        return WasmSplitIndices {
-            train: (0..n_rows).collect(),
-            val: Vec::new(),
-        };
-    }
-
-    let mut idx: Vec<usize> = (0..n_rows).collect();
-    let mut rng = Rng::with_seed(seed ^ 0x6a09_e667_f3bc_c909);
-    shuffle(&mut rng, &mut idx);
-
-    let val = idx[..n_val].to_vec();
-    let train = idx[n_val..].to_vec();
-
-    WasmSplitIndices { train, val }
-}
-
-fn split_train_val(
-    dataset_all: &Dataset<f64>,
-    weights_all: Option<&Array1<f64>>,
-    variable_names: &[String],
-    split: &WasmSplitIndices,
-) -> Result<(Dataset<f64>, Option<Dataset<f64>>), String> {
-    let (x_train, y_train, w_train) = select_rows(&dataset_all.x, &dataset_all.y, weights_all, &split.train)?;
-    let train = Dataset::with_weights_and_names(x_train, y_train, w_train, variable_names.to_vec());
-
-    if split.val.is_empty() {
-        return Ok((train, None));
-    }
-    let (x_val, y_val, w_val) = select_rows(&dataset_all.x, &dataset_all.y, weights_all, &split.val)?;
-    let val = Dataset::with_weights_and_names(x_val, y_val, w_val, variable_names.to_vec());
-    Ok((train, Some(val)))
-}
-
-fn select_rows(
-    x: &Array2<f64>,
-    y: &Array1<f64>,
-    w: Option<&Array1<f64>>,
-    indices: &[usize],
-) -> Result<SelectedRows, String> {
-    let (n_features, n_rows) = x.dim();
-    for &i in indices {
-        if i >= n_rows {
-            return Err(format!("index out of range while splitting: {i} (n_rows={n_rows})"));
-        }
-    }
-
-    let mut xo = Array2::<f64>::zeros((n_features, indices.len()));
-    let mut yo = Array1::<f64>::zeros(indices.len());
-    let mut wo = w.map(|_| Array1::<f64>::zeros(indices.len()));
-
-    for (i_new, &i_old) in indices.iter().enumerate() {
-        for j in 0..n_features {
-            xo[(j, i_new)] = x[(j, i_old)];
-        }
-        yo[i_new] = y[i_old];
-        if let (Some(w_in), Some(w_out)) = (w, wo.as_mut()) {
-            w_out[i_new] = w_in[i_old];
-        }
-    }
-    Ok((xo, yo, wo))
+    Ok(Dataset::with_weights_and_names(x, y, w, variable_names))
 }
 
 #[cfg(test)]